@@ -1,8 +1,8 @@
 use std::path::Path;
 
 use liber::epub::{
-    ContentBuilder, ContentReference, EpubBuilder, ImageType, MetadataBuilder, ReferenceType,
-    Resource,
+    ContentBuilder, ContentReference, EpubBuilder, FontType, ImageType, MetadataBuilder,
+    ReferenceType, Resource,
 };
 
 fn main() {
@@ -45,7 +45,10 @@ fn create() -> Result<(), Box<dyn std::error::Error>> {
     let epub_builder = EpubBuilder::new(MetadataBuilder::title(title).creator("author").build())
         .stylesheet(&style)
         .cover_image(Path::new("/path/to/img.jpg"), ImageType::Jpg)
-        .add_resource(Resource::Font(Path::new("/path/to/some_font.otf")))
+        .add_resource(Resource::Font(
+            Path::new("/path/to/some_font.otf"),
+            FontType::OpenType,
+        ))
         .add_content(
             ContentBuilder::new(&chapter1, ReferenceType::Text("Chapter 1".to_string()))
                 .filename("chapter1.xhtml")
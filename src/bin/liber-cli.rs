@@ -0,0 +1,458 @@
+//! # liber-cli
+//!
+//! Creates an EPUB from a TOML manifest listing its metadata, chapters and assets.
+//! Paths in the manifest are relative to the directory of the manifest.
+//!
+//! ```toml
+//! output = "book.epub"
+//! stylesheet = "style.css"
+//! cover = "images/cover.jpg"
+//! assets = ["images/map.png", "fonts/Lora-Regular.ttf"]
+//!
+//! [metadata]
+//! title = "My Book"
+//! creator = "Author"
+//! language = "en"
+//! isbn = "978-3-16-148410-0"
+//! date = "2024-05-01"
+//! conforms_to = "EPUB Accessibility 1.1 - WCAG 2.1 Level AA"
+//!
+//! [[chapters]]
+//! title = "Preface"
+//! file = "preface.xhtml"
+//! type = "preface"
+//!
+//! [[chapters]]
+//! title = "Chapter 1"
+//! file = "chapter1.xhtml"
+//!
+//! [[chapters.children]]
+//! title = "Chapter 1.1"
+//! file = "chapter1-1.xhtml"
+//! ```
+//!
+//! Usage: `liber-cli <MANIFEST> [-o <OUTPUT>]`
+
+use std::{
+    error::Error,
+    path::{Path, PathBuf},
+};
+
+use chrono::{NaiveDate, TimeZone, Utc};
+use liber::epub::{
+    AgeRange, Audience, Certification, Conformance, Content, ContentBuilder, EpubBuilder,
+    Identifier, Language, Metadata, MetadataBuilder, ReferenceType, Resource,
+};
+use serde::Deserialize;
+
+const USAGE: &str = "Usage: liber-cli <MANIFEST> [-o <OUTPUT>]
+
+Creates an EPUB from a TOML manifest listing its metadata, chapters and assets.
+
+Options:
+  -o, --output <OUTPUT>  The EPUB file to create (default: the `output` of the manifest, or book.epub)
+  -h, --help             Print this help
+  -V, --version          Print the version";
+
+/// The manifest of a book.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Manifest {
+    /// The EPUB file to create, if not given on the command line.
+    output: Option<PathBuf>,
+    /// The stylesheet of the contents.
+    stylesheet: Option<PathBuf>,
+    /// The cover image.
+    cover: Option<PathBuf>,
+    /// The images, fonts and other files referenced by the chapters.
+    #[serde(default)]
+    assets: Vec<PathBuf>,
+    /// The descriptive metadata.
+    metadata: ManifestMetadata,
+    /// The chapters, in reading order.
+    #[serde(default)]
+    chapters: Vec<Chapter>,
+}
+
+/// The metadata of a book in the manifest.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ManifestMetadata {
+    title: String,
+    creator: Option<String>,
+    contributor: Option<String>,
+    publisher: Option<String>,
+    /// The imprint of the publisher.
+    imprint: Option<String>,
+    /// The place (usually the city) of publication.
+    publication_place: Option<String>,
+    subject: Option<String>,
+    description: Option<String>,
+    /// The ISO 639-1 code of the language (e.g. `en`).
+    language: Option<String>,
+    /// The ISBN, used as identifier instead of a random UUID.
+    isbn: Option<String>,
+    /// The UUID, used as identifier instead of a random one.
+    uuid: Option<String>,
+    /// The publication date, as `YYYY-MM-DD`.
+    date: Option<String>,
+    /// The intended audience (e.g. `Children` or `Young Adult`).
+    audience: Option<String>,
+    /// The education level the book is meant for (e.g. `Grade 5`).
+    education_level: Option<String>,
+    /// The typical age range of the readers (e.g. `8-12` or `14-`).
+    age_range: Option<String>,
+    /// The accessibility conformance claim (e.g. `EPUB Accessibility 1.1 - WCAG 2.1 Level AA`).
+    conforms_to: Option<String>,
+    /// The party that certified the accessibility conformance claim.
+    certified_by: Option<String>,
+}
+
+/// A chapter of a book in the manifest.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Chapter {
+    /// The title of the chapter.
+    title: String,
+    /// The XHTML (or body fragment) file of the chapter.
+    file: PathBuf,
+    /// The kind of the chapter, by the name of a [`ReferenceType`] (default: `text`).
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    /// The filename of the chapter inside the EPUB.
+    filename: Option<String>,
+    /// The label of the chapter in the table of contents, if not its title.
+    toc_label: Option<String>,
+    /// The nested chapters.
+    #[serde(default)]
+    children: Vec<Chapter>,
+}
+
+/// A chapter with the content of its file read.
+struct LoadedChapter<'m> {
+    chapter: &'m Chapter,
+    body: Vec<u8>,
+    children: Vec<LoadedChapter<'m>>,
+}
+
+impl<'m> LoadedChapter<'m> {
+    /// Reads the file of `chapter` and of its children, relative to `base`.
+    fn load(chapter: &'m Chapter, base: &Path) -> Result<Self, Box<dyn Error>> {
+        let path = base.join(&chapter.file);
+        let body = std::fs::read(&path).map_err(|e| format!("{}: {e}", path.display()))?;
+        let children = chapter
+            .children
+            .iter()
+            .map(|child| Self::load(child, base))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            chapter,
+            body,
+            children,
+        })
+    }
+
+    /// Builds the content of the chapter and of its children.
+    fn content(&self) -> Result<Content<'_>, Box<dyn Error>> {
+        let chapter = self.chapter;
+        let mut builder = ContentBuilder::new(
+            &self.body,
+            reference_type(chapter.kind.as_deref(), &chapter.title)?,
+        );
+        if let Some(ref filename) = chapter.filename {
+            builder = builder.filename(filename);
+        }
+        if let Some(ref toc_label) = chapter.toc_label {
+            builder = builder.toc_label(toc_label);
+        }
+        for child in &self.children {
+            builder = builder.add_child(child.content()?);
+        }
+
+        Ok(builder.build())
+    }
+}
+
+/// Maps the name of a kind of chapter (e.g. `preface`) to its [`ReferenceType`].
+fn reference_type(kind: Option<&str>, title: &str) -> Result<ReferenceType, Box<dyn Error>> {
+    let title = title.to_string();
+    let reference_type = match kind.unwrap_or("text").to_lowercase().as_str() {
+        "acknowledgements" => ReferenceType::Acknowledgements(title),
+        "bibliography" => ReferenceType::Bibliography(title),
+        "colophon" => ReferenceType::Colophon(title),
+        "copyright" => ReferenceType::Copyright(title),
+        "cover" => ReferenceType::Cover(title),
+        "dedication" => ReferenceType::Dedication(title),
+        "epigraph" => ReferenceType::Epigraph(title),
+        "foreword" => ReferenceType::Foreword(title),
+        "glossary" => ReferenceType::Glossary(title),
+        "index" => ReferenceType::Index(title),
+        "loi" => ReferenceType::Loi(title),
+        "lot" => ReferenceType::Lot(title),
+        "notes" => ReferenceType::Notes(title),
+        "preface" => ReferenceType::Preface(title),
+        "text" => ReferenceType::Text(title),
+        "title-page" => ReferenceType::TitlePage(title),
+        "toc" => ReferenceType::Toc(title),
+        other => return Err(format!("unknown chapter type `{other}` of `{title}`").into()),
+    };
+
+    Ok(reference_type)
+}
+
+impl ManifestMetadata {
+    /// Builds the [`Metadata`] of the book.
+    fn build(&self) -> Result<Metadata, Box<dyn Error>> {
+        let mut builder = MetadataBuilder::title(&self.title);
+        if let Some(ref creator) = self.creator {
+            builder = builder.creator(creator);
+        }
+        if let Some(ref contributor) = self.contributor {
+            builder = builder.contributor(contributor);
+        }
+        if let Some(ref publisher) = self.publisher {
+            builder = builder.publisher(publisher);
+        }
+        if let Some(ref imprint) = self.imprint {
+            builder = builder.imprint(imprint);
+        }
+        if let Some(ref publication_place) = self.publication_place {
+            builder = builder.publication_place(publication_place);
+        }
+        if let Some(ref subject) = self.subject {
+            builder = builder.subject(subject);
+        }
+        if let Some(ref description) = self.description {
+            builder = builder.description(description);
+        }
+        if let Some(ref code) = self.language {
+            let language =
+                Language::from_code(code).ok_or_else(|| format!("unknown language `{code}`"))?;
+            builder = builder.language(language);
+        }
+        match (&self.isbn, &self.uuid) {
+            (Some(_), Some(_)) => return Err("only one of `isbn` and `uuid` can be set".into()),
+            (Some(isbn), None) => builder = builder.identifier(Identifier::ISBN(isbn.clone())),
+            (None, Some(uuid)) => builder = builder.identifier(Identifier::UUID(uuid.clone())),
+            (None, None) => {}
+        }
+        if let Some(ref date) = self.date {
+            let date = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .map_err(|e| format!("invalid date `{date}`: {e}"))?;
+            builder = builder.date(Utc.from_utc_datetime(&date.and_time(Default::default())));
+        }
+        if let Some(ref audience) = self.audience {
+            builder = builder.audience(Audience::parse(audience));
+        }
+        if let Some(ref education_level) = self.education_level {
+            builder = builder.education_level(education_level);
+        }
+        if let Some(ref age_range) = self.age_range {
+            let age_range = AgeRange::parse(age_range)
+                .ok_or_else(|| format!("invalid age range `{age_range}`"))?;
+            builder = builder.age_range(age_range);
+        }
+        if let Some(ref conforms_to) = self.conforms_to {
+            builder = builder.conforms_to(Conformance::parse(conforms_to));
+        }
+        if let Some(ref certified_by) = self.certified_by {
+            builder = builder.certification(Certification::new(certified_by));
+        }
+
+        Ok(builder.build())
+    }
+}
+
+/// Creates the EPUB described by `manifest`, whose paths are relative to `base`, in memory.
+fn create(manifest: &Manifest, base: &Path) -> Result<Vec<u8>, Box<dyn Error>> {
+    let read = |path: &Path| {
+        let path = base.join(path);
+        std::fs::read(&path).map_err(|e| format!("{}: {e}", path.display()))
+    };
+
+    let stylesheet = manifest.stylesheet.as_deref().map(read).transpose()?;
+    let cover = manifest.cover.as_ref().map(|cover| base.join(cover));
+    let assets = manifest
+        .assets
+        .iter()
+        .map(|asset| base.join(asset))
+        .collect::<Vec<_>>();
+    let chapters = manifest
+        .chapters
+        .iter()
+        .map(|chapter| LoadedChapter::load(chapter, base))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut epub_builder = EpubBuilder::new(manifest.metadata.build()?);
+    if let Some(ref stylesheet) = stylesheet {
+        epub_builder = epub_builder.stylesheet(stylesheet);
+    }
+    if let Some(ref cover) = cover {
+        let Some(Resource::Image(path, image_type)) = Resource::from_path(cover) else {
+            return Err(format!("{}: the cover is not a supported image", cover.display()).into());
+        };
+        epub_builder = epub_builder.cover_image(path, image_type);
+    }
+    for asset in &assets {
+        let resource = Resource::from_path(asset)
+            .ok_or_else(|| format!("{}: unknown type of asset", asset.display()))?;
+        epub_builder = epub_builder.add_resource(resource);
+    }
+    for chapter in &chapters {
+        epub_builder = epub_builder.add_content(chapter.content()?);
+    }
+
+    Ok(epub_builder.create_to_vec()?)
+}
+
+/// The command line arguments.
+#[derive(Debug, PartialEq)]
+struct Args {
+    /// The manifest of the book.
+    manifest: PathBuf,
+    /// The EPUB file to create, overriding the `output` of the manifest.
+    output: Option<PathBuf>,
+}
+
+/// Parses the command line arguments. Returns `None` if only the help or the version was asked.
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<Args>, Box<dyn Error>> {
+    let mut manifest = None;
+    let mut output = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return Ok(None);
+            }
+            "-V" | "--version" => {
+                println!("liber-cli {}", env!("CARGO_PKG_VERSION"));
+                return Ok(None);
+            }
+            "-o" | "--output" => {
+                let path = args.next().ok_or("missing value of --output")?;
+                output = Some(PathBuf::from(path));
+            }
+            _ if arg.starts_with('-') => return Err(format!("unknown option `{arg}`").into()),
+            _ if manifest.is_none() => manifest = Some(PathBuf::from(arg)),
+            _ => return Err(format!("unexpected argument `{arg}`").into()),
+        }
+    }
+
+    let manifest = manifest.ok_or("missing manifest")?;
+    Ok(Some(Args { manifest, output }))
+}
+
+fn run() -> Result<(), Box<dyn Error>> {
+    let Some(Args {
+        manifest: manifest_path,
+        output,
+    }) = parse_args(std::env::args().skip(1))?
+    else {
+        return Ok(());
+    };
+
+    let manifest = std::fs::read_to_string(&manifest_path)
+        .map_err(|e| format!("{}: {e}", manifest_path.display()))?;
+    let manifest: Manifest =
+        toml::from_str(&manifest).map_err(|e| format!("{}: {e}", manifest_path.display()))?;
+    let base = manifest_path.parent().unwrap_or(Path::new(""));
+
+    let epub = create(&manifest, base)?;
+    let output = output
+        .or_else(|| manifest.output.as_ref().map(|output| base.join(output)))
+        .unwrap_or_else(|| PathBuf::from("book.epub"));
+    std::fs::write(&output, epub).map_err(|e| format!("{}: {e}", output.display()))?;
+    println!("{}", output.display());
+
+    Ok(())
+}
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("liber-cli: {e}\n\n{USAGE}");
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use liber::EpubReader;
+
+    #[test]
+    fn test_create_from_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("style.css"), "body {}").unwrap();
+        std::fs::write(dir.path().join("preface.xhtml"), "<body><p>Hi</p></body>").unwrap();
+        std::fs::write(dir.path().join("c1.xhtml"), "<body><h1>One</h1></body>").unwrap();
+        std::fs::write(dir.path().join("c1-1.xhtml"), "<body><h2>Two</h2></body>").unwrap();
+
+        let manifest: Manifest = toml::from_str(
+            r#"
+            stylesheet = "style.css"
+
+            [metadata]
+            title = "Manifest Book"
+            creator = "Author"
+            language = "es"
+            isbn = "978-3-16-148410-0"
+            date = "2024-05-01"
+
+            [[chapters]]
+            title = "Preface"
+            file = "preface.xhtml"
+            type = "preface"
+
+            [[chapters]]
+            title = "Chapter 1"
+            file = "c1.xhtml"
+            toc_label = "One"
+
+            [[chapters.children]]
+            title = "Chapter 1.1"
+            file = "c1-1.xhtml"
+            "#,
+        )
+        .unwrap();
+
+        let epub = create(&manifest, dir.path()).unwrap();
+        let book = EpubReader::from_reader(std::io::Cursor::new(epub)).unwrap();
+        let metadata = book.metadata();
+        assert_eq!(metadata.title, "Manifest Book");
+        assert_eq!(metadata.creator.as_deref(), Some("Author"));
+        assert_eq!(metadata.language.as_ref(), "es");
+        assert_eq!(book.contents().len(), 2);
+    }
+
+    #[test]
+    fn test_manifest_errors() {
+        assert!(reference_type(Some("chapter"), "Chapter 1").is_err());
+        assert!(toml::from_str::<Manifest>("[metadata]\ntitle = \"T\"\nauthor = \"A\"").is_err());
+
+        let manifest: Manifest = toml::from_str(
+            "[metadata]\ntitle = \"T\"\n[[chapters]]\ntitle = \"C\"\nfile = \"missing.xhtml\"",
+        )
+        .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let error = create(&manifest, dir.path()).unwrap_err();
+        assert!(error.to_string().contains("missing.xhtml"));
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let args = |args: &[&str]| parse_args(args.iter().map(|arg| arg.to_string()));
+
+        assert_eq!(
+            args(&["book.toml", "-o", "out.epub"]).unwrap(),
+            Some(Args {
+                manifest: PathBuf::from("book.toml"),
+                output: Some(PathBuf::from("out.epub")),
+            })
+        );
+        assert!(args(&[]).is_err());
+        assert!(args(&["a.toml", "b.toml"]).is_err());
+        assert!(args(&["-x"]).is_err());
+    }
+}
@@ -0,0 +1,224 @@
+use std::fmt::Display;
+
+use crate::epub::unicode;
+
+/// An accessibility conformance claim of the publication, declared as `dcterms:conformsTo` in
+/// the package metadata (see the EPUB Accessibility specification).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Conformance {
+    /// EPUB Accessibility 1.1, meeting the given WCAG version and level, e.g.
+    /// `EPUB Accessibility 1.1 - WCAG 2.1 Level AA`.
+    EpubA11y11(WcagVersion, WcagLevel),
+    /// EPUB Accessibility 1.0, meeting the given WCAG 2.0 level, identified by its URL (e.g.
+    /// `http://www.idpf.org/epub/a11y/accessibility-20170105.html#wcag-aa`).
+    EpubA11y10(WcagLevel),
+    /// Any other conformance statement, written as is.
+    Other(String),
+}
+
+/// The URL identifying EPUB Accessibility 1.0, followed by `#wcag-` and the level.
+const EPUB_A11Y_10_URL: &str = "http://www.idpf.org/epub/a11y/accessibility-20170105.html#wcag-";
+
+/// The prefix of the EPUB Accessibility 1.1 conformance statements.
+const EPUB_A11Y_11_PREFIX: &str = "EPUB Accessibility 1.1 - WCAG ";
+
+impl Conformance {
+    /// Parses a `dcterms:conformsTo` statement, keeping the unknown ones as [`Conformance::Other`].
+    pub fn parse(statement: &str) -> Self {
+        let statement = statement.trim();
+
+        if let Some(level) = statement
+            .strip_prefix(EPUB_A11Y_10_URL)
+            .and_then(WcagLevel::parse)
+        {
+            return Self::EpubA11y10(level);
+        }
+
+        statement
+            .strip_prefix(EPUB_A11Y_11_PREFIX)
+            .and_then(|rest| rest.split_once(" Level "))
+            .and_then(|(version, level)| Some((WcagVersion::parse(version)?, level)))
+            .and_then(|(version, level)| Some(Self::EpubA11y11(version, WcagLevel::parse(level)?)))
+            .unwrap_or_else(|| Self::Other(statement.to_string()))
+    }
+}
+
+/// Displays the statement declared as `dcterms:conformsTo`.
+impl Display for Conformance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::EpubA11y11(version, level) => write!(
+                f,
+                "{EPUB_A11Y_11_PREFIX}{} Level {}",
+                version.as_ref(),
+                level.as_ref()
+            ),
+            Self::EpubA11y10(level) => write!(
+                f,
+                "{EPUB_A11Y_10_URL}{}",
+                level.as_ref().to_ascii_lowercase()
+            ),
+            Self::Other(statement) => f.write_str(statement),
+        }
+    }
+}
+
+/// A version of the Web Content Accessibility Guidelines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WcagVersion {
+    /// WCAG 2.0.
+    V2_0,
+    /// WCAG 2.1.
+    V2_1,
+    /// WCAG 2.2.
+    V2_2,
+}
+
+impl WcagVersion {
+    /// Selects the version from its number (e.g. `2.1`).
+    fn parse(version: &str) -> Option<Self> {
+        [Self::V2_0, Self::V2_1, Self::V2_2]
+            .into_iter()
+            .find(|v| v.as_ref() == version)
+    }
+}
+
+/// Helper implementation to get the number of the version (e.g. `2.1`).
+impl AsRef<str> for WcagVersion {
+    fn as_ref(&self) -> &str {
+        match self {
+            Self::V2_0 => "2.0",
+            Self::V2_1 => "2.1",
+            Self::V2_2 => "2.2",
+        }
+    }
+}
+
+/// A conformance level of the Web Content Accessibility Guidelines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WcagLevel {
+    /// Level A, the minimum level.
+    A,
+    /// Level AA, the level required by most accessibility regulations.
+    AA,
+    /// Level AAA, the highest level.
+    AAA,
+}
+
+impl WcagLevel {
+    /// Selects the level from its name (e.g. `AA`), ignoring the case.
+    fn parse(level: &str) -> Option<Self> {
+        [Self::A, Self::AA, Self::AAA]
+            .into_iter()
+            .find(|l| l.as_ref().eq_ignore_ascii_case(level))
+    }
+}
+
+/// Helper implementation to get the name of the level (e.g. `AA`).
+impl AsRef<str> for WcagLevel {
+    fn as_ref(&self) -> &str {
+        match self {
+            Self::A => "A",
+            Self::AA => "AA",
+            Self::AAA => "AAA",
+        }
+    }
+}
+
+/// The party that certified the accessibility conformance of the publication, declared as
+/// `a11y:certifiedBy` (with its `a11y:certifierCredential` and `a11y:certifierReport`) in the
+/// package metadata.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Certification {
+    /// The name of the party that evaluated the publication.
+    pub certified_by: String,
+    /// Optional credential (e.g. a badge or accreditation) of the certifier.
+    pub credential: Option<String>,
+    /// Optional URL of the accessibility report of the evaluation.
+    pub report: Option<String>,
+}
+
+impl Certification {
+    /// Creates a certification by the party named `certified_by`.
+    pub fn new<S: Into<String>>(certified_by: S) -> Self {
+        Self {
+            certified_by: certified_by.into(),
+            credential: None,
+            report: None,
+        }
+    }
+
+    /// Sets the **credential** of the certifier.
+    pub fn credential<S: Into<String>>(mut self, credential: S) -> Self {
+        self.credential = Some(credential.into());
+        self
+    }
+
+    /// Sets the URL of the accessibility **report** of the evaluation.
+    pub fn report<S: Into<String>>(mut self, report: S) -> Self {
+        self.report = Some(report.into());
+        self
+    }
+
+    /// Gets the `name` and `content` of the `<meta>` elements declaring the certification.
+    pub(crate) fn metas(&self) -> impl Iterator<Item = (&'static str, &str)> {
+        [
+            ("a11y:certifiedBy", Some(self.certified_by.as_str())),
+            ("a11y:certifierCredential", self.credential.as_deref()),
+            ("a11y:certifierReport", self.report.as_deref()),
+        ]
+        .into_iter()
+        .filter_map(|(name, content)| Some((name, content?)))
+    }
+
+    /// Converts the names to Unicode Normalization Form C.
+    pub(crate) fn normalize_unicode(&mut self) {
+        unicode::nfc(&mut self.certified_by);
+        self.credential.iter_mut().for_each(unicode::nfc);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conformance_statements() {
+        let statements = [
+            (
+                Conformance::EpubA11y11(WcagVersion::V2_1, WcagLevel::AA),
+                "EPUB Accessibility 1.1 - WCAG 2.1 Level AA",
+            ),
+            (
+                Conformance::EpubA11y10(WcagLevel::A),
+                "http://www.idpf.org/epub/a11y/accessibility-20170105.html#wcag-a",
+            ),
+            (
+                Conformance::Other("Internal guidelines".to_string()),
+                "Internal guidelines",
+            ),
+        ];
+
+        for (conformance, statement) in statements {
+            assert_eq!(conformance.to_string(), statement);
+            assert_eq!(Conformance::parse(statement), conformance);
+        }
+        assert_eq!(
+            Conformance::parse("EPUB Accessibility 1.1 - WCAG 3.0 Level AA"),
+            Conformance::Other("EPUB Accessibility 1.1 - WCAG 3.0 Level AA".to_string())
+        );
+    }
+
+    #[test]
+    fn test_certification_metas() {
+        let certification =
+            Certification::new("Accessibility Testers").report("https://a11y.example/r");
+        assert_eq!(
+            certification.metas().collect::<Vec<_>>(),
+            [
+                ("a11y:certifiedBy", "Accessibility Testers"),
+                ("a11y:certifierReport", "https://a11y.example/r"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,152 @@
+use std::fmt::Display;
+
+/// The intended audience of the publication, declared as `dcterms:audience` in the package
+/// metadata.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Audience {
+    /// General readers (trade publications).
+    General,
+    /// Children.
+    Children,
+    /// Young adults (teenagers).
+    YoungAdult,
+    /// Adults only.
+    Adult,
+    /// Students and teachers (educational publications).
+    Educational,
+    /// Professionals and scholars (academic or technical publications).
+    Professional,
+    /// Any other audience, written as is.
+    Other(String),
+}
+
+impl Audience {
+    /// Parses a `dcterms:audience` value, ignoring the case and keeping the unknown ones as
+    /// [`Audience::Other`].
+    pub fn parse(audience: &str) -> Self {
+        let audience = audience.trim();
+        [
+            Self::General,
+            Self::Children,
+            Self::YoungAdult,
+            Self::Adult,
+            Self::Educational,
+            Self::Professional,
+        ]
+        .into_iter()
+        .find(|a| a.to_string().eq_ignore_ascii_case(audience))
+        .unwrap_or_else(|| Self::Other(audience.to_string()))
+    }
+}
+
+/// Displays the value declared as `dcterms:audience`.
+impl Display for Audience {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::General => "General",
+            Self::Children => "Children",
+            Self::YoungAdult => "Young Adult",
+            Self::Adult => "Adult",
+            Self::Educational => "Educational",
+            Self::Professional => "Professional",
+            Self::Other(audience) => audience,
+        })
+    }
+}
+
+/// The typical age range of the readers of the publication, declared as
+/// `schema:typicalAgeRange` (e.g. `8-12`, or `14-` with no upper bound).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AgeRange {
+    /// The minimum age, if any.
+    pub min: Option<u8>,
+    /// The maximum age, if any.
+    pub max: Option<u8>,
+}
+
+impl AgeRange {
+    /// Creates the range of the ages from `min` to `max` (in any order), both included.
+    pub fn between(min: u8, max: u8) -> Self {
+        Self {
+            min: Some(min.min(max)),
+            max: Some(min.max(max)),
+        }
+    }
+
+    /// Creates the range of the ages from `min` on.
+    pub fn from_age(min: u8) -> Self {
+        Self {
+            min: Some(min),
+            max: None,
+        }
+    }
+
+    /// Creates the range of the ages up to `max`, included.
+    pub fn up_to(max: u8) -> Self {
+        Self {
+            min: None,
+            max: Some(max),
+        }
+    }
+
+    /// Parses a `schema:typicalAgeRange` value (`min-max`, `min-` or `-max`).
+    ///
+    /// Returns `None` if it is not a range of ages.
+    pub fn parse(range: &str) -> Option<Self> {
+        let (min, max) = range.trim().split_once('-')?;
+        let age = |age: &str| match age.trim() {
+            "" => Ok(None),
+            age => age.parse().map(Some),
+        };
+        match (age(min).ok()?, age(max).ok()?) {
+            (None, None) => None,
+            (min, max) => Some(Self { min, max }),
+        }
+    }
+}
+
+/// Displays the value declared as `schema:typicalAgeRange`.
+impl Display for AgeRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(min) = self.min {
+            write!(f, "{min}")?;
+        }
+        f.write_str("-")?;
+        if let Some(max) = self.max {
+            write!(f, "{max}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_audience_values() {
+        assert_eq!(Audience::YoungAdult.to_string(), "Young Adult");
+        assert_eq!(Audience::parse("young adult"), Audience::YoungAdult);
+        assert_eq!(
+            Audience::parse(" Librarians "),
+            Audience::Other("Librarians".to_string())
+        );
+    }
+
+    #[test]
+    fn test_age_range_values() {
+        let ranges = [
+            (AgeRange::between(12, 8), "8-12"),
+            (AgeRange::from_age(14), "14-"),
+            (AgeRange::up_to(5), "-5"),
+        ];
+
+        for (range, value) in ranges {
+            assert_eq!(range.to_string(), value);
+            assert_eq!(AgeRange::parse(value), Some(range));
+        }
+        assert_eq!(AgeRange::parse("-"), None);
+        assert_eq!(AgeRange::parse("teens"), None);
+        assert_eq!(AgeRange::parse("8-x"), None);
+    }
+}
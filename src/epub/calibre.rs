@@ -0,0 +1,158 @@
+use chrono::{DateTime, Utc};
+
+use crate::{epub::unicode, output::manifest::json_escape};
+
+/// The Calibre-specific metadata of the book, declared as `calibre:*` metas in the package
+/// metadata, so books imported into Calibre libraries keep their sort, series and custom
+/// column data.
+///
+/// Use it with [`EpubBuilder::calibre`](crate::epub::EpubBuilder::calibre).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CalibreMetadata {
+    /// Optional date the book was added to the library (`calibre:timestamp`).
+    pub timestamp: Option<DateTime<Utc>>,
+    /// Optional title used to sort the book (`calibre:title_sort`), e.g. `Hobbit, The`.
+    pub title_sort: Option<String>,
+    /// Optional series the book belongs to (`calibre:series`).
+    pub series: Option<String>,
+    /// Optional position of the book in its series (`calibre:series_index`).
+    pub series_index: Option<f32>,
+    /// Optional rating of the book, from 0 to 10 (`calibre:rating`).
+    pub rating: Option<u8>,
+    /// Optional text values of custom columns, by lookup name without the `#`
+    /// (`calibre:user_metadata:#<name>`).
+    pub custom_columns: Option<Vec<(String, String)>>,
+}
+
+impl CalibreMetadata {
+    /// Creates an empty Calibre metadata section.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the **timestamp**: the date the book was added to the library.
+    pub fn timestamp(mut self, timestamp: DateTime<Utc>) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    /// Sets the **title** used to sort the book (e.g. `Hobbit, The`).
+    pub fn title_sort<S: Into<String>>(mut self, title_sort: S) -> Self {
+        self.title_sort = Some(title_sort.into());
+        self
+    }
+
+    /// Sets the **series** the book belongs to, and its position in it (e.g. `2.0`).
+    pub fn series<S: Into<String>>(mut self, series: S, index: f32) -> Self {
+        self.series = Some(series.into());
+        self.series_index = Some(index);
+        self
+    }
+
+    /// Sets the **rating** of the book, from 0 to 10 (clamped), as Calibre stores it (two per
+    /// star).
+    pub fn rating(mut self, rating: u8) -> Self {
+        self.rating = Some(rating.min(10));
+        self
+    }
+
+    /// Adds the text value of the **custom column** with the lookup name `name` (without the
+    /// `#`, e.g. `genre`).
+    pub fn custom_column<N: Into<String>, V: Into<String>>(mut self, name: N, value: V) -> Self {
+        self.custom_columns
+            .get_or_insert_with(Vec::new)
+            .push((name.into(), value.into()));
+        self
+    }
+
+    /// Gets the `name` and `content` of the `<meta>` elements declaring the metadata.
+    pub(crate) fn metas(&self) -> Vec<(String, String)> {
+        let mut metas = [
+            (
+                "calibre:timestamp",
+                self.timestamp.map(|timestamp| timestamp.to_rfc3339()),
+            ),
+            ("calibre:title_sort", self.title_sort.clone()),
+            ("calibre:series", self.series.clone()),
+            (
+                "calibre:series_index",
+                self.series_index.map(|index| format!("{index:.1}")),
+            ),
+            (
+                "calibre:rating",
+                self.rating.map(|rating| rating.to_string()),
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(name, content)| Some((name.to_string(), content?)))
+        .collect::<Vec<_>>();
+
+        metas.extend(self.custom_columns.iter().flatten().map(|(name, value)| {
+            (
+                format!("calibre:user_metadata:#{name}"),
+                custom_column_json(name, value),
+            )
+        }));
+        metas
+    }
+
+    /// Converts the sort title, series and custom column values to Unicode Normalization Form C.
+    pub(crate) fn normalize_unicode(&mut self) {
+        self.title_sort
+            .iter_mut()
+            .chain(self.series.iter_mut())
+            .for_each(unicode::nfc);
+        self.custom_columns
+            .iter_mut()
+            .flatten()
+            .for_each(|(_, value)| unicode::nfc(value));
+    }
+}
+
+/// Generates the JSON definition of the text custom column `name` holding `value`, as Calibre
+/// stores it in the `calibre:user_metadata:#<name>` meta.
+fn custom_column_json(name: &str, value: &str) -> String {
+    format!(
+        r##"{{"label": "{name}", "name": "{name}", "datatype": "text", "is_multiple": {{}}, "display": {{}}, "#value#": "{value}"}}"##,
+        name = json_escape(name),
+        value = json_escape(value)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn test_calibre_metas() {
+        let calibre = CalibreMetadata::new()
+            .timestamp(Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap())
+            .title_sort("Hobbit, The")
+            .series("Middle-earth", 2.0)
+            .rating(12)
+            .custom_column("genre", r#"Fantasy "epic""#);
+
+        let metas = calibre.metas();
+        let meta = |name: &str| {
+            metas
+                .iter()
+                .find(|(meta, _)| meta == name)
+                .map(|(_, content)| content.as_str())
+        };
+        assert_eq!(meta("calibre:timestamp"), Some("2024-05-01T12:00:00+00:00"));
+        assert_eq!(meta("calibre:title_sort"), Some("Hobbit, The"));
+        assert_eq!(meta("calibre:series"), Some("Middle-earth"));
+        assert_eq!(meta("calibre:series_index"), Some("2.0"));
+        assert_eq!(meta("calibre:rating"), Some("10"));
+        assert_eq!(
+            meta("calibre:user_metadata:#genre"),
+            Some(
+                r##"{"label": "genre", "name": "genre", "datatype": "text", "is_multiple": {}, "display": {}, "#value#": "Fantasy \"epic\""}"##
+            )
+        );
+        assert!(CalibreMetadata::new().metas().is_empty());
+    }
+}
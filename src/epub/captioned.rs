@@ -0,0 +1,250 @@
+use quick_xml::{
+    Reader,
+    escape::{self, escape},
+    events::{BytesStart, Event},
+};
+
+use crate::epub::NavTarget;
+
+/// A captioned element of a content body (a figure or a table), listed in a generated list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Captioned {
+    /// The `id` of the element, if it has one.
+    pub(crate) id: Option<String>,
+    /// The label of the element: its caption, or else the alt text of its image (figures).
+    pub(crate) label: String,
+}
+
+impl Captioned {
+    /// Gets the navigation target of the element in the content generated as `filename`: its
+    /// anchor, or else the content itself.
+    pub(crate) fn target(&self, filename: &str) -> NavTarget {
+        let src = match self.id {
+            Some(ref id) => format!("{filename}#{id}"),
+            None => filename.to_string(),
+        };
+        NavTarget::new(self.label.clone(), src)
+    }
+}
+
+/// Finds the figures of `body`: the `<figure>` elements with a `<figcaption>` or an image
+/// with alt text, labelled with their caption, or else with the alt text.
+///
+/// The scan stops silently at the first markup error (bodies are not required to be well
+/// formed).
+pub(crate) fn figures(body: &[u8]) -> Vec<Captioned> {
+    scan(body, b"figure", b"figcaption", true)
+}
+
+/// Finds the tables of `body`: the `<table>` elements with a `<caption>`, labelled with it.
+///
+/// The scan stops silently at the first markup error, like [`figures`].
+pub(crate) fn tables(body: &[u8]) -> Vec<Captioned> {
+    scan(body, b"table", b"caption", false)
+}
+
+/// Merges the elements `registered` by the caller into the ones `found` in a body: a
+/// registered element relabels the found one with the same `id`, or else is appended.
+pub(crate) fn merge(mut found: Vec<Captioned>, registered: &[Captioned]) -> Vec<Captioned> {
+    for element in registered {
+        match found.iter_mut().find(|found| found.id == element.id) {
+            Some(found) => found.label.clone_from(&element.label),
+            None => found.push(element.clone()),
+        }
+    }
+    found
+}
+
+/// Finds the `element_name` elements of `body`, labelled with the text of their
+/// `caption_name` child, or else (if `alt`) with the alt text of their first image.
+fn scan(body: &[u8], element_name: &[u8], caption_name: &[u8], alt: bool) -> Vec<Captioned> {
+    let mut reader = Reader::from_reader(body);
+    reader.config_mut().check_end_names = false;
+    let mut found = Vec::new();
+    let mut scanned: Option<Scanned> = None;
+    let mut in_caption = false;
+    let mut buffer = Vec::new();
+
+    loop {
+        match reader.read_event_into(&mut buffer) {
+            Ok(Event::Start(element)) => match element.local_name().as_ref() {
+                name if name == element_name => {
+                    scanned = Some(Scanned {
+                        id: attribute(&element, b"id"),
+                        ..Scanned::default()
+                    });
+                }
+                name if name == caption_name => in_caption = scanned.is_some(),
+                b"img" if alt => set_alt(&mut scanned, &element),
+                _ => {}
+            },
+            Ok(Event::Empty(element)) if alt && element.local_name().as_ref() == b"img" => {
+                set_alt(&mut scanned, &element);
+            }
+            Ok(Event::Text(text)) if in_caption => {
+                if let (Some(scanned), Ok(text)) = (&mut scanned, text.decode()) {
+                    scanned.caption.push_str(&text);
+                }
+            }
+            Ok(Event::GeneralRef(reference)) if in_caption => {
+                if let Some(scanned) = &mut scanned {
+                    match reference.resolve_char_ref() {
+                        Ok(Some(ch)) => scanned.caption.push(ch),
+                        _ => {
+                            let name = reference.decode().unwrap_or_default();
+                            let resolved = escape::resolve_predefined_entity(&name).unwrap_or(" ");
+                            scanned.caption.push_str(resolved);
+                        }
+                    }
+                }
+            }
+            Ok(Event::End(element)) => match element.local_name().as_ref() {
+                name if name == caption_name => in_caption = false,
+                name if name == element_name => {
+                    found.extend(scanned.take().and_then(Scanned::close));
+                }
+                _ => {}
+            },
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+        buffer.clear();
+    }
+
+    found
+}
+
+/// Renders the body of a generated list page: its `title` as heading and an ordered list of
+/// links to its `targets`, with the class `class` (e.g. `loi`).
+pub(crate) fn list_body(title: &str, class: &str, targets: &[NavTarget]) -> String {
+    let items = targets
+        .iter()
+        .map(|target| {
+            format!(
+                r#"<li><a href="{}">{}</a></li>"#,
+                escape(target.src.as_str()),
+                escape(target.label.as_str())
+            )
+        })
+        .collect::<String>();
+    format!(
+        r#"<body><h1>{}</h1><ol class="{class}">{items}</ol></body>"#,
+        escape(title)
+    )
+}
+
+/// Gets the unescaped value of the attribute `name` of `element`, if present.
+fn attribute(element: &BytesStart<'_>, name: &[u8]) -> Option<String> {
+    element
+        .try_get_attribute(name)
+        .ok()
+        .flatten()
+        .and_then(|attribute| attribute.unescape_value().ok())
+        .map(|value| value.into_owned())
+}
+
+/// Keeps the alt text of the first image of the element being scanned, if any.
+fn set_alt(scanned: &mut Option<Scanned>, image: &BytesStart<'_>) {
+    if let Some(scanned) = scanned
+        && scanned.alt.is_none()
+    {
+        scanned.alt = attribute(image, b"alt");
+    }
+}
+
+/// A captioned element being scanned.
+#[derive(Debug, Default)]
+struct Scanned {
+    /// The `id` of the element, if it has one.
+    id: Option<String>,
+    /// The text of its caption so far.
+    caption: String,
+    /// The alt text of its first image, if any.
+    alt: Option<String>,
+}
+
+impl Scanned {
+    /// Labels the scanned element with its caption (whitespace collapsed), or else the alt text
+    /// of its image. Returns `None` if it has neither.
+    fn close(self) -> Option<Captioned> {
+        let caption = self
+            .caption
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        let label = Some(caption)
+            .filter(|caption| !caption.is_empty())
+            .or(self.alt.filter(|alt| !alt.trim().is_empty()))?;
+        Some(Captioned { id: self.id, label })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_figures() {
+        let body = br#"<body>
+            <figure id="map"><img src="map.png" alt="Map"/><figcaption>The <em>old</em>
+                map &amp; its roads</figcaption></figure>
+            <figure><img src="photo.png" alt="A photo"/></figure>
+            <figure id="empty"><img src="deco.png" alt=""/></figure>
+            <img src="loose.png" alt="Loose"/>
+        </body>"#;
+
+        assert_eq!(
+            figures(body),
+            vec![
+                Captioned {
+                    id: Some("map".to_string()),
+                    label: "The old map & its roads".to_string(),
+                },
+                Captioned {
+                    id: None,
+                    label: "A photo".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tables() {
+        let body = br#"<body>
+            <table id="prices"><caption>Prices</caption><tr><td><img src="x.png" alt="X"/></td></tr></table>
+            <table><tr><td><img src="y.png" alt="Y"/></td></tr></table>
+        </body>"#;
+        let tables = tables(body);
+        assert_eq!(
+            tables,
+            vec![Captioned {
+                id: Some("prices".to_string()),
+                label: "Prices".to_string(),
+            }]
+        );
+
+        let registered = [
+            Captioned {
+                id: Some("prices".to_string()),
+                label: "Prices in 2024".to_string(),
+            },
+            Captioned {
+                id: Some("rates".to_string()),
+                label: "Rates".to_string(),
+            },
+        ];
+        assert_eq!(merge(tables, &registered), registered);
+    }
+
+    #[test]
+    fn test_list_body() {
+        let targets = [
+            NavTarget::new("Map & roads", "c02.xhtml#map"),
+            NavTarget::new("Photo", "c03.xhtml"),
+        ];
+        assert_eq!(
+            list_body("Illustrations", "loi", &targets),
+            r#"<body><h1>Illustrations</h1><ol class="loi"><li><a href="c02.xhtml#map">Map &amp; roads</a></li><li><a href="c03.xhtml">Photo</a></li></ol></body>"#
+        );
+    }
+}
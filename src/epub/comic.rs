@@ -0,0 +1,281 @@
+use std::io::Read;
+
+use quick_xml::escape::escape;
+
+use crate::{
+    epub::{ContentBuilder, EpubBuilder, Metadata, ReferenceType, ResourceItem, image_dimensions},
+    output::file_content::COPY_CHUNK_SIZE,
+};
+
+/// The size of a page when the dimensions of its image cannot be detected.
+const DEFAULT_PAGE_SIZE: (u32, u32) = (1600, 2400);
+
+/// The layout of a pre-paginated book (e.g. a comic or a picture book), whose pages are shown
+/// one per screen with fixed dimensions instead of reflowing. Set with
+/// [`EpubBuilder::fixed_layout`].
+///
+/// It is declared with the `fixed-layout`, `original-resolution` and (right to left)
+/// `primary-writing-mode` metas of the package metadata, the `fixed-layout` option of
+/// `META-INF/com.apple.ibooks.display-options.xml` and the `page-progression-direction` of
+/// the spine. Each page declares its own size with a `<meta name="viewport">`, as the pages
+/// generated by the [`ComicBuilder`] do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedLayout {
+    /// The width of the pages in pixels.
+    width: u32,
+    /// The height of the pages in pixels.
+    height: u32,
+    /// Whether the pages are turned from right to left (e.g. manga).
+    right_to_left: bool,
+}
+
+impl FixedLayout {
+    /// Creates the layout of a book whose pages are `width` by `height` pixels, turned from left
+    /// to right.
+    #[must_use]
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            right_to_left: false,
+        }
+    }
+
+    /// Sets whether the pages are turned **from right to left** (e.g. manga).
+    ///
+    /// This is a fluent method, returning `Self`.
+    pub fn right_to_left(mut self, right_to_left: bool) -> Self {
+        self.right_to_left = right_to_left;
+        self
+    }
+
+    /// Checks whether the pages are turned from right to left.
+    pub(crate) fn is_right_to_left(&self) -> bool {
+        self.right_to_left
+    }
+
+    /// Gets the `<meta>` elements (name, content) declaring the layout in the package metadata.
+    pub(crate) fn metas(&self) -> Vec<(&'static str, String)> {
+        let mut metas = vec![
+            ("fixed-layout", "true".to_string()),
+            (
+                "original-resolution",
+                format!("{}x{}", self.width, self.height),
+            ),
+        ];
+        if self.right_to_left {
+            metas.push(("primary-writing-mode", "horizontal-rl".to_string()));
+        }
+        metas
+    }
+}
+
+/// A builder of a pre-paginated book from an ordered list of page images, such as a comic or a
+/// manga: every image gets its own page, sized to the image with a `<meta name="viewport">`,
+/// and the book gets a [`FixedLayout`].
+///
+/// [`ComicBuilder::build`] returns an [`EpubBuilder`], so the book can be customized further
+/// (e.g. with a cover image) before being created.
+///
+/// ```rust,no_run
+/// use std::{fs::File, path::Path};
+///
+/// use liber::epub::{ComicBuilder, ImageType, MetadataBuilder, Resource};
+///
+/// let pages = ["001.jpg", "002.jpg", "003.jpg"]
+///     .map(|page| Resource::Image(Path::new(page), ImageType::Jpg));
+///
+/// let mut file = File::create("manga.epub").unwrap();
+/// ComicBuilder::new(MetadataBuilder::title("My Manga").build())
+///     .add_pages(pages)
+///     .right_to_left(true)
+///     .build()
+///     .unwrap()
+///     .create(&mut file)
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct ComicBuilder<'a> {
+    /// The descriptive metadata of the book.
+    metadata: Metadata,
+    /// The page images, in reading order.
+    pages: Vec<ResourceItem<'a>>,
+    /// Optional size of every page in pixels, instead of the size of its image.
+    page_size: Option<(u32, u32)>,
+    /// Whether the pages are turned from right to left.
+    right_to_left: bool,
+}
+
+impl<'a> ComicBuilder<'a> {
+    /// Starts the builder by providing the mandatory descriptive metadata.
+    #[must_use]
+    pub fn new(metadata: Metadata) -> Self {
+        Self {
+            metadata,
+            pages: Vec::new(),
+            page_size: None,
+            right_to_left: false,
+        }
+    }
+
+    /// Adds a **page image** after the previous ones.
+    ///
+    /// This is a fluent method, returning `Self`.
+    pub fn add_page<R: Into<ResourceItem<'a>>>(mut self, page: R) -> Self {
+        self.pages.push(page.into());
+        self
+    }
+
+    /// Adds **page images** after the previous ones, in order.
+    ///
+    /// This is a fluent method, returning `Self`.
+    pub fn add_pages<I, R>(mut self, pages: I) -> Self
+    where
+        I: IntoIterator<Item = R>,
+        R: Into<ResourceItem<'a>>,
+    {
+        self.pages.extend(pages.into_iter().map(Into::into));
+        self
+    }
+
+    /// Sets the **size of every page** in pixels, the images being scaled to it. By default,
+    /// every page has the size of its image, detected from its header (falling back to
+    /// 1600x2400), and the book the size of the first page.
+    ///
+    /// This is a fluent method, returning `Self`.
+    pub fn page_size(mut self, width: u32, height: u32) -> Self {
+        self.page_size = Some((width, height));
+        self
+    }
+
+    /// Sets whether the pages are turned **from right to left** (e.g. manga).
+    ///
+    /// This is a fluent method, returning `Self`.
+    pub fn right_to_left(mut self, right_to_left: bool) -> Self {
+        self.right_to_left = right_to_left;
+        self
+    }
+
+    /// Generates a page titled `Page N` for every image, with the image attached, and returns
+    /// an [`EpubBuilder`] holding them, with a [`FixedLayout`].
+    ///
+    /// # Errors
+    /// Returns an error if the header of an image cannot be read (when no page size is set) or
+    /// an image has no valid filename.
+    pub fn build(self) -> crate::Result<EpubBuilder<'a>> {
+        let sizes = self
+            .pages
+            .iter()
+            .map(|page| match self.page_size {
+                Some(size) => Ok(size),
+                None => Ok(page_dimensions(page)?.unwrap_or(DEFAULT_PAGE_SIZE)),
+            })
+            .collect::<crate::Result<Vec<_>>>()?;
+        let (width, height) = sizes.first().copied().unwrap_or(DEFAULT_PAGE_SIZE);
+
+        let contents = self
+            .pages
+            .into_iter()
+            .zip(sizes)
+            .enumerate()
+            .map(|(index, (page, size))| {
+                let title = format!("Page {}", index + 1);
+                let body = render_page(&page.filename()?, &title, size);
+                Ok(ContentBuilder::generated(body, ReferenceType::Text(title))
+                    .add_resource(page)
+                    .build())
+            })
+            .collect::<crate::Result<Vec<_>>>()?;
+
+        Ok(EpubBuilder::new(self.metadata)
+            .fixed_layout(FixedLayout::new(width, height).right_to_left(self.right_to_left))
+            .add_contents(contents))
+    }
+}
+
+/// Detects the dimensions of the image of `page` from its header.
+///
+/// # Errors
+/// Returns an error if the image cannot be read.
+fn page_dimensions(page: &ResourceItem<'_>) -> crate::Result<Option<(u32, u32)>> {
+    let mut header = Vec::new();
+    page.file_content()?
+        .bytes
+        .reader()?
+        .take(COPY_CHUNK_SIZE as u64)
+        .read_to_end(&mut header)?;
+    Ok(image_dimensions(&header))
+}
+
+/// Renders a page of a pre-paginated book: an XHTML document of `width` by `height` pixels
+/// declared with a `<meta name="viewport">`, filled by the image at `image_href`.
+fn render_page(image_href: &str, title: &str, (width, height): (u32, u32)) -> String {
+    let title = escape(title);
+
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?><!DOCTYPE html PUBLIC "-//W3C//DTD XHTML 1.1//EN" "http://www.w3.org/TR/xhtml11/DTD/xhtml11.dtd"><html xmlns="http://www.w3.org/1999/xhtml"><head><title>{title}</title><meta name="viewport" content="width={width}, height={height}"/><style type="text/css">html, body {{ margin: 0; padding: 0; width: {width}px; height: {height}px; }} img {{ display: block; }}</style></head><body><div class="page"><img src="{href}" alt="{title}" width="{width}" height="{height}"/></div></body></html>"#,
+        href = escape(image_href),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::epub::{ImageType, MetadataBuilder, Resource};
+
+    #[test]
+    fn test_fixed_layout_metas() {
+        assert_eq!(
+            FixedLayout::new(800, 1200).metas(),
+            [
+                ("fixed-layout", "true".to_string()),
+                ("original-resolution", "800x1200".to_string())
+            ]
+        );
+        let layout = FixedLayout::new(800, 1200).right_to_left(true);
+        assert!(layout.is_right_to_left());
+        assert_eq!(
+            layout.metas().last(),
+            Some(&("primary-writing-mode", "horizontal-rl".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_comic_builder() {
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+        png.extend(600u32.to_be_bytes());
+        png.extend(900u32.to_be_bytes());
+
+        let epub_builder = ComicBuilder::new(MetadataBuilder::title("Comic").build())
+            .add_page(Resource::Raw("p1.png", &png, "image/png".to_string()))
+            .add_page(Resource::Raw(
+                "p2.gif",
+                b"GIF89a\x58\x02\x20\x03",
+                "image/gif".to_string(),
+            ))
+            .right_to_left(true)
+            .build()
+            .unwrap();
+
+        assert_eq!(epub_builder.resource_names(), ["p1.png", "p2.gif"]);
+        assert_eq!(epub_builder.content_count(), 2);
+
+        let page = render_page("images/p1 & 2.png", "Page 1", (600, 900));
+        assert!(page.contains(r#"<meta name="viewport" content="width=600, height=900"/>"#));
+        assert!(page.contains(
+            r#"<img src="images/p1 &amp; 2.png" alt="Page 1" width="600" height="900"/>"#
+        ));
+
+        assert!(
+            ComicBuilder::new(MetadataBuilder::title("Comic").build())
+                .add_page(Resource::Image(
+                    Path::new("/missing/p1.jpg"),
+                    ImageType::Jpg
+                ))
+                .build()
+                .is_err()
+        );
+    }
+}
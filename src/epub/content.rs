@@ -1,7 +1,26 @@
-use std::borrow::Cow;
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    fmt::{self, Write},
+    path::Path,
+};
+
+use quick_xml::escape;
+
+#[cfg(feature = "async")]
+use futures::future;
 
+#[cfg(feature = "async")]
+use crate::epub::{AsyncContentSource, Spawner};
 use crate::{
-    epub::ContentReference,
+    ResultExt,
+    epub::{
+        BookPart, ContentReference, ContentSource, IndexTerm, LinkAnnotation, Metadata,
+        ResourceItem, TextTransform, Typography,
+        captioned::{self, Captioned},
+        content_source::Body,
+        extend_list, external_link, punctuation, unicode,
+    },
     output::{file_content::FileContent, xml},
 };
 
@@ -9,7 +28,7 @@ use crate::{
 ///
 /// Each variant carries a `String` which serves as the **display title** (e.g., "Chapter 1", "Glossary").
 /// The variant name itself maps to a machine-readable type string (e.g., `toc`, `foreword`).
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ReferenceType {
     /// Content preceding the main text, like a thank you section.
     Acknowledgements(String),
@@ -47,6 +66,10 @@ pub enum ReferenceType {
     Toc(String),
 }
 
+/// The guide reference types a book has at most one of: its cover, table of contents and
+/// title page.
+pub(crate) const UNIQUE_GUIDE_TYPES: &[&str] = &["cover", "toc", "title-page"];
+
 impl ReferenceType {
     /// Retrieves the tuple containing the machine-readable **type string** and the **display title**.
     ///
@@ -72,16 +95,113 @@ impl ReferenceType {
             Self::Toc(s) => ("toc", s),
         }
     }
+
+    /// Retrieves the DPUB-ARIA role of this type of content (e.g. `doc-chapter` for
+    /// [`ReferenceType::Text`]), if there is one.
+    pub(crate) fn aria_role(&self) -> Option<&'static str> {
+        match self {
+            Self::Acknowledgements(_) => Some("doc-acknowledgments"),
+            Self::Bibliography(_) => Some("doc-bibliography"),
+            Self::Colophon(_) => Some("doc-colophon"),
+            Self::Dedication(_) => Some("doc-dedication"),
+            Self::Epigraph(_) => Some("doc-epigraph"),
+            Self::Foreword(_) => Some("doc-foreword"),
+            Self::Glossary(_) => Some("doc-glossary"),
+            Self::Index(_) => Some("doc-index"),
+            Self::Notes(_) => Some("doc-endnotes"),
+            Self::Preface(_) => Some("doc-preface"),
+            Self::Text(_) => Some("doc-chapter"),
+            Self::Toc(_) => Some("doc-toc"),
+            Self::Copyright(_)
+            | Self::Cover(_)
+            | Self::Loi(_)
+            | Self::Lot(_)
+            | Self::TitlePage(_) => None,
+        }
+    }
+
+    /// Retrieves the part of the book this type of content belongs to, if it is numbered at all
+    /// (the cover, title page, copyright page, dedication, epigraph, colophon and lists are not).
+    pub(crate) fn book_part(&self) -> Option<BookPart> {
+        match self {
+            Self::Acknowledgements(_) | Self::Foreword(_) | Self::Preface(_) => {
+                Some(BookPart::FrontMatter)
+            }
+            Self::Text(_) => Some(BookPart::BodyMatter),
+            Self::Bibliography(_) | Self::Glossary(_) | Self::Index(_) | Self::Notes(_) => {
+                Some(BookPart::BackMatter)
+            }
+            Self::Colophon(_)
+            | Self::Copyright(_)
+            | Self::Cover(_)
+            | Self::Dedication(_)
+            | Self::Epigraph(_)
+            | Self::Loi(_)
+            | Self::Lot(_)
+            | Self::TitlePage(_)
+            | Self::Toc(_) => None,
+        }
+    }
+
+    /// Gets a mutable reference to the display title.
+    fn title_mut(&mut self) -> &mut String {
+        match self {
+            Self::Acknowledgements(s)
+            | Self::Bibliography(s)
+            | Self::Colophon(s)
+            | Self::Copyright(s)
+            | Self::Cover(s)
+            | Self::Dedication(s)
+            | Self::Epigraph(s)
+            | Self::Foreword(s)
+            | Self::Glossary(s)
+            | Self::Index(s)
+            | Self::Loi(s)
+            | Self::Lot(s)
+            | Self::Notes(s)
+            | Self::Preface(s)
+            | Self::Text(s)
+            | Self::TitlePage(s)
+            | Self::Toc(s) => s,
+        }
+    }
+
+    /// Creates the reference type matching the machine-readable **type string** (see
+    /// [`Self::type_and_title`]), with `title` as display title.
+    ///
+    /// Unknown type strings fall back to [`ReferenceType::Text`].
+    pub(crate) fn from_type_and_title(ref_type: &str, title: String) -> Self {
+        match ref_type {
+            "acknowledgements" => Self::Acknowledgements(title),
+            "bibliography" => Self::Bibliography(title),
+            "colophon" => Self::Colophon(title),
+            "copyright-page" => Self::Copyright(title),
+            "cover" => Self::Cover(title),
+            "dedication" => Self::Dedication(title),
+            "epigraph" => Self::Epigraph(title),
+            "foreword" => Self::Foreword(title),
+            "glossary" => Self::Glossary(title),
+            "index" => Self::Index(title),
+            "loi" => Self::Loi(title),
+            "lot" => Self::Lot(title),
+            "notes" => Self::Notes(title),
+            "preface" => Self::Preface(title),
+            "title-page" => Self::TitlePage(title),
+            "toc" => Self::Toc(title),
+            _ => Self::Text(title),
+        }
+    }
 }
 
 /// Represents a single hierarchical content unit within a document structure.
 ///
 /// This structure can hold raw XHTML body bytes, be nested via `subcontents`,
 /// and reference other content units via `content_references`.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Content<'a> {
-    /// A byte slice containing the raw body of the content (assumed to be XHTML fragments).
-    body: &'a [u8],
+    /// The raw body of the content (assumed to be XHTML fragments), in memory or produced on
+    /// demand by a [`ContentSource`].
+    body: Body<'a>,
     /// The semantic type and display title of this content unit.
     pub(crate) reference_type: ReferenceType,
     /// An optional vector of children, enabling hierarchical (chapter/section) nesting.
@@ -90,17 +210,35 @@ pub struct Content<'a> {
     pub(crate) content_references: Option<Vec<ContentReference>>,
     /// An optional, user-defined filename. If `None`, a sequential name is generated.
     filename: Option<String>,
+    /// An optional label of the table of contents entry. If `None`, the title is used.
+    toc_label: Option<String>,
+    /// Optional figures of the body registered by the caller, for the list of illustrations.
+    figures: Option<Vec<Captioned>>,
+    /// Optional tables of the body registered by the caller, for the list of tables.
+    tables: Option<Vec<Captioned>>,
+    /// Optional terms of the back-of-book index tagged at anchors of the body.
+    index_terms: Option<Vec<IndexTerm>>,
+    /// Whether the body starts on a new page. If `None`, the setting of the book applies.
+    page_break: Option<bool>,
+    /// Optional resources used by the body, registered when the content is added to the book.
+    resources: Option<Vec<ResourceItem<'a>>>,
 }
 
 impl<'a> Content<'a> {
     /// Creates a new `Content` instance with mandatory fields and uninitialized optional fields.
-    fn new(body: &'a [u8], reference_type: ReferenceType) -> Self {
+    fn new(body: Body<'a>, reference_type: ReferenceType) -> Self {
         Self {
             body,
             reference_type,
             subcontents: None,
             content_references: None,
             filename: None,
+            toc_label: None,
+            figures: None,
+            tables: None,
+            index_terms: None,
+            page_break: None,
+            resources: None,
         }
     }
 
@@ -129,69 +267,145 @@ impl<'a> Content<'a> {
         content_references_level.max(subcontents_cont_ref_level)
     }
 
-    /// Recursively converts this content unit and all subcontents into a vector of [`FileContent`] structs.
-    ///
-    /// This handles serialization to final XHTML files and assigns sequential filenames.
+    /// Checks whether the body of this content unit, or of any of its subcontents, contains `text`
+    /// (e.g. the filename of a resource it links to).
     ///
-    /// # Arguments
-    /// * `number`: A mutable counter to generate sequential filenames.
-    /// * `add_stylesheet`: Flag to include a CSS link in the generated XHTML header.
+    /// Bodies read from files or produced on demand by a [`ContentSource`] are not loaded here,
+    /// and are assumed to mention it.
+    pub(crate) fn mentions(&self, text: &str) -> bool {
+        self.body
+            .bytes()
+            .is_none_or(|body| String::from_utf8_lossy(body).contains(text))
+            || self
+                .subcontents
+                .as_ref()
+                .is_some_and(|subcontents| subcontents.iter().any(|content| content.mentions(text)))
+    }
+
+    /// Gets the number preceding the first file of every content unit of `contents`, counting
+    /// from `number`, so the units can be rendered independently (e.g. concurrently) with the
+    /// same sequential filenames.
+    pub(crate) fn first_numbers(contents: &[Content<'_>], number: usize) -> Vec<usize> {
+        contents
+            .iter()
+            .scan(number, |number, content| {
+                let first_number = *number;
+                *number += content.file_count();
+                Some(first_number)
+            })
+            .collect()
+    }
+
+    /// Counts the XHTML files produced by this content unit: itself and all its subcontents, recursively.
+    pub(crate) fn file_count(&self) -> usize {
+        1 + self.subcontents.as_ref().map_or(0, |subcontents| {
+            subcontents.iter().map(Content::file_count).sum()
+        })
+    }
+
+    /// Converts this content unit alone (without its subcontents) into the [`FileContent`] of
+    /// its XHTML file, numbered `number`.
     ///
     /// # Errors
-    /// Returns a [`crate::Result`] if the body is not valid UTF-8 or if XML formatting fails.
-    pub(crate) fn file_content(
+    /// Returns a [`crate::Result`] if the body cannot be produced by its source, is not valid
+    /// UTF-8 or if XML formatting fails.
+    pub(crate) fn render(
         &self,
-        number: &mut usize,
-        add_stylesheet: bool,
-    ) -> crate::Result<Vec<FileContent<String, String>>> {
-        *number += 1;
-        let filepath = format!("OEBPS/{}", self.filename(*number));
-        let mut file_contents = Vec::new();
-
-        let xhtml_content =
-            xml::format(&self.xhtml(std::str::from_utf8(self.body)?, add_stylesheet))?;
-
-        file_contents.push(FileContent::new(filepath, xhtml_content));
+        number: usize,
+        options: XhtmlOptions<'_>,
+    ) -> crate::Result<FileContent<String, String>> {
+        let filepath = format!("OEBPS/{}", self.filename(number));
+
+        let xhtml_content = self
+            .body
+            .load(self.title())
+            .and_then(|body| {
+                let xhtml = self.xhtml(std::str::from_utf8(&body)?, options);
+                if options.pretty_print {
+                    xml::format(&xhtml)
+                } else {
+                    Ok(xhtml.into_owned())
+                }
+            })
+            .with_context(|| self.error_context(&filepath))?;
+
+        Ok(FileContent::new(filepath, xhtml_content))
+    }
 
-        if let Some(ref subcontents) = self.subcontents {
-            for content in subcontents {
-                let contents = content.file_content(number, add_stylesheet)?;
-                file_contents.extend(contents);
+    /// Lists `contents` and their subcontents, recursively and in reading order, with the
+    /// numbers of their files, counting from the one after `number`.
+    pub(crate) fn documents<'c>(
+        contents: &'c [Content<'a>],
+        number: usize,
+    ) -> Vec<(&'c Content<'a>, usize)> {
+        fn push<'c, 'a>(
+            contents: &'c [Content<'a>],
+            number: &mut usize,
+            documents: &mut Vec<(&'c Content<'a>, usize)>,
+        ) {
+            for content in contents {
+                *number += 1;
+                documents.push((content, *number));
+                push(content.children(), number, documents);
             }
         }
-        Ok(file_contents)
+
+        let mut number = number;
+        let mut documents = Vec::new();
+        push(contents, &mut number, &mut documents);
+        documents
     }
 
     /// Asynchronously converts content and subcontents into a vector of [`FileContent`] structs.
     ///
+    /// The subcontents are rendered concurrently, keeping their order.
+    ///
     /// This method requires the **`async` feature** to be enabled.
     #[cfg(feature = "async")]
     pub(crate) async fn async_file_content(
         &self,
         number: &mut usize,
-        add_stylesheet: bool,
+        options: XhtmlOptions<'_>,
+        spawner: Option<&dyn Spawner>,
     ) -> crate::Result<Vec<FileContent<String, String>>> {
         *number += 1;
         let filepath = format!("OEBPS/{}", self.filename(*number));
         let mut file_contents = Vec::new();
 
-        let xhtml_content = xml::async_format(
-            self.xhtml(std::str::from_utf8(self.body)?, add_stylesheet)
-                .into_owned(),
-        )
-        .await?;
-
-        file_contents.push(FileContent::new(filepath.to_string(), xhtml_content));
-
-        if let Some(ref subcontents) = self.subcontents {
-            for content in subcontents {
-                let contents = content.file_content(number, add_stylesheet)?;
-                file_contents.extend(contents);
+        let xhtml_content = async {
+            let body = self.body.async_load(spawner).await?;
+            let xhtml = self
+                .xhtml(std::str::from_utf8(&body)?, options)
+                .into_owned();
+            if options.pretty_print {
+                xml::async_format(xhtml, spawner).await
+            } else {
+                Ok(xhtml)
             }
         }
+        .await
+        .with_context(|| self.error_context(&filepath))?;
+
+        file_contents.push(FileContent::new(filepath, xhtml_content));
+
+        let subcontents = self.children();
+        let renders = subcontents
+            .iter()
+            .zip(Content::first_numbers(subcontents, *number))
+            .map(|(content, mut number)| async move {
+                Box::pin(content.async_file_content(&mut number, options, spawner)).await
+            });
+        file_contents.extend(future::try_join_all(renders).await?.into_iter().flatten());
+        *number += subcontents.iter().map(Content::file_count).sum::<usize>();
+
         Ok(file_contents)
     }
 
+    /// Identifies this content unit and its file in the errors of its generation.
+    fn error_context(&self, filepath: &str) -> String {
+        format!("content '{}' ({filepath})", self.title())
+    }
+
     /// Gets the final output filename for this content unit.
     ///
     /// If `filename` is set, it uses that; otherwise, it formats a sequential name like `c01.xhtml`.
@@ -203,44 +417,532 @@ impl<'a> Content<'a> {
         }
     }
 
+    /// Gets the path from the directory of this content unit's file back to the `OEBPS`
+    /// directory: `../` for every subdirectory of a custom filename (e.g. `Text/c01.xhtml`
+    /// read from an existing EPUB), or an empty string.
+    pub(crate) fn root_path(&self) -> String {
+        let depth = self
+            .filename
+            .as_deref()
+            .map_or(0, |filename| filename.matches('/').count());
+        "../".repeat(depth)
+    }
+
+    /// Gets the filename of this content unit like [`Self::filename`], writing the generated
+    /// ones into `buffer` (cleared first) instead of allocating a new string every time.
+    pub(crate) fn filename_in<'s>(&'s self, number: usize, buffer: &'s mut String) -> &'s str {
+        if let Some(ref filename) = self.filename {
+            filename
+        } else {
+            buffer.clear();
+            let _ = write!(buffer, "c{number:02}.xhtml");
+            buffer
+        }
+    }
+
+    /// Visits this content unit and its subcontents, recursively and in reading order, with the
+    /// filenames they will be generated with.
+    ///
+    /// # Arguments
+    /// * `number`: A mutable counter to generate sequential filenames.
+    /// * `visit`: The function called with every content unit and its filename.
+    pub(crate) fn walk<'s>(
+        &'s self,
+        number: &mut usize,
+        visit: &mut dyn FnMut(&'s Content<'a>, String),
+    ) {
+        *number += 1;
+        visit(self, self.filename(*number).into_owned());
+        for content in self.children() {
+            content.walk(number, visit);
+        }
+    }
+
+    /// Writes this content unit, its content references and its subcontents as an indented tree
+    /// (one line per entry), naming the files as they will be generated.
+    ///
+    /// # Arguments
+    /// * `number`: A mutable counter to generate sequential filenames.
+    /// * `indent`: The nesting level of this content unit.
+    pub(crate) fn write_tree(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        number: &mut usize,
+        indent: usize,
+    ) -> fmt::Result {
+        *number += 1;
+        let filename = self.filename(*number);
+        let (ref_type, title) = self.reference_type.type_and_title();
+        writeln!(
+            f,
+            "{:indent$}{filename} [{ref_type}] {title}",
+            "",
+            indent = indent * 2
+        )?;
+
+        let mut link_number = 0;
+        for content_reference in self.content_references() {
+            content_reference.write_tree(f, &filename, &mut link_number, indent + 1)?;
+        }
+        for content in self.children() {
+            content.write_tree(f, number, indent + 1)?;
+        }
+        Ok(())
+    }
+
+    /// Converts the title, the filename and the content references of this content unit, and of
+    /// its subcontents, to Unicode Normalization Form C.
+    ///
+    /// The body is normalized when the XHTML is generated (see [`XhtmlOptions`]).
+    pub(crate) fn normalize_unicode(&mut self) {
+        unicode::nfc(self.reference_type.title_mut());
+        self.filename.iter_mut().for_each(unicode::nfc);
+        self.toc_label.iter_mut().for_each(unicode::nfc);
+        self.content_references
+            .iter_mut()
+            .flatten()
+            .for_each(ContentReference::normalize_unicode);
+        self.subcontents
+            .iter_mut()
+            .flatten()
+            .for_each(Content::normalize_unicode);
+    }
+
+    /// Gets the raw body of this content unit (an XHTML fragment), if it is in memory rather
+    /// than read from a file or produced on demand by a [`ContentSource`].
+    pub(crate) fn body(&self) -> Option<&[u8]> {
+        self.body.bytes()
+    }
+
+    /// Gets the figures of this content unit, for the list of illustrations: the ones found in
+    /// its body (if in memory) and the registered ones (see [`ContentBuilder::add_figure`]).
+    pub(crate) fn figures(&self) -> Vec<Captioned> {
+        let found = self.body().map(captioned::figures).unwrap_or_default();
+        captioned::merge(found, self.figures.as_deref().unwrap_or_default())
+    }
+
+    /// Gets the tables of this content unit, for the list of tables: the ones found in its body
+    /// (if in memory) and the registered ones (see [`ContentBuilder::add_table`]).
+    pub(crate) fn tables(&self) -> Vec<Captioned> {
+        let found = self.body().map(captioned::tables).unwrap_or_default();
+        captioned::merge(found, self.tables.as_deref().unwrap_or_default())
+    }
+
+    /// Gets the terms of the back-of-book index tagged in this content unit (see
+    /// [`ContentBuilder::add_index_term`]).
+    pub fn index_terms(&self) -> &[IndexTerm] {
+        self.index_terms.as_deref().unwrap_or_default()
+    }
+
+    /// Checks whether the body of this content unit was generated by liber (e.g. a list of
+    /// illustrations).
+    pub(crate) fn is_generated(&self) -> bool {
+        matches!(self.body, Body::Generated(_))
+    }
+
+    /// Checks that the file of this content unit, if its body is read from a file, exists.
+    pub(crate) fn body_problem(&self) -> Option<crate::Error> {
+        self.body.problem()
+    }
+
+    /// Takes the resources attached to this content unit and its subcontents, recursively and
+    /// in order (see [`ContentBuilder::add_resource`]).
+    pub(crate) fn take_resources(&mut self) -> Vec<ResourceItem<'a>> {
+        let mut resources = self.resources.take().unwrap_or_default();
+        for content in self.subcontents.iter_mut().flatten() {
+            resources.extend(content.take_resources());
+        }
+        resources
+    }
+
     /// Gets the display title of this content unit from its `ReferenceType`.
-    pub(crate) fn title(&self) -> &str {
+    pub fn title(&self) -> &str {
         self.reference_type.type_and_title().1
     }
 
+    /// Gets the label of the table of contents entry of this content unit, which defaults to
+    /// its title.
+    pub fn toc_label(&self) -> &str {
+        self.toc_label.as_deref().unwrap_or_else(|| self.title())
+    }
+
+    /// Gets the semantic type (and display title) of this content unit.
+    pub fn reference_type(&self) -> &ReferenceType {
+        &self.reference_type
+    }
+
+    /// Gets the custom filename of this content unit, if one was set (see
+    /// [`ContentBuilder::filename`]).
+    pub fn custom_filename(&self) -> Option<&str> {
+        self.filename.as_deref()
+    }
+
+    /// Gets the children (subcontents) of this content unit.
+    pub fn children(&self) -> &[Content<'a>] {
+        self.subcontents.as_deref().unwrap_or_default()
+    }
+
+    /// Gets the content references (table of contents entries inside the body) of this
+    /// content unit.
+    pub fn content_references(&self) -> &[ContentReference] {
+        self.content_references.as_deref().unwrap_or_default()
+    }
+
+    /// Gets the anchor ids of all the content references of this content unit, recursively and
+    /// in order: the custom ones, or the generated `id01`, `id02`...
+    pub(crate) fn anchors(&self) -> Vec<String> {
+        let mut anchors = Vec::new();
+        let mut link_number = 0;
+        for content_reference in self.content_references() {
+            content_reference.anchors(&mut link_number, &mut anchors);
+        }
+        anchors
+    }
+
+    /// Gets the anchor ids used by more than one content reference of this content unit (e.g.
+    /// a custom `id02` colliding with the one generated for the second reference), once each.
+    pub(crate) fn duplicate_anchors(&self) -> Vec<String> {
+        let mut seen = HashSet::new();
+        let mut duplicates = Vec::new();
+        for anchor in self.anchors() {
+            if !seen.insert(anchor.clone()) && !duplicates.contains(&anchor) {
+                duplicates.push(anchor);
+            }
+        }
+        duplicates
+    }
+
     /// Wraps the content body and necessary boilerplate into a complete XHTML 1.1 document string.
-    fn xhtml(&self, text: &'a str, add_stylesheet: bool) -> Cow<'a, str> {
+    ///
+    /// Bodies that are already complete documents (starting with an XML declaration) are kept as is.
+    fn xhtml<'t>(&self, text: &'t str, options: XhtmlOptions<'_>) -> Cow<'t, str> {
+        let text = if options.unicode_normalization {
+            unicode::nfc_str(text)
+        } else {
+            Cow::Borrowed(text)
+        };
+        let text = match options.resource_aliases {
+            Some(aliases) => Cow::Owned(alias_resources(&text, aliases)),
+            None => text,
+        };
+
         if !text.starts_with(r#"<?xml version="1.0" encoding="utf-8"?>"#) {
-            let stylesheet = if add_stylesheet {
-                r#"<link href="style.css" rel="stylesheet" type="text/css"/>"#
+            let stylesheet = if options.add_stylesheet {
+                format!(
+                    r#"<link href="{}style.css" rel="stylesheet" type="text/css"/>"#,
+                    self.root_path()
+                )
+            } else {
+                String::new()
+            };
+            let text = options
+                .text_transforms
+                .iter()
+                .fold(text, |text, transform| Cow::Owned(transform.apply(&text)));
+            let text = if options.smart_punctuation {
+                Cow::Owned(punctuation::smarten(&text))
+            } else {
+                text
+            };
+            let text = match options.typography {
+                Some(typography) if typography.applies_to(&self.reference_type) => {
+                    Cow::Owned(typography.apply(&text))
+                }
+                _ => text,
+            };
+            let text = match options.external_links {
+                Some(annotation) => Cow::Owned(external_link::annotate(&text, annotation)),
+                None => text,
+            };
+            let page_break = self.page_break.unwrap_or(
+                options.page_breaks && matches!(self.reference_type, ReferenceType::Text(_)),
+            );
+            let text = if page_break {
+                Cow::Owned(page_break_body(&text))
             } else {
-                ""
+                text
+            };
+            let text = if options.aria_roles {
+                Cow::Owned(self.aria_body(&text))
+            } else {
+                text
             };
+            let dir = options
+                .text_direction
+                .map(|direction| format!(r#" dir="{}""#, direction.as_ref()))
+                .unwrap_or_default();
+            let dublin_core = options
+                .dublin_core
+                .map(dublin_core_meta)
+                .unwrap_or_default();
 
             Cow::Owned(format!(
                 r#"<?xml version="1.0" encoding="utf-8"?><!DOCTYPE html PUBLIC "-//W3C//DTD XHTML 1.1//EN" "http://www.w3.org/TR/xhtml11/DTD/xhtml11.dtd">
-            <html xmlns="http://www.w3.org/1999/xhtml"><head><title>{}</title>{}</head>{}</html>"#,
+            <html xmlns="http://www.w3.org/1999/xhtml"{}><head><title>{}</title>{}{}</head>{}</html>"#,
+                dir,
                 self.title(),
+                dublin_core,
                 stylesheet,
                 text
             ))
         } else {
-            Cow::Borrowed(text)
+            text
         }
     }
+
+    /// Adds DPUB-ARIA roles to a body: notes marked with `epub:type` get their role (see
+    /// [`note_roles`]), and the content of the `<body>` is wrapped in a `<section>` with the role
+    /// of the reference type (e.g. `doc-chapter`), labelled with the title.
+    fn aria_body(&self, text: &str) -> String {
+        let text = note_roles(text);
+        let Some(role) = self.reference_type.aria_role() else {
+            return text;
+        };
+
+        let start = text
+            .find("<body")
+            .and_then(|start| Some(start + text[start..].find('>')? + 1));
+        match (start, text.rfind("</body>")) {
+            (Some(start), Some(end)) if start <= end && !text[..start].ends_with("/>") => format!(
+                r#"{}<section role="{role}" aria-label="{}">{}</section>{}"#,
+                &text[..start],
+                escape::escape(self.title()),
+                &text[start..end],
+                &text[end..]
+            ),
+            _ => text,
+        }
+    }
+}
+
+/// Points the `src`, `href` (or `xlink:href`) and `poster` attributes of `text` referencing a
+/// resource dropped as a duplicate at the resource sharing its content.
+fn alias_resources(text: &str, aliases: &HashMap<String, String>) -> String {
+    let mut aliased = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(index) = rest.find(r#"=""#) {
+        let start = index + 2;
+        let Some(length) = rest[start..].find('"') else {
+            break;
+        };
+        aliased.push_str(&rest[..start]);
+
+        let value = &rest[start..start + length];
+        let alias = ["src", "href", "poster"]
+            .iter()
+            .any(|name| rest[..index].ends_with(name))
+            .then(|| escape::unescape(value).ok())
+            .flatten()
+            .and_then(|filename| aliases.get(filename.as_ref()));
+        match alias {
+            Some(alias) => aliased.push_str(&escape::escape(alias)),
+            None => aliased.push_str(value),
+        }
+        rest = &rest[start + length..];
+    }
+    aliased.push_str(rest);
+    aliased
+}
+
+/// Adds `page-break-before: always` and `break-before: page` to the inline CSS of the `<body>`
+/// of `text`, keeping the declarations of a `style` attribute it already has.
+fn page_break_body(text: &str) -> String {
+    const PAGE_BREAK: &str = "page-break-before: always; break-before: page;";
+
+    let Some(start) = text.find("<body") else {
+        return text.to_string();
+    };
+    let Some(length) = text[start..].find('>') else {
+        return text.to_string();
+    };
+    let tag = &text[start..start + length];
+    let tag = tag.strip_suffix('/').unwrap_or(tag);
+    let tag_end = start + tag.len();
+
+    match tag.find(r#"style=""#) {
+        Some(style) => {
+            let index = start + style + r#"style=""#.len();
+            format!("{}{PAGE_BREAK} {}", &text[..index], &text[index..])
+        }
+        None => format!(
+            r#"{} style="{PAGE_BREAK}"{}"#,
+            &text[..tag_end],
+            &text[tag_end..]
+        ),
+    }
+}
+
+/// Adds the DPUB-ARIA role matching the `epub:type` of footnotes (`doc-footnote`), endnote
+/// sections (`doc-endnotes`) and note references (`doc-noteref`) that have no `role` yet.
+fn note_roles(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('<') {
+        let Some(length) = rest[start..].find('>') else {
+            break;
+        };
+        let tag = &rest[start..start + length];
+        let (tag, slash) = tag.strip_suffix('/').map_or((tag, ""), |tag| (tag, "/"));
+
+        result.push_str(&rest[..start]);
+        result.push_str(tag);
+        if let Some(role) = note_role(tag) {
+            let _ = write!(result, r#" role="{role}""#);
+        }
+        result.push_str(slash);
+        result.push('>');
+        rest = &rest[start + length + 1..];
+    }
+
+    result.push_str(rest);
+    result
+}
+
+/// Gets the DPUB-ARIA role of a start tag (without its closing `>`) from its `epub:type`,
+/// unless it already has a `role`.
+fn note_role(tag: &str) -> Option<&'static str> {
+    if tag.contains(" role=") {
+        return None;
+    }
+
+    let value = tag.split_once("epub:type=")?.1;
+    let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    let value = value[1..].split(quote).next()?;
+
+    value
+        .split_whitespace()
+        .find_map(|epub_type| match epub_type {
+            "footnote" => Some("doc-footnote"),
+            "endnotes" => Some("doc-endnotes"),
+            "noteref" => Some("doc-noteref"),
+            _ => None,
+        })
+}
+
+/// Generates the Dublin Core `<meta>` tags (title, creator and language) of `metadata`,
+/// with the `schema.DC` link declaring their vocabulary.
+fn dublin_core_meta(metadata: &Metadata) -> String {
+    let mut tags = r#"<link rel="schema.DC" href="http://purl.org/dc/elements/1.1/"/>"#.to_string();
+    for (name, content) in [
+        ("DC.title", Some(metadata.title.as_str())),
+        ("DC.creator", metadata.creator.as_deref()),
+        ("DC.language", Some(metadata.language.as_ref())),
+    ] {
+        if let Some(content) = content {
+            let _ = write!(
+                tags,
+                r#"<meta name="{name}" content="{}"/>"#,
+                escape::escape(content)
+            );
+        }
+    }
+    tags
+}
+
+/// The base direction of the text, declared with the `dir` attribute of the generated XHTML.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextDirection {
+    /// Left to right (e.g. English, Spanish).
+    Ltr,
+    /// Right to left (e.g. Hebrew, Arabic, Persian).
+    Rtl,
+}
+
+impl AsRef<str> for TextDirection {
+    fn as_ref(&self) -> &str {
+        match self {
+            Self::Ltr => "ltr",
+            Self::Rtl => "rtl",
+        }
+    }
+}
+
+/// The book settings that shape the XHTML file generated for every content.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct XhtmlOptions<'o> {
+    /// Whether to link the stylesheet (`style.css`) in the header.
+    pub add_stylesheet: bool,
+    /// Whether to indent the XHTML (a full XML parse per file).
+    pub pretty_print: bool,
+    /// Whether to add DPUB-ARIA roles (see [`crate::epub::EpubBuilder::aria_roles`]).
+    pub aria_roles: bool,
+    /// The base direction of the text, if declared.
+    pub text_direction: Option<TextDirection>,
+    /// Whether to convert the body to Unicode Normalization Form C.
+    pub unicode_normalization: bool,
+    /// The metadata declared as Dublin Core `<meta>` tags in the header, if enabled (see
+    /// [`crate::epub::EpubBuilder::dublin_core_meta`]).
+    pub dublin_core: Option<&'o Metadata>,
+    /// The typographic touches applied to the body, if set (see
+    /// [`crate::epub::EpubBuilder::typography`]).
+    pub typography: Option<&'o Typography>,
+    /// Whether the chapters start on a new page (see
+    /// [`crate::epub::EpubBuilder::page_breaks`]).
+    pub page_breaks: bool,
+    /// Whether the punctuation of the text nodes is converted to typographic punctuation (see
+    /// [`crate::epub::EpubBuilder::smart_punctuation`]).
+    pub smart_punctuation: bool,
+    /// The transforms of the body, in registration order (see
+    /// [`crate::epub::EpubBuilder::add_text_transform`]).
+    pub text_transforms: &'o [TextTransform],
+    /// How the external links are annotated, if they are (see
+    /// [`crate::epub::EpubBuilder::external_links`]).
+    pub external_links: Option<LinkAnnotation>,
+    /// The filenames of the resources dropped as duplicates, mapped to the filename of the
+    /// resource sharing their content, if any were dropped.
+    pub resource_aliases: Option<&'o HashMap<String, String>>,
 }
 
 /// A builder for creating and configuring hierarchical [`Content`] structures.
 ///
 /// This provides a **fluent interface** to manage children and references.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ContentBuilder<'a>(Content<'a>);
 
 impl<'a> ContentBuilder<'a> {
     /// Creates a new builder instance, initializing the content with the raw body and required type.
     #[must_use]
     pub fn new(body: &'a [u8], reference_type: ReferenceType) -> Self {
-        Self(Content::new(body, reference_type))
+        Self(Content::new(Body::Bytes(body), reference_type))
+    }
+
+    /// Creates a new builder instance whose body was generated by liber (e.g. a list of
+    /// illustrations).
+    pub(crate) fn generated(body: String, reference_type: ReferenceType) -> Self {
+        Self(Content::new(
+            Body::Generated(body.into_bytes().into()),
+            reference_type,
+        ))
+    }
+
+    /// Creates a new builder instance whose body is read from the file at `path` when the EPUB is
+    /// created, instead of being held in memory.
+    ///
+    /// The async API reads the files of all the contents concurrently.
+    #[must_use]
+    pub fn from_path(path: &'a Path, reference_type: ReferenceType) -> Self {
+        Self(Content::new(Body::Path(path), reference_type))
+    }
+
+    /// Creates a new builder instance whose body is produced on demand by `source` when the
+    /// EPUB is created, instead of being held in memory.
+    #[must_use]
+    pub fn from_source(source: &'a dyn ContentSource, reference_type: ReferenceType) -> Self {
+        Self(Content::new(Body::Source(source), reference_type))
+    }
+
+    /// Creates a new builder instance whose body is produced on demand by the async `source`
+    /// when the EPUB is created with the async API.
+    ///
+    /// This method is only available when the **`async` feature** is enabled.
+    #[cfg(feature = "async")]
+    #[must_use]
+    pub fn from_async_source(
+        source: &'a dyn AsyncContentSource,
+        reference_type: ReferenceType,
+    ) -> Self {
+        Self(Content::new(Body::AsyncSource(source), reference_type))
     }
 
     /// Adds a single [`Content`] unit as a **child** (subcontent) of the current unit.
@@ -253,13 +955,11 @@ impl<'a> ContentBuilder<'a> {
         self
     }
 
-    /// Adds a vector of [`Content`] units as **children** (subcontents) of the current unit.
-    pub fn add_children(mut self, contents: Vec<Content<'a>>) -> Self {
-        if let Some(ref mut subcontents) = self.0.subcontents {
-            subcontents.extend(contents);
-        } else {
-            self.0.subcontents = Some(contents);
-        }
+    /// Adds a collection of [`Content`] units as **children** (subcontents) of the current unit.
+    ///
+    /// Accepts any iterable (e.g. a `Vec` or a mapped iterator).
+    pub fn add_children<I: IntoIterator<Item = Content<'a>>>(mut self, contents: I) -> Self {
+        extend_list(&mut self.0.subcontents, contents);
         self
     }
 
@@ -275,22 +975,104 @@ impl<'a> ContentBuilder<'a> {
         self
     }
 
-    /// Adds a vector of [`ContentReference`] structs to the current unit's reference list.
-    pub fn add_content_references(mut self, content_references: Vec<ContentReference>) -> Self {
-        if let Some(ref mut self_content_references) = self.0.content_references {
-            self_content_references.extend(content_references);
-        } else {
-            self.0.content_references = Some(content_references);
-        }
+    /// Adds a collection of [`ContentReference`] structs to the current unit's reference list.
+    ///
+    /// Accepts any iterable (e.g. a `Vec` or a mapped iterator).
+    pub fn add_content_references<I: IntoIterator<Item = ContentReference>>(
+        mut self,
+        content_references: I,
+    ) -> Self {
+        extend_list(&mut self.0.content_references, content_references);
         self
     }
 
+    /// Alias of [`ContentBuilder::add_content_references`].
+    pub fn extend_content_references<I: IntoIterator<Item = ContentReference>>(
+        self,
+        content_references: I,
+    ) -> Self {
+        self.add_content_references(content_references)
+    }
+
     /// Sets a custom **filename** for the final output file corresponding to this content unit.
+    ///
+    /// It may include subdirectories of `OEBPS` (e.g. `Text/c01.xhtml`); the stylesheet is then
+    /// linked relative to that directory.
     pub fn filename<S: Into<String>>(mut self, name: S) -> Self {
         self.0.filename = Some(name.into());
         self
     }
 
+    /// Sets a **label** for the table of contents entry of this content unit, distinct from the
+    /// title of its [`ReferenceType`] (which is still used by the guide and the XHTML `<title>`).
+    ///
+    /// E.g. the guide says `Text` while the table of contents says `Chapter One: The Road`.
+    pub fn toc_label<S: Into<String>>(mut self, label: S) -> Self {
+        self.0.toc_label = Some(label.into());
+        self
+    }
+
+    /// Attaches a **resource** used by the body (e.g. the file of an [`crate::epub::Audio`]),
+    /// registered in the book when the content is added to it, unless it already has it.
+    pub fn add_resource<R: Into<ResourceItem<'a>>>(mut self, resource: R) -> Self {
+        extend_list(&mut self.0.resources, [resource.into()]);
+        self
+    }
+
+    /// Sets whether this content unit starts on a **new page**, overriding
+    /// [`EpubBuilder::page_breaks`] (which only applies to the chapters).
+    pub fn page_break(mut self, page_break: bool) -> Self {
+        self.0.page_break = Some(page_break);
+        self
+    }
+
+    /// Registers a figure of the body, by the `id` of its anchor and its **caption**, for the
+    /// list of illustrations (see [`EpubBuilder::list_of_illustrations`]).
+    ///
+    /// Figures of bodies in memory are found without registering them; this is for the bodies
+    /// read from a file or produced by a [`ContentSource`], or to relabel a found figure.
+    ///
+    /// [`EpubBuilder::list_of_illustrations`]: crate::epub::EpubBuilder::list_of_illustrations
+    pub fn add_figure<I: Into<String>, C: Into<String>>(mut self, id: I, caption: C) -> Self {
+        let figure = Captioned {
+            id: Some(id.into()),
+            label: caption.into(),
+        };
+        extend_list(&mut self.0.figures, [figure]);
+        self
+    }
+
+    /// Registers a table of the body, by the `id` of its anchor and its **caption**, for the
+    /// list of tables (see [`EpubBuilder::list_of_tables`]), like
+    /// [`ContentBuilder::add_figure`].
+    ///
+    /// [`EpubBuilder::list_of_tables`]: crate::epub::EpubBuilder::list_of_tables
+    pub fn add_table<I: Into<String>, C: Into<String>>(mut self, id: I, caption: C) -> Self {
+        let table = Captioned {
+            id: Some(id.into()),
+            label: caption.into(),
+        };
+        extend_list(&mut self.0.tables, [table]);
+        self
+    }
+
+    /// Tags a term of the back-of-book index at an anchor of the body (see
+    /// [`EpubBuilder::index`]).
+    ///
+    /// [`EpubBuilder::index`]: crate::epub::EpubBuilder::index
+    pub fn add_index_term(mut self, index_term: IndexTerm) -> Self {
+        extend_list(&mut self.0.index_terms, [index_term]);
+        self
+    }
+
+    /// Tags a collection of terms of the back-of-book index at anchors of the body.
+    ///
+    /// Accepts any iterable (e.g. a `Vec` or a mapped iterator).
+    pub fn add_index_terms<I: IntoIterator<Item = IndexTerm>>(mut self, index_terms: I) -> Self {
+        extend_list(&mut self.0.index_terms, index_terms);
+        self
+    }
+
     /// Consumes the builder and returns the final [`Content`] instance.
     pub fn build(self) -> Content<'a> {
         self.0
@@ -300,6 +1082,7 @@ impl<'a> ContentBuilder<'a> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::epub::{Language, MetadataBuilder};
 
     fn make_content(body: &'static str, title: &'static str) -> Content<'static> {
         ContentBuilder::new(body.as_bytes(), ReferenceType::Text(title.to_string())).build()
@@ -309,6 +1092,13 @@ mod tests {
         ContentReference::new(title)
     }
 
+    fn pretty_print() -> XhtmlOptions<'static> {
+        XhtmlOptions {
+            pretty_print: true,
+            ..Default::default()
+        }
+    }
+
     #[test]
     fn test_content_builder_add_child() {
         let parent_body = b"parent";
@@ -321,7 +1111,7 @@ mod tests {
 
         let subs = parent_content.subcontents.unwrap();
         assert_eq!(subs.len(), 1);
-        assert_eq!(subs[0].body, b"child");
+        assert_eq!(subs[0].body(), Some(b"child".as_slice()));
     }
 
     #[test]
@@ -389,6 +1179,36 @@ mod tests {
         assert_eq!(parent.level(), 2);
     }
 
+    #[test]
+    fn test_content_eq() {
+        let content = |title: &str| {
+            ContentBuilder::new(b"<body/>", ReferenceType::Text(title.to_string()))
+                .add_content_reference(make_cr("Section").id("section"))
+                .build()
+        };
+
+        assert_eq!(content("One"), content("One"));
+        assert_ne!(content("One"), content("Two"));
+        assert_ne!(
+            content("One"),
+            ContentBuilder::new(b"<body/>", ReferenceType::Text("One".to_string())).build()
+        );
+    }
+
+    #[test]
+    fn test_content_file_count() {
+        let leaf = || ContentBuilder::new(b"", ReferenceType::Text("T".to_string())).build();
+        assert_eq!(leaf().file_count(), 1);
+
+        let child = ContentBuilder::new(b"", ReferenceType::Text("C".to_string()))
+            .add_child(leaf())
+            .build();
+        let parent = ContentBuilder::new(b"", ReferenceType::Text("P".to_string()))
+            .add_children(vec![child, leaf()])
+            .build();
+        assert_eq!(parent.file_count(), 4);
+    }
+
     #[test]
     fn test_level_reference_content_only_content_references() {
         let deep_cr = make_cr("Deep CR").add_child(make_cr("Sub"));
@@ -435,7 +1255,10 @@ mod tests {
         let content = make_content("<body>Content</body>", "Test");
         let expected = r#"<?xml version="1.0" encoding="utf-8"?><!DOCTYPE html PUBLIC "-//W3C//DTD XHTML 1.1//EN" "http://www.w3.org/TR/xhtml11/DTD/xhtml11.dtd">
             <html xmlns="http://www.w3.org/1999/xhtml"><head><title>Test</title></head><body>Content</body></html>"#;
-        assert_eq!(content.xhtml("<body>Content</body>", false), expected);
+        assert_eq!(
+            content.xhtml("<body>Content</body>", XhtmlOptions::default()),
+            expected
+        );
     }
 
     #[test]
@@ -443,36 +1266,76 @@ mod tests {
         let content = make_content("<body>Content</body>", "Test");
         let expected = r#"<?xml version="1.0" encoding="utf-8"?><!DOCTYPE html PUBLIC "-//W3C//DTD XHTML 1.1//EN" "http://www.w3.org/TR/xhtml11/DTD/xhtml11.dtd">
             <html xmlns="http://www.w3.org/1999/xhtml"><head><title>Test</title><link href="style.css" rel="stylesheet" type="text/css"/></head><body>Content</body></html>"#;
-        assert_eq!(content.xhtml("<body>Content</body>", true), expected);
+        assert_eq!(
+            content.xhtml(
+                "<body>Content</body>",
+                XhtmlOptions {
+                    add_stylesheet: true,
+                    ..Default::default()
+                }
+            ),
+            expected
+        );
     }
 
     #[test]
-    fn test_content_file_content_no_subcontents() {
+    fn test_content_render() {
         let content = make_content("body text", "Chapter 1");
-        let mut number = 0;
-        let files = content.file_content(&mut number, false).unwrap();
+        let file = content.render(1, pretty_print()).unwrap();
 
-        assert_eq!(number, 1);
-        assert_eq!(files.len(), 1);
-        assert_eq!(files[0].filepath, "OEBPS/c01.xhtml");
+        assert_eq!(file.filepath, "OEBPS/c01.xhtml");
+        assert!(file.bytes.contains("<title>Chapter 1</title>"));
+        assert!(file.bytes.contains("body text"));
+    }
 
-        assert!(files[0].bytes.contains("<title>Chapter 1</title>"));
-        assert!(files[0].bytes.contains("body text"));
+    #[test]
+    fn test_content_render_from_source() {
+        let calls = std::sync::atomic::AtomicUsize::new(0);
+        let source = || {
+            calls.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            Ok(b"<body><p>generated</p></body>".to_vec())
+        };
+        let content =
+            ContentBuilder::from_source(&source, ReferenceType::Text("Chapter 1".to_string()))
+                .build();
+        assert_eq!(calls.load(std::sync::atomic::Ordering::Relaxed), 0);
+        assert!(content.body().is_none());
+        assert!(content.mentions("map.png"));
+
+        let file = content.render(1, pretty_print()).unwrap();
+        assert!(file.bytes.contains("<p>generated</p>"));
+        assert_eq!(calls.load(std::sync::atomic::Ordering::Relaxed), 1);
+
+        let failing = || Err(crate::Error::InvalidEpub("query failed".to_string()));
+        let content =
+            ContentBuilder::from_source(&failing, ReferenceType::Text("Chapter 2".to_string()))
+                .build();
+        assert!(content.render(1, pretty_print()).is_err());
     }
 
     #[test]
-    fn test_content_file_content_with_subcontents() {
+    fn test_content_documents_with_subcontents() {
         let child1 = make_content("c1", "Section 1.1");
         let child2 = make_content("c2", "Section 1.2");
         let parent = ContentBuilder::new(b"p", ReferenceType::Text("Chapter 1".to_string()))
             .add_child(child1)
             .add_child(child2)
             .build();
-
-        let mut number = 0;
-        let files = parent.file_content(&mut number, false).unwrap();
-
-        assert_eq!(number, 3);
+        let numbers = |contents, number| {
+            Content::documents(contents, number)
+                .into_iter()
+                .map(|(content, number)| (content.title(), number))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            numbers(std::slice::from_ref(&parent), 4),
+            [("Chapter 1", 5), ("Section 1.1", 6), ("Section 1.2", 7)]
+        );
+
+        let files = Content::documents(std::slice::from_ref(&parent), 0)
+            .into_iter()
+            .map(|(content, number)| content.render(number, pretty_print()).unwrap())
+            .collect::<Vec<_>>();
         assert_eq!(files.len(), 3);
 
         assert_eq!(files[0].filepath, "OEBPS/c01.xhtml");
@@ -483,4 +1346,144 @@ mod tests {
         assert!(files[1].bytes.contains("<title>Section 1.1</title>"));
         assert!(files[2].bytes.contains("<title>Section 1.2</title>"));
     }
+
+    #[test]
+    fn test_content_xhtml_aria_roles() {
+        let options = XhtmlOptions {
+            aria_roles: true,
+            ..Default::default()
+        };
+
+        let chapter = make_content("", "Tom & Jerry");
+        let xhtml = chapter.xhtml(
+            r##"<body class="x"><p>Text<a epub:type="noteref" href="#n1">1</a></p><aside epub:type="footnote" id="n1"/><aside epub:type='footnote' role="note"/></body>"##,
+            options,
+        );
+        assert!(xhtml.ends_with(
+            r##"<body class="x"><section role="doc-chapter" aria-label="Tom &amp; Jerry"><p>Text<a epub:type="noteref" href="#n1" role="doc-noteref">1</a></p><aside epub:type="footnote" id="n1" role="doc-footnote"/><aside epub:type='footnote' role="note"/></section></body></html>"##
+        ));
+
+        let title_page =
+            ContentBuilder::new(b"", ReferenceType::TitlePage("Title".to_string())).build();
+        assert!(
+            title_page
+                .xhtml("<body><h1>Title</h1></body>", options)
+                .ends_with("<head><title>Title</title></head><body><h1>Title</h1></body></html>")
+        );
+
+        let document = r#"<?xml version="1.0" encoding="utf-8"?><html><body/></html>"#;
+        assert_eq!(chapter.xhtml(document, options), document);
+    }
+
+    #[test]
+    fn test_content_xhtml_dublin_core_meta() {
+        let metadata = MetadataBuilder::title("Tom & Jerry")
+            .creator("Author")
+            .language(Language::French)
+            .build();
+        let options = XhtmlOptions {
+            dublin_core: Some(&metadata),
+            ..Default::default()
+        };
+
+        let xhtml = make_content("", "Chapter 1").xhtml("<body/>", options);
+        assert!(xhtml.contains(
+            r#"<title>Chapter 1</title><link rel="schema.DC" href="http://purl.org/dc/elements/1.1/"/><meta name="DC.title" content="Tom &amp; Jerry"/><meta name="DC.creator" content="Author"/><meta name="DC.language" content="fr"/></head>"#
+        ));
+    }
+
+    #[test]
+    fn test_content_xhtml_text_direction() {
+        let content = make_content("", "שלום");
+        let xhtml = content.xhtml(
+            "<body><p>שלום</p></body>",
+            XhtmlOptions {
+                text_direction: Some(TextDirection::Rtl),
+                ..Default::default()
+            },
+        );
+        assert!(xhtml.contains(r#"<html xmlns="http://www.w3.org/1999/xhtml" dir="rtl"><head>"#));
+    }
+
+    #[test]
+    fn test_content_xhtml_smart_punctuation() {
+        let xhtml = make_content("", "Chapter 1").xhtml(
+            r#"<body><p class="x">"Wait..." -- <code>"y"</code></p></body>"#,
+            XhtmlOptions {
+                smart_punctuation: true,
+                ..Default::default()
+            },
+        );
+        assert!(
+            xhtml.ends_with(r#"<body><p class="x">“Wait…” — <code>"y"</code></p></body></html>"#)
+        );
+    }
+
+    #[test]
+    fn test_content_xhtml_text_transforms() {
+        let text_transforms = [
+            TextTransform::new("strip", |body| body.replace("<!-- ed -->", "")),
+            TextTransform::new("quote", |body| body.replace("<em>", "<em>\"")),
+        ];
+        let xhtml = make_content("", "Chapter 1").xhtml(
+            "<body><p><!-- ed --><em>Hi</em></p></body>",
+            XhtmlOptions {
+                smart_punctuation: true,
+                text_transforms: &text_transforms,
+                ..Default::default()
+            },
+        );
+        assert!(xhtml.ends_with("<body><p><em>“Hi</em></p></body></html>"));
+    }
+
+    #[test]
+    fn test_content_xhtml_page_breaks() {
+        let options = XhtmlOptions {
+            page_breaks: true,
+            ..Default::default()
+        };
+
+        let chapter = make_content("", "Chapter 1");
+        assert!(
+            chapter.xhtml("<body/>", options).ends_with(
+                r#"<body style="page-break-before: always; break-before: page;"/></html>"#
+            )
+        );
+        assert!(
+            chapter
+                .xhtml(r#"<body class="x" style="color: red"><p/></body>"#, options)
+                .ends_with(r#"<body class="x" style="page-break-before: always; break-before: page; color: red"><p/></body></html>"#)
+        );
+        assert!(
+            chapter
+                .xhtml("<body><p/></body>", XhtmlOptions::default())
+                .ends_with("<body><p/></body></html>")
+        );
+
+        let preface = ContentBuilder::new(b"", ReferenceType::Preface("Preface".to_string()));
+        assert!(
+            preface
+                .clone()
+                .build()
+                .xhtml("<body></body>", options)
+                .ends_with("<body></body></html>")
+        );
+        assert!(
+            preface
+                .page_break(true)
+                .build()
+                .xhtml("<body></body>", XhtmlOptions::default())
+                .ends_with(
+                    r#"<body style="page-break-before: always; break-before: page;"></body></html>"#
+                )
+        );
+        let chapter = ContentBuilder::new(b"", ReferenceType::Text("One".to_string()))
+            .page_break(false)
+            .build();
+        assert!(
+            chapter
+                .xhtml("<body></body>", options)
+                .ends_with("<body></body></html>")
+        );
+    }
 }
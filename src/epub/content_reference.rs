@@ -1,7 +1,11 @@
+use std::{borrow::Cow, fmt};
+
+use crate::epub::{extend_list, unicode};
+
 /// Represents a single entry in a hierarchical list of references (e.g., a Table of Contents entry).
 ///
 /// This structure links a title to a specific location (via `id`) and supports nested sub-references.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ContentReference {
     /// The display title for this reference entry (e.g., "Section 1.1: The Beginning").
     pub(crate) title: String,
@@ -45,18 +49,33 @@ impl ContentReference {
         self
     }
 
-    /// Adds a vector of [`ContentReference`] structs as nested **children**.
+    /// Adds a collection of [`ContentReference`] structs as nested **children**.
     ///
+    /// Accepts any iterable (e.g. a `Vec` or a mapped iterator).
     /// This is a fluent method, returning `Self`.
-    pub fn add_children(mut self, content_references: Vec<ContentReference>) -> Self {
-        if let Some(ref mut subcontent_references) = self.subcontent_references {
-            subcontent_references.extend(content_references);
-        } else {
-            self.subcontent_references = Some(content_references);
-        }
+    pub fn add_children<I: IntoIterator<Item = ContentReference>>(
+        mut self,
+        content_references: I,
+    ) -> Self {
+        extend_list(&mut self.subcontent_references, content_references);
         self
     }
 
+    /// Gets the display title of this reference.
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// Gets the anchor ID of this reference, if one was set (see [`ContentReference::id`]).
+    pub fn anchor_id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    /// Gets the nested children of this reference.
+    pub fn children(&self) -> &[ContentReference] {
+        self.subcontent_references.as_deref().unwrap_or_default()
+    }
+
     /// Recursively calculates the maximum nesting depth of subcontent references.
     ///
     /// Returns `0` for leaf nodes.
@@ -68,6 +87,81 @@ impl ContentReference {
             })
     }
 
+    /// Converts the title and anchor id of this reference, and of its children, to Unicode
+    /// Normalization Form C.
+    pub(crate) fn normalize_unicode(&mut self) {
+        unicode::nfc(&mut self.title);
+        self.id.iter_mut().for_each(unicode::nfc);
+        self.subcontent_references
+            .iter_mut()
+            .flatten()
+            .for_each(ContentReference::normalize_unicode);
+    }
+
+    /// Counts this reference and all its children, recursively.
+    pub(crate) fn count(&self) -> usize {
+        1 + self
+            .subcontent_references
+            .iter()
+            .flatten()
+            .map(ContentReference::count)
+            .sum::<usize>()
+    }
+
+    /// Writes this reference and its children as an indented tree (one line per entry), naming
+    /// the anchors as they will be generated.
+    ///
+    /// # Arguments
+    /// * `xhtml`: The filename of the content unit this reference points to.
+    /// * `link_number`: A mutable counter to generate the default anchor IDs.
+    /// * `indent`: The nesting level of this reference.
+    pub(crate) fn write_tree(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        xhtml: &str,
+        link_number: &mut usize,
+        indent: usize,
+    ) -> fmt::Result {
+        *link_number += 1;
+        writeln!(
+            f,
+            "{:indent$}{} {}",
+            "",
+            self.reference_name(xhtml, *link_number),
+            self.title,
+            indent = indent * 2
+        )?;
+        for content_reference in self.children() {
+            content_reference.write_tree(f, xhtml, link_number, indent + 1)?;
+        }
+        Ok(())
+    }
+
+    /// Collects the anchor IDs of this reference and its children, recursively, in the order
+    /// they are numbered.
+    ///
+    /// # Arguments
+    /// * `link_number`: A mutable counter to generate the default anchor IDs.
+    /// * `anchors`: The list the anchor IDs are appended to.
+    pub(crate) fn anchors(&self, link_number: &mut usize, anchors: &mut Vec<String>) {
+        *link_number += 1;
+        anchors.push(self.anchor(*link_number).into_owned());
+        for content_reference in self.children() {
+            content_reference.anchors(link_number, anchors);
+        }
+    }
+
+    /// Gets the anchor ID of this reference: the custom `id`, or a sequential one like `id01`.
+    ///
+    /// # Arguments
+    /// * `number`: A sequential number used for generating a default anchor ID if `self.id` is `None`.
+    pub(crate) fn anchor(&self, number: usize) -> Cow<'_, str> {
+        match self.id {
+            Some(ref id) => Cow::Borrowed(id),
+            None => Cow::Owned(format!("id{number:02}")),
+        }
+    }
+
     /// Generates the full file-path anchor string for this reference.
     ///
     /// It combines the provided XHTML filename with either the custom `id` or a sequential one.
@@ -76,10 +170,7 @@ impl ContentReference {
     /// * `xhtml`: The base filename (e.g., `c01.xhtml`) this reference points to.
     /// * `number`: A sequential number used for generating a default anchor ID if `self.id` is `None`.
     pub(crate) fn reference_name(&self, xhtml: &str, number: usize) -> String {
-        self.id
-            .as_ref()
-            .map(|id| format!("{xhtml}#{id}"))
-            .unwrap_or(format!("{xhtml}#id{number:02}"))
+        format!("{xhtml}#{}", self.anchor(number))
     }
 }
 
@@ -0,0 +1,264 @@
+use std::{borrow::Cow, fs, path::Path, sync::Arc};
+
+#[cfg(feature = "async")]
+use futures::future::BoxFuture;
+
+#[cfg(feature = "async")]
+use crate::epub::{Spawner, run_blocking};
+
+/// A provider of the body of a content unit, producing it on demand while the EPUB is created
+/// (e.g. from a database query, a template renderer or a generator) instead of holding it in
+/// memory before the [`EpubBuilder`](crate::epub::EpubBuilder) is constructed.
+///
+/// The body has the same format as the bytes given to [`ContentBuilder::new`](crate::epub::ContentBuilder::new):
+/// an XHTML body fragment or a complete XHTML document. It is requested once per creation.
+///
+/// Any `Fn() -> crate::Result<Vec<u8>>` closure is a content source.
+///
+/// ```rust
+/// use std::borrow::Cow;
+///
+/// use liber::epub::{ContentBuilder, ContentSource, ReferenceType};
+///
+/// #[derive(Debug)]
+/// struct Greeting(&'static str);
+///
+/// impl ContentSource for Greeting {
+///     fn body(&self) -> liber::Result<Cow<'_, [u8]>> {
+///         Ok(Cow::Owned(format!("<body><p>Hello, {}!</p></body>", self.0).into_bytes()))
+///     }
+/// }
+///
+/// let greeting = Greeting("world");
+/// let content = ContentBuilder::from_source(&greeting, ReferenceType::Text("Greeting".to_string())).build();
+/// ```
+pub trait ContentSource: Sync {
+    /// Produces the body of the content unit.
+    ///
+    /// # Errors
+    /// Any error aborts the creation of the EPUB.
+    fn body(&self) -> crate::Result<Cow<'_, [u8]>>;
+}
+
+impl<F> ContentSource for F
+where
+    F: Fn() -> crate::Result<Vec<u8>> + Sync,
+{
+    fn body(&self) -> crate::Result<Cow<'_, [u8]>> {
+        self().map(Cow::Owned)
+    }
+}
+
+/// The asynchronous version of [`ContentSource`], producing the body of a content unit on
+/// demand while the EPUB is created with the async API (e.g. from an async database client).
+///
+/// Contents with an async source can only be created with the async API; the blocking API
+/// returns a [`crate::Error::AsyncContentSource`].
+///
+/// This trait is only available when the **`async` feature** is enabled.
+#[cfg(feature = "async")]
+pub trait AsyncContentSource: Sync {
+    /// Produces the body of the content unit.
+    ///
+    /// # Errors
+    /// Any error aborts the creation of the EPUB.
+    fn body(&self) -> BoxFuture<'_, crate::Result<Cow<'_, [u8]>>>;
+}
+
+/// The body of a content unit: bytes held by the caller or generated by liber, a file or a
+/// source producing them on demand.
+#[derive(Clone)]
+pub(crate) enum Body<'a> {
+    /// A body in memory.
+    Bytes(&'a [u8]),
+    /// A body generated by liber (e.g. a list of illustrations), shared by the clones of the
+    /// content.
+    Generated(Arc<[u8]>),
+    /// A body read from a file while the EPUB is created.
+    Path(&'a Path),
+    /// A body produced on demand.
+    Source(&'a dyn ContentSource),
+    /// A body produced on demand by the async API.
+    #[cfg(feature = "async")]
+    AsyncSource(&'a dyn AsyncContentSource),
+}
+
+impl<'a> Body<'a> {
+    /// Gets the body when it is already in memory, without producing it.
+    pub(crate) fn bytes(&self) -> Option<&[u8]> {
+        match self {
+            Self::Bytes(bytes) => Some(bytes),
+            Self::Generated(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    /// Gets the body, reading it if it comes from a file or producing it if it comes from a
+    /// [`ContentSource`].
+    ///
+    /// # Errors
+    /// Returns the error of the read or of the source, or a [`crate::Error::AsyncContentSource`]
+    /// for an [`AsyncContentSource`], which only the async API can produce.
+    #[cfg_attr(not(feature = "async"), allow(unused_variables))]
+    pub(crate) fn load(&self, title: &str) -> crate::Result<Cow<'a, [u8]>> {
+        match self {
+            Self::Bytes(bytes) => Ok(Cow::Borrowed(bytes)),
+            Self::Generated(bytes) => Ok(Cow::Owned(bytes.to_vec())),
+            Self::Path(path) => fs::read(path)
+                .map(Cow::Owned)
+                .map_err(|e| read_error(path, e)),
+            Self::Source(source) => source.body(),
+            #[cfg(feature = "async")]
+            Self::AsyncSource(_) => Err(crate::Error::AsyncContentSource(title.to_string())),
+        }
+    }
+
+    /// Gets the body, reading it if it comes from a file (running the blocking read with
+    /// `spawner`, see [`Spawner`]) or producing it if it comes from a [`ContentSource`] or an
+    /// [`AsyncContentSource`].
+    ///
+    /// # Errors
+    /// Returns the error of the read or of the source.
+    #[cfg(feature = "async")]
+    pub(crate) async fn async_load(
+        &self,
+        spawner: Option<&dyn Spawner>,
+    ) -> crate::Result<Cow<'a, [u8]>> {
+        match self {
+            Self::Bytes(bytes) => Ok(Cow::Borrowed(bytes)),
+            Self::Generated(bytes) => Ok(Cow::Owned(bytes.to_vec())),
+            Self::Path(path) => {
+                let path_buf = path.to_path_buf();
+                run_blocking(spawner, move || fs::read(path_buf))
+                    .await?
+                    .map(Cow::Owned)
+                    .map_err(|e| read_error(path, e))
+            }
+            Self::Source(source) => source.body(),
+            Self::AsyncSource(source) => source.body().await,
+        }
+    }
+
+    /// Checks that the file of a body read from a file exists, without reading it.
+    pub(crate) fn problem(&self) -> Option<crate::Error> {
+        match self {
+            Self::Path(path) => fs::metadata(path).err().map(|e| read_error(path, e)),
+            _ => None,
+        }
+    }
+}
+
+/// Maps a failed read of the file of a body, reporting a missing file with its path.
+fn read_error(path: &Path, error: std::io::Error) -> crate::Error {
+    if error.kind() == std::io::ErrorKind::NotFound {
+        crate::Error::ResourceNotFound {
+            path: path.to_path_buf(),
+        }
+    } else {
+        crate::Error::Io(error)
+    }
+}
+
+/// Bodies in memory are equal when their bytes are, bodies read from files when their paths are
+/// and sources when they are the same object.
+impl PartialEq for Body<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Bytes(bytes), Self::Bytes(other)) => bytes == other,
+            (Self::Generated(bytes), Self::Generated(other)) => bytes == other,
+            (Self::Path(path), Self::Path(other)) => path == other,
+            (Self::Source(source), Self::Source(other)) => std::ptr::addr_eq(*source, *other),
+            #[cfg(feature = "async")]
+            (Self::AsyncSource(source), Self::AsyncSource(other)) => {
+                std::ptr::addr_eq(*source, *other)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Body<'_> {}
+
+impl std::fmt::Debug for Body<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Bytes(bytes) => f.debug_tuple("Bytes").field(bytes).finish(),
+            Self::Generated(bytes) => f.debug_tuple("Generated").field(bytes).finish(),
+            Self::Path(path) => f.debug_tuple("Path").field(path).finish(),
+            Self::Source(_) => f.write_str("Source"),
+            #[cfg(feature = "async")]
+            Self::AsyncSource(_) => f.write_str("AsyncSource"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct Generated(usize);
+
+    impl ContentSource for Generated {
+        fn body(&self) -> crate::Result<Cow<'_, [u8]>> {
+            Ok(Cow::Owned(
+                format!("<body><p>{}</p></body>", self.0).into_bytes(),
+            ))
+        }
+    }
+
+    #[test]
+    fn test_body_load() {
+        let generated = Generated(7);
+        let failing = || Err(crate::Error::InvalidEpub("query failed".to_string()));
+
+        assert_eq!(
+            Body::Bytes(b"<body/>").load("Bytes").unwrap().as_ref(),
+            b"<body/>"
+        );
+        assert_eq!(
+            Body::Source(&generated).load("Generated").unwrap().as_ref(),
+            b"<body><p>7</p></body>"
+        );
+        assert!(Body::Source(&generated).bytes().is_none());
+        assert!(Body::Source(&failing).load("Failing").is_err());
+    }
+
+    #[test]
+    fn test_body_load_path() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("chapter.xhtml");
+        std::fs::write(&path, b"<body><p>File</p></body>").unwrap();
+        let missing = temp_dir.path().join("missing.xhtml");
+
+        assert_eq!(
+            Body::Path(&path).load("File").unwrap().as_ref(),
+            b"<body><p>File</p></body>"
+        );
+        assert!(Body::Path(&path).bytes().is_none());
+        assert!(Body::Path(&path).problem().is_none());
+        assert!(matches!(
+            Body::Path(&missing).load("Missing"),
+            Err(crate::Error::ResourceNotFound { path }) if path == missing
+        ));
+        assert!(matches!(
+            Body::Path(&missing).problem(),
+            Some(crate::Error::ResourceNotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn test_body_eq() {
+        let generated = Generated(7);
+        let other = Generated(7);
+
+        assert_eq!(Body::Bytes(b"<body/>"), Body::Bytes(b"<body/>"));
+        assert_ne!(Body::Bytes(b"<body/>"), Body::Bytes(b"<body></body>"));
+        assert_eq!(Body::Source(&generated), Body::Source(&generated));
+        assert_ne!(Body::Source(&generated), Body::Source(&other));
+        assert_ne!(
+            Body::Source(&generated),
+            Body::Bytes(b"<body><p>7</p></body>")
+        );
+    }
+}
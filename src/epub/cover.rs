@@ -0,0 +1,379 @@
+use quick_xml::{Reader, escape::escape, events::Event};
+
+use crate::epub::Metadata;
+
+/// The filename used for the generated cover inside the `OEBPS` directory.
+pub(crate) const GENERATED_COVER_FILENAME: &str = "cover.svg";
+
+/// The filename used for the SVG-wrapped cover page inside the `OEBPS` directory.
+pub(crate) const COVER_PAGE_FILENAME: &str = "cover.xhtml";
+
+/// The size of the cover page when the dimensions of the cover image cannot be detected.
+const DEFAULT_COVER_SIZE: (u32, u32) = (1600, 2560);
+
+/// The media type of the generated cover.
+pub(crate) const GENERATED_COVER_MEDIA_TYPE: &str = "image/svg+xml";
+
+/// Maximum number of characters per line when wrapping the title.
+const TITLE_LINE_LENGTH: usize = 18;
+
+/// A simple typographic cover rendered from the book's title and creator.
+///
+/// It is only used when no cover image is supplied, producing an SVG image with a solid
+/// background and the title and author set in the chosen font and palette.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeneratedCover {
+    /// The background color (any CSS color, e.g. `#1d3557`).
+    background: String,
+    /// The text color (any CSS color, e.g. `#f1faee`).
+    foreground: String,
+    /// The font family used for the title and the author.
+    font_family: String,
+    /// The width of the cover in pixels.
+    width: u32,
+    /// The height of the cover in pixels.
+    height: u32,
+}
+
+impl Default for GeneratedCover {
+    /// Creates a 1600x2560 cover with white serif text on a dark blue background.
+    fn default() -> Self {
+        Self {
+            background: "#1d3557".to_string(),
+            foreground: "#f1faee".to_string(),
+            font_family: "serif".to_string(),
+            width: 1600,
+            height: 2560,
+        }
+    }
+}
+
+impl GeneratedCover {
+    /// Creates a new `GeneratedCover` with the default palette, font and size.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the **palette** of the cover: the background and text colors.
+    ///
+    /// This is a fluent method, returning `Self`.
+    pub fn palette<S: Into<String>>(mut self, background: S, foreground: S) -> Self {
+        self.background = background.into();
+        self.foreground = foreground.into();
+        self
+    }
+
+    /// Sets the **font family** used for the title and the author (e.g. `Georgia, serif`).
+    ///
+    /// This is a fluent method, returning `Self`.
+    pub fn font_family<S: Into<String>>(mut self, font_family: S) -> Self {
+        self.font_family = font_family.into();
+        self
+    }
+
+    /// Sets the **size** of the cover in pixels.
+    ///
+    /// This is a fluent method, returning `Self`.
+    pub fn size(mut self, width: u32, height: u32) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    /// Renders the cover as an SVG document using the title and creator of the [`Metadata`].
+    pub(crate) fn render(&self, metadata: &Metadata) -> String {
+        let (width, height) = (self.width, self.height);
+        let center = width / 2;
+        let title_size = width / 12;
+        let author_size = width / 24;
+
+        let title_lines = wrap(&metadata.title, TITLE_LINE_LENGTH);
+        let title_top =
+            (height * 2 / 5).saturating_sub((title_lines.len() as u32 - 1) * title_size * 6 / 10);
+
+        let title = title_lines
+            .iter()
+            .enumerate()
+            .map(|(index, line)| {
+                format!(
+                    r#"<tspan x="{center}" y="{y}">{line}</tspan>"#,
+                    y = title_top + index as u32 * title_size * 12 / 10,
+                    line = escape(line.as_str())
+                )
+            })
+            .collect::<String>();
+
+        let author = metadata
+            .creator
+            .as_ref()
+            .map(|creator| {
+                format!(
+                    r#"<text x="{center}" y="{y}" font-size="{author_size}" text-anchor="middle">{creator}</text>"#,
+                    y = height * 4 / 5,
+                    creator = escape(creator.as_str())
+                )
+            })
+            .unwrap_or_default();
+
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?><svg xmlns="http://www.w3.org/2000/svg" version="1.1" width="{width}" height="{height}" viewBox="0 0 {width} {height}"><rect width="100%" height="100%" fill="{background}"/><g fill="{foreground}" font-family="{font_family}"><text font-size="{title_size}" font-weight="bold" text-anchor="middle">{title}</text>{author}</g></svg>"#,
+            background = escape(self.background.as_str()),
+            foreground = escape(self.foreground.as_str()),
+            font_family = escape(self.font_family.as_str()),
+        )
+    }
+}
+
+/// Renders the cover page: an XHTML document showing the cover image at `image_href` inside an
+/// SVG `viewBox` with the dimensions of the image, so it scales to fit any screen while keeping
+/// its aspect ratio.
+///
+/// The dimensions are detected from the `image` bytes (see [`image_dimensions`]), falling back
+/// to 1600x2560.
+pub(crate) fn render_cover_page(image_href: &str, image: &[u8], title: &str) -> String {
+    let (width, height) = image_dimensions(image).unwrap_or(DEFAULT_COVER_SIZE);
+    let title = escape(title);
+
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?><!DOCTYPE html PUBLIC "-//W3C//DTD XHTML 1.1//EN" "http://www.w3.org/TR/xhtml11/DTD/xhtml11.dtd"><html xmlns="http://www.w3.org/1999/xhtml"><head><title>{title}</title><style type="text/css">html, body {{ margin: 0; padding: 0; height: 100%; }} svg {{ display: block; }}</style></head><body><svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" version="1.1" width="100%" height="100%" viewBox="0 0 {width} {height}" preserveAspectRatio="xMidYMid meet"><title>{title}</title><image width="{width}" height="{height}" xlink:href="{href}"/></svg></body></html>"#,
+        href = escape(image_href),
+    )
+}
+
+/// Detects the dimensions in pixels (width, height) of a PNG, GIF, JPEG, WebP or SVG image from
+/// its header.
+///
+/// Returns `None` for other formats or truncated headers.
+pub(crate) fn image_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    let le16 = |at: usize| Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?) as u32);
+    let be32 = |at: usize| Some(u32::from_be_bytes(bytes.get(at..at + 4)?.try_into().ok()?));
+    let le24 = |at: usize| {
+        let value = bytes.get(at..at + 3)?;
+        Some(u32::from(value[0]) | u32::from(value[1]) << 8 | u32::from(value[2]) << 16)
+    };
+
+    let dimensions = if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        (be32(16)?, be32(20)?)
+    } else if bytes.starts_with(b"GIF8") {
+        (le16(6)?, le16(8)?)
+    } else if bytes.starts_with(&[0xff, 0xd8]) {
+        jpeg_dimensions(bytes)?
+    } else if bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WEBP") {
+        match bytes.get(12..16)? {
+            b"VP8 " => (le16(26)? & 0x3fff, le16(28)? & 0x3fff),
+            b"VP8L" => {
+                let bits = u32::from_le_bytes(bytes.get(21..25)?.try_into().ok()?);
+                (1 + (bits & 0x3fff), 1 + ((bits >> 14) & 0x3fff))
+            }
+            b"VP8X" => (1 + le24(24)?, 1 + le24(27)?),
+            _ => return None,
+        }
+    } else {
+        svg_dimensions(bytes)?
+    };
+
+    (dimensions.0 > 0 && dimensions.1 > 0).then_some(dimensions)
+}
+
+/// Reads the dimensions of a JPEG image from its first start-of-frame segment.
+fn jpeg_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    let mut at = 2;
+    loop {
+        if *bytes.get(at)? != 0xff {
+            return None;
+        }
+        let marker = *bytes.get(at + 1)?;
+        if marker == 0xff {
+            // Fill byte before a marker
+            at += 1;
+            continue;
+        }
+
+        let length = u16::from_be_bytes(bytes.get(at + 2..at + 4)?.try_into().ok()?) as usize;
+        if matches!(marker, 0xc0..=0xc3 | 0xc5..=0xc7 | 0xc9..=0xcb | 0xcd..=0xcf) {
+            let height = u16::from_be_bytes(bytes.get(at + 5..at + 7)?.try_into().ok()?);
+            let width = u16::from_be_bytes(bytes.get(at + 7..at + 9)?.try_into().ok()?);
+            return Some((u32::from(width), u32::from(height)));
+        }
+        at += 2 + length;
+    }
+}
+
+/// Reads the dimensions of an SVG image from the `viewBox` of its root element, or else from
+/// its `width` and `height` in pixels.
+fn svg_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    let mut reader = Reader::from_reader(bytes);
+    let mut buffer = Vec::new();
+
+    loop {
+        match reader.read_event_into(&mut buffer).ok()? {
+            Event::Start(element) | Event::Empty(element) => {
+                if element.local_name().as_ref() != b"svg" {
+                    return None;
+                }
+                let attribute = |name: &[u8]| {
+                    element
+                        .try_get_attribute(name)
+                        .ok()
+                        .flatten()
+                        .map(|attribute| String::from_utf8_lossy(&attribute.value).into_owned())
+                };
+                let length = |value: String| {
+                    value
+                        .trim()
+                        .trim_end_matches("px")
+                        .parse::<f64>()
+                        .ok()
+                        .map(|value| value.round() as u32)
+                };
+
+                let view_box = attribute(b"viewBox").and_then(|view_box| {
+                    let values = view_box
+                        .split(|c: char| c.is_whitespace() || c == ',')
+                        .filter(|value| !value.is_empty())
+                        .map(|value| length(value.to_string()))
+                        .collect::<Option<Vec<_>>>()?;
+                    match values[..] {
+                        [_, _, width, height] => Some((width, height)),
+                        _ => None,
+                    }
+                });
+
+                return view_box.or_else(|| {
+                    Some((
+                        length(attribute(b"width")?)?,
+                        length(attribute(b"height")?)?,
+                    ))
+                });
+            }
+            Event::Eof => return None,
+            _ => {}
+        }
+        buffer.clear();
+    }
+}
+
+/// Wraps `text` into lines of at most `line_length` characters, breaking on whitespace.
+///
+/// Words longer than `line_length` are kept whole on their own line.
+fn wrap(text: &str, line_length: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+
+    for word in text.split_whitespace() {
+        match lines.last_mut() {
+            Some(line) if line.chars().count() + 1 + word.chars().count() <= line_length => {
+                line.push(' ');
+                line.push_str(word);
+            }
+            _ => lines.push(word.to_string()),
+        }
+    }
+
+    if lines.is_empty() {
+        lines.push(String::new());
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::epub::MetadataBuilder;
+
+    #[test]
+    fn test_wrap() {
+        assert_eq!(wrap("Short", 18), vec!["Short"]);
+        assert_eq!(
+            wrap("The Adventures of Sherlock Holmes", 18),
+            vec!["The Adventures of", "Sherlock Holmes"]
+        );
+        assert_eq!(
+            wrap("Supercalifragilisticexpialidocious day", 10),
+            vec!["Supercalifragilisticexpialidocious", "day"]
+        );
+        assert_eq!(wrap("   ", 10), vec![""]);
+    }
+
+    #[test]
+    fn test_render() {
+        let metadata = MetadataBuilder::title("Dune & Co")
+            .creator("Frank Herbert")
+            .build();
+
+        let svg = GeneratedCover::new()
+            .palette("#000000", "#ffffff")
+            .font_family("Georgia, serif")
+            .size(600, 800)
+            .render(&metadata);
+
+        assert!(svg.starts_with(r#"<?xml version="1.0" encoding="UTF-8"?><svg"#));
+        assert!(svg.contains(r#"viewBox="0 0 600 800""#));
+        assert!(svg.contains(r##"<rect width="100%" height="100%" fill="#000000"/>"##));
+        assert!(svg.contains(r##"fill="#ffffff" font-family="Georgia, serif""##));
+        assert!(svg.contains(">Dune &amp; Co</tspan>"));
+        assert!(svg.contains(">Frank Herbert</text>"));
+        assert!(crate::output::xml::format(&svg).is_ok());
+    }
+
+    #[test]
+    fn test_image_dimensions() {
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+        png.extend(600u32.to_be_bytes());
+        png.extend(800u32.to_be_bytes());
+        assert_eq!(image_dimensions(&png), Some((600, 800)));
+
+        assert_eq!(
+            image_dimensions(b"GIF89a\x58\x02\x20\x03"),
+            Some((600, 800))
+        );
+
+        let jpeg = [
+            0xff, 0xd8, 0xff, 0xe0, 0x00, 0x04, 0x00, 0x00, 0xff, 0xc0, 0x00, 0x11, 0x08, 0x03,
+            0x20, 0x02, 0x58,
+        ];
+        assert_eq!(image_dimensions(&jpeg), Some((600, 800)));
+
+        let mut webp = b"RIFF\0\0\0\0WEBPVP8X\0\0\0\0\0\0\0\0".to_vec();
+        webp.extend([0x57, 0x02, 0x00, 0x1f, 0x03, 0x00]);
+        assert_eq!(image_dimensions(&webp), Some((600, 800)));
+
+        assert_eq!(
+            image_dimensions(br#"<?xml version="1.0"?><svg viewBox="0 0 600 800"/>"#),
+            Some((600, 800))
+        );
+        assert_eq!(
+            image_dimensions(br#"<svg width="600px" height="800px"></svg>"#),
+            Some((600, 800))
+        );
+        assert_eq!(image_dimensions(b"not an image"), None);
+        assert_eq!(image_dimensions(b"\x89PNG\r\n\x1a\n"), None);
+    }
+
+    #[test]
+    fn test_render_cover_page() {
+        let page = render_cover_page(
+            "images/cover.svg",
+            br#"<svg width="600" height="800"/>"#,
+            "Dune & Co",
+        );
+
+        assert!(page.contains(r#"viewBox="0 0 600 800" preserveAspectRatio="xMidYMid meet""#));
+        assert!(page.contains(r#"<title>Dune &amp; Co</title><image width="600" height="800" xlink:href="images/cover.svg"/>"#));
+        assert!(crate::output::xml::format(&page).is_ok());
+
+        assert!(
+            render_cover_page("cover.jpg", b"", "Cover").contains(r#"viewBox="0 0 1600 2560""#)
+        );
+    }
+
+    #[test]
+    fn test_render_without_creator() {
+        let metadata = MetadataBuilder::title("Anonymous").build();
+        let svg = GeneratedCover::default().render(&metadata);
+
+        assert!(svg.contains(">Anonymous</tspan>"));
+        assert_eq!(svg.matches("<text").count(), 1);
+    }
+}
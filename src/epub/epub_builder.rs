@@ -1,11 +1,59 @@
-use std::{io::Write, path::Path};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet, hash_map::Entry},
+    fmt,
+    io::{Cursor, Write},
+    path::Path,
+};
+
+use sha2::{Digest, Sha256};
 
-use crate::ZipCompression;
+#[cfg(all(feature = "mdbook", not(target_arch = "wasm32")))]
+use crate::epub::MdBook;
+#[cfg(feature = "crypto")]
+use crate::epub::PackageSigner;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::epub::ResourceDir;
+#[cfg(feature = "async")]
+use crate::epub::Spawner;
+#[cfg(feature = "onix")]
+use crate::output::onix;
+use crate::{Checksum, ZipCompression};
+#[cfg(feature = "image")]
+use crate::{ImageOptions, ResultExt, output::optimizer};
+use crate::{
+    ValidationIssue,
+    epub::{
+        BookPart, CalibreMetadata, Content, ContentBuilder, EpubPlugin, FixedLayout,
+        GeneratedCover, ImageType, LinkAnnotation, NavList, NavTarget, Numbering, NumberingStyle,
+        PageTarget, Profile, ReferenceType, Resource, ResourceItem, TextDirection, TextTransform,
+        Theme, Typography, XhtmlOptions,
+        captioned::{self, Captioned},
+        cover::{self, COVER_PAGE_FILENAME, GENERATED_COVER_FILENAME, GENERATED_COVER_MEDIA_TYPE},
+        extend_list, font_face, index_term,
+        metadata::Metadata,
+    },
+    input::validation,
+    output::{
+        creator::{self, EpubFile},
+        file_content::{FileBytes, FileContent},
+        files::EpubFiles,
+        obfuscation,
+        opds::{self, OpdsLinks},
+        sink::{PlanSink, PlannedFile},
+        warning::Warning,
+        xml,
+    },
+};
+#[cfg(feature = "http")]
 use crate::{
-    epub::{Content, ImageType, Resource, metadata::Metadata},
-    output::creator::EpubFile,
+    epub::{DeadLink, external_link},
+    output::http,
 };
 
+/// A loaded resource (or cover image) file, ready to be written into the archive.
+type ResourceContent = FileContent<String, FileBytes>;
+
 /// The main structure representing a complete EPUB document ready for generation.
 ///
 /// It holds all the necessary components: metadata, styling, resources, and ordered content.
@@ -16,12 +64,100 @@ pub(crate) struct Epub<'a> {
     pub metadata: Metadata,
     /// Optional stylesheet content (CSS bytes) to be included in the EPUB.
     pub stylesheet: Option<&'a [u8]>,
+    /// Optional built-in stylesheet preset, preceding the stylesheet.
+    pub theme: Option<Theme>,
+    /// Optional typographic touches applied to the bodies, whose CSS precedes the stylesheet.
+    pub typography: Option<Typography>,
+    /// Whether `@font-face` rules are generated for the embedded fonts and appended to the
+    /// stylesheet.
+    pub font_faces: bool,
     /// Optional resource designated as the cover image.
-    pub cover_image: Option<Resource<'a>>,
+    pub cover_image: Option<ResourceItem<'a>>,
+    /// Optional typographic cover rendered when no cover image is supplied.
+    pub generated_cover: Option<GeneratedCover>,
+    /// Whether a cover page showing the cover inside a scalable SVG wrapper is generated.
+    pub svg_cover_page: bool,
+    /// Optional fixed layout of a pre-paginated book.
+    pub fixed_layout: Option<FixedLayout>,
     /// Optional list of external resources (images, fonts, audio) used by the content.
-    pub resources: Option<Vec<Resource<'a>>>,
+    pub resources: Option<Vec<ResourceItem<'a>>>,
+    /// The filenames of the resources dropped as duplicates, mapped to the filename of the
+    /// resource (or cover image) sharing their content, which their references point at.
+    pub resource_aliases: HashMap<String, String>,
     /// Optional, ordered list of main content units (chapters, sections, appendices).
     pub contents: Option<Vec<Content<'a>>>,
+    /// Optional downscaling and compression applied to the cover image (and other images).
+    #[cfg(feature = "image")]
+    pub image_options: Option<ImageOptions>,
+    /// Optional retailer the EPUB is built for, whose requirements are checked.
+    pub profile: Option<Profile>,
+    /// Whether `META-INF/com.apple.ibooks.display-options.xml` is written. Defaults to `true`.
+    pub display_options: bool,
+    /// The zip compression method of the archive entries (but the `mimetype`). Defaults to
+    /// [`ZipCompression::Stored`].
+    pub compression: ZipCompression,
+    /// Whether the build must be reproducible (fixed zip timestamps, no generated date or UUID).
+    pub deterministic: bool,
+    /// Optional comment stored in the zip archive.
+    pub archive_comment: Option<String>,
+    /// Optional name (and version) of the producing tool, declared in the package metadata.
+    pub generator: Option<String>,
+    /// Optional Calibre-specific metadata, declared as `calibre:*` metas.
+    pub calibre: Option<CalibreMetadata>,
+    /// Whether `META-INF/liber-manifest.json`, describing the generated files, is embedded.
+    /// Defaults to `false`.
+    pub build_manifest: bool,
+    /// Optional signer of the package, generating `META-INF/signatures.xml`.
+    #[cfg(feature = "crypto")]
+    pub signer: Option<&'a dyn PackageSigner>,
+    /// Optional paths of the archive entries signed (e.g. `OEBPS/content.opf`), when not all
+    /// of them (but the `mimetype`) are.
+    #[cfg(feature = "crypto")]
+    pub signed_entries: Option<Vec<String>>,
+    /// Whether the generated XML files (OPF, NCX, XHTML) are indented. Defaults to `true`.
+    pub pretty_print: bool,
+    /// Whether all the XHTML files are rendered in parallel before being written. Defaults to
+    /// `true`.
+    #[cfg(feature = "rayon")]
+    pub parallel_rendering: bool,
+    /// Whether DPUB-ARIA roles are added to the generated XHTML wrappers. Defaults to `false`.
+    pub aria_roles: bool,
+    /// Whether the title, creator and language are declared as Dublin Core `<meta>` tags in the
+    /// header of every generated XHTML file. Defaults to `false`.
+    pub dublin_core_meta: bool,
+    /// Whether the chapters start on a new page, with a page break before their body. Defaults
+    /// to `false`.
+    pub page_breaks: bool,
+    /// Whether straight quotes, double hyphens and triple dots of the bodies are converted to
+    /// typographic punctuation. Defaults to `false`.
+    pub smart_punctuation: bool,
+    /// Optional transforms of the bodies, in registration order.
+    pub text_transforms: Option<Vec<TextTransform>>,
+    /// Optional annotation of the external links of the bodies.
+    pub external_links: Option<LinkAnnotation>,
+    /// Optional base direction of the text, declared on every generated XHTML file.
+    pub text_direction: Option<TextDirection>,
+    /// Optional maximum nesting of the navigation points of the table of contents.
+    pub toc_depth: Option<usize>,
+    /// Whether all the navigation points of the table of contents are at one level.
+    pub flat_toc: bool,
+    /// Optional reading order of the contents, by filename, when it differs from the order of
+    /// the table of contents.
+    pub spine_order: Option<Vec<String>>,
+    /// The numbering styles of the auto-generated labels of the table of contents and page list.
+    pub numbering: Numbering,
+    /// Optional print page targets, listed in the `pageList` of the table of contents.
+    pub page_list: Option<Vec<PageTarget>>,
+    /// Optional secondary navigation lists (e.g. a list of illustrations) of the table of contents.
+    pub nav_lists: Option<Vec<NavList>>,
+    /// Whether titles, metadata, filenames, anchor ids and bodies are converted to Unicode
+    /// Normalization Form C when generating the files. Defaults to `true`.
+    pub unicode_normalization: bool,
+    /// Optional hooks transforming the generated files, in registration order.
+    pub plugins: Option<Vec<&'a dyn EpubPlugin>>,
+    /// Optional spawner running the blocking work of the async API.
+    #[cfg(feature = "async")]
+    pub spawner: Option<&'a dyn Spawner>,
 }
 
 impl<'a> Epub<'a> {
@@ -30,38 +166,610 @@ impl<'a> Epub<'a> {
         Self {
             metadata,
             stylesheet: None,
+            theme: None,
+            typography: None,
+            font_faces: false,
             cover_image: None,
+            generated_cover: None,
+            svg_cover_page: false,
+            fixed_layout: None,
             resources: None,
+            resource_aliases: HashMap::new(),
             contents: None,
+            #[cfg(feature = "image")]
+            image_options: None,
+            profile: None,
+            display_options: true,
+            compression: ZipCompression::default(),
+            deterministic: false,
+            archive_comment: None,
+            generator: None,
+            calibre: None,
+            build_manifest: false,
+            #[cfg(feature = "crypto")]
+            signer: None,
+            #[cfg(feature = "crypto")]
+            signed_entries: None,
+            pretty_print: true,
+            #[cfg(feature = "rayon")]
+            parallel_rendering: true,
+            aria_roles: false,
+            dublin_core_meta: false,
+            page_breaks: false,
+            smart_punctuation: false,
+            text_transforms: None,
+            external_links: None,
+            text_direction: None,
+            toc_depth: None,
+            flat_toc: false,
+            spine_order: None,
+            numbering: Numbering::default(),
+            page_list: None,
+            nav_lists: None,
+            unicode_normalization: true,
+            plugins: None,
+            #[cfg(feature = "async")]
+            spawner: None,
+        }
+    }
+
+    /// Gets the registered plugins, in registration order.
+    pub(crate) fn plugins(&self) -> &[&'a dyn EpubPlugin] {
+        self.plugins.as_deref().unwrap_or_default()
+    }
+
+    /// Lets every plugin transform the final (normalized) metadata.
+    pub(crate) fn finalize_metadata(&mut self) -> crate::Result {
+        self.plugins
+            .iter()
+            .flatten()
+            .try_for_each(|plugin| plugin.metadata_finalized(&mut self.metadata))
+    }
+
+    /// Whether the EPUB has a stylesheet (`style.css`): one, a theme or a typography is set, or
+    /// `@font-face` rules are generated for embedded fonts.
+    pub(crate) fn has_stylesheet(&self) -> bool {
+        self.stylesheet.is_some()
+            || self.theme.is_some()
+            || self.typography.is_some()
+            || (self.font_faces && self.resources.as_deref().is_some_and(font_face::has_fonts))
+    }
+
+    /// Gets the content of the stylesheet (`style.css`): the CSS of the theme and of the
+    /// typography, if any, then the stylesheet and the generated `@font-face` rules of the
+    /// embedded fonts that it does not mention, if they are enabled.
+    ///
+    /// Returns `None` if the EPUB has no stylesheet.
+    pub(crate) fn stylesheet_content(&self) -> Option<Cow<'a, [u8]>> {
+        let rules = match self.resources {
+            Some(ref resources) if self.font_faces => {
+                font_face::font_face_rules(resources, self.stylesheet)
+            }
+            _ => String::new(),
+        };
+
+        let theme = self.theme.map(Theme::css).unwrap_or_default();
+        let typography = self
+            .typography
+            .as_ref()
+            .map(Typography::css)
+            .unwrap_or_default();
+        match self.stylesheet {
+            Some(stylesheet) if theme.is_empty() && typography.is_empty() && rules.is_empty() => {
+                Some(Cow::Borrowed(stylesheet))
+            }
+            stylesheet => {
+                let parts = [
+                    theme.as_bytes(),
+                    typography.as_bytes(),
+                    stylesheet.unwrap_or_default(),
+                    rules.as_bytes(),
+                ];
+                let parts = parts.iter().filter(|part| !part.is_empty());
+                let content = parts.copied().collect::<Vec<_>>().join(b"\n".as_slice());
+                (!content.is_empty()).then_some(Cow::Owned(content))
+            }
+        }
+    }
+
+    /// Whether the files are rendered in parallel (see [`EpubBuilder::parallel_rendering`]),
+    /// always `false` without the **`rayon` feature**.
+    pub(crate) fn renders_in_parallel(&self) -> bool {
+        #[cfg(feature = "rayon")]
+        return self.parallel_rendering;
+        #[cfg(not(feature = "rayon"))]
+        false
+    }
+
+    /// Gets the settings that shape the XHTML file of every content.
+    pub(crate) fn xhtml_options(&self) -> XhtmlOptions<'_> {
+        XhtmlOptions {
+            add_stylesheet: self.has_stylesheet(),
+            pretty_print: self.pretty_print,
+            aria_roles: self.aria_roles,
+            text_direction: self.text_direction,
+            unicode_normalization: self.unicode_normalization,
+            dublin_core: self.dublin_core_meta.then_some(&self.metadata),
+            page_breaks: self.page_breaks,
+            smart_punctuation: self.smart_punctuation,
+            text_transforms: self.text_transforms.as_deref().unwrap_or_default(),
+            external_links: self.external_links,
+            typography: self.typography.as_ref(),
+            resource_aliases: (!self.resource_aliases.is_empty()).then_some(&self.resource_aliases),
+        }
+    }
+
+    /// Converts the metadata, the content titles, filenames and anchor ids, and the resource
+    /// filenames to Unicode Normalization Form C, unless normalization is disabled.
+    ///
+    /// Content bodies are normalized when their XHTML is generated.
+    pub(crate) fn normalize_unicode(&mut self) {
+        if !self.unicode_normalization {
+            return;
+        }
+
+        self.metadata.normalize_unicode();
+        self.contents
+            .iter_mut()
+            .flatten()
+            .for_each(Content::normalize_unicode);
+        self.cover_image
+            .iter_mut()
+            .chain(self.resources.iter_mut().flatten())
+            .for_each(ResourceItem::normalize_unicode);
+        self.page_list
+            .iter_mut()
+            .flatten()
+            .for_each(PageTarget::normalize_unicode);
+        self.nav_lists
+            .iter_mut()
+            .flatten()
+            .for_each(NavList::normalize_unicode);
+        self.calibre
+            .iter_mut()
+            .for_each(CalibreMetadata::normalize_unicode);
+    }
+
+    /// Gets the manifest **id** of the cover image, referenced by the `cover` meta of the
+    /// content package metadata.
+    ///
+    /// Returns `None` if neither a cover image nor a generated cover is set.
+    pub fn cover_image_id(&self) -> Option<String> {
+        match self.cover_image {
+            Some(ref cover_image) => cover_image.manifest_id().ok(),
+            None => self
+                .generated_cover
+                .as_ref()
+                .map(|_| GENERATED_COVER_FILENAME.to_string()),
+        }
+    }
+
+    /// Whether the SVG-wrapped cover page is generated: it is enabled and there is a cover image
+    /// or a generated cover.
+    pub(crate) fn has_cover_page(&self) -> bool {
+        self.svg_cover_page && (self.cover_image.is_some() || self.generated_cover.is_some())
+    }
+
+    /// Renders the **SVG-wrapped cover page** showing the loaded `cover_image`, sized after its
+    /// detected dimensions.
+    ///
+    /// Returns `None` if the cover page is disabled.
+    pub(crate) fn cover_page_file_content(
+        &self,
+        cover_image: &mut ResourceContent,
+    ) -> crate::Result<Option<FileContent<String, String>>> {
+        if !self.svg_cover_page {
+            return Ok(None);
         }
+
+        let image_href = cover_image
+            .filepath
+            .strip_prefix("OEBPS/")
+            .unwrap_or(&cover_image.filepath);
+        Ok(Some(FileContent::new(
+            format!("OEBPS/{COVER_PAGE_FILENAME}"),
+            cover::render_cover_page(image_href, cover_image.bytes.load()?, &self.metadata.title),
+        )))
     }
 
-    /// Generates the XML `<meta>` tag for the EPUB's NCX file, specifying the maximum **navigation depth**.
-    pub fn level_as_toc_xml(&self) -> String {
-        format!(r#"<meta name="dtb:depth" content="{}"/>"#, self.level())
+    /// Gets the media type declared in the manifest for the file at `filepath` inside the EPUB
+    /// (e.g. `OEBPS/c01.xhtml`).
+    ///
+    /// Returns `None` for files outside the manifest (`mimetype` and the `META-INF` files).
+    pub(crate) fn manifest_media_type(&self, filepath: &str) -> Option<String> {
+        let filename = filepath.strip_prefix("OEBPS/")?;
+
+        let resource = self
+            .cover_image
+            .iter()
+            .chain(self.resources.iter().flatten())
+            .find(|item| item.filename().is_ok_and(|name| name == filename));
+
+        if let Some(resource) = resource {
+            return Some(resource.media_type().to_string());
+        }
+
+        let media_type = match filename {
+            "content.opf" => "application/oebps-package+xml",
+            "toc.ncx" => "application/x-dtbncx+xml",
+            "style.css" => "text/css",
+            GENERATED_COVER_FILENAME => GENERATED_COVER_MEDIA_TYPE,
+            _ => "application/xhtml+xml",
+        };
+
+        Some(media_type.to_string())
     }
 
-    /// Generates the XML `<meta>` tag for the **cover image**, used in the content package metadata.
+    /// Renders the **generated cover**, used only when no cover image is supplied.
     ///
-    /// Returns `None` if no cover image is set.
-    pub fn cover_image_as_metadata_xml(&self) -> Option<String> {
-        Some(format!(
-            r#"<meta name="cover" content="{}"/>"#,
-            self.cover_image.as_ref()?.filename().ok()?
+    /// Returns `None` if a cover image is set or no generated cover is configured.
+    pub(crate) fn generated_cover_file_content(&self) -> Option<ResourceContent> {
+        if self.cover_image.is_some() {
+            return None;
+        }
+
+        let generated_cover = self.generated_cover.as_ref()?;
+        Some(FileContent::new(
+            format!("OEBPS/{GENERATED_COVER_FILENAME}"),
+            generated_cover.render(&self.metadata).into_bytes().into(),
         ))
     }
 
-    /// Generates the XML `<item>` tag for the **cover image**, used in the manifest section.
+    /// Verifies the cover image and resources before anything is written.
+    ///
+    /// Every source file must exist, be readable and be non-empty, and no two different source
+    /// files may be packaged under the same filename (including `style.css` and the generated cover).
+    /// The same file added twice is not a collision, since it is deduplicated when packaging.
+    ///
+    /// # Errors
+    /// Returns a [`crate::Error::InvalidResources`] listing all the problems found at once.
+    pub(crate) fn validate_resources(&self) -> crate::Result {
+        let problems = self.resource_problems(&mut self.reserved_filenames());
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(crate::Error::InvalidResources(
+                problems.iter().map(ToString::to_string).collect(),
+            ))
+        }
+    }
+
+    /// Gets the filenames of the contents in reading order: the ones listed by the spine order
+    /// first (skipping missing and repeated filenames), followed by the others in book order.
+    pub(crate) fn spine(&self) -> Vec<String> {
+        let mut filenames = Vec::new();
+        let mut number = 0;
+        for content in self.contents.iter().flatten() {
+            content.walk(&mut number, &mut |_, filename| filenames.push(filename));
+        }
+
+        let Some(ref spine_order) = self.spine_order else {
+            return filenames;
+        };
+        let mut spine = Vec::with_capacity(filenames.len());
+        for filename in spine_order {
+            if filenames.contains(filename) && !spine.contains(filename) {
+                spine.push(filename.clone());
+            }
+        }
+        for filename in filenames {
+            if !spine.contains(&filename) {
+                spine.push(filename);
+            }
+        }
+        spine
+    }
+
+    /// Gets the filenames of the files generated besides the contents and the resources,
+    /// mapped to a description of the file.
+    fn reserved_filenames(&self) -> HashMap<String, String> {
+        let mut filenames = HashMap::new();
+
+        if self.has_stylesheet() {
+            filenames.insert("style.css".to_string(), "stylesheet".to_string());
+        }
+
+        if self.cover_image.is_none() && self.generated_cover.is_some() {
+            filenames.insert(
+                GENERATED_COVER_FILENAME.to_string(),
+                "generated cover".to_string(),
+            );
+        }
+
+        if self.has_cover_page() {
+            filenames.insert(COVER_PAGE_FILENAME.to_string(), "cover page".to_string());
+        }
+
+        filenames
+    }
+
+    /// Checks the cover image and resources (see [`Epub::validate_resources`]), adding their
+    /// filenames to `filenames`, mapped to their sources.
+    fn resource_problems(&self, filenames: &mut HashMap<String, String>) -> Vec<crate::Error> {
+        let mut problems = Vec::new();
+
+        let items = self
+            .cover_image
+            .iter()
+            .chain(self.resources.iter().flatten());
+
+        for item in items {
+            problems.extend(item.problem());
+
+            let filename = match item.filename() {
+                Ok(filename) => filename,
+                Err(e) => {
+                    problems.push(e);
+                    continue;
+                }
+            };
+
+            let source = item.resource.to_string();
+            match filenames.entry(filename) {
+                Entry::Occupied(entry) if *entry.get() != source => {
+                    problems.push(crate::Error::DuplicateFilename {
+                        filename: entry.key().clone(),
+                        first: entry.get().clone(),
+                        second: source,
+                    })
+                }
+                Entry::Occupied(_) => {}
+                Entry::Vacant(entry) => {
+                    entry.insert(source);
+                }
+            }
+        }
+
+        problems
+    }
+
+    /// Runs every check of [`EpubBuilder::validate`] on the book, collecting all the problems
+    /// found instead of stopping at the first one.
+    pub(crate) fn problems(&self) -> Vec<crate::Error> {
+        let mut problems = Vec::new();
+
+        problems.extend(self.metadata.identifier.problem());
+
+        let mut filenames = self.reserved_filenames();
+        filenames.insert("content.opf".to_string(), "package document".to_string());
+        filenames.insert("toc.ncx".to_string(), "table of contents".to_string());
+        problems.extend(self.resource_problems(&mut filenames));
+
+        let mut files = Vec::new();
+        let mut number = 0;
+        for content in self.contents.iter().flatten() {
+            content.walk(&mut number, &mut |content, filename| {
+                files.push((filename, content))
+            });
+        }
+        if files.is_empty() {
+            problems.push(crate::Error::EmptySpine);
+        }
+
+        for (filename, content) in &files {
+            if !filename.ends_with(".xhtml") {
+                problems.push(crate::Error::ContentFilename(filename.clone()));
+            }
+            let source = format!("content {}", content.title());
+            match filenames.entry(filename.clone()) {
+                Entry::Occupied(entry) => problems.push(crate::Error::DuplicateFilename {
+                    filename: filename.clone(),
+                    first: entry.get().clone(),
+                    second: source,
+                }),
+                Entry::Vacant(entry) => {
+                    entry.insert(source);
+                }
+            }
+            problems.extend(content.body_problem());
+            problems.extend(content_anchor_problems(filename, content));
+        }
+
+        let content_filenames: HashSet<&str> = files
+            .iter()
+            .map(|(filename, _)| filename.as_str())
+            .collect();
+        let targets = self
+            .page_list
+            .iter()
+            .flatten()
+            .map(|page_target| &page_target.src)
+            .chain(
+                self.nav_lists
+                    .iter()
+                    .flatten()
+                    .flat_map(|nav_list| &nav_list.targets)
+                    .map(|nav_target| &nav_target.src),
+            );
+        for src in targets {
+            let filename = src.split_once('#').map_or(src.as_str(), |(file, _)| file);
+            if !content_filenames.contains(filename) {
+                problems.push(crate::Error::MissingTarget {
+                    src: src.clone(),
+                    filename: filename.to_string(),
+                });
+            }
+        }
+
+        let mut spine_filenames = HashSet::new();
+        for filename in self.spine_order.iter().flatten() {
+            if !content_filenames.contains(filename.as_str()) {
+                problems.push(crate::Error::MissingSpineItem(filename.clone()));
+            } else if !spine_filenames.insert(filename) {
+                problems.push(crate::Error::DuplicateSpineItem(filename.clone()));
+            }
+        }
+
+        problems
+    }
+
+    /// Prepares the loaded cover image and resources for packaging.
+    ///
+    /// Duplicated resources are dropped, images are optimized (only with the **`image` feature**)
+    /// and flagged resources are obfuscated.
+    ///
+    /// # Arguments
+    /// * `cover_image`: The loaded cover image, if any.
+    /// * `resource_contents`: The loaded resources, in the same order as `self.resources`.
+    ///
+    /// # Errors
+    /// Returns a [`crate::Result`] if an image cannot be optimized.
+    pub(crate) fn prepare_resources(
+        &mut self,
+        cover_image: Option<ResourceContent>,
+        resource_contents: Vec<ResourceContent>,
+    ) -> crate::Result<(Option<ResourceContent>, Vec<ResourceContent>)> {
+        let mut resource_contents =
+            self.deduplicate_resources(cover_image.as_ref(), resource_contents)?;
+
+        #[cfg(feature = "image")]
+        let mut cover_image = cover_image;
+        #[cfg(feature = "image")]
+        self.optimize_images(cover_image.as_mut(), &mut resource_contents)?;
+
+        self.obfuscate_resources(&mut resource_contents)?;
+
+        Ok((cover_image, resource_contents))
+    }
+
+    /// Drops resources that duplicate the cover image or an earlier resource.
+    ///
+    /// A resource is a duplicate when its content has the same SHA-256 digest, so a single
+    /// archive entry is written per content, e.g. when a file is added both as cover and as a
+    /// regular resource, or under two names. Duplicates are removed from `self.resources` as
+    /// well, so the manifest lists a single item for them, and the references of the contents
+    /// to a duplicate with another filename point at the shared file (see
+    /// `Epub::resource_aliases`).
+    ///
+    /// # Arguments
+    /// * `cover_image`: The loaded cover image, if any.
+    /// * `resource_contents`: The loaded resources, in the same order as `self.resources`.
+    ///
+    /// # Errors
+    /// Returns an error if a resource file cannot be read to be hashed.
+    pub(crate) fn deduplicate_resources(
+        &mut self,
+        cover_image: Option<&ResourceContent>,
+        resource_contents: Vec<ResourceContent>,
+    ) -> crate::Result<Vec<ResourceContent>> {
+        let Some(resources) = self.resources.take() else {
+            return Ok(resource_contents);
+        };
+
+        // Only files sharing their size are hashed, so other resource files are not read
+        let mut size_counts: HashMap<u64, usize> = HashMap::new();
+        for file_content in cover_image.into_iter().chain(&resource_contents) {
+            *size_counts.entry(file_content.bytes.len()).or_default() += 1;
+        }
+        let digest = |file_content: &ResourceContent| match size_counts[&file_content.bytes.len()] {
+            1 => Ok(None),
+            _ => content_digest(&file_content.bytes).map(Some),
+        };
+
+        let mut shared: HashMap<[u8; 32], String> = HashMap::new();
+        if let Some(cover_image) = cover_image
+            && let Some(digest) = digest(cover_image)?
+        {
+            shared.insert(digest, cover_image.filepath.clone());
+        }
+
+        let mut unique_resources = Vec::with_capacity(resources.len());
+        let mut unique_contents = Vec::with_capacity(resource_contents.len());
+        for (resource, file_content) in resources.into_iter().zip(resource_contents) {
+            if let Some(digest) = digest(&file_content)? {
+                match shared.entry(digest) {
+                    Entry::Occupied(entry) => {
+                        if let (Some(duplicate), Some(original)) = (
+                            file_content.filepath.strip_prefix("OEBPS/"),
+                            entry.get().strip_prefix("OEBPS/"),
+                        ) && duplicate != original
+                        {
+                            self.resource_aliases
+                                .insert(duplicate.to_string(), original.to_string());
+                        }
+                        continue;
+                    }
+                    Entry::Vacant(entry) => {
+                        entry.insert(file_content.filepath.clone());
+                    }
+                }
+            }
+            unique_resources.push(resource);
+            unique_contents.push(file_content);
+        }
+
+        self.resources = Some(unique_resources);
+        Ok(unique_contents)
+    }
+
+    /// Applies the IDPF font obfuscation to the loaded resources flagged with [`ResourceItem::obfuscate`].
     ///
-    /// Returns `None` if no cover image is set.
-    pub fn cover_image_as_manifest_xml(&self) -> Option<String> {
-        self.cover_image.as_ref()?.as_manifest_xml()
+    /// # Arguments
+    /// * `resource_contents`: The loaded resources, in the same order as `self.resources`.
+    ///
+    /// # Errors
+    /// Returns an error if an obfuscated resource file cannot be read.
+    pub(crate) fn obfuscate_resources(
+        &self,
+        resource_contents: &mut [ResourceContent],
+    ) -> crate::Result {
+        let Some(ref resources) = self.resources else {
+            return Ok(());
+        };
+
+        let key = obfuscation::key(&String::from(&self.metadata.identifier));
+
+        for (resource, file_content) in resources.iter().zip(resource_contents) {
+            if resource.obfuscated {
+                obfuscation::obfuscate(&key, file_content.bytes.to_mut()?);
+            }
+        }
+        Ok(())
+    }
+
+    /// Downscales and compresses the cover image, and the image resources when enabled in [`ImageOptions`].
+    ///
+    /// This method is only compiled when the **`image` feature** is enabled.
+    #[cfg(feature = "image")]
+    fn optimize_images(
+        &self,
+        cover_image: Option<&mut ResourceContent>,
+        resource_contents: &mut [ResourceContent],
+    ) -> crate::Result {
+        let Some(ref options) = self.image_options else {
+            return Ok(());
+        };
+
+        let optimize = |item: &ResourceItem<'a>, file_content: &mut ResourceContent| {
+            if let Resource::Image(_, ref image_type) = item.resource
+                && let Some(bytes) =
+                    optimizer::optimize(file_content.bytes.load()?, image_type, options)
+                        .with_context(|| format!("image '{}'", file_content.filepath))?
+            {
+                file_content.bytes = bytes.into();
+            }
+            crate::Result::Ok(())
+        };
+
+        if let (Some(item), Some(file_content)) = (self.cover_image.as_ref(), cover_image) {
+            optimize(item, file_content)?;
+        }
+
+        if options.includes_interior_images()
+            && let Some(ref resources) = self.resources
+        {
+            for (item, file_content) in resources.iter().zip(resource_contents) {
+                optimize(item, file_content)?;
+            }
+        }
+
+        Ok(())
     }
 
     /// Calculates the maximum nesting level based on all content and content references.
     ///
     /// This value is used to set the `dtb:depth` property in the TOC/NCX file.
-    fn level(&self) -> usize {
+    pub(crate) fn level(&self) -> usize {
         if let Some(ref contents) = self.contents {
             let level_subcontents = contents
                 .iter()
@@ -82,10 +790,56 @@ impl<'a> Epub<'a> {
     }
 }
 
+/// Checks the anchor IDs of the content references of `content`, generated as `filename`: they
+/// must be valid and unique, and (when the body is in memory) present in the body.
+fn content_anchor_problems(filename: &str, content: &Content<'_>) -> Vec<crate::Error> {
+    let mut problems = Vec::new();
+
+    let body = match content.body().map(std::str::from_utf8) {
+        Some(Ok(body)) => Some(body),
+        Some(Err(e)) => {
+            problems.push(e.into());
+            None
+        }
+        None => None,
+    };
+
+    let mut seen = HashSet::new();
+    for anchor in content.anchors() {
+        let invalid = anchor.is_empty() || anchor.contains(char::is_whitespace);
+        let duplicate = !invalid && !seen.insert(anchor.clone());
+        let missing = body.is_some_and(|body| {
+            !body.contains(&format!("id=\"{anchor}\"")) && !body.contains(&format!("id='{anchor}'"))
+        });
+        let filename = filename.to_string();
+
+        if invalid {
+            problems.push(crate::Error::InvalidAnchor { filename, anchor });
+        } else if duplicate {
+            problems.push(crate::Error::DuplicateAnchor { filename, anchor });
+        } else if missing {
+            problems.push(crate::Error::MissingAnchor { filename, anchor });
+        }
+    }
+
+    problems
+}
+
+/// Computes the SHA-256 digest of file content to detect duplicated resources, reading
+/// resource files in chunks of bounded size.
+fn content_digest(bytes: &FileBytes) -> std::io::Result<[u8; 32]> {
+    let mut hasher = Sha256::new();
+    bytes.copy_to(&mut hasher)?;
+    Ok(hasher.finalize().into())
+}
+
 /// A fluent builder for creating and configuring an Epub.
 ///
 /// Use the `create()` method to serialize the EPUB to a file.
-#[derive(Debug)]
+///
+/// The builder is cloneable, so variants of the same book (e.g. a free sample and the full
+/// edition, or a different cover per retailer) can be branched from one base configuration.
+#[derive(Debug, Clone)]
 pub struct EpubBuilder<'a>(pub(crate) Epub<'a>);
 
 impl<'a> EpubBuilder<'a> {
@@ -95,9 +849,50 @@ impl<'a> EpubBuilder<'a> {
         Self(Epub::new(metadata))
     }
 
+    /// Starts the builder from an [`MdBook`] project: the metadata of its `book.toml`, its
+    /// chapters (nesting preserved) and their local images.
+    ///
+    /// The book can be read and exported in a single statement:
+    /// `EpubBuilder::from_mdbook(&MdBook::read("my-book")?).create(&mut file)?`.
+    /// This is only available when the **`mdbook` feature** is enabled.
+    #[cfg(all(feature = "mdbook", not(target_arch = "wasm32")))]
+    #[must_use]
+    pub fn from_mdbook(mdbook: &'a MdBook) -> Self {
+        Self::new(mdbook.metadata())
+            .add_contents(mdbook.contents())
+            .add_resources(mdbook.resources())
+    }
+
     /// Sets the raw byte content for the required stylesheet (`style.css`).
     pub fn stylesheet(mut self, stylesheet: &'a [u8]) -> Self {
-        self.0.stylesheet = Some(stylesheet);
+        self.set_stylesheet(stylesheet);
+        self
+    }
+
+    /// Sets a built-in stylesheet preset (see [`Theme`]), so no stylesheet is needed for a
+    /// readable book. A stylesheet set too follows the theme, overriding its rules.
+    pub fn theme(mut self, theme: Theme) -> Self {
+        self.0.theme = Some(theme);
+        self
+    }
+
+    /// Sets the typographic touches (drop caps, small-caps lead-ins and section-break ornaments)
+    /// applied to the bodies of some types of content, appending their CSS to the stylesheet
+    /// (see [`Typography`]). A stylesheet set too follows their CSS, overriding it.
+    pub fn typography(mut self, typography: Typography) -> Self {
+        self.0.typography = Some(typography);
+        self
+    }
+
+    /// Sets whether the **`@font-face` rules** of the embedded fonts are generated and appended
+    /// to the stylesheet (which is created if none is set).
+    ///
+    /// The family name is taken from [`ResourceItem::font_family`], or else from the filename,
+    /// whose style suffix sets the weight and style (e.g. `Merriweather-BoldItalic.ttf` is the
+    /// bold italic face of `Merriweather`). Fonts already mentioned by the stylesheet are
+    /// skipped. Defaults to `false`.
+    pub fn font_faces(mut self, font_faces: bool) -> Self {
+        self.0.font_faces = font_faces;
         self
     }
 
@@ -105,154 +900,3203 @@ impl<'a> EpubBuilder<'a> {
     ///
     /// The cover image is automatically registered as a resource.
     pub fn cover_image(mut self, path: &'a Path, image_type: ImageType) -> Self {
-        self.0.cover_image = Some(Resource::Image(path, image_type));
+        self.0.cover_image = Some(Resource::Image(path, image_type).into());
         self
     }
 
-    /// Adds a single external [`Resource`] (e.g., a font or extra image) to the EPUB package.
-    pub fn add_resource(mut self, resource: Resource<'a>) -> Self {
-        if let Some(ref mut resources) = self.0.resources {
-            resources.push(resource);
-        } else {
-            self.0.resources = Some(vec![resource]);
-        }
+    /// Sets the primary **cover image** from a [`ResourceItem`], e.g. to rename it inside the EPUB.
+    ///
+    /// The cover image is automatically registered as a resource.
+    pub fn cover_image_item(mut self, cover_image: ResourceItem<'a>) -> Self {
+        self.set_cover(cover_image);
         self
     }
 
-    /// Adds a collection of external [`Resource`] items to the EPUB package.
-    pub fn add_resources(mut self, resources: Vec<Resource<'a>>) -> Self {
-        if let Some(ref mut self_resources) = self.0.resources {
-            self_resources.extend(resources);
-        } else {
-            self.0.resources = Some(resources);
-        }
+    /// Sets a **generated cover** rendered from the title and creator when no cover image is supplied.
+    ///
+    /// It is ignored if [`EpubBuilder::cover_image`] or [`EpubBuilder::cover_image_item`] is also used.
+    pub fn generated_cover(mut self, generated_cover: GeneratedCover) -> Self {
+        self.0.generated_cover = Some(generated_cover);
         self
     }
 
-    /// Adds a single [`Content`] unit (like a chapter or section) to the main book flow.
-    pub fn add_content(mut self, content: Content<'a>) -> Self {
-        if let Some(ref mut contents) = self.0.contents {
-            contents.push(content);
-        } else {
-            self.0.contents = Some(vec![content]);
-        }
+    /// Sets whether a **cover page** is generated, showing the cover image (or the generated
+    /// cover) inside an SVG `viewBox` wrapper so it scales to fit any screen while keeping its
+    /// aspect ratio.
+    ///
+    /// The page (`cover.xhtml`) is the first of the reading order and the `cover` of the guide;
+    /// the `viewBox` is sized after the dimensions detected from the image (PNG, GIF, JPEG, WebP
+    /// or SVG). It is ignored without a cover image or generated cover. Defaults to `false`.
+    pub fn svg_cover_page(mut self, svg_cover_page: bool) -> Self {
+        self.0.svg_cover_page = svg_cover_page;
         self
     }
 
-    /// Adds a collection of [`Content`] units to the main book flow.
-    pub fn add_contents(mut self, contents: Vec<Content<'a>>) -> Self {
-        if let Some(ref mut self_contents) = self.0.contents {
-            self_contents.extend(contents);
-        } else {
-            self.0.contents = Some(contents);
-        }
+    /// Sets the **fixed layout** of a pre-paginated book (see [`FixedLayout`]), whose contents
+    /// are pages of fixed dimensions (e.g. the pages generated by the
+    /// [`ComicBuilder`](crate::epub::ComicBuilder)).
+    pub fn fixed_layout(mut self, fixed_layout: FixedLayout) -> Self {
+        self.0.fixed_layout = Some(fixed_layout);
         self
     }
 
-    /// Finalizes the builder and **synchronously** generates the EPUB file, writing the contents to the provided writer.
+    /// Sets the **image options** used to downscale and compress the cover image before writing it,
+    /// and optionally the other image resources.
     ///
-    /// Uses the default zip compression method.
-    ///
-    /// # Errors
-    /// Returns a [`crate::Result`] if there are any I/O issues or errors during XML generation.
-    pub fn create<W>(self, writer: &mut W) -> crate::Result
-    where
-        W: Write + Send,
-    {
-        self.create_with_compression(writer, ZipCompression::default())
+    /// This method is only available when the **`image` feature** is enabled.
+    #[cfg(feature = "image")]
+    pub fn image_options(mut self, image_options: ImageOptions) -> Self {
+        self.0.image_options = Some(image_options);
+        self
     }
 
-    /// Finalizes the builder and **synchronously** generates the EPUB file, using a specified zip compression method.
+    /// Builds the EPUB for the retailer of `profile` in one switch: writes its vendor files
+    /// (the iBooks display options file only for Apple Books), generates the SVG cover page it
+    /// shows, and compresses the archive entries (deflated).
     ///
-    /// # Errors
-    /// Returns a [`crate::Result`] if there are any I/O issues or errors during XML generation.
-    pub fn create_with_compression<W>(
-        self,
-        writer: &mut W,
-        compression: ZipCompression,
-    ) -> crate::Result
-    where
-        W: Write + Send,
-    {
-        EpubFile::new(self.0, writer, compression).create()
+    /// The cover constraints and the metadata requirements of the retailer are reported by
+    /// [`EpubBuilder::create_with_warnings`]. Each setting can still be overridden afterwards
+    /// (e.g. with [`EpubBuilder::svg_cover_page`]).
+    pub fn profile(mut self, profile: Profile) -> Self {
+        self.0.profile = Some(profile);
+        self.0.display_options = profile.display_options();
+        self.0.svg_cover_page = profile.svg_cover_page();
+        self.0.compression = ZipCompression::Deflated;
+        self
     }
 
-    /// **Asynchronously** generates the EPUB file, writing the contents to the provided `tokio::io::AsyncWrite` writer.
-    ///
-    /// This method is only available when the **`async` feature** is enabled.
-    #[cfg(feature = "async")]
-    pub async fn async_create<W>(self, writer: &mut W) -> crate::Result
-    where
-        W: tokio::io::AsyncWrite + Unpin + Send,
-    {
-        self.async_create_with_compression(writer, ZipCompression::default())
-            .await
+    /// Sets whether the Apple Books display options file
+    /// (`META-INF/com.apple.ibooks.display-options.xml`, enabling embedded fonts) is written.
+    /// Defaults to `true`.
+    pub fn display_options(mut self, display_options: bool) -> Self {
+        self.0.display_options = display_options;
+        self
     }
 
-    /// **Asynchronously** generates the EPUB file with a specified zip compression method.
-    ///
-    /// This method is only available when the **`async` feature** is enabled.
-    #[cfg(feature = "async")]
-    pub async fn async_create_with_compression<W>(
-        self,
-        writer: &mut W,
-        compression: ZipCompression,
-    ) -> crate::Result
-    where
-        W: tokio::io::AsyncWrite + Unpin + Send,
-    {
-        use crate::output::creator_async::EpubFile;
+    /// Sets the zip **compression** method of the archive entries (the `mimetype` is always
+    /// stored), used by the `create` methods that take none. Defaults to
+    /// [`ZipCompression::Stored`].
+    pub fn compression(mut self, compression: ZipCompression) -> Self {
+        self.0.compression = compression;
+        self
+    }
 
-        EpubFile::new(self.0, writer, compression).create().await
+    /// Makes the build **reproducible**: two builds of the same inputs are byte-identical.
+    ///
+    /// Zip entry timestamps are fixed (entry order and permissions are always stable), the current
+    /// UTC time is not used as publication date and the random UUID identifier is replaced by one
+    /// derived from the title and creator. A date or identifier set explicitly in the
+    /// [`Metadata`] is kept.
+    pub fn deterministic(mut self) -> Self {
+        self.0.deterministic = true;
+        self.0.metadata.make_deterministic();
+        self
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use std::fs::File;
+    /// Sets the **comment** of the zip archive (e.g. the producing tool and version),
+    /// as required by some publishing QA pipelines.
+    pub fn archive_comment<S: Into<String>>(mut self, archive_comment: S) -> Self {
+        self.0.archive_comment = Some(archive_comment.into());
+        self
+    }
 
-    use tempfile::tempdir;
+    /// Sets the **generator** (e.g. `my-tool 1.2.0`), declared as `<meta name="generator"/>`
+    /// in the package metadata.
+    pub fn generator<S: Into<String>>(mut self, generator: S) -> Self {
+        self.0.generator = Some(generator.into());
+        self
+    }
 
-    use super::*;
-    use crate::epub::{ContentBuilder, ContentReference, ReferenceType, metadata::MetadataBuilder};
+    /// Sets whether a machine-readable **build manifest** is embedded as the last file of the
+    /// archive, `META-INF/liber-manifest.json`, listing every other file with its size and
+    /// SHA-256 digest, and the liber version that generated them, so downstream QA tooling can
+    /// verify the package contents.
+    ///
+    /// Resource files streamed from disk are read once more to be hashed. Defaults to `false`.
+    pub fn build_manifest(mut self, build_manifest: bool) -> Self {
+        self.0.build_manifest = build_manifest;
+        self
+    }
 
-    #[test]
-    fn test_epub_builder_new() {
-        let metadata = MetadataBuilder::title("Title").build();
-        let builder = EpubBuilder::new(metadata);
+    /// Signs the package with `signer`, generating the XML digital signature
+    /// `META-INF/signatures.xml` as the last file of the archive, e.g. for institutional
+    /// distribution requirements.
+    ///
+    /// Every entry but the `mimetype` is signed, unless some are selected with
+    /// [`EpubBuilder::add_signed_entry`]. Resource files streamed from disk are read once more
+    /// to be hashed.
+    ///
+    /// This method is only available when the **`crypto` feature** is enabled.
+    #[cfg(feature = "crypto")]
+    pub fn sign(mut self, signer: &'a dyn PackageSigner) -> Self {
+        self.0.signer = Some(signer);
+        self
+    }
 
-        assert!(builder.0.stylesheet.is_none());
+    /// Adds the archive entry at `path` (e.g. `OEBPS/content.opf`) to the entries signed with
+    /// [`EpubBuilder::sign`], instead of all of them.
+    ///
+    /// Creating the EPUB fails with [`Error::FilenameNotFound`](crate::Error::FilenameNotFound)
+    /// if there is no such entry.
+    ///
+    /// This method is only available when the **`crypto` feature** is enabled.
+    #[cfg(feature = "crypto")]
+    pub fn add_signed_entry<S: Into<String>>(mut self, path: S) -> Self {
+        self.0
+            .signed_entries
+            .get_or_insert_with(Vec::new)
+            .push(path.into());
+        self
     }
 
-    #[test]
-    fn test_epub_builder_stylesheet_file() {
-        let metadata = MetadataBuilder::title("Title").build();
+    /// Sets the **Calibre** metadata section (sort title, series, rating, custom columns),
+    /// declared as `calibre:*` metas in the package metadata.
+    pub fn calibre(mut self, calibre: CalibreMetadata) -> Self {
+        self.0.calibre = Some(calibre);
+        self
+    }
 
+    /// Sets whether the generated XML files (OPF, NCX, XHTML) are **pretty-printed**.
+    ///
+    /// Indenting costs a full XML parse per file, so large builds can disable it to produce
+    /// compact output faster. Note that the content bodies are then not checked to be
+    /// well-formed XML. Defaults to `true`.
+    pub fn pretty_print(mut self, pretty_print: bool) -> Self {
+        self.0.pretty_print = pretty_print;
+        self
+    }
+
+    /// Sets whether the XHTML files of all the contents (loading the body, wrapping it and
+    /// pretty-printing it) are rendered **in parallel** threads before the archive is written,
+    /// and the `content.opf` and `toc.ncx` files formatted at the same time.
+    ///
+    /// XML formatting dominates the time of large synchronous builds, so this spreads it over
+    /// all the cores, at the cost of holding every rendered chapter in memory at once. When
+    /// disabled, the chapters are rendered one at a time, as they are written. Defaults to
+    /// `true`.
+    ///
+    /// This method is only available when the **`rayon` feature** is enabled.
+    #[cfg(feature = "rayon")]
+    pub fn parallel_rendering(mut self, parallel_rendering: bool) -> Self {
+        self.0.parallel_rendering = parallel_rendering;
+        self
+    }
+
+    /// Sets whether **DPUB-ARIA roles** are added to the generated XHTML wrappers, improving
+    /// screen-reader navigation.
+    ///
+    /// The body of every content is wrapped in a `<section>` with the role of its reference type
+    /// (e.g. `doc-chapter`, `doc-toc`, `doc-endnotes`) labelled with its title, and footnotes and
+    /// note references marked with `epub:type` get the `doc-footnote` and `doc-noteref` roles.
+    /// Bodies given as complete XHTML documents are left untouched.
+    ///
+    /// These attributes target EPUB 3 reading systems: the XHTML 1.1 DTD of EPUB 2 does not
+    /// declare them, so epubcheck reports them. Defaults to `false`.
+    pub fn aria_roles(mut self, aria_roles: bool) -> Self {
+        self.0.aria_roles = aria_roles;
+        self
+    }
+
+    /// Sets whether the title, creator and language of the book are declared as Dublin Core
+    /// `<meta>` tags (`DC.title`, `DC.creator`, `DC.language`) in the header of every generated
+    /// XHTML file, so single pages extracted from the book remain attributable.
+    ///
+    /// Bodies given as complete XHTML documents are left untouched. Defaults to `false`.
+    pub fn dublin_core_meta(mut self, dublin_core_meta: bool) -> Self {
+        self.0.dublin_core_meta = dublin_core_meta;
+        self
+    }
+
+    /// Sets whether the **chapters** ([`ReferenceType::Text`]) start on a new page: their
+    /// `<body>` gets `page-break-before: always` and its EPUB 3 counterpart
+    /// `break-before: page` as inline CSS, so no stylesheet is needed.
+    ///
+    /// Any content can opt in or out with [`ContentBuilder::page_break`]. Bodies given as
+    /// complete XHTML documents are left untouched. Defaults to `false`.
+    pub fn page_breaks(mut self, page_breaks: bool) -> Self {
+        self.0.page_breaks = page_breaks;
+        self
+    }
+
+    /// Sets whether the **straight quotes**, double hyphens and triple dots of the text of every
+    /// content are converted to typographic quotes (`“”‘’`), em dashes (`—`) and ellipses (`…`).
+    ///
+    /// Only text nodes are converted: tags, attributes, comments and the text of `<code>`,
+    /// `<pre>`, `<kbd>`, `<samp>`, `<var>`, `<script>` and `<style>` are left untouched, as are
+    /// bodies given as complete XHTML documents. Defaults to `false`.
+    pub fn smart_punctuation(mut self, smart_punctuation: bool) -> Self {
+        self.0.smart_punctuation = smart_punctuation;
+        self
+    }
+
+    /// Sets how the **external links** (`<a>` elements whose `href` has a URI scheme, like
+    /// `https:` or `mailto:`) of every content are annotated with their URL, for reading
+    /// systems without a browser and printouts (see [`LinkAnnotation`]).
+    ///
+    /// Bodies given as complete XHTML documents are left untouched. The syntax of the external
+    /// links is checked regardless (see [`Warning::InvalidLink`]).
+    pub fn external_links(mut self, annotation: LinkAnnotation) -> Self {
+        self.0.external_links = Some(annotation);
+        self
+    }
+
+    /// Sets the base **text direction** (e.g. [`TextDirection::Rtl`] for Hebrew, Arabic or
+    /// Persian), declared with the `dir` attribute on the `<html>` of every generated XHTML file.
+    ///
+    /// Bodies given as complete XHTML documents are left untouched. By default no direction is
+    /// declared, and reading systems assume left to right.
+    pub fn text_direction(mut self, text_direction: TextDirection) -> Self {
+        self.0.text_direction = Some(text_direction);
+        self
+    }
+
+    /// Sets the **maximum depth** of the table of contents (`toc.ncx`), e.g. `1` to list the
+    /// top-level contents only, however deeply the contents and their references are nested.
+    ///
+    /// Deeper entries are left out of the navigation map (their files are still part of the
+    /// book), and `dtb:depth` is adjusted accordingly. A depth of `0` is treated as `1`. By
+    /// default the full hierarchy is listed.
+    pub fn toc_depth(mut self, toc_depth: usize) -> Self {
+        self.0.toc_depth = Some(toc_depth.max(1));
+        self
+    }
+
+    /// Sets whether the table of contents (`toc.ncx`) is **flattened**: all the navigation
+    /// points are written at one level, in reading order, regardless of the nesting of the
+    /// contents and their references.
+    ///
+    /// Some dated reading apps render a flat list better than a deep hierarchy. The entries left
+    /// out by [`EpubBuilder::toc_depth`] are still left out. Defaults to `false`.
+    pub fn flat_toc(mut self, flat_toc: bool) -> Self {
+        self.0.flat_toc = flat_toc;
+        self
+    }
+
+    /// Sets the **reading order** (the spine) of the contents by their filenames (e.g.
+    /// `c03.xhtml`, or the one set with [`ContentBuilder::filename`]), when it differs from the
+    /// order of the table of contents.
+    ///
+    /// E.g. notes nested under their chapter in the table of contents can be read at the end of
+    /// the book. The contents left out follow the listed ones, in book order. Filenames that
+    /// are not contents, or listed twice, are reported by [`EpubBuilder::validate`] and skipped.
+    pub fn spine_order<I, S>(mut self, filenames: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.0.spine_order = Some(filenames.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Numbers the table of contents entries of the top-level contents of a **part** of the book
+    /// in a **style** (e.g. `ii. Preface` for the front matter in lowercase roman numerals,
+    /// `3. The Road` for the body matter in arabic numerals), and the
+    /// [`PageTarget::numbered`] pages of that part.
+    ///
+    /// Each part is numbered on its own, starting at `1`; the parts without a style are not
+    /// numbered. The cover, title page, copyright page, dedication, epigraph, colophon and
+    /// lists do not belong to any part and are never numbered.
+    pub fn numbering(mut self, part: BookPart, style: NumberingStyle) -> Self {
+        self.0.numbering.set_style(part, style);
+        self
+    }
+
+    /// Sets whether the titles, the metadata, the filenames, the anchor ids and the content
+    /// bodies are converted to **Unicode Normalization Form C** (precomposed characters) when
+    /// generating the files.
+    ///
+    /// Sources with decomposed characters (e.g. macOS filenames, some CMSes) otherwise cause
+    /// search and sort glitches in reading systems. Defaults to `true`.
+    pub fn unicode_normalization(mut self, unicode_normalization: bool) -> Self {
+        self.0.unicode_normalization = unicode_normalization;
+        self
+    }
+
+    /// Adds a single external [`Resource`] (e.g., a font or extra image) to the EPUB package.
+    ///
+    /// Accepts either a plain [`Resource`] or a [`ResourceItem`] carrying packaging options.
+    pub fn add_resource<R: Into<ResourceItem<'a>>>(mut self, resource: R) -> Self {
+        self.push_resource(resource);
+        self
+    }
+
+    /// Adds a collection of external [`Resource`] items to the EPUB package.
+    ///
+    /// Accepts any iterable (e.g. a `Vec` or a mapped iterator) of resources or [`ResourceItem`]s.
+    pub fn add_resources<I>(mut self, resources: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<ResourceItem<'a>>,
+    {
+        self.push_resources(resources);
+        self
+    }
+
+    /// Alias of [`EpubBuilder::add_resources`].
+    pub fn extend_resources<I>(self, resources: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<ResourceItem<'a>>,
+    {
+        self.add_resources(resources)
+    }
+
+    /// Adds every file of a walked [`ResourceDir`] as a resource, preserving the relative
+    /// subpaths under `OEBPS/` and inferring the media types from the file extensions.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn add_resources_from_dir(self, resource_dir: &'a ResourceDir) -> Self {
+        self.add_resources(resource_dir.resources())
+    }
+
+    /// Adds a single [`Content`] unit (like a chapter or section) to the main book flow.
+    pub fn add_content(mut self, content: Content<'a>) -> Self {
+        self.push_content(content);
+        self
+    }
+
+    /// Adds a collection of [`Content`] units to the main book flow.
+    ///
+    /// Accepts any iterable (e.g. a `Vec` or a mapped iterator).
+    pub fn add_contents<I: IntoIterator<Item = Content<'a>>>(mut self, contents: I) -> Self {
+        self.push_contents(contents);
+        self
+    }
+
+    /// Alias of [`EpubBuilder::add_contents`].
+    pub fn extend_contents<I: IntoIterator<Item = Content<'a>>>(self, contents: I) -> Self {
+        self.add_contents(contents)
+    }
+
+    /// Adds a single [`PageTarget`] to the `pageList` of the table of contents (`toc.ncx`),
+    /// mapping a print page number to its location in the book.
+    pub fn add_page_target(mut self, page_target: PageTarget) -> Self {
+        self.push_page_target(page_target);
+        self
+    }
+
+    /// Adds a collection of [`PageTarget`]s, in reading order, to the `pageList` of the table of
+    /// contents (`toc.ncx`).
+    ///
+    /// Accepts any iterable (e.g. a `Vec` or a mapped iterator).
+    pub fn add_page_targets<I: IntoIterator<Item = PageTarget>>(mut self, page_targets: I) -> Self {
+        extend_list(&mut self.0.page_list, page_targets);
+        self
+    }
+
+    /// Adds a [`NavList`] (e.g. a list of illustrations or of tables) to the table of contents
+    /// (`toc.ncx`), after the navigation map and the page list.
+    pub fn add_nav_list(mut self, nav_list: NavList) -> Self {
+        self.push_nav_list(nav_list);
+        self
+    }
+
+    /// Generates a list of illustrations titled `title`: a page listing, with links, the
+    /// figures of the contents added so far (their `<figure>` elements, labelled with their
+    /// `<figcaption>`, or else with the alt text of their image), guided as
+    /// [`ReferenceType::Loi`], and the same list as a [`NavList`] of the table of contents.
+    ///
+    /// Call it once every content is added. The page is placed right after the table of
+    /// contents (or else before the first chapter). Figures with an `id` are linked to directly,
+    /// the others to their content. Only the bodies in memory are scanned: the figures of the
+    /// ones read from a file or produced by a [`ContentSource`](crate::epub::ContentSource) are
+    /// registered with [`ContentBuilder::add_figure`]. Nothing is added if no figure is found.
+    ///
+    /// ```rust
+    /// use liber::epub::{ContentBuilder, EpubBuilder, MetadataBuilder, ReferenceType};
+    ///
+    /// let epub_builder = EpubBuilder::new(MetadataBuilder::title("Atlas").build())
+    ///     .add_content(
+    ///         ContentBuilder::new(
+    ///             br#"<body><figure id="map"><img src="map.png" alt="Map"/>
+    ///                 <figcaption>The old map</figcaption></figure></body>"#,
+    ///             ReferenceType::Text("Maps".to_string()),
+    ///         )
+    ///         .build(),
+    ///     )
+    ///     .list_of_illustrations("Illustrations");
+    /// assert_eq!(epub_builder.content_count(), 2);
+    /// assert_eq!(epub_builder.nav_lists().len(), 1);
+    /// ```
+    pub fn list_of_illustrations<S: Into<String>>(mut self, title: S) -> Self {
+        self.insert_generated_list(ReferenceType::Loi(title.into()));
+        self
+    }
+
+    /// Generates a list of tables titled `title`, like [`EpubBuilder::list_of_illustrations`]:
+    /// a page listing, with links, the tables of the contents added so far (their `<table>`
+    /// elements with a `<caption>`, and the ones registered with [`ContentBuilder::add_table`]),
+    /// guided as [`ReferenceType::Lot`], and the same list as a [`NavList`].
+    ///
+    /// Call it once every content is added. The page is placed after the table of contents and
+    /// the lists generated before it (or else before the first chapter). Nothing is added if no
+    /// table is found.
+    ///
+    /// ```rust
+    /// use liber::epub::{ContentBuilder, EpubBuilder, MetadataBuilder, ReferenceType};
+    ///
+    /// let epub_builder = EpubBuilder::new(MetadataBuilder::title("Almanac").build())
+    ///     .add_content(
+    ///         ContentBuilder::new(
+    ///             br#"<body><table id="tides"><caption>Tides</caption></table></body>"#,
+    ///             ReferenceType::Text("Sea".to_string()),
+    ///         )
+    ///         .build(),
+    ///     )
+    ///     .list_of_tables("Tables");
+    /// assert_eq!(epub_builder.content_count(), 2);
+    /// ```
+    pub fn list_of_tables<S: Into<String>>(mut self, title: S) -> Self {
+        self.insert_generated_list(ReferenceType::Lot(title.into()));
+        self
+    }
+
+    /// Generates a back-of-book index titled `title`: an alphabetized page, grouped by initial
+    /// letter, listing the terms tagged in the contents added so far (see
+    /// [`ContentBuilder::add_index_term`]) with numbered links back to every occurrence, and
+    /// guided as [`ReferenceType::Index`].
+    ///
+    /// Call it once every content is added. The page is placed at the end of the book. Nothing is
+    /// added if no term is tagged.
+    ///
+    /// ```rust
+    /// use liber::epub::{ContentBuilder, EpubBuilder, IndexTerm, MetadataBuilder, ReferenceType};
+    ///
+    /// let epub_builder = EpubBuilder::new(MetadataBuilder::title("Geography").build())
+    ///     .add_content(
+    ///         ContentBuilder::new(
+    ///             br#"<body><p id="nile">The Nile flows north.</p></body>"#,
+    ///             ReferenceType::Text("Rivers".to_string()),
+    ///         )
+    ///         .add_index_term(IndexTerm::new("Nile", "nile"))
+    ///         .build(),
+    ///     )
+    ///     .index("Index");
+    /// assert_eq!(epub_builder.content_count(), 2);
+    /// ```
+    pub fn index<S: Into<String>>(mut self, title: S) -> Self {
+        self.insert_generated_list(ReferenceType::Index(title.into()));
+        self
+    }
+
+    /// Inserts a generated list page guided as `reference_type` (a [`ReferenceType::Loi`],
+    /// [`ReferenceType::Lot`] or [`ReferenceType::Index`]), and its [`NavList`] if any. Nothing
+    /// is inserted if it lists nothing.
+    ///
+    /// The index goes at the end of the book. The other lists go right after the table of
+    /// contents and the lists generated before them, or else before the first chapter.
+    fn insert_generated_list(&mut self, reference_type: ReferenceType) {
+        let Some(ref mut contents) = self.0.contents else {
+            return;
+        };
+        if let ReferenceType::Index(_) = reference_type {
+            contents.push(ContentBuilder::generated(String::new(), reference_type).build());
+            let index = contents.len() - 1;
+            if !self.render_generated_list(index)
+                && let Some(ref mut contents) = self.0.contents
+            {
+                contents.pop();
+            }
+            return;
+        }
+        let is_list = |content: &Content<'_>| {
+            matches!(
+                content.reference_type,
+                ReferenceType::Loi(_) | ReferenceType::Lot(_)
+            )
+        };
+        let index = contents
+            .iter()
+            .position(|content| matches!(content.reference_type, ReferenceType::Toc(_)))
+            .map(|toc| {
+                toc + 1
+                    + contents[toc + 1..]
+                        .iter()
+                        .take_while(|c| is_list(c))
+                        .count()
+            })
+            .or_else(|| {
+                contents
+                    .iter()
+                    .position(|content| matches!(content.reference_type, ReferenceType::Text(_)))
+            })
+            .unwrap_or(contents.len());
+
+        // The placeholder takes its place first, so the filenames of the targets are final.
+        contents.insert(
+            index,
+            ContentBuilder::generated(String::new(), reference_type).build(),
+        );
+        if !self.render_generated_list(index) {
+            if let Some(ref mut contents) = self.0.contents {
+                contents.remove(index);
+            }
+            return;
+        }
+
+        // The new page shifts the generated filenames after it, so the lists generated before
+        // are rendered again.
+        let generated = self
+            .contents()
+            .iter()
+            .enumerate()
+            .filter(|(other, content)| *other != index && content.is_generated())
+            .map(|(other, _)| other)
+            .collect::<Vec<_>>();
+        for other in generated {
+            self.render_generated_list(other);
+        }
+    }
+
+    /// Renders the generated list page at `index` of the contents, linking to the figures (list
+    /// of illustrations), the tables (list of tables) or the index terms (index) of the
+    /// contents, and its [`NavList`] (replacing the one with the same heading) if it is not the
+    /// index. Returns whether it lists anything.
+    fn render_generated_list(&mut self, index: usize) -> bool {
+        let Some(ref mut contents) = self.0.contents else {
+            return false;
+        };
+        let reference_type = contents[index].reference_type.clone();
+        if let ReferenceType::Index(ref title) = reference_type {
+            let mut occurrences = Vec::new();
+            let mut number = 0;
+            for content in contents.iter() {
+                content.walk(&mut number, &mut |content, filename| {
+                    let terms = content.index_terms().iter();
+                    occurrences.extend(terms.map(|term| (term, filename.clone())));
+                });
+            }
+            if occurrences.is_empty() {
+                return false;
+            }
+            let body = index_term::index_body(title, &occurrences);
+            contents[index] = ContentBuilder::generated(body, reference_type).build();
+            return true;
+        }
+        let (class, find): (_, fn(&Content<'a>) -> Vec<Captioned>) = match reference_type {
+            ReferenceType::Loi(_) => ("loi", Content::figures),
+            ReferenceType::Lot(_) => ("lot", Content::tables),
+            _ => return false,
+        };
+
+        let mut targets = Vec::new();
+        let mut number = 0;
+        for content in contents.iter() {
+            content.walk(&mut number, &mut |content, filename| {
+                targets.extend(find(content).iter().map(|found| found.target(&filename)));
+            });
+        }
+        if targets.is_empty() {
+            return false;
+        }
+
+        let title = reference_type.type_and_title().1.to_string();
+        let body = captioned::list_body(&title, class, &targets);
+        contents[index] = ContentBuilder::generated(body, reference_type).build();
+        let nav_list = NavList::new(title).add_targets(targets);
+        let nav_lists = self.0.nav_lists.get_or_insert_with(Vec::new);
+        match nav_lists
+            .iter_mut()
+            .find(|list| list.label == nav_list.label)
+        {
+            Some(list) => *list = nav_list,
+            None => nav_lists.push(nav_list),
+        }
+        true
+    }
+
+    /// Registers an [`EpubPlugin`], whose callbacks transform the generated files (metadata,
+    /// rendered chapters, package document and archive entries). Plugins run in registration
+    /// order.
+    pub fn add_plugin(mut self, plugin: &'a dyn EpubPlugin) -> Self {
+        self.push_plugin(plugin);
+        self
+    }
+
+    /// Registers a [`TextTransform`] of the body of every content, run when its XHTML is
+    /// generated. Transforms run in registration order, before smart punctuation and
+    /// typography.
+    pub fn add_text_transform(mut self, text_transform: TextTransform) -> Self {
+        extend_list(&mut self.0.text_transforms, [text_transform]);
+        self
+    }
+
+    /// Sets the raw byte content of the stylesheet, like [`EpubBuilder::stylesheet`] but
+    /// through a mutable reference.
+    ///
+    /// The `push_*` and `set_*` methods mirror the fluent ones for building the EPUB inside
+    /// loops and conditional logic, without reassigning the builder.
+    pub fn set_stylesheet(&mut self, stylesheet: &'a [u8]) -> &mut Self {
+        self.0.stylesheet = Some(stylesheet);
+        self
+    }
+
+    /// Sets the primary **cover image**, like [`EpubBuilder::cover_image_item`] but through a
+    /// mutable reference.
+    ///
+    /// Accepts either a plain [`Resource`] or a [`ResourceItem`] carrying packaging options.
+    pub fn set_cover<R: Into<ResourceItem<'a>>>(&mut self, cover_image: R) -> &mut Self {
+        self.0.cover_image = Some(cover_image.into());
+        self
+    }
+
+    /// Adds a single [`Content`] unit, like [`EpubBuilder::add_content`] but through a
+    /// mutable reference.
+    pub fn push_content(&mut self, mut content: Content<'a>) -> &mut Self {
+        for resource in content.take_resources() {
+            if !self.resources().contains(&resource) {
+                self.push_resource(resource);
+            }
+        }
+        if let Some(ref mut contents) = self.0.contents {
+            contents.push(content);
+        } else {
+            self.0.contents = Some(vec![content]);
+        }
+        self
+    }
+
+    /// Adds a collection of [`Content`] units, like [`EpubBuilder::add_contents`] but through
+    /// a mutable reference.
+    pub fn push_contents<I: IntoIterator<Item = Content<'a>>>(&mut self, contents: I) -> &mut Self {
+        for content in contents {
+            self.push_content(content);
+        }
+        self
+    }
+
+    /// Adds a single external resource, like [`EpubBuilder::add_resource`] but through a
+    /// mutable reference.
+    pub fn push_resource<R: Into<ResourceItem<'a>>>(&mut self, resource: R) -> &mut Self {
+        let resource = resource.into();
+        if let Some(ref mut resources) = self.0.resources {
+            resources.push(resource);
+        } else {
+            self.0.resources = Some(vec![resource]);
+        }
+        self
+    }
+
+    /// Adds a collection of external resources, like [`EpubBuilder::add_resources`] but
+    /// through a mutable reference.
+    pub fn push_resources<I>(&mut self, resources: I) -> &mut Self
+    where
+        I: IntoIterator,
+        I::Item: Into<ResourceItem<'a>>,
+    {
+        extend_list(&mut self.0.resources, resources.into_iter().map(Into::into));
+        self
+    }
+
+    /// Adds a single [`PageTarget`], like [`EpubBuilder::add_page_target`] but through a
+    /// mutable reference.
+    pub fn push_page_target(&mut self, page_target: PageTarget) -> &mut Self {
+        if let Some(ref mut page_list) = self.0.page_list {
+            page_list.push(page_target);
+        } else {
+            self.0.page_list = Some(vec![page_target]);
+        }
+        self
+    }
+
+    /// Adds a [`NavList`], like [`EpubBuilder::add_nav_list`] but through a mutable reference.
+    pub fn push_nav_list(&mut self, nav_list: NavList) -> &mut Self {
+        if let Some(ref mut nav_lists) = self.0.nav_lists {
+            nav_lists.push(nav_list);
+        } else {
+            self.0.nav_lists = Some(vec![nav_list]);
+        }
+        self
+    }
+
+    /// Registers an [`EpubPlugin`], like [`EpubBuilder::add_plugin`] but through a mutable
+    /// reference.
+    pub fn push_plugin(&mut self, plugin: &'a dyn EpubPlugin) -> &mut Self {
+        if let Some(ref mut plugins) = self.0.plugins {
+            plugins.push(plugin);
+        } else {
+            self.0.plugins = Some(vec![plugin]);
+        }
+        self
+    }
+
+    /// Sets the [`Spawner`] running the blocking work of the async API (reading resource files
+    /// and formatting the XML files), e.g. to use the async API on a runtime other than Tokio.
+    ///
+    /// This method is only available when the **`async` feature** is enabled.
+    #[cfg(feature = "async")]
+    pub fn spawner(mut self, spawner: &'a dyn Spawner) -> Self {
+        self.0.spawner = Some(spawner);
+        self
+    }
+
+    /// Gets the metadata of the EPUB for in-place edits.
+    pub fn metadata_mut(&mut self) -> &mut Metadata {
+        &mut self.0.metadata
+    }
+
+    /// Gets the metadata of the EPUB.
+    pub fn metadata(&self) -> &Metadata {
+        &self.0.metadata
+    }
+
+    /// Whether the EPUB has a stylesheet: one is set, or `@font-face` rules are generated for
+    /// embedded fonts.
+    pub fn has_stylesheet(&self) -> bool {
+        self.0.has_stylesheet()
+    }
+
+    /// Whether a cover image is set.
+    pub fn has_cover_image(&self) -> bool {
+        self.0.cover_image.is_some()
+    }
+
+    /// Gets the added resources, in insertion order (the cover image is not included).
+    pub fn resources(&self) -> &[ResourceItem<'a>] {
+        self.0.resources.as_deref().unwrap_or_default()
+    }
+
+    /// Gets the filenames of the added resources inside the EPUB, skipping the ones without a
+    /// valid filename.
+    pub fn resource_names(&self) -> Vec<String> {
+        self.resources()
+            .iter()
+            .filter_map(|resource| resource.filename().ok())
+            .collect()
+    }
+
+    /// Gets the top-level content units, in reading order.
+    pub fn contents(&self) -> &[Content<'a>] {
+        self.0.contents.as_deref().unwrap_or_default()
+    }
+
+    /// Gets the total number of content units (and so of XHTML files), counting nested
+    /// subcontents.
+    pub fn content_count(&self) -> usize {
+        self.contents().iter().map(Content::file_count).sum()
+    }
+
+    /// Gets the added print page targets.
+    pub fn page_targets(&self) -> &[PageTarget] {
+        self.0.page_list.as_deref().unwrap_or_default()
+    }
+
+    /// Gets the added secondary navigation lists.
+    pub fn nav_lists(&self) -> &[NavList] {
+        self.0.nav_lists.as_deref().unwrap_or_default()
+    }
+
+    /// Prints the structure of the book as an indented tree: the stylesheet, the cover, the
+    /// contents (with their nesting, content references and generated filenames) and the
+    /// resources, showing what will be generated without building the EPUB.
+    ///
+    /// This is the same output as the [`Display`](fmt::Display) implementation.
+    pub fn debug_tree(&self) -> String {
+        self.to_string()
+    }
+
+    /// Finalizes the builder and **synchronously** generates the EPUB file, writing the contents to the provided writer.
+    ///
+    /// Uses the zip compression method set with [`EpubBuilder::compression`].
+    ///
+    /// # Errors
+    /// Returns a [`crate::Result`] if there are any I/O issues or errors during XML generation.
+    pub fn create<W>(self, writer: &mut W) -> crate::Result
+    where
+        W: Write + Send,
+    {
+        let compression = self.0.compression;
+        self.create_with_compression(writer, compression)
+    }
+
+    /// Finalizes the builder and **synchronously** generates the EPUB file, using a specified zip compression method.
+    ///
+    /// The cover image and resources are validated before anything is written to `writer`.
+    ///
+    /// # Errors
+    /// Returns a [`crate::Result`] if there are any I/O issues or errors during XML generation,
+    /// or a [`crate::Error::InvalidResources`] if the resources are not valid.
+    pub fn create_with_compression<W>(
+        self,
+        writer: &mut W,
+        compression: ZipCompression,
+    ) -> crate::Result
+    where
+        W: Write + Send,
+    {
+        self.0.validate_resources()?;
+        EpubFile::new(self.0, writer, compression).create()
+    }
+
+    /// Finalizes the builder and **synchronously** generates the EPUB file like
+    /// [`EpubBuilder::create`], also returning the non-fatal quality issues found (deprecated
+    /// guide types, images without alt text, oversized files, unreferenced resources...).
+    ///
+    /// The EPUB is written whatever the warnings, so pipelines can surface them without
+    /// failing the build.
+    ///
+    /// # Errors
+    /// Returns the same errors as [`EpubBuilder::create`].
+    pub fn create_with_warnings<W>(self, writer: &mut W) -> crate::Result<Vec<Warning>>
+    where
+        W: Write + Send,
+    {
+        self.0.validate_resources()?;
+        let compression = self.0.compression;
+        EpubFile::new(self.0, writer, compression).create_with_warnings()
+    }
+
+    /// Finalizes the builder and **synchronously** generates the EPUB file like
+    /// [`EpubBuilder::create`], also returning the SHA-256 checksum of every entry of the
+    /// archive (in the order they are written) for integrity verification in distribution
+    /// pipelines.
+    ///
+    /// To embed the checksums in the archive instead, see [`EpubBuilder::build_manifest`].
+    ///
+    /// # Errors
+    /// Returns the same errors as [`EpubBuilder::create`].
+    pub fn create_with_checksums<W>(self, writer: &mut W) -> crate::Result<Vec<Checksum>>
+    where
+        W: Write + Send,
+    {
+        self.0.validate_resources()?;
+        let compression = self.0.compression;
+        EpubFile::new(self.0, writer, compression).create_with_checksums()
+    }
+
+    /// Finalizes the builder and returns an iterator over the generated files, in the order
+    /// they are written to the archive (starting with `mimetype`, which must be stored
+    /// uncompressed), instead of packaging them.
+    ///
+    /// Files are generated as the iterator advances, so consumers can process them
+    /// incrementally (e.g. upload them as they are produced, or package them in a custom way).
+    ///
+    /// ```rust
+    /// use liber::epub::{ContentBuilder, EpubBuilder, MetadataBuilder, ReferenceType};
+    ///
+    /// let epub_builder = EpubBuilder::new(MetadataBuilder::title("My Book").build()).add_content(
+    ///     ContentBuilder::new(b"<body><p>Hi</p></body>", ReferenceType::Text("One".to_string()))
+    ///         .build(),
+    /// );
+    ///
+    /// for file in epub_builder.files()? {
+    ///     let file = file?;
+    ///     println!("{} ({} bytes)", file.filepath, file.bytes.len());
+    /// }
+    /// # Ok::<(), liber::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    /// Returns a [`crate::Error::InvalidResources`] if the resources are not valid. The iterator
+    /// returns the same errors as [`EpubBuilder::create`] while generating the files.
+    pub fn files(self) -> crate::Result<EpubFiles<'a>> {
+        self.0.validate_resources()?;
+        EpubFiles::new(self.0)
+    }
+
+    /// Finalizes the builder and returns a stream of the generated files, like
+    /// [`EpubBuilder::files`], generating them with the async API (see
+    /// [`EpubBuilder::async_create_futures`]).
+    ///
+    /// This method is only available when the **`async` feature** is enabled.
+    ///
+    /// # Errors
+    /// Returns the same errors as [`EpubBuilder::files`].
+    #[cfg(feature = "async")]
+    pub fn async_files(
+        self,
+    ) -> crate::Result<
+        impl futures::Stream<Item = crate::Result<FileContent<String, FileBytes>>> + 'a,
+    > {
+        self.0.validate_resources()?;
+        crate::output::files::async_files(self.0)
+    }
+
+    /// Runs the build without producing the archive, returning the files that would be written
+    /// in order, with their media types and sizes.
+    ///
+    /// This allows tools to preview a build and catch problems (e.g. missing resources) quickly.
+    /// Resources are loaded to report their final sizes, after deduplication and optimization.
+    ///
+    /// # Errors
+    /// Returns the same errors as [`EpubBuilder::create`].
+    pub fn plan(&self) -> crate::Result<Vec<PlannedFile>> {
+        self.0.validate_resources()?;
+
+        let epub = self.normalized();
+        let mut sink = PlanSink::default();
+        EpubFile::with_sink(epub.clone(), &mut sink).create()?;
+
+        Ok(sink
+            .files
+            .into_iter()
+            .map(|file| PlannedFile {
+                media_type: epub.manifest_media_type(&file.path),
+                ..file
+            })
+            .collect())
+    }
+
+    /// Checks the book without creating it, reporting every problem found at once instead of
+    /// failing on the first one while writing:
+    ///
+    /// - **filenames**: content filenames must end with `.xhtml` and be unique among all files.
+    /// - **identifiers**: the book identifier must be set (and be a valid ISBN if it is one), and
+    ///   the anchor IDs of the content references must be valid and unique within their file.
+    /// - **references**: content references must point to anchors of their body (when it is in
+    ///   memory), and page and navigation targets to existing contents.
+    /// - **resources**: the same checks as [`EpubBuilder::create`] (see [`crate::Error::InvalidResources`]).
+    ///
+    /// # Errors
+    /// Returns every problem found, or `Ok(())` if there are none.
+    pub fn validate(&self) -> std::result::Result<(), Vec<crate::Error>> {
+        let problems = self.0.problems();
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems)
+        }
+    }
+
+    /// Builds the EPUB in memory and checks it with [`crate::validate`]: mimetype placement,
+    /// manifest/spine consistency, missing hrefs, duplicate ids and guide correctness.
+    ///
+    /// This allows catching problems before running epubcheck on the generated file.
+    ///
+    /// # Returns
+    /// Returns every problem found, or an empty vector if the EPUB is valid.
+    ///
+    /// # Errors
+    /// Returns the same errors as [`EpubBuilder::create`].
+    pub fn validate_archive(&self) -> crate::Result<Vec<ValidationIssue>> {
+        let bytes = self.clone().create_to_vec()?;
+        validation::validate_archive(Cursor::new(bytes))
+    }
+
+    /// Checks every `http` and `https` external link of the content bodies in memory with a
+    /// `HEAD` request (see [`crate::output::http::check`]), returning the ones that fail, in
+    /// reading order. Every URL is requested once, however many contents link to it.
+    ///
+    /// This function is only compiled when the **`http` feature** is enabled.
+    #[cfg(feature = "http")]
+    pub fn dead_links(&self) -> Vec<DeadLink> {
+        let mut links = Vec::new();
+        let mut number = 0;
+        for content in self.contents() {
+            content.walk(&mut number, &mut |content, filename| {
+                let urls = content
+                    .body()
+                    .map(external_link::external_links)
+                    .unwrap_or_default();
+                links.extend(urls.into_iter().map(|url| (filename.clone(), url)));
+            });
+        }
+
+        let mut checked = HashMap::new();
+        links
+            .into_iter()
+            .filter(|(_, url)| {
+                let scheme = url.split(':').next().unwrap_or_default();
+                scheme.eq_ignore_ascii_case("http") || scheme.eq_ignore_ascii_case("https")
+            })
+            .filter_map(|(filename, url)| {
+                let reason = checked
+                    .entry(url.clone())
+                    .or_insert_with(|| http::check(&url).err().map(|e| e.to_string()))
+                    .clone()?;
+                Some(DeadLink {
+                    filename,
+                    url,
+                    reason,
+                })
+            })
+            .collect()
+    }
+
+    /// Splits the book into one builder per **top-level content** (with its subcontents), e.g. to
+    /// publish a book as a serial.
+    ///
+    /// Every part carries over the metadata, the stylesheet, the cover and the other settings of
+    /// this builder, and only the resources it references: those whose filename appears in the
+    /// content bodies, or in the stylesheet (e.g. fonts). The title of each part is suffixed with
+    /// the title of its content, and its identifier is derived from the book identifier and the
+    /// part number. Links between contents of different parts are not rewritten, and the spine
+    /// order is not carried over.
+    ///
+    /// Each part keeps only the page targets and the [`NavList`] entries that point into its own
+    /// files (renamed after the files of the part), and drops the lists left empty. The generated
+    /// lists of illustrations and tables and the generated index do not become parts.
+    ///
+    /// Returns an empty vector if the book has no contents.
+    pub fn split(&self) -> Vec<EpubBuilder<'a>> {
+        let Some(ref contents) = self.0.contents else {
+            return Vec::new();
+        };
+
+        let stylesheet = self.0.stylesheet_content();
+        let stylesheet = stylesheet.as_deref().map(String::from_utf8_lossy);
+
+        contents
+            .iter()
+            .zip(Content::first_numbers(contents, 0))
+            .filter(|(content, _)| {
+                !(content.is_generated()
+                    && matches!(
+                        content.reference_type,
+                        ReferenceType::Loi(_) | ReferenceType::Lot(_) | ReferenceType::Index(_)
+                    ))
+            })
+            .enumerate()
+            .map(|(index, (content, mut number))| {
+                // The filenames of this content in the whole book, and in the part.
+                let mut filenames = HashMap::new();
+                let mut book_filenames = Vec::new();
+                content.walk(&mut number, &mut |_, filename| {
+                    book_filenames.push(filename)
+                });
+                let mut book_filenames = book_filenames.into_iter();
+                content.walk(&mut 0, &mut |_, filename| {
+                    if let Some(book_filename) = book_filenames.next() {
+                        filenames.insert(book_filename, filename);
+                    }
+                });
+                let retarget = |src: &str| {
+                    let (file, fragment) = src.split_once('#').unwrap_or((src, ""));
+                    let file = filenames.get(file)?;
+                    Some(if fragment.is_empty() {
+                        file.clone()
+                    } else {
+                        format!("{file}#{fragment}")
+                    })
+                };
+
+                let page_list = self.0.page_list.as_ref().map(|page_list| {
+                    page_list
+                        .iter()
+                        .filter_map(|page_target| {
+                            Some(PageTarget {
+                                src: retarget(&page_target.src)?,
+                                ..page_target.clone()
+                            })
+                        })
+                        .collect::<Vec<_>>()
+                });
+                let nav_lists = self.0.nav_lists.as_ref().map(|nav_lists| {
+                    nav_lists
+                        .iter()
+                        .map(|nav_list| NavList {
+                            targets: nav_list
+                                .targets
+                                .iter()
+                                .filter_map(|target| {
+                                    Some(NavTarget {
+                                        src: retarget(&target.src)?,
+                                        ..target.clone()
+                                    })
+                                })
+                                .collect(),
+                            ..nav_list.clone()
+                        })
+                        .filter(|nav_list| !nav_list.targets.is_empty())
+                        .collect::<Vec<_>>()
+                });
+
+                let resources = self.0.resources.as_ref().map(|resources| {
+                    resources
+                        .iter()
+                        .filter(|resource| {
+                            resource.filename().is_ok_and(|filename| {
+                                content.mentions(&filename)
+                                    || stylesheet
+                                        .as_ref()
+                                        .is_some_and(|stylesheet| stylesheet.contains(&filename))
+                            })
+                        })
+                        .cloned()
+                        .collect::<Vec<_>>()
+                });
+
+                EpubBuilder(Epub {
+                    metadata: self.0.metadata.part(index + 1, content.title()),
+                    resources: resources.filter(|resources| !resources.is_empty()),
+                    contents: Some(vec![content.clone()]),
+                    page_list: page_list.filter(|page_list| !page_list.is_empty()),
+                    nav_lists: nav_lists.filter(|nav_lists| !nav_lists.is_empty()),
+                    spine_order: None,
+                    ..self.0.clone()
+                })
+            })
+            .collect()
+    }
+
+    /// Renders the **content.opf** package document as a string, without packaging anything.
+    ///
+    /// The output is the same as the `OEBPS/content.opf` file of the archive, except that
+    /// resources are not loaded, so duplicated resources are not dropped from the manifest.
+    /// This allows custom packaging pipelines and snapshot tests of the generated XML.
+    ///
+    /// # Errors
+    /// Returns a [`crate::Result`] if there are any errors during XML generation.
+    pub fn render_content_opf(&self) -> crate::Result<String> {
+        Ok(creator::render_content_opf(&self.finalized()?)?.bytes)
+    }
+
+    /// Renders the **toc.ncx** navigation document as a string, without packaging anything.
+    ///
+    /// # Errors
+    /// Returns a [`crate::Result`] if there are any errors during XML generation.
+    pub fn render_toc_ncx(&self) -> crate::Result<String> {
+        Ok(creator::render_toc_ncx(&self.finalized()?)?.bytes)
+    }
+
+    /// Renders the **ONIX 3.0** product record of the book, generated from the same metadata
+    /// as the EPUB, so the package and its distribution metadata come from one source of truth.
+    ///
+    /// The record is formatted unless pretty-printing is disabled.
+    ///
+    /// # Errors
+    /// Returns a [`crate::Result`] if there are any errors during XML generation.
+    #[cfg(feature = "onix")]
+    pub fn render_onix(&self) -> crate::Result<String> {
+        let record = onix::product_record(&self.finalized()?.metadata)?;
+        if self.0.pretty_print {
+            xml::format(&record)
+        } else {
+            Ok(record)
+        }
+    }
+
+    /// Renders the **OPDS catalog entry** (an Atom `<entry>`) of the book, linking to the book
+    /// and its cover as served at `links`, so self-hosted catalogs can be fed from the same model.
+    ///
+    /// The entry is formatted unless pretty-printing is disabled.
+    ///
+    /// # Errors
+    /// Returns a [`crate::Result`] if there are any errors during XML generation.
+    pub fn render_opds_entry(&self, links: &OpdsLinks) -> crate::Result<String> {
+        let entry = opds::entry(&self.finalized()?, links)?;
+        if self.0.pretty_print {
+            xml::format(&entry)
+        } else {
+            Ok(entry)
+        }
+    }
+
+    /// Renders the XHTML file of every content (and subcontent), in book order, without
+    /// packaging anything.
+    ///
+    /// Each file is returned as its path inside the archive (e.g. `OEBPS/c01.xhtml`) and its XHTML.
+    ///
+    /// # Errors
+    /// Returns a [`crate::Result`] if there are any errors during XHTML generation.
+    pub fn render_contents(&self) -> crate::Result<Vec<(String, String)>> {
+        let epub = self.finalized()?;
+        let Some(ref contents) = epub.contents else {
+            return Ok(Vec::new());
+        };
+
+        Ok(creator::render_contents(
+            contents,
+            0,
+            epub.xhtml_options(),
+            epub.plugins(),
+            epub.renders_in_parallel(),
+        )?
+        .into_iter()
+        .map(|file_content| (file_content.filepath, file_content.bytes))
+        .collect())
+    }
+
+    /// Gets a copy of the book with its Unicode text normalized (see
+    /// [`EpubBuilder::unicode_normalization`]), as it is generated.
+    fn normalized(&self) -> Epub<'a> {
+        let mut epub = self.0.clone();
+        epub.normalize_unicode();
+        epub
+    }
+
+    /// Gets a copy of the book normalized (see [`EpubBuilder::normalized`]) and with its
+    /// metadata finalized by the plugins, as it is rendered.
+    fn finalized(&self) -> crate::Result<Epub<'a>> {
+        let mut epub = self.normalized();
+        epub.finalize_metadata()?;
+        Ok(epub)
+    }
+
+    /// Finalizes the builder and writes the EPUB contents as an **unpacked directory tree**
+    /// (`mimetype`, `META-INF`, `OEBPS`) below `path` instead of a zip archive.
+    ///
+    /// This is useful for debugging, or to post-process the files before packaging them.
+    ///
+    /// # Errors
+    /// Returns a [`crate::Result`] if there are any I/O issues or errors during XML generation,
+    /// or a [`crate::Error::InvalidResources`] if the resources are not valid.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn create_dir<P: AsRef<Path>>(self, path: P) -> crate::Result {
+        self.0.validate_resources()?;
+        EpubFile::new_dir(self.0, path.as_ref()).create()
+    }
+
+    /// Finalizes the builder and **synchronously** generates the EPUB file fully in memory,
+    /// returning its bytes (e.g. to hand them to an HTTP response body).
+    ///
+    /// Uses the zip compression method set with [`EpubBuilder::compression`].
+    ///
+    /// # Errors
+    /// Returns a [`crate::Result`] if there are any I/O issues or errors during XML generation.
+    pub fn create_to_vec(self) -> crate::Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        self.create(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// **Asynchronously** generates the EPUB file, writing the contents to the provided `tokio::io::AsyncWrite` writer.
+    ///
+    /// See [`EpubBuilder::async_create_futures`] for other runtimes.
+    ///
+    /// This method is only available when the **`async` feature** is enabled.
+    #[cfg(feature = "async")]
+    pub async fn async_create<W>(self, writer: &mut W) -> crate::Result
+    where
+        W: tokio::io::AsyncWrite + Unpin + Send,
+    {
+        let compression = self.0.compression;
+        self.async_create_with_compression(writer, compression)
+            .await
+    }
+
+    /// **Asynchronously** generates the EPUB file with a specified zip compression method.
+    ///
+    /// This method is only available when the **`async` feature** is enabled.
+    #[cfg(feature = "async")]
+    pub async fn async_create_with_compression<W>(
+        self,
+        writer: &mut W,
+        compression: ZipCompression,
+    ) -> crate::Result
+    where
+        W: tokio::io::AsyncWrite + Unpin + Send,
+    {
+        use crate::output::creator_async::EpubFile;
+
+        self.0.validate_resources()?;
+        EpubFile::with_zip_writer(
+            self.0,
+            async_zip::base::write::ZipFileWriter::with_tokio(writer),
+            compression,
+        )
+        .create()
+        .await
+    }
+
+    /// **Asynchronously** generates the EPUB file, writing the contents to the provided
+    /// `futures::io::AsyncWrite` writer, without depending on a specific async runtime
+    /// (e.g. with async-std, smol or `futures::executor::block_on`).
+    ///
+    /// The blocking work runs with the [`Spawner`] set with [`EpubBuilder::spawner`], including
+    /// the download of remote resources (**`http` feature**), unless they are downloaded with
+    /// `reqwest` (**`http-async` feature**), which needs a Tokio runtime.
+    ///
+    /// This method is only available when the **`async` feature** is enabled.
+    #[cfg(feature = "async")]
+    pub async fn async_create_futures<W>(self, writer: &mut W) -> crate::Result
+    where
+        W: futures::io::AsyncWrite + Unpin + Send,
+    {
+        let compression = self.0.compression;
+        self.async_create_futures_with_compression(writer, compression)
+            .await
+    }
+
+    /// **Asynchronously** generates the EPUB file like [`EpubBuilder::async_create_futures`],
+    /// with a specified zip compression method.
+    ///
+    /// This method is only available when the **`async` feature** is enabled.
+    #[cfg(feature = "async")]
+    pub async fn async_create_futures_with_compression<W>(
+        self,
+        writer: &mut W,
+        compression: ZipCompression,
+    ) -> crate::Result
+    where
+        W: futures::io::AsyncWrite + Unpin + Send,
+    {
+        use crate::output::creator_async::EpubFile;
+
+        self.0.validate_resources()?;
+        EpubFile::new(self.0, writer, compression).create().await
+    }
+
+    /// **Asynchronously** generates the EPUB file fully in memory, returning its bytes.
+    ///
+    /// It does not depend on a specific async runtime (see [`EpubBuilder::async_create_futures`]).
+    ///
+    /// This method is only available when the **`async` feature** is enabled.
+    #[cfg(feature = "async")]
+    pub async fn async_create_to_vec(self) -> crate::Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        self.async_create_futures(&mut bytes).await?;
+        Ok(bytes)
+    }
+}
+
+impl fmt::Display for EpubBuilder<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let epub = &self.0;
+        writeln!(f, "{}", epub.metadata.title)?;
+
+        if epub.has_stylesheet() {
+            writeln!(f, "stylesheet: style.css")?;
+        }
+        if let Some(ref cover_image) = epub.cover_image {
+            let filename = cover_image.filename().unwrap_or_default();
+            writeln!(f, "cover image: {filename} ({})", cover_image.media_type())?;
+        } else if epub.generated_cover.is_some() {
+            writeln!(f, "cover image: {GENERATED_COVER_FILENAME} (generated)")?;
+        }
+        if epub.has_cover_page() {
+            writeln!(f, "cover page: {COVER_PAGE_FILENAME}")?;
+        }
+
+        if !self.contents().is_empty() {
+            writeln!(f, "contents:")?;
+            let mut number = 0;
+            for content in self.contents() {
+                content.write_tree(f, &mut number, 1)?;
+            }
+        }
+
+        if !self.resources().is_empty() {
+            writeln!(f, "resources:")?;
+            for resource in self.resources() {
+                let filename = resource
+                    .filename()
+                    .unwrap_or_else(|_| "<invalid filename>".to_string());
+                writeln!(f, "  {filename} ({})", resource.media_type())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs::File,
+        io::{Cursor, Read},
+    };
+
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::epub::{
+        Audio, AudioType, ComicBuilder, ContentReference, FontType, Identifier, IndexTerm,
+        NavTarget, metadata::MetadataBuilder,
+    };
+    use crate::output::file_content;
+
+    #[test]
+    fn test_epub_builder_new() {
+        let metadata = MetadataBuilder::title("Title").build();
+        let builder = EpubBuilder::new(metadata);
+
+        assert!(builder.0.stylesheet.is_none());
+    }
+
+    #[test]
+    fn test_epub_builder_stylesheet_file() {
+        let metadata = MetadataBuilder::title("Title").build();
+
+        let temp_dir = tempdir().expect("Error creating tempdir");
+        let stylesheet_path = temp_dir.path().join("style.css");
+        let mut file = File::create(&stylesheet_path).expect("Error creating mock css");
+        file.write_all(b"dummy font data")
+            .expect("Error writing to mock css");
+
+        let stylesheet = std::fs::read(stylesheet_path).expect("Error reading mock css");
+
+        let builder = EpubBuilder::new(metadata).stylesheet(&stylesheet);
+
+        assert!(builder.0.stylesheet.is_some());
+    }
+
+    #[test]
+    fn test_epub_builder_stylesheet_raw() {
+        let metadata = MetadataBuilder::title("Title").build();
+
+        let stylesheet_content = "body { color: red; }";
+        let builder = EpubBuilder::new(metadata).stylesheet(stylesheet_content.as_bytes());
+
+        if let Some(stylesheet) = builder.0.stylesheet {
+            assert_eq!(stylesheet, "body { color: red; }".as_bytes());
+        } else {
+            panic!("Stylesheet was not set to raw content");
+        }
+    }
+
+    #[test]
+    fn test_epub_builder_cover_image_item() {
+        let metadata = MetadataBuilder::title("Title").build();
+        let path = Path::new("/path/to/image1.jpg");
+
+        let builder = EpubBuilder::new(metadata)
+            .cover_image_item(Resource::Image(path, ImageType::Jpg).as_name("cover-final.jpg"));
+
+        assert_eq!(builder.0.cover_image_id().unwrap(), "cover-final.jpg");
+    }
+
+    #[test]
+    fn test_epub_builder_generated_cover() {
+        let metadata = MetadataBuilder::title("Title").build();
+        let builder = EpubBuilder::new(metadata).generated_cover(GeneratedCover::new());
+
+        let content_opf = file_content::content_opf(&builder.0).unwrap().bytes;
+        assert!(content_opf.contains(r#"<meta name="cover" content="cover.svg"/>"#));
+        assert!(
+            content_opf
+                .contains(r#"<item id="cover.svg" href="cover.svg" media-type="image/svg+xml"/>"#)
+        );
+
+        let file_content = builder.0.generated_cover_file_content().unwrap();
+        assert_eq!(file_content.filepath, "OEBPS/cover.svg");
+        assert!(
+            String::from_utf8(file_content.bytes.into_vec().unwrap())
+                .unwrap()
+                .contains(">Title</tspan>")
+        );
+
+        let builder = builder.cover_image(Path::new("/path/to/cover.jpg"), ImageType::Jpg);
+        assert!(builder.0.generated_cover_file_content().is_none());
+        assert_eq!(builder.0.cover_image_id().unwrap(), "cover.jpg");
+    }
+
+    #[test]
+    fn test_epub_builder_svg_cover_page() {
+        let bytes = EpubBuilder::new(MetadataBuilder::title("Title").build())
+            .generated_cover(GeneratedCover::new().size(600, 900))
+            .svg_cover_page(true)
+            .add_content(
+                ContentBuilder::new(
+                    "<body><h1>Chapter 1</h1></body>".as_bytes(),
+                    ReferenceType::Text("Chapter 1".to_string()),
+                )
+                .build(),
+            )
+            .create_to_vec()
+            .unwrap();
+
+        let mut archive = zip::ZipArchive::new(Cursor::new(&bytes)).unwrap();
+        let mut cover_page = String::new();
+        archive
+            .by_name("OEBPS/cover.xhtml")
+            .unwrap()
+            .read_to_string(&mut cover_page)
+            .unwrap();
+        assert!(cover_page.contains(r#"viewBox="0 0 600 900""#));
+        assert!(cover_page.contains(r#"xlink:href="cover.svg""#));
+
+        let content_opf = file_content::content_opf(
+            &EpubBuilder::new(MetadataBuilder::title("Title").build())
+                .generated_cover(GeneratedCover::new())
+                .svg_cover_page(true)
+                .0,
+        )
+        .unwrap()
+        .bytes;
+        assert!(content_opf.contains(r#"<spine toc="ncx"><itemref idref="cover.xhtml"/>"#));
+        assert!(
+            content_opf
+                .contains(r#"<guide><reference type="cover" title="Cover" href="cover.xhtml"/>"#)
+        );
+
+        assert!(
+            validation::validate_archive(Cursor::new(bytes))
+                .unwrap()
+                .is_empty()
+        );
+
+        let builder =
+            EpubBuilder::new(MetadataBuilder::title("Title").build()).svg_cover_page(true);
+        assert!(!builder.0.has_cover_page());
+    }
+
+    #[test]
+    fn test_epub_builder_font_faces() {
+        let builder = EpubBuilder::new(MetadataBuilder::title("Title").build())
+            .font_faces(true)
+            .add_resource(Resource::Raw(
+                "fonts/Lora-Bold.ttf",
+                b"font",
+                "font/ttf".to_string(),
+            ))
+            .add_content(
+                ContentBuilder::new(
+                    "<body><h1>Chapter 1</h1></body>".as_bytes(),
+                    ReferenceType::Text("Chapter 1".to_string()),
+                )
+                .build(),
+            );
+
+        let stylesheet = builder.0.stylesheet_content().unwrap();
+        assert!(String::from_utf8_lossy(&stylesheet).contains(
+            "font-family: \"Lora\";\n  font-weight: 700;\n  font-style: normal;\n  src: url(\"fonts/Lora-Bold.ttf\") format(\"truetype\");"
+        ));
+
+        let bytes = builder.create_to_vec().unwrap();
+        let mut archive = zip::ZipArchive::new(Cursor::new(&bytes)).unwrap();
+        let mut chapter = String::new();
+        archive
+            .by_name("OEBPS/c01.xhtml")
+            .unwrap()
+            .read_to_string(&mut chapter)
+            .unwrap();
+        assert!(chapter.contains(r#"<link href="style.css" rel="stylesheet" type="text/css"/>"#));
+        assert!(archive.by_name("OEBPS/style.css").is_ok());
+
+        let builder = EpubBuilder::new(MetadataBuilder::title("Title").build())
+            .stylesheet(b"body {}")
+            .add_resource(Resource::Raw(
+                "fonts/Lora-Bold.ttf",
+                b"font",
+                "font/ttf".to_string(),
+            ));
+        assert_eq!(builder.0.stylesheet_content().unwrap().as_ref(), b"body {}");
+    }
+
+    #[test]
+    fn test_epub_builder_add_from_iterators() {
+        let titles = ["One", "Two", "Three"];
+        let builder = EpubBuilder::new(MetadataBuilder::title("Title").build())
+            .add_contents(titles.iter().map(|title| {
+                ContentBuilder::new(b"<body/>", ReferenceType::Text(title.to_string()))
+                    .add_children(std::iter::empty())
+                    .extend_content_references(
+                        (1..=2).map(|n| ContentReference::new(format!("{title} {n}"))),
+                    )
+                    .build()
+            }))
+            .extend_contents(None)
+            .extend_resources(Vec::<Resource>::new())
+            .add_page_targets([PageTarget::numbered("c01.xhtml")]);
+
+        let contents = builder.0.contents.as_ref().unwrap();
+        assert_eq!(contents.len(), 3);
+        assert!(contents[0].subcontents.is_none());
+        assert_eq!(contents[2].content_references.as_ref().unwrap().len(), 2);
+        assert_eq!(contents[0].level(), 0);
+        assert!(builder.0.resources.is_none());
+        assert_eq!(builder.0.page_list.as_ref().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_epub_builder_getters() {
+        let builder = EpubBuilder::new(MetadataBuilder::title("Title").build())
+            .stylesheet(b"p { margin: 0; }")
+            .add_resource(Resource::Raw(
+                "logo.svg",
+                b"<svg/>",
+                "image/svg+xml".to_string(),
+            ))
+            .add_content(
+                ContentBuilder::new(b"<body/>", ReferenceType::Text("One".to_string()))
+                    .add_child(
+                        ContentBuilder::new(b"<body/>", ReferenceType::Text("Two".to_string()))
+                            .build(),
+                    )
+                    .add_content_reference(ContentReference::new("Part").id("part"))
+                    .build(),
+            );
+
+        assert_eq!(builder.metadata().title, "Title");
+        assert!(builder.has_stylesheet());
+        assert!(!builder.has_cover_image());
+        assert_eq!(builder.resource_names(), ["logo.svg"]);
+        assert_eq!(builder.resources()[0].media_type(), "image/svg+xml");
+        assert_eq!(builder.contents().len(), 1);
+        assert_eq!(builder.content_count(), 2);
+        assert_eq!(builder.contents()[0].children()[0].title(), "Two");
+        assert_eq!(
+            builder.contents()[0].content_references()[0].anchor_id(),
+            Some("part")
+        );
+        assert!(builder.page_targets().is_empty());
+        assert!(builder.nav_lists().is_empty());
+    }
+
+    #[test]
+    fn test_epub_builder_debug_tree() {
+        let builder = EpubBuilder::new(MetadataBuilder::title("Title").build())
+            .stylesheet(b"p { margin: 0; }")
+            .add_resource(Resource::Raw(
+                "logo.svg",
+                b"<svg/>",
+                "image/svg+xml".to_string(),
+            ))
+            .add_content(
+                ContentBuilder::new(b"<body/>", ReferenceType::Text("One".to_string()))
+                    .add_content_reference(
+                        ContentReference::new("Part")
+                            .id("part")
+                            .add_child(ContentReference::new("Section")),
+                    )
+                    .add_child(
+                        ContentBuilder::new(b"<body/>", ReferenceType::Text("Two".to_string()))
+                            .build(),
+                    )
+                    .build(),
+            )
+            .add_content(
+                ContentBuilder::new(b"<body/>", ReferenceType::Notes("Notes".to_string()))
+                    .filename("notes.xhtml")
+                    .build(),
+            );
+
+        assert_eq!(
+            builder.debug_tree(),
+            "Title\n\
+             stylesheet: style.css\n\
+             contents:\n  \
+             c01.xhtml [text] One\n    \
+             c01.xhtml#part Part\n      \
+             c01.xhtml#id02 Section\n    \
+             c02.xhtml [text] Two\n  \
+             notes.xhtml [notes] Notes\n\
+             resources:\n  \
+             logo.svg (image/svg+xml)\n"
+        );
+    }
+
+    #[test]
+    fn test_epub_builder_mutable_api() {
+        let mut builder = EpubBuilder::new(MetadataBuilder::title("Title").build());
+        for (number, title) in ["One", "Two", "Three"].into_iter().enumerate() {
+            builder.push_content(
+                ContentBuilder::new(b"<body/>", ReferenceType::Text(title.to_string())).build(),
+            );
+            if number % 2 == 0 {
+                builder.push_page_target(PageTarget::numbered(format!("c{:02}.xhtml", number + 1)));
+            }
+        }
+        builder
+            .set_stylesheet(b"p {}")
+            .set_cover(Resource::Raw(
+                "cover.svg",
+                b"<svg/>",
+                "image/svg+xml".to_string(),
+            ))
+            .push_resources(Vec::<Resource>::new())
+            .metadata_mut()
+            .publisher = Some("ACME".to_string());
+
+        assert_eq!(builder.content_count(), 3);
+        assert_eq!(builder.page_targets().len(), 2);
+        assert!(builder.has_stylesheet());
+        assert!(builder.has_cover_image());
+        assert!(builder.0.resources.is_none());
+        assert_eq!(builder.metadata().publisher.as_deref(), Some("ACME"));
+    }
+
+    #[test]
+    fn test_epub_builder_clone_variants() {
+        let chapter = |title: &str| {
+            ContentBuilder::new(
+                b"<body><p>Text</p></body>",
+                ReferenceType::Text(title.to_string()),
+            )
+            .build()
+        };
+        let base =
+            EpubBuilder::new(MetadataBuilder::title("Title").build()).add_content(chapter("One"));
+
+        let sample = base.clone().generated_cover(GeneratedCover::default());
+        let full = base
+            .add_content(chapter("Two"))
+            .add_content(chapter("Three"));
+
+        assert_eq!(sample.content_count(), 1);
+        assert!(sample.0.generated_cover.is_some());
+        assert_eq!(full.content_count(), 3);
+        assert!(full.0.generated_cover.is_none());
+        assert!(sample.create_to_vec().is_ok());
+        assert!(full.create_to_vec().is_ok());
+    }
+
+    #[test]
+    fn test_epub_builder_error_context() {
+        let error = EpubBuilder::new(MetadataBuilder::title("Title").build())
+            .add_content(
+                ContentBuilder::new(b"<body/>", ReferenceType::Text("One".to_string())).build(),
+            )
+            .add_content(
+                ContentBuilder::new(b"<body>\xff</body>", ReferenceType::Text("Two".to_string()))
+                    .build(),
+            )
+            .create_to_vec()
+            .unwrap_err();
+
+        assert!(
+            error
+                .to_string()
+                .starts_with("content 'Two' (OEBPS/c02.xhtml): invalid utf-8")
+        );
+        assert!(matches!(error.root_cause(), crate::Error::Utf8(_)));
+    }
+
+    #[test]
+    fn test_epub_builder_plugins() {
+        use std::sync::Mutex;
+
+        #[derive(Default)]
+        struct Recorder {
+            entries: Mutex<Vec<String>>,
+        }
+
+        impl EpubPlugin for Recorder {
+            fn metadata_finalized(&self, metadata: &mut Metadata) -> crate::Result<()> {
+                metadata.publisher = Some("ACME".to_string());
+                Ok(())
+            }
+
+            fn chapter_rendered(&self, filepath: &str, xhtml: &mut String) -> crate::Result<()> {
+                *xhtml = xhtml.replace("</body>", &format!("<p>{filepath}</p></body>"));
+                Ok(())
+            }
+
+            fn manifest_assembled(&self, content_opf: &mut String) -> crate::Result<()> {
+                *content_opf = content_opf
+                    .replace("</metadata>", "<meta name=\"x\" content=\"y\"/></metadata>");
+                Ok(())
+            }
+
+            fn before_entry(&self, filepath: &str, _bytes: &mut Vec<u8>) -> crate::Result<()> {
+                self.entries.lock().unwrap().push(filepath.to_string());
+                Ok(())
+            }
+        }
+
+        struct Uppercase;
+
+        impl EpubPlugin for Uppercase {
+            fn before_entry(&self, filepath: &str, bytes: &mut Vec<u8>) -> crate::Result<()> {
+                if filepath == "OEBPS/style.css" {
+                    bytes.make_ascii_uppercase();
+                }
+                Ok(())
+            }
+        }
+
+        let recorder = Recorder::default();
+        let epub_builder = EpubBuilder::new(MetadataBuilder::title("Title").build())
+            .stylesheet(b"body {}")
+            .add_content(
+                ContentBuilder::new(b"<body></body>", ReferenceType::Text("C1".to_string()))
+                    .build(),
+            )
+            .add_plugin(&recorder)
+            .add_plugin(&Uppercase);
+        assert!(format!("{:?}", epub_builder.0.plugins).contains("Uppercase"));
+        assert!(
+            epub_builder
+                .render_content_opf()
+                .unwrap()
+                .contains("<dc:publisher>ACME</dc:publisher>")
+        );
+
+        let bytes = epub_builder.create_to_vec().unwrap();
+        let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).unwrap();
+        let mut read = |name: &str| {
+            let mut content = String::new();
+            archive
+                .by_name(name)
+                .unwrap()
+                .read_to_string(&mut content)
+                .unwrap();
+            content
+        };
+
+        assert!(read("OEBPS/c01.xhtml").contains("<p>OEBPS/c01.xhtml</p>"));
+        let content_opf = read("OEBPS/content.opf");
+        assert!(content_opf.contains(r#"<meta name="x" content="y"/>"#));
+        assert!(content_opf.contains("ACME"));
+        assert_eq!(read("OEBPS/style.css"), "BODY {}");
+
+        let entries = recorder.entries.lock().unwrap();
+        assert!(!entries.contains(&"mimetype".to_string()));
+        assert!(entries.contains(&"OEBPS/toc.ncx".to_string()));
+    }
+
+    #[test]
+    fn test_epub_builder_in_memory_resources() {
+        // The only kind of resources available without a file system (e.g. on wasm32)
+        let bytes = EpubBuilder::new(MetadataBuilder::title("Title").build())
+            .cover_image_item(
+                Resource::Raw("cover.svg", b"<svg/>", "image/svg+xml".to_string()).into(),
+            )
+            .add_resource(Resource::Raw(
+                "fonts/Lora-Regular.ttf",
+                b"font",
+                "font/ttf".to_string(),
+            ))
+            .add_content(
+                ContentBuilder::new(
+                    "<body><h1>Chapter 1</h1></body>".as_bytes(),
+                    ReferenceType::Text("Chapter 1".to_string()),
+                )
+                .build(),
+            )
+            .create_to_vec()
+            .unwrap();
+
+        let archive = zip::ZipArchive::new(Cursor::new(&bytes)).unwrap();
+        assert!(archive.file_names().any(|name| name == "OEBPS/cover.svg"));
+        assert!(
+            archive
+                .file_names()
+                .any(|name| name == "OEBPS/fonts/Lora-Regular.ttf")
+        );
+    }
+
+    #[test]
+    fn test_epub_deduplicate_resources() {
+        let temp_dir = tempdir().expect("Error creating tempdir");
+        let cover_image = temp_dir.path().join("cover.png");
+        let font = temp_dir.path().join("SomeFont.ttf");
+        std::fs::write(&cover_image, b"dummy image data").expect("Error writing mock image");
+        std::fs::write(&font, b"dummy font data").expect("Error writing mock font");
+
+        let mut epub = EpubBuilder::new(MetadataBuilder::title("Title").build())
+            .cover_image(&cover_image, ImageType::Png)
+            .add_resource(Resource::Image(&cover_image, ImageType::Png))
+            .add_resource(Resource::Font(&font, FontType::TrueType))
+            .add_resource(Resource::Font(&font, FontType::TrueType))
+            .add_resource(Resource::Image(&cover_image, ImageType::Png).as_name("copy.png"))
+            .0;
+
+        let cover_content = epub.cover_image.as_ref().unwrap().file_content().unwrap();
+        let resource_contents = epub
+            .resources
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(|resource| resource.file_content().unwrap())
+            .collect();
+
+        let resource_contents = epub
+            .deduplicate_resources(Some(&cover_content), resource_contents)
+            .unwrap();
+
+        assert_eq!(resource_contents.len(), 1);
+        assert_eq!(resource_contents[0].filepath, "OEBPS/SomeFont.ttf");
+        assert_eq!(epub.resources.as_ref().unwrap().len(), 1);
+        assert_eq!(
+            epub.resource_aliases,
+            HashMap::from([("copy.png".to_string(), "cover.png".to_string())])
+        );
+
+        let content = ContentBuilder::new(
+            br#"<body><img src="copy.png" alt="copy.png"/><img src="cover.png"/></body>"#,
+            ReferenceType::Text("Images".to_string()),
+        )
+        .build();
+        let file = content.render(1, epub.xhtml_options()).unwrap();
+        assert!(
+            file.bytes
+                .contains(r#"<img src="cover.png" alt="copy.png"/>"#)
+        );
+        assert!(!file.bytes.contains(r#"src="copy.png""#));
+    }
+
+    #[test]
+    fn test_epub_obfuscate_resources() {
+        let epub = EpubBuilder::new(
+            MetadataBuilder::title("Title")
+                .identifier(Identifier::UUID("1234".to_string()))
+                .build(),
+        )
+        .add_resource(Resource::Font(Path::new("a.otf"), FontType::OpenType).obfuscate())
+        .add_resource(Resource::Font(Path::new("b.otf"), FontType::OpenType))
+        .0;
+
+        let mut resource_contents = vec![
+            FileContent::new("OEBPS/a.otf".to_string(), vec![0u8; 4].into()),
+            FileContent::new("OEBPS/b.otf".to_string(), vec![0u8; 4].into()),
+        ];
+        epub.obfuscate_resources(&mut resource_contents).unwrap();
+
+        let key = obfuscation::key("urn:uuid:1234");
+        assert_eq!(resource_contents[0].bytes, key[..4]);
+        assert_eq!(resource_contents[1].bytes, vec![0u8; 4]);
+    }
+
+    #[test]
+    fn test_epub_builder_duplicated_resources() {
+        let temp_dir = tempdir().expect("Error creating tempdir");
+        let cover_image = temp_dir.path().join("cover.png");
+        std::fs::write(&cover_image, b"dummy image data").expect("Error writing mock image");
+
+        let epub_result = EpubBuilder::new(MetadataBuilder::title("Title").build())
+            .cover_image(&cover_image, ImageType::Png)
+            .add_resource(Resource::Image(&cover_image, ImageType::Png))
+            .create(&mut std::io::sink());
+
+        assert!(epub_result.is_ok());
+    }
+
+    #[test]
+    fn test_epub_builder_create_to_vec() {
+        let bytes = EpubBuilder::new(MetadataBuilder::title("Title").build())
+            .add_content(
+                ContentBuilder::new(
+                    "<body><h1>Chapter 1</h1></body>".as_bytes(),
+                    ReferenceType::Text("Chapter 1".to_string()),
+                )
+                .build(),
+            )
+            .create_to_vec()
+            .unwrap();
+
+        assert!(bytes.starts_with(b"PK"));
+        assert_eq!(&bytes[30..38], b"mimetype");
+        assert_eq!(&bytes[38..58], b"application/epub+zip");
+        assert_complete_local_headers(&bytes);
+
+        let archive = zip::ZipArchive::new(std::io::Cursor::new(bytes)).unwrap();
+        let names = archive.file_names().collect::<Vec<_>>();
+        assert_eq!(names.len(), 6);
+        assert!(names.contains(&"OEBPS/c01.xhtml"));
+        assert!(names.contains(&"OEBPS/content.opf"));
+    }
+
+    /// Reads the general purpose flags, compression method, CRC-32, compressed size and size of
+    /// the local header of the ZIP entry at `offset`.
+    fn local_header(bytes: &[u8], offset: usize) -> (u16, u16, u32, u32, u32) {
+        let field = |at: usize, len: usize| {
+            bytes[offset + at..offset + at + len]
+                .iter()
+                .rev()
+                .fold(0u32, |value, byte| value << 8 | u32::from(*byte))
+        };
+        assert_eq!(&bytes[offset..offset + 4], b"PK\x03\x04");
+        (
+            field(6, 2) as u16,
+            field(8, 2) as u16,
+            field(14, 4),
+            field(18, 4),
+            field(22, 4),
+        )
+    }
+
+    /// Asserts that the local header of every entry of the ZIP archive `bytes` is complete: no
+    /// data descriptor (general purpose flag bit 3), and the CRC-32 and sizes of the central
+    /// directory.
+    fn assert_complete_local_headers(bytes: &[u8]) {
+        assert_local_headers(bytes, false);
+    }
+
+    /// Asserts that the CRC-32 and sizes of every entry of the ZIP archive `bytes` are the ones
+    /// of the central directory, in its local header or, if `data_descriptors` are allowed (for
+    /// the resource files streamed by the async API), in a data descriptor following its data.
+    fn assert_local_headers(bytes: &[u8], data_descriptors: bool) {
+        let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).unwrap();
+        for index in 0..archive.len() {
+            let file = archive.by_index(index).unwrap();
+            let (flags, method, crc32, compressed_size, size) =
+                local_header(bytes, file.header_start() as usize);
+            if flags & 0x0008 != 0 {
+                assert!(data_descriptors, "{}", file.name());
+                let mut descriptor = (file.data_start() + file.compressed_size()) as usize;
+                if bytes[descriptor..descriptor + 4] == *b"PK\x07\x08" {
+                    descriptor += 4;
+                }
+                let field = |at: usize| {
+                    u32::from_le_bytes(
+                        bytes[descriptor + at..descriptor + at + 4]
+                            .try_into()
+                            .unwrap(),
+                    )
+                };
+                assert_eq!(field(0), file.crc32(), "{}", file.name());
+                // The sizes are either in the descriptor or, with the 0xFFFFFFFF placeholder, in
+                // the ZIP64 extra field of the central directory
+                for (at, expected) in [(4, file.compressed_size()), (8, file.size())] {
+                    assert!(
+                        [expected, 0xFFFF_FFFF].contains(&u64::from(field(at))),
+                        "{}",
+                        file.name()
+                    );
+                }
+                continue;
+            }
+            assert_eq!(crc32, file.crc32(), "{}", file.name());
+            assert_eq!(
+                (u64::from(compressed_size), u64::from(size)),
+                (file.compressed_size(), file.size()),
+                "{}",
+                file.name()
+            );
+            if method == 0 {
+                assert_eq!(compressed_size, size, "{}", file.name());
+            }
+        }
+    }
+
+    #[test]
+    fn test_epub_builder_mimetype_local_header() {
+        let bytes = EpubBuilder::new(MetadataBuilder::title("Title").build())
+            .create_to_vec()
+            .unwrap();
+
+        let (flags, method, crc32, compressed_size, size) = local_header(&bytes, 0);
+        assert_eq!(flags, 0);
+        assert_eq!(method, 0);
+        assert_ne!(crc32, 0);
+        assert_eq!((compressed_size, size), (20, 20));
+        // No extra field: the name is followed by the content
+        assert_eq!(&bytes[26..30], [8, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_epub_builder_mimetype_stored_when_deflated() {
+        let mut bytes = Vec::new();
+        EpubBuilder::new(MetadataBuilder::title("Title").build())
+            .create_with_compression(&mut bytes, ZipCompression::Deflated)
+            .unwrap();
+
+        assert_eq!(&bytes[30..58], b"mimetypeapplication/epub+zip");
+        let (flags, method, crc32, compressed_size, size) = local_header(&bytes, 0);
+        assert_eq!((flags, method), (0, 0));
+        assert_ne!(crc32, 0);
+        assert_eq!((compressed_size, size), (20, 20));
+        assert_complete_local_headers(&bytes);
+
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes)).unwrap();
+        let mimetype = archive.by_index(0).unwrap();
+        assert_eq!(mimetype.name(), "mimetype");
+        assert_eq!(mimetype.compression(), zip::CompressionMethod::Stored);
+        assert!(mimetype.extra_data().is_none_or(<[u8]>::is_empty));
+        drop(mimetype);
+
+        let container = archive.by_name("META-INF/container.xml").unwrap();
+        assert_eq!(container.compression(), zip::CompressionMethod::Deflated);
+    }
+
+    #[test]
+    fn test_epub_builder_build_manifest() {
+        let files = EpubBuilder::new(MetadataBuilder::title("Title").build())
+            .build_manifest(true)
+            .add_content(
+                ContentBuilder::new(
+                    b"<body><p>Text</p></body>",
+                    ReferenceType::Text("Chapter 1".to_string()),
+                )
+                .build(),
+            )
+            .files()
+            .unwrap()
+            .collect::<crate::Result<Vec<_>>>()
+            .unwrap();
+
+        let (manifest, files) = files.split_last().unwrap();
+        assert_eq!(manifest.filepath, "META-INF/liber-manifest.json");
+        let manifest = std::str::from_utf8(manifest.bytes.as_slice().unwrap()).unwrap();
+        for file in files {
+            assert!(manifest.contains(&format!(
+                r#""path": "{}", "size": {}"#,
+                file.filepath,
+                file.bytes.len()
+            )));
+        }
+        assert!(manifest.contains(
+            r#"{ "path": "mimetype", "size": 20, "sha256": "e468e350d1143eb648f60c7b0bd6031101ec0544a361ca74ecef256ac901f48b" }"#
+        ));
+    }
+
+    #[test]
+    fn test_epub_builder_profile() {
+        let png = |width: u32, height: u32| {
+            let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+            png.extend(width.to_be_bytes());
+            png.extend(height.to_be_bytes());
+            png
+        };
+        let cover = png(800, 1200);
+        let epub_builder = EpubBuilder::new(MetadataBuilder::title("Title").build())
+            .cover_image_item(Resource::Raw("cover.png", &cover, "image/png".to_string()).into());
+
+        let apple = epub_builder.clone().profile(Profile::Apple);
+        assert!(apple.0.display_options && apple.0.svg_cover_page);
+        assert_eq!(apple.0.compression, ZipCompression::Deflated);
+        let warnings = apple.create_with_warnings(&mut Vec::new()).unwrap();
+        assert!(warnings.contains(&Warning::SmallCover {
+            profile: Profile::Apple,
+            width: 800,
+            height: 1200,
+            min_width: 1400,
+        }));
+        assert!(warnings.contains(&Warning::MissingProfileMetadata {
+            profile: Profile::Apple,
+            field: "dc:creator".to_string(),
+        }));
+
+        let google = epub_builder.clone().profile(Profile::Google);
+        assert!(!google.0.display_options && !google.0.svg_cover_page);
+        let files = google
+            .clone()
+            .files()
+            .unwrap()
+            .map(|file| file.unwrap().filepath)
+            .collect::<Vec<_>>();
+        assert!(!files.contains(&"META-INF/com.apple.ibooks.display-options.xml".to_string()));
+        let warnings = google.create_with_warnings(&mut Vec::new()).unwrap();
+        assert!(
+            !warnings
+                .iter()
+                .any(|warning| matches!(warning, Warning::SmallCover { .. }))
+        );
+
+        let warnings = epub_builder.create_with_warnings(&mut Vec::new()).unwrap();
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_epub_builder_theme() {
+        let epub_builder = EpubBuilder::new(MetadataBuilder::title("Title").build())
+            .theme(Theme::Sepia)
+            .add_content(
+                ContentBuilder::new(b"<body></body>", ReferenceType::Text("One".to_string()))
+                    .build(),
+            );
+        assert!(epub_builder.has_stylesheet());
+        assert_eq!(
+            epub_builder.0.stylesheet_content().unwrap(),
+            Theme::Sepia.css().as_bytes()
+        );
+
+        let epub_builder = epub_builder.stylesheet(b"body { margin: 0; }");
+        let stylesheet = format!("{}\nbody {{ margin: 0; }}", Theme::Sepia.css());
+        assert_eq!(
+            epub_builder.0.stylesheet_content().unwrap(),
+            stylesheet.as_bytes()
+        );
+        let files = epub_builder
+            .files()
+            .unwrap()
+            .collect::<crate::Result<Vec<_>>>()
+            .unwrap();
+        assert!(files.iter().any(|file| file.filepath == "OEBPS/style.css"));
+    }
+
+    #[test]
+    fn test_epub_builder_content_resources() {
+        let audio = Audio::new(Path::new("/audio/theme.mp3"), AudioType::Mp3);
+        let epub_builder = EpubBuilder::new(MetadataBuilder::title("Title").build())
+            .add_content(
+                ContentBuilder::new(b"<body></body>", ReferenceType::Text("One".to_string()))
+                    .add_child(audio.clone().build("Theme").unwrap())
+                    .build(),
+            )
+            .add_contents([ContentBuilder::new(
+                b"<body></body>",
+                ReferenceType::Text("Two".to_string()),
+            )
+            .add_resource(audio.resource())
+            .add_resource(Resource::Image(
+                Path::new("/images/map.png"),
+                ImageType::Png,
+            ))
+            .build()]);
+
+        assert_eq!(
+            epub_builder.resource_names(),
+            ["theme.mp3".to_string(), "map.png".to_string()]
+        );
+        assert_eq!(epub_builder.content_count(), 3);
+    }
+
+    #[test]
+    fn test_epub_builder_external_links() {
+        let epub_builder = EpubBuilder::new(MetadataBuilder::title("Title").build())
+            .pretty_print(false)
+            .external_links(LinkAnnotation::Footnotes)
+            .add_content(
+                ContentBuilder::new(
+                    br#"<body><p><a href="https://example.com">Site</a></p></body>"#,
+                    ReferenceType::Text("One".to_string()),
+                )
+                .build(),
+            );
+
+        let files = epub_builder
+            .files()
+            .unwrap()
+            .collect::<crate::Result<Vec<_>>>()
+            .unwrap();
+        let file = files
+            .iter()
+            .find(|file| file.filepath == "OEBPS/c01.xhtml")
+            .unwrap();
+        let xhtml = String::from_utf8(file.bytes.as_slice().unwrap().to_vec()).unwrap();
+        assert!(xhtml.contains(
+            r##"<a href="https://example.com">Site</a><sup class="link-ref"><a id="link-ref-1" href="#link-note-1">1</a></sup></p><section class="link-notes">"##
+        ));
+    }
+
+    #[test]
+    fn test_epub_builder_typography() {
+        let epub_builder = EpubBuilder::new(MetadataBuilder::title("Title").build())
+            .pretty_print(false)
+            .typography(Typography::new().drop_caps(true).ornament("~"))
+            .add_content(
+                ContentBuilder::new(
+                    b"<body><p>Preface</p></body>",
+                    ReferenceType::Preface("Preface".to_string()),
+                )
+                .build(),
+            )
+            .add_content(
+                ContentBuilder::new(
+                    b"<body><p>Once</p><hr/><p>Later</p></body>",
+                    ReferenceType::Text("One".to_string()),
+                )
+                .build(),
+            );
+        assert!(epub_builder.has_stylesheet());
+        assert!(
+            String::from_utf8_lossy(&epub_builder.0.stylesheet_content().unwrap())
+                .contains(".drop-cap {")
+        );
+
+        let files = epub_builder
+            .files()
+            .unwrap()
+            .collect::<crate::Result<Vec<_>>>()
+            .unwrap();
+        let file = |filepath: &str| {
+            let file = files.iter().find(|file| file.filepath == filepath).unwrap();
+            String::from_utf8(file.bytes.as_slice().unwrap().to_vec()).unwrap()
+        };
+        assert!(file("OEBPS/c01.xhtml").contains("<p>Preface</p>"));
+        assert!(file("OEBPS/c02.xhtml").contains(
+            r#"<p><span class="drop-cap">O</span>nce</p><p class="ornament" role="separator">~</p>"#
+        ));
+    }
+
+    #[test]
+    fn test_epub_builder_list_of_illustrations() {
+        let epub_builder = EpubBuilder::new(MetadataBuilder::title("Title").build())
+            .pretty_print(false)
+            .add_content(
+                ContentBuilder::new(b"<body></body>", ReferenceType::Toc("Contents".to_string()))
+                    .build(),
+            )
+            .add_content(
+                ContentBuilder::new(
+                    br#"<body><figure id="map"><img src="map.png" alt="Map"/><figcaption>The map</figcaption></figure></body>"#,
+                    ReferenceType::Text("One".to_string()),
+                )
+                .add_child(
+                    ContentBuilder::new(
+                        br#"<body><figure><img src="photo.png" alt="A photo"/></figure></body>"#,
+                        ReferenceType::Text("Two".to_string()),
+                    )
+                    .build(),
+                )
+                .build(),
+            );
+
+        let no_figures = EpubBuilder::new(MetadataBuilder::title("Title").build())
+            .add_content(
+                ContentBuilder::new(b"<body></body>", ReferenceType::Text("One".to_string()))
+                    .build(),
+            )
+            .list_of_illustrations("Illustrations");
+        assert_eq!(no_figures.content_count(), 1);
+        assert!(no_figures.nav_lists().is_empty());
+
+        let epub_builder = epub_builder.list_of_illustrations("Illustrations");
+        assert_eq!(
+            epub_builder.contents()[1].reference_type(),
+            &ReferenceType::Loi("Illustrations".to_string())
+        );
+        assert_eq!(
+            epub_builder.nav_lists(),
+            [NavList::new("Illustrations").add_targets([
+                NavTarget::new("The map", "c03.xhtml#map"),
+                NavTarget::new("A photo", "c04.xhtml"),
+            ])]
+        );
+
+        let files = epub_builder
+            .files()
+            .unwrap()
+            .collect::<crate::Result<Vec<_>>>()
+            .unwrap();
+        let file = |filepath: &str| {
+            let file = files.iter().find(|file| file.filepath == filepath).unwrap();
+            String::from_utf8(file.bytes.as_slice().unwrap().to_vec()).unwrap()
+        };
+        assert!(file("OEBPS/c02.xhtml").contains(
+            r#"<ol class="loi"><li><a href="c03.xhtml#map">The map</a></li><li><a href="c04.xhtml">A photo</a></li></ol>"#
+        ));
+        assert!(
+            file("OEBPS/content.opf")
+                .contains(r#"<reference type="loi" title="Illustrations" href="c02.xhtml"/>"#)
+        );
+        assert!(file("OEBPS/toc.ncx").contains("<navList>"));
+    }
+
+    #[test]
+    fn test_epub_builder_list_of_tables() {
+        let epub_builder = EpubBuilder::new(MetadataBuilder::title("Title").build())
+            .pretty_print(false)
+            .add_content(
+                ContentBuilder::new(b"<body></body>", ReferenceType::Toc("Contents".to_string()))
+                    .build(),
+            )
+            .add_content(
+                ContentBuilder::new(
+                    br#"<body><figure id="map"><figcaption>Map</figcaption></figure><table id="prices"><caption>Prices</caption></table><table id="rates"></table></body>"#,
+                    ReferenceType::Text("One".to_string()),
+                )
+                .add_table("rates", "Rates")
+                .build(),
+            )
+            .list_of_illustrations("Illustrations")
+            .list_of_tables("Tables");
+
+        let types = epub_builder
+            .contents()
+            .iter()
+            .map(|content| content.reference_type().type_and_title().0)
+            .collect::<Vec<_>>();
+        assert_eq!(types, ["toc", "loi", "lot", "text"]);
+        assert_eq!(
+            epub_builder.nav_lists()[1],
+            NavList::new("Tables").add_targets([
+                NavTarget::new("Prices", "c04.xhtml#prices"),
+                NavTarget::new("Rates", "c04.xhtml#rates"),
+            ])
+        );
+        assert_eq!(
+            epub_builder.nav_lists()[0],
+            NavList::new("Illustrations").add_target(NavTarget::new("Map", "c04.xhtml#map"))
+        );
+
+        let files = epub_builder
+            .files()
+            .unwrap()
+            .collect::<crate::Result<Vec<_>>>()
+            .unwrap();
+        let lot = files
+            .iter()
+            .find(|file| file.filepath == "OEBPS/c03.xhtml")
+            .unwrap();
+        let lot = String::from_utf8(lot.bytes.as_slice().unwrap().to_vec()).unwrap();
+        assert!(lot.contains(
+            r#"<ol class="lot"><li><a href="c04.xhtml#prices">Prices</a></li><li><a href="c04.xhtml#rates">Rates</a></li></ol>"#
+        ));
+    }
+
+    #[test]
+    fn test_epub_builder_index() {
+        let epub_builder = EpubBuilder::new(MetadataBuilder::title("Title").build())
+            .pretty_print(false)
+            .add_content(
+                ContentBuilder::new(
+                    br#"<body><p id="nile">Nile</p><figure id="map"><figcaption>Map</figcaption></figure></body>"#,
+                    ReferenceType::Text("One".to_string()),
+                )
+                .add_index_term(IndexTerm::new("Nile", "nile"))
+                .build(),
+            )
+            .add_content(
+                ContentBuilder::new(
+                    br#"<body><p id="amazon">Amazon</p></body>"#,
+                    ReferenceType::Text("Two".to_string()),
+                )
+                .add_index_terms([
+                    IndexTerm::new("Rivers", "amazon").sub_term("Amazon"),
+                    IndexTerm::new("Amazon", "amazon"),
+                ])
+                .build(),
+            )
+            .index("Index");
+        assert_eq!(
+            epub_builder.contents()[2].reference_type(),
+            &ReferenceType::Index("Index".to_string())
+        );
+        assert!(epub_builder.nav_lists().is_empty());
+
+        // The list of illustrations shifts the chapters, and the index follows them.
+        let files = epub_builder
+            .list_of_illustrations("Illustrations")
+            .files()
+            .unwrap()
+            .collect::<crate::Result<Vec<_>>>()
+            .unwrap();
+        let index = files
+            .iter()
+            .find(|file| file.filepath == "OEBPS/c04.xhtml")
+            .unwrap();
+        let index = String::from_utf8(index.bytes.as_slice().unwrap().to_vec()).unwrap();
+        assert!(index.contains(
+            r#"<h2>N</h2><ul class="index"><li>Nile, <a href="c02.xhtml#nile">1</a></li>"#
+        ));
+        assert!(index.contains(
+            r#"<li>Rivers<ul><li>Amazon, <a href="c03.xhtml#amazon">1</a></li></ul></li>"#
+        ));
+
+        let untagged = EpubBuilder::new(MetadataBuilder::title("Title").build())
+            .add_content(
+                ContentBuilder::new(b"<body></body>", ReferenceType::Text("One".to_string()))
+                    .build(),
+            )
+            .index("Index");
+        assert_eq!(untagged.content_count(), 1);
+    }
+
+    #[cfg(feature = "crypto")]
+    #[test]
+    fn test_epub_builder_sign() {
+        struct Signer;
+
+        impl PackageSigner for Signer {
+            fn signature_method(&self) -> &str {
+                "http://www.w3.org/2000/09/xmldsig#hmac-sha1"
+            }
+
+            fn sign(&self, _signed_info: &[u8]) -> crate::Result<Vec<u8>> {
+                Ok(b"signature".to_vec())
+            }
+        }
+
+        let files = EpubBuilder::new(MetadataBuilder::title("Title").build())
+            .build_manifest(true)
+            .sign(&Signer)
+            .files()
+            .unwrap()
+            .collect::<crate::Result<Vec<_>>>()
+            .unwrap();
+
+        let (signatures, files) = files.split_last().unwrap();
+        assert_eq!(signatures.filepath, "META-INF/signatures.xml");
+        let signatures = std::str::from_utf8(signatures.bytes.as_slice().unwrap()).unwrap();
+        assert!(signatures.contains("<SignatureValue>c2lnbmF0dXJl</SignatureValue>"));
+        assert!(!signatures.contains(r#"URI="mimetype""#));
+        for file in files.iter().skip(1) {
+            assert!(signatures.contains(&format!(r#"URI="{}""#, file.filepath)));
+        }
+        assert!(signatures.contains(r#"URI="META-INF/liber-manifest.json""#));
+
+        let result = EpubBuilder::new(MetadataBuilder::title("Title").build())
+            .sign(&Signer)
+            .add_signed_entry("OEBPS/missing.css")
+            .create_to_vec();
+        assert!(
+            matches!(result, Err(crate::Error::FilenameNotFound(path)) if path == "OEBPS/missing.css")
+        );
+    }
+
+    #[test]
+    fn test_epub_builder_create_with_checksums() {
+        let mut bytes = Vec::new();
+        let checksums = EpubBuilder::new(MetadataBuilder::title("Title").build())
+            .add_content(
+                ContentBuilder::new(
+                    b"<body><p>Text</p></body>",
+                    ReferenceType::Text("Chapter 1".to_string()),
+                )
+                .build(),
+            )
+            .create_with_checksums(&mut bytes)
+            .unwrap();
+
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes)).unwrap();
+        assert_eq!(checksums.len(), archive.len());
+        for (index, checksum) in checksums.iter().enumerate() {
+            let file = archive.by_index(index).unwrap();
+            assert_eq!(
+                (file.name(), file.size()),
+                (checksum.path.as_str(), checksum.size)
+            );
+        }
+        assert_eq!(
+            checksums[0].sha256,
+            "e468e350d1143eb648f60c7b0bd6031101ec0544a361ca74ecef256ac901f48b"
+        );
+    }
+
+    #[test]
+    fn test_epub_builder_calibre() {
+        let content_opf = EpubBuilder::new(MetadataBuilder::title("The Hobbit").build())
+            .calibre(
+                CalibreMetadata::new()
+                    .title_sort("Hobbit, The")
+                    .series("Middle-earth", 1.0),
+            )
+            .pretty_print(false)
+            .render_content_opf()
+            .unwrap();
+
+        assert!(
+            content_opf.contains(r#"<meta name="calibre:title_sort" content="Hobbit, The"/><meta name="calibre:series" content="Middle-earth"/><meta name="calibre:series_index" content="1.0"/></metadata>"#)
+        );
+    }
+
+    #[test]
+    fn test_epub_builder_fixed_layout() {
+        let content_opf = EpubBuilder::new(MetadataBuilder::title("Manga").build())
+            .fixed_layout(FixedLayout::new(1200, 1800).right_to_left(true))
+            .pretty_print(false)
+            .render_content_opf()
+            .unwrap();
+
+        assert!(
+            content_opf.contains(r#"<meta name="fixed-layout" content="true"/><meta name="original-resolution" content="1200x1800"/><meta name="primary-writing-mode" content="horizontal-rl"/></metadata>"#)
+        );
+        assert!(content_opf.contains(r#"<spine toc="ncx" page-progression-direction="rtl">"#));
+
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+        png.extend(600u32.to_be_bytes());
+        png.extend(900u32.to_be_bytes());
+        let epub = ComicBuilder::new(MetadataBuilder::title("Comic").build())
+            .add_page(Resource::Raw("p1.png", &png, "image/png".to_string()))
+            .build()
+            .unwrap()
+            .create_to_vec()
+            .unwrap();
+
+        let mut archive = zip::ZipArchive::new(Cursor::new(epub)).unwrap();
+        let mut display_options = String::new();
+        archive
+            .by_name("META-INF/com.apple.ibooks.display-options.xml")
+            .unwrap()
+            .read_to_string(&mut display_options)
+            .unwrap();
+        assert!(display_options.contains(r#"<option name="fixed-layout">true</option>"#));
+
+        let mut content_opf = String::new();
+        archive
+            .by_name("OEBPS/content.opf")
+            .unwrap()
+            .read_to_string(&mut content_opf)
+            .unwrap();
+        assert!(content_opf.contains(r#"<meta name="original-resolution" content="600x900"/>"#));
+        assert!(content_opf.contains(r#"<spine toc="ncx">"#));
+    }
+
+    #[test]
+    fn test_epub_builder_archive_comment_and_generator() {
+        let builder = EpubBuilder::new(MetadataBuilder::title("Title").build())
+            .archive_comment("Made with my-tool 1.2.0")
+            .generator("my-tool 1.2.0");
+
+        assert!(
+            file_content::content_opf(&builder.0)
+                .unwrap()
+                .bytes
+                .contains(r#"<meta name="generator" content="my-tool 1.2.0"/>"#)
+        );
+
+        let archive =
+            zip::ZipArchive::new(std::io::Cursor::new(builder.create_to_vec().unwrap())).unwrap();
+        assert_eq!(archive.comment(), b"Made with my-tool 1.2.0");
+    }
+
+    #[test]
+    fn test_epub_builder_deterministic() {
+        let temp_dir = tempdir().expect("Error creating tempdir");
+        let font = temp_dir.path().join("SomeFont.ttf");
+        std::fs::write(&font, b"dummy font data").unwrap();
+
+        let build = || {
+            EpubBuilder::new(MetadataBuilder::title("Title").creator("Author").build())
+                .deterministic()
+                .add_resource(Resource::Font(&font, FontType::TrueType))
+                .add_content(
+                    ContentBuilder::new(
+                        "<body><h1>Chapter 1</h1></body>".as_bytes(),
+                        ReferenceType::Text("Chapter 1".to_string()),
+                    )
+                    .build(),
+                )
+                .create_to_vec()
+                .unwrap()
+        };
+
+        let bytes = build();
+        assert_eq!(bytes, build());
+
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes)).unwrap();
+        let entry = archive.by_name("OEBPS/SomeFont.ttf").unwrap();
+        assert_eq!(entry.last_modified(), Some(zip::DateTime::default()));
+    }
+
+    #[test]
+    fn test_epub_builder_create_dir() {
+        let temp_dir = tempdir().expect("Error creating tempdir");
+        let root = temp_dir.path().join("book");
+
+        EpubBuilder::new(MetadataBuilder::title("Title").build())
+            .stylesheet(b"body { color: red; }")
+            .add_content(
+                ContentBuilder::new(
+                    "<body><h1>Chapter 1</h1></body>".as_bytes(),
+                    ReferenceType::Text("Chapter 1".to_string()),
+                )
+                .build(),
+            )
+            .create_dir(&root)
+            .unwrap();
+
+        assert_eq!(
+            std::fs::read(root.join("mimetype")).unwrap(),
+            b"application/epub+zip"
+        );
+        assert!(root.join("META-INF/container.xml").is_file());
+        assert!(root.join("OEBPS/style.css").is_file());
+        assert!(root.join("OEBPS/c01.xhtml").is_file());
+        assert!(root.join("OEBPS/content.opf").is_file());
+        assert!(root.join("OEBPS/toc.ncx").is_file());
+    }
+
+    #[test]
+    fn test_epub_builder_plan() {
         let temp_dir = tempdir().expect("Error creating tempdir");
-        let stylesheet_path = temp_dir.path().join("style.css");
-        let mut file = File::create(&stylesheet_path).expect("Error creating mock css");
-        file.write_all(b"dummy font data")
-            .expect("Error writing to mock css");
+        let font = temp_dir.path().join("SomeFont.ttf");
+        std::fs::write(&font, b"dummy font data").unwrap();
 
-        let stylesheet = std::fs::read(stylesheet_path).expect("Error reading mock css");
+        let builder = EpubBuilder::new(MetadataBuilder::title("Title").build())
+            .add_resource(Resource::Font(&font, FontType::TrueType))
+            .add_content(
+                ContentBuilder::new(
+                    "<body><h1>Chapter 1</h1></body>".as_bytes(),
+                    ReferenceType::Text("Chapter 1".to_string()),
+                )
+                .build(),
+            );
 
-        let builder = EpubBuilder::new(metadata).stylesheet(&stylesheet);
+        let plan = builder.plan().unwrap();
+        let paths = plan
+            .iter()
+            .map(|file| file.path.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            vec![
+                "mimetype",
+                "META-INF/container.xml",
+                "META-INF/com.apple.ibooks.display-options.xml",
+                "OEBPS/SomeFont.ttf",
+                "OEBPS/c01.xhtml",
+                "OEBPS/content.opf",
+                "OEBPS/toc.ncx",
+            ]
+        );
 
-        assert!(builder.0.stylesheet.is_some());
+        assert_eq!(
+            plan[0],
+            PlannedFile {
+                path: "mimetype".to_string(),
+                media_type: None,
+                size: 20
+            }
+        );
+        assert_eq!(plan[3].media_type.as_deref(), Some("font/ttf"));
+        assert_eq!(plan[3].size, 15);
+        assert_eq!(plan[4].media_type.as_deref(), Some("application/xhtml+xml"));
+
+        assert!(builder.create_to_vec().is_ok());
     }
 
     #[test]
-    fn test_epub_builder_stylesheet_raw() {
-        let metadata = MetadataBuilder::title("Title").build();
+    fn test_epub_builder_pretty_print_disabled() {
+        let build = |pretty_print: bool| {
+            let temp_dir = tempdir().expect("Error creating tempdir");
+            EpubBuilder::new(MetadataBuilder::title("Title").build())
+                .pretty_print(pretty_print)
+                .add_content(
+                    ContentBuilder::new(
+                        "<body><h1>Chapter 1</h1></body>".as_bytes(),
+                        ReferenceType::Text("Chapter 1".to_string()),
+                    )
+                    .build(),
+                )
+                .create_dir(temp_dir.path())
+                .unwrap();
 
-        let stylesheet_content = "body { color: red; }";
-        let builder = EpubBuilder::new(metadata).stylesheet(stylesheet_content.as_bytes());
+            let content_opf = std::fs::read_to_string(temp_dir.path().join("OEBPS/content.opf"));
+            let xhtml = std::fs::read_to_string(temp_dir.path().join("OEBPS/c01.xhtml"));
+            (content_opf.unwrap(), xhtml.unwrap())
+        };
 
-        if let Some(stylesheet) = builder.0.stylesheet {
-            assert_eq!(stylesheet, "body { color: red; }".as_bytes());
-        } else {
-            panic!("Stylesheet was not set to raw content");
+        let (compact_opf, compact_xhtml) = build(false);
+        assert!(compact_xhtml.ends_with("<body><h1>Chapter 1</h1></body></html>"));
+
+        let (pretty_opf, pretty_xhtml) = build(true);
+        assert!(pretty_xhtml.contains("  <body>"));
+        assert!(compact_opf.lines().count() < pretty_opf.lines().count());
+    }
+
+    #[test]
+    #[cfg(feature = "rayon")]
+    fn test_epub_builder_parallel_rendering() {
+        let files = |parallel_rendering: bool| {
+            EpubBuilder::new(MetadataBuilder::title("Title").build())
+                .deterministic()
+                .parallel_rendering(parallel_rendering)
+                .add_contents((1..=20).map(|n| {
+                    ContentBuilder::new(
+                        b"<body><p>Part</p></body>",
+                        ReferenceType::Text(format!("Part {n}")),
+                    )
+                    .add_children((1..=5).map(|m| {
+                        ContentBuilder::new(
+                            b"<body><p>Chapter</p></body>",
+                            ReferenceType::Text(format!("Chapter {n}.{m}")),
+                        )
+                        .build()
+                    }))
+                    .build()
+                }))
+                .files()
+                .unwrap()
+                .map(|file| file.map(|file| (file.filepath, file.bytes.into_vec().unwrap())))
+                .collect::<crate::Result<Vec<_>>>()
+                .unwrap()
+        };
+
+        let parallel = files(true);
+        assert_eq!(parallel, files(false));
+        assert_eq!(parallel[parallel.len() - 3].0, "OEBPS/c120.xhtml");
+        assert!(
+            String::from_utf8_lossy(&parallel[parallel.len() - 3].1)
+                .contains("<title>Chapter 20.5</title>")
+        );
+    }
+
+    #[test]
+    fn test_epub_builder_split() {
+        let builder = EpubBuilder::new(MetadataBuilder::title("Serial").build())
+            .stylesheet(b"@font-face { src: url(fonts/serif.otf); }")
+            .add_resource(Resource::Font(
+                Path::new("fonts/serif.otf"),
+                FontType::OpenType,
+            ))
+            .add_resource(Resource::Image(Path::new("img/map.png"), ImageType::Png))
+            .add_resource(Resource::Image(Path::new("img/ship.png"), ImageType::Png))
+            .add_content(
+                ContentBuilder::new(
+                    br#"<body><img src="map.png" alt="Map"/></body>"#,
+                    ReferenceType::Text("Episode 1".to_string()),
+                )
+                .add_child(
+                    ContentBuilder::new(
+                        br#"<body><img src="ship.png" alt="Ship"/></body>"#,
+                        ReferenceType::Text("Episode 1.1".to_string()),
+                    )
+                    .build(),
+                )
+                .build(),
+            )
+            .add_content(
+                ContentBuilder::new(
+                    b"<body><p>Episode 2</p></body>",
+                    ReferenceType::Text("Episode 2".to_string()),
+                )
+                .build(),
+            );
+
+        let parts = builder.split();
+        assert_eq!(parts.len(), 2);
+
+        let filenames = |part: &EpubBuilder<'_>| {
+            part.0
+                .resources
+                .iter()
+                .flatten()
+                .map(|resource| resource.filename().unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            filenames(&parts[0]),
+            vec!["serif.otf", "map.png", "ship.png"]
+        );
+        assert_eq!(filenames(&parts[1]), vec!["serif.otf"]);
+
+        assert_eq!(parts[0].0.metadata.title, "Serial: Episode 1");
+        assert_eq!(parts[1].0.metadata.title, "Serial: Episode 2");
+        assert_ne!(
+            String::from(&parts[0].0.metadata.identifier),
+            String::from(&parts[1].0.metadata.identifier)
+        );
+        assert_eq!(
+            String::from(&parts[1].0.metadata.identifier),
+            String::from(&builder.split()[1].0.metadata.identifier)
+        );
+        assert_eq!(parts[0].render_contents().unwrap().len(), 2);
+        assert!(parts[1].0.stylesheet.is_some());
+
+        assert!(
+            EpubBuilder::new(MetadataBuilder::title("Empty").build())
+                .split()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_epub_builder_split_page_list() {
+        let builder = EpubBuilder::new(MetadataBuilder::title("Serial").build())
+            .add_content(
+                ContentBuilder::new(
+                    br#"<body><p id="page-1"/><figure id="map"><figcaption>Map</figcaption></figure></body>"#,
+                    ReferenceType::Text("Episode 1".to_string()),
+                )
+                .add_child(
+                    ContentBuilder::new(
+                        br#"<body><p id="page-2"/></body>"#,
+                        ReferenceType::Text("Episode 1.1".to_string()),
+                    )
+                    .build(),
+                )
+                .build(),
+            )
+            .add_content(
+                ContentBuilder::new(
+                    br#"<body><p id="page-3"/><figure id="ship"><figcaption>Ship</figcaption></figure></body>"#,
+                    ReferenceType::Text("Episode 2".to_string()),
+                )
+                .build(),
+            )
+            .list_of_illustrations("Illustrations")
+            .add_page_targets([
+                PageTarget::new("1", "c02.xhtml#page-1"),
+                PageTarget::new("2", "c03.xhtml#page-2"),
+                PageTarget::new("3", "c04.xhtml#page-3"),
+            ]);
+
+        let parts = builder.split();
+        assert_eq!(parts.len(), 2);
+
+        assert_eq!(
+            parts[0].page_targets(),
+            [
+                PageTarget::new("1", "c01.xhtml#page-1"),
+                PageTarget::new("2", "c02.xhtml#page-2"),
+            ]
+        );
+        assert_eq!(
+            parts[0].nav_lists(),
+            [NavList::new("Illustrations").add_target(NavTarget::new("Map", "c01.xhtml#map"))]
+        );
+        assert_eq!(
+            parts[1].page_targets(),
+            [PageTarget::new("3", "c01.xhtml#page-3")]
+        );
+        assert_eq!(
+            parts[1].nav_lists(),
+            [NavList::new("Illustrations").add_target(NavTarget::new("Ship", "c01.xhtml#ship"))]
+        );
+
+        for part in &parts {
+            assert!(
+                !part
+                    .0
+                    .problems()
+                    .iter()
+                    .any(|problem| matches!(problem, crate::Error::MissingTarget { .. }))
+            );
+            assert!(!part.render_toc_ncx().unwrap().contains("c03.xhtml"));
         }
+
+        let without_list = EpubBuilder::new(MetadataBuilder::title("Serial").build())
+            .add_content(
+                ContentBuilder::new(b"<body></body>", ReferenceType::Text("One".to_string()))
+                    .build(),
+            )
+            .add_page_target(PageTarget::new("9", "c09.xhtml"));
+        assert!(without_list.split()[0].0.page_list.is_none());
+    }
+
+    #[test]
+    fn test_epub_builder_render() {
+        let builder = EpubBuilder::new(MetadataBuilder::title("Title").build())
+            .deterministic()
+            .add_content(
+                ContentBuilder::new(
+                    "<body><h1>Chapter 1</h1></body>".as_bytes(),
+                    ReferenceType::Text("Chapter 1".to_string()),
+                )
+                .add_child(
+                    ContentBuilder::new(
+                        "<body><h1>Section 1.1</h1></body>".as_bytes(),
+                        ReferenceType::Text("Section 1.1".to_string()),
+                    )
+                    .build(),
+                )
+                .build(),
+            );
+
+        let content_opf = builder.render_content_opf().unwrap();
+        let toc_ncx = builder.render_toc_ncx().unwrap();
+        let contents = builder.render_contents().unwrap();
+
+        assert!(content_opf.contains(r#"<itemref idref="c02.xhtml"/>"#));
+        assert!(toc_ncx.contains("<text>Section 1.1</text>"));
+        assert_eq!(
+            contents
+                .iter()
+                .map(|(path, _)| path.as_str())
+                .collect::<Vec<_>>(),
+            vec!["OEBPS/c01.xhtml", "OEBPS/c02.xhtml"]
+        );
+        assert!(contents[1].1.contains("<h1>Section 1.1</h1>"));
+
+        let temp_dir = tempdir().expect("Error creating tempdir");
+        builder.create_dir(temp_dir.path()).unwrap();
+        let read = |path: &str| std::fs::read_to_string(temp_dir.path().join(path)).unwrap();
+        assert_eq!(content_opf, read("OEBPS/content.opf"));
+        assert_eq!(toc_ncx, read("OEBPS/toc.ncx"));
+        assert_eq!(contents[0].1, read("OEBPS/c01.xhtml"));
+    }
+
+    #[test]
+    fn test_epub_builder_validate() {
+        let builder = EpubBuilder::new(
+            MetadataBuilder::title("Title")
+                .identifier(Identifier::ISBN("978-3-16-148410-1".to_string()))
+                .build(),
+        )
+        .add_resource(Resource::Raw(
+            "notes.xhtml",
+            b"<html/>",
+            "application/xhtml+xml".to_string(),
+        ))
+        .add_content(
+            ContentBuilder::new(
+                r#"<body><h2 id="s1">Section 1</h2></body>"#.as_bytes(),
+                ReferenceType::Text("Chapter 1".to_string()),
+            )
+            .add_content_reference(ContentReference::new("Section 1").id("s1"))
+            .add_content_reference(ContentReference::new("Again").id("s1"))
+            .add_content_reference(ContentReference::new("Section 2").id("s2"))
+            .add_content_reference(ContentReference::new("Bad").id("a b"))
+            .build(),
+        )
+        .add_content(
+            ContentBuilder::new(b"<body/>", ReferenceType::Notes("Notes".to_string()))
+                .filename("notes.xhtml")
+                .build(),
+        )
+        .add_content(
+            ContentBuilder::new(b"<body/>", ReferenceType::Text("Chapter 2".to_string()))
+                .filename("c02.html")
+                .build(),
+        )
+        .add_page_target(PageTarget::new("1", "c09.xhtml#page-1"));
+
+        let problems = builder.validate().unwrap_err();
+
+        assert_eq!(problems.len(), 7);
+        assert!(
+            matches!(&problems[0], crate::Error::InvalidIsbn(isbn) if isbn == "978-3-16-148410-1")
+        );
+        assert!(matches!(
+            &problems[1],
+            crate::Error::DuplicateAnchor { filename, anchor } if filename == "c01.xhtml" && anchor == "s1"
+        ));
+        assert!(matches!(
+            &problems[2],
+            crate::Error::MissingAnchor { filename, anchor } if filename == "c01.xhtml" && anchor == "s2"
+        ));
+        assert!(matches!(
+            &problems[3],
+            crate::Error::InvalidAnchor { filename, anchor } if filename == "c01.xhtml" && anchor == "a b"
+        ));
+        assert_eq!(
+            problems[4].to_string(),
+            "'notes.xhtml': filename used by both 'notes.xhtml' and 'content Notes'"
+        );
+        assert!(
+            matches!(&problems[5], crate::Error::ContentFilename(filename) if filename == "c02.html")
+        );
+        assert!(matches!(
+            &problems[6],
+            crate::Error::MissingTarget { src, filename } if src == "c09.xhtml#page-1" && filename == "c09.xhtml"
+        ));
+
+        let empty = EpubBuilder::new(MetadataBuilder::title("Title").build());
+        assert!(matches!(
+            empty.validate().unwrap_err().as_slice(),
+            [crate::Error::EmptySpine]
+        ));
+    }
+
+    #[test]
+    fn test_epub_builder_spine_order() {
+        let builder = |spine_order: &[&str]| {
+            EpubBuilder::new(MetadataBuilder::title("Title").build())
+                .add_content(
+                    ContentBuilder::new(
+                        b"<body><p>Chapter 1</p></body>",
+                        ReferenceType::Text("Chapter 1".to_string()),
+                    )
+                    .add_child(
+                        ContentBuilder::new(
+                            b"<body><p>Notes</p></body>",
+                            ReferenceType::Notes("Notes".to_string()),
+                        )
+                        .filename("notes.xhtml")
+                        .build(),
+                    )
+                    .build(),
+                )
+                .add_contents((2..=3).map(|n| {
+                    ContentBuilder::new(
+                        b"<body><p>Chapter</p></body>",
+                        ReferenceType::Text(format!("Chapter {n}")),
+                    )
+                    .build()
+                }))
+                .spine_order(spine_order.iter().copied())
+        };
+
+        let builder_notes_last = builder(&["c01.xhtml", "c03.xhtml", "c04.xhtml"]);
+        assert!(builder_notes_last.validate().is_ok());
+        assert_eq!(
+            builder_notes_last.0.spine(),
+            ["c01.xhtml", "c03.xhtml", "c04.xhtml", "notes.xhtml"]
+        );
+        let content_opf = builder_notes_last.render_content_opf().unwrap();
+        let spine = &content_opf[content_opf.find("<spine").unwrap()..];
+        assert!(spine.find("c04.xhtml").unwrap() < spine.find("notes.xhtml").unwrap());
+        let toc_ncx = builder_notes_last.render_toc_ncx().unwrap();
+        assert!(toc_ncx.find("notes.xhtml").unwrap() < toc_ncx.find("c03.xhtml").unwrap());
+
+        let builder_invalid = builder(&["c04.xhtml", "c09.xhtml", "c04.xhtml"]);
+        assert_eq!(
+            builder_invalid.0.spine(),
+            ["c04.xhtml", "c01.xhtml", "notes.xhtml", "c03.xhtml"]
+        );
+        let problems = builder_invalid.validate().unwrap_err();
+        assert_eq!(
+            problems.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            [
+                "'c09.xhtml': the spine order lists a missing content",
+                "'c04.xhtml': the spine order lists this content more than once",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_epub_builder_validate_archive() {
+        let builder = EpubBuilder::new(MetadataBuilder::title("Title").build())
+            .stylesheet("body {}".as_bytes())
+            .add_resource(Resource::Raw(
+                "images/map.png",
+                &[0x89, b'P', b'N', b'G'],
+                "image/png".to_string(),
+            ))
+            .add_content(
+                ContentBuilder::new(
+                    "<body><h1>Title</h1></body>".as_bytes(),
+                    ReferenceType::TitlePage("Title".to_string()),
+                )
+                .build(),
+            )
+            .add_content(
+                ContentBuilder::new(
+                    r#"<body><h1>Chapter 1</h1><h2 id="id01">Section 1.1</h2></body>"#.as_bytes(),
+                    ReferenceType::Text("Chapter 1".to_string()),
+                )
+                .add_content_reference(ContentReference::new("Section 1.1"))
+                .add_child(
+                    ContentBuilder::new(
+                        "<body><h1>Chapter 2</h1></body>".as_bytes(),
+                        ReferenceType::Text("Chapter 2".to_string()),
+                    )
+                    .build(),
+                )
+                .build(),
+            );
+
+        assert!(builder.validate().is_ok());
+        assert_eq!(builder.validate_archive().unwrap(), Vec::new());
+    }
+
+    #[test]
+    fn test_epub_builder_unicode_normalization() {
+        let builder = || {
+            EpubBuilder::new(MetadataBuilder::title("Cafe\u{301}").build()).add_content(
+                ContentBuilder::new(
+                    "<body><h2 id=\"se\u{301}\">Se\u{301}ance</h2></body>".as_bytes(),
+                    ReferenceType::Text("Chapitre e\u{301}te\u{301}".to_string()),
+                )
+                .add_content_reference(ContentReference::new("Se\u{301}ance").id("se\u{301}"))
+                .build(),
+            )
+        };
+
+        let builder_nfc = builder();
+        assert!(
+            builder_nfc
+                .render_content_opf()
+                .unwrap()
+                .contains("Caf\u{e9}")
+        );
+        assert!(
+            builder_nfc
+                .render_toc_ncx()
+                .unwrap()
+                .contains("c01.xhtml#s\u{e9}")
+        );
+        let contents = builder_nfc.render_contents().unwrap();
+        assert!(
+            contents[0]
+                .1
+                .contains("<title>Chapitre \u{e9}t\u{e9}</title>")
+        );
+        assert!(contents[0].1.contains("id=\"s\u{e9}\""));
+        assert!(builder_nfc.validate_archive().unwrap().is_empty());
+
+        let builder_raw = builder().unicode_normalization(false);
+        assert!(
+            builder_raw
+                .render_content_opf()
+                .unwrap()
+                .contains("Cafe\u{301}")
+        );
+    }
+
+    #[test]
+    fn test_epub_builder_invalid_resources() {
+        let temp_dir = tempdir().expect("Error creating tempdir");
+        let cover_image = temp_dir.path().join("cover.png");
+        let other_cover_image = temp_dir.path().join("other").join("cover.png");
+        let empty_font = temp_dir.path().join("empty.ttf");
+        let missing_font = temp_dir.path().join("missing.ttf");
+        std::fs::create_dir(temp_dir.path().join("other")).unwrap();
+        std::fs::write(&cover_image, b"dummy image data").unwrap();
+        std::fs::write(&other_cover_image, b"other image data").unwrap();
+        std::fs::write(&empty_font, b"").unwrap();
+
+        let mut bytes = Vec::new();
+        let result = EpubBuilder::new(MetadataBuilder::title("Title").build())
+            .cover_image(&cover_image, ImageType::Png)
+            .add_resource(Resource::Image(&cover_image, ImageType::Png))
+            .add_resource(Resource::Image(&other_cover_image, ImageType::Png))
+            .add_resource(Resource::Font(&empty_font, FontType::TrueType))
+            .add_resource(Resource::Font(&missing_font, FontType::TrueType))
+            .create(&mut bytes);
+
+        let Err(crate::Error::InvalidResources(problems)) = result else {
+            panic!("expected invalid resources, got {result:?}");
+        };
+
+        assert_eq!(problems.len(), 3);
+        assert!(problems[0].starts_with("'cover.png': filename used by both"));
+        assert!(problems[1].ends_with("empty.ttf': file is empty"));
+        assert!(problems[2].contains("missing.ttf"));
+        assert!(bytes.is_empty());
     }
 
     #[test]
@@ -272,7 +4116,7 @@ mod tests {
         let epub_result = EpubBuilder::new(MetadataBuilder::title("Title").build())
             .stylesheet(b"body { color: red; }")
             .cover_image(&cover_image, ImageType::Png)
-            .add_resource(Resource::Font(&font))
+            .add_resource(Resource::Font(&font, FontType::TrueType))
             .add_content(
                 ContentBuilder::new(
                     "<body><h1>Part I</h1></body>".as_bytes(),
@@ -342,4 +4186,347 @@ mod tests {
 
         assert!(epub_result.is_ok());
     }
+
+    #[tokio::test]
+    #[cfg(feature = "async")]
+    async fn test_epub_builder_copies_resources_in_chunks() {
+        let temp_dir = tempdir().unwrap();
+        let audio = temp_dir.path().join("track.mp3");
+        let bytes = (0..file_content::COPY_CHUNK_SIZE * 3 + 17)
+            .map(|i| (i % 251) as u8)
+            .collect::<Vec<_>>();
+        std::fs::write(&audio, &bytes).unwrap();
+
+        let epub_builder = || {
+            EpubBuilder::new(MetadataBuilder::title("Title").build())
+                .add_resource(Resource::Audio(&audio, crate::epub::AudioType::Mp3))
+                .add_content(
+                    ContentBuilder::new(
+                        b"<body><p>Text</p></body>",
+                        ReferenceType::Text("One".to_string()),
+                    )
+                    .build(),
+                )
+        };
+        let read = |epub: Vec<u8>, data_descriptors| {
+            assert_local_headers(&epub, data_descriptors);
+            let mut archive = zip::ZipArchive::new(Cursor::new(epub)).unwrap();
+            let mut track = Vec::new();
+            archive
+                .by_name("OEBPS/track.mp3")
+                .unwrap()
+                .read_to_end(&mut track)
+                .unwrap();
+            track
+        };
+
+        assert_eq!(read(epub_builder().create_to_vec().unwrap(), false), bytes);
+        assert_eq!(
+            read(epub_builder().async_create_to_vec().await.unwrap(), true),
+            bytes
+        );
+    }
+
+    #[test]
+    fn test_epub_builder_files() {
+        let epub_builder = || {
+            EpubBuilder::new(MetadataBuilder::title("Title").build())
+                .deterministic()
+                .stylesheet(b"body {}")
+                .add_resource(Resource::Raw(
+                    "notes.txt",
+                    b"notes",
+                    "text/plain".to_string(),
+                ))
+                .add_contents((1..=3).map(|n| {
+                    ContentBuilder::new(
+                        b"<body><p>Text</p></body>",
+                        ReferenceType::Text(format!("Chapter {n}")),
+                    )
+                    .build()
+                }))
+        };
+
+        let files = epub_builder()
+            .files()
+            .unwrap()
+            .map(|file| file.map(|file| (file.filepath, file.bytes.into_vec().unwrap())))
+            .collect::<crate::Result<Vec<_>>>()
+            .unwrap();
+
+        let epub = epub_builder().create_to_vec().unwrap();
+        assert_complete_local_headers(&epub);
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(epub)).unwrap();
+        let entries = (0..archive.len())
+            .map(|i| {
+                let mut file = archive.by_index(i).unwrap();
+                let mut bytes = Vec::new();
+                file.read_to_end(&mut bytes).unwrap();
+                (file.name().to_string(), bytes)
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(files, entries);
+        assert_eq!(files[0].0, "mimetype");
+        assert_eq!(files.last().unwrap().0, "OEBPS/toc.ncx");
+
+        let missing = Path::new("/missing/chapter.xhtml");
+        let mut files = EpubBuilder::new(MetadataBuilder::title("Title").build())
+            .add_content(
+                ContentBuilder::from_path(missing, ReferenceType::Text("Missing".to_string()))
+                    .build(),
+            )
+            .files()
+            .unwrap();
+        assert!(files.by_ref().take_while(Result::is_ok).count() > 0);
+        assert!(files.next().is_none());
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "async")]
+    async fn test_async_epub_builder_files() {
+        use futures::TryStreamExt;
+
+        let epub_builder = || {
+            EpubBuilder::new(MetadataBuilder::title("Title").build())
+                .deterministic()
+                .add_contents((1..=3).map(|n| {
+                    ContentBuilder::new(
+                        b"<body><p>Text</p></body>",
+                        ReferenceType::Text(format!("Chapter {n}")),
+                    )
+                    .build()
+                }))
+        };
+
+        let files = epub_builder()
+            .files()
+            .unwrap()
+            .collect::<crate::Result<Vec<_>>>()
+            .unwrap();
+        let async_files = epub_builder()
+            .async_files()
+            .unwrap()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+
+        assert_eq!(files, async_files);
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "async")]
+    async fn test_async_epub_builder_concurrent_rendering_keeps_order() {
+        let chapter = |title: String| {
+            ContentBuilder::new(b"<body><p>Text</p></body>", ReferenceType::Text(title))
+        };
+        let epub_builder = || {
+            EpubBuilder::new(MetadataBuilder::title("Title").build())
+                .deterministic()
+                .add_contents((1..=4).map(|n| {
+                    chapter(format!("Chapter {n}"))
+                        .add_children((1..=3).map(|m| chapter(format!("Section {n}.{m}")).build()))
+                        .build()
+                }))
+        };
+
+        let read = |bytes: Vec<u8>| {
+            let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes)).unwrap();
+            (0..archive.len())
+                .map(|i| {
+                    let mut file = archive.by_index(i).unwrap();
+                    let mut text = String::new();
+                    file.read_to_string(&mut text).unwrap();
+                    (file.name().to_string(), text)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let sync_files = read(epub_builder().create_to_vec().unwrap());
+        let async_files = read(epub_builder().async_create_to_vec().await.unwrap());
+
+        assert_eq!(sync_files, async_files);
+        assert!(async_files.iter().any(|(name, text)| {
+            name == "OEBPS/c16.xhtml" && text.contains("<title>Section 4.3</title>")
+        }));
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "async")]
+    async fn test_async_epub_builder_contents_from_paths() {
+        let temp_dir = tempdir().unwrap();
+        let paths = (1..=3)
+            .map(|n| {
+                let path = temp_dir.path().join(format!("chapter{n}.xhtml"));
+                std::fs::write(&path, format!("<body><p>Chapter {n}</p></body>")).unwrap();
+                path
+            })
+            .collect::<Vec<_>>();
+        let missing = temp_dir.path().join("missing.xhtml");
+
+        let epub_builder = EpubBuilder::new(MetadataBuilder::title("Title").build()).add_contents(
+            paths.iter().enumerate().map(|(i, path)| {
+                ContentBuilder::from_path(path, ReferenceType::Text(format!("Chapter {}", i + 1)))
+                    .build()
+            }),
+        );
+        assert!(epub_builder.validate().is_ok());
+
+        let bytes = epub_builder.async_create_to_vec().await.unwrap();
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes)).unwrap();
+        let mut text = String::new();
+        archive
+            .by_name("OEBPS/c03.xhtml")
+            .unwrap()
+            .read_to_string(&mut text)
+            .unwrap();
+        assert!(text.contains("<p>Chapter 3</p>"));
+
+        let epub_builder = EpubBuilder::new(MetadataBuilder::title("Title").build()).add_content(
+            ContentBuilder::from_path(&missing, ReferenceType::Text("Missing".to_string())).build(),
+        );
+        assert!(matches!(
+            epub_builder.validate().unwrap_err().as_slice(),
+            [crate::Error::ResourceNotFound { .. }]
+        ));
+        let error = epub_builder.async_create_to_vec().await.unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "content 'Missing' (OEBPS/c01.xhtml): '{}': resource not found",
+                missing.display()
+            )
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "async")]
+    fn test_async_epub_builder_without_runtime() {
+        let temp_dir = tempdir().unwrap();
+        let image = temp_dir.path().join("map.png");
+        std::fs::write(&image, b"png").unwrap();
+
+        let epub_builder = EpubBuilder::new(MetadataBuilder::title("Title").build())
+            .spawner(&crate::epub::InlineSpawner)
+            .add_resource(Resource::Image(&image, ImageType::Png))
+            .add_content(
+                ContentBuilder::new(
+                    b"<body><p>Text</p></body>",
+                    ReferenceType::Text("One".to_string()),
+                )
+                .build(),
+            );
+
+        let mut bytes = futures::io::Cursor::new(Vec::new());
+        futures::executor::block_on(epub_builder.async_create_futures(&mut bytes)).unwrap();
+
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes.into_inner())).unwrap();
+        let mut map = Vec::new();
+        archive
+            .by_name("OEBPS/map.png")
+            .unwrap()
+            .read_to_end(&mut map)
+            .unwrap();
+        assert_eq!(map, b"png");
+        assert!(archive.by_name("OEBPS/c01.xhtml").is_ok());
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "async")]
+    async fn test_async_epub_builder_create_to_vec() {
+        let bytes = EpubBuilder::new(MetadataBuilder::title("Title").build())
+            .stylesheet(b"body { color: red; }")
+            .add_content(
+                ContentBuilder::new(
+                    "<body><h1>Chapter 1</h1></body>".as_bytes(),
+                    ReferenceType::Text("Chapter 1".to_string()),
+                )
+                .build(),
+            )
+            .async_create_to_vec()
+            .await
+            .unwrap();
+
+        assert_eq!(&bytes[30..38], b"mimetype");
+
+        let archive = zip::ZipArchive::new(std::io::Cursor::new(bytes)).unwrap();
+        assert_eq!(archive.len(), 7);
+        assert!(archive.file_names().any(|name| name == "OEBPS/style.css"));
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "async")]
+    async fn test_async_epub_builder_async_content_source() {
+        struct Query;
+
+        impl crate::epub::AsyncContentSource for Query {
+            fn body(&self) -> futures::future::BoxFuture<'_, crate::Result<Cow<'_, [u8]>>> {
+                Box::pin(async { Ok(Cow::Borrowed(b"<body><p>queried</p></body>".as_slice())) })
+            }
+        }
+
+        let query = Query;
+        let epub_builder = || {
+            EpubBuilder::new(MetadataBuilder::title("Title").build()).add_content(
+                ContentBuilder::new(b"<body/>", ReferenceType::Text("Part".to_string()))
+                    .add_child(
+                        ContentBuilder::from_async_source(
+                            &query,
+                            ReferenceType::Text("Chapter 1".to_string()),
+                        )
+                        .build(),
+                    )
+                    .build(),
+            )
+        };
+
+        let bytes = epub_builder().async_create_to_vec().await.unwrap();
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes)).unwrap();
+        let mut chapter = String::new();
+        archive
+            .by_name("OEBPS/c02.xhtml")
+            .unwrap()
+            .read_to_string(&mut chapter)
+            .unwrap();
+        assert!(chapter.contains("<p>queried</p>"));
+
+        let error = epub_builder().create_to_vec().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "content 'Chapter 1' (OEBPS/c02.xhtml): Content 'Chapter 1' has an async source, \
+             which only the async API can produce"
+        );
+        assert!(matches!(
+            error.root_cause(),
+            crate::Error::AsyncContentSource(title) if title == "Chapter 1"
+        ));
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "async")]
+    async fn test_async_epub_builder_mimetype_stored_when_deflated() {
+        let mut bytes = Vec::new();
+        EpubBuilder::new(MetadataBuilder::title("Title").build())
+            .async_create_with_compression(&mut bytes, ZipCompression::Deflated)
+            .await
+            .unwrap();
+
+        assert_eq!(&bytes[30..58], b"mimetypeapplication/epub+zip");
+        let (flags, method, crc32, compressed_size, size) = local_header(&bytes, 0);
+        // The UTF-8 filename flag (bit 11) is set, but no data descriptor
+        assert_eq!((flags & 0x0008, method), (0, 0));
+        assert_ne!(crc32, 0);
+        assert_eq!((compressed_size, size), (20, 20));
+        assert_complete_local_headers(&bytes);
+
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes)).unwrap();
+        assert_eq!(
+            archive.by_index(0).unwrap().compression(),
+            zip::CompressionMethod::Stored
+        );
+        assert_eq!(
+            archive.by_index(1).unwrap().compression(),
+            zip::CompressionMethod::Deflated
+        );
+    }
 }
@@ -0,0 +1,256 @@
+use quick_xml::{
+    Reader,
+    escape::escape,
+    events::{BytesStart, Event},
+};
+
+/// How the external hyperlinks of the bodies are annotated, so readers of devices without a
+/// browser (or of a printout) can still follow them. Set with
+/// [`EpubBuilder::external_links`](crate::epub::EpubBuilder::external_links).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkAnnotation {
+    /// The URL follows the link, in parentheses: `<span class="link-url">(https://…)</span>`.
+    Inline,
+    /// The link gets a numbered note reference (`<sup class="link-ref">`), and the URLs are
+    /// listed at the end of the body in a `<section class="link-notes">`.
+    Footnotes,
+}
+
+/// An external hyperlink that did not answer a `HEAD` request, found by
+/// [`EpubBuilder::dead_links`](crate::epub::EpubBuilder::dead_links).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeadLink {
+    /// The filename of the content linking to it (e.g. `c03.xhtml`).
+    pub filename: String,
+    /// The URL of the link.
+    pub url: String,
+    /// Why the request failed (e.g. the HTTP status or the network error).
+    pub reason: String,
+}
+
+impl std::fmt::Display for DeadLink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {} is dead ({})",
+            self.filename, self.url, self.reason
+        )
+    }
+}
+
+/// Checks whether `href` leads outside the book: it has a URI scheme (e.g. `https:` or
+/// `mailto:`).
+pub(crate) fn is_external(href: &str) -> bool {
+    href.split_once(':').is_some_and(|(scheme, _)| {
+        scheme.len() > 1
+            && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+    })
+}
+
+/// Checks the syntax of the external link `href`, returning why it is invalid, if it is.
+pub(crate) fn link_syntax_error(href: &str) -> Option<&'static str> {
+    if href.chars().any(char::is_whitespace) {
+        return Some("contains whitespace");
+    }
+    let (scheme, rest) = href.split_once(':')?;
+    match scheme.to_ascii_lowercase().as_str() {
+        "http" | "https" => {
+            let Some(authority) = rest.strip_prefix("//") else {
+                return Some("is missing the // before the host");
+            };
+            let host = authority.split(['/', '?', '#']).next().unwrap_or_default();
+            let host = host.rsplit('@').next().unwrap_or_default();
+            let (host, port) = match host.strip_prefix('[') {
+                Some(ip) => match ip.split_once(']') {
+                    Some((ip, port)) => (ip, port.strip_prefix(':').unwrap_or(port)),
+                    None => return Some("has an invalid host"),
+                },
+                None => host.split_once(':').unwrap_or((host, "")),
+            };
+            if host.is_empty() {
+                Some("has no host")
+            } else if !port.chars().all(|c| c.is_ascii_digit()) {
+                Some("has an invalid port")
+            } else if !host
+                .chars()
+                .all(|c| c.is_alphanumeric() || "-.:".contains(c))
+            {
+                Some("has an invalid host")
+            } else {
+                None
+            }
+        }
+        "mailto" if !rest.contains('@') => Some("has no e-mail address"),
+        _ => None,
+    }
+}
+
+/// Finds the external links of `body`: the `href` of its `<a>` elements with a URI scheme,
+/// in order, unescaped.
+///
+/// The scan stops silently at the first markup error (bodies are not required to be well
+/// formed).
+pub(crate) fn external_links(body: &[u8]) -> Vec<String> {
+    let mut reader = Reader::from_reader(body);
+    reader.config_mut().check_end_names = false;
+    let mut links = Vec::new();
+    let mut buffer = Vec::new();
+
+    loop {
+        match reader.read_event_into(&mut buffer) {
+            Ok(Event::Start(ref element) | Event::Empty(ref element)) => {
+                links.extend(external_href(element));
+            }
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+        buffer.clear();
+    }
+    links
+}
+
+/// Annotates the external links of the XHTML `body` as `annotation` says.
+///
+/// The body is left as is if it has no external link or a markup error.
+pub(crate) fn annotate(body: &str, annotation: LinkAnnotation) -> String {
+    let mut reader = Reader::from_str(body);
+    reader.config_mut().check_end_names = false;
+    let mut link_ends = Vec::new();
+    let mut open = Vec::new();
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(ref element)) if element.local_name().as_ref() == b"a" => {
+                open.push(external_href(element));
+            }
+            Ok(Event::End(ref element)) if element.local_name().as_ref() == b"a" => {
+                if let Some(Some(href)) = open.pop() {
+                    link_ends.push((reader.buffer_position() as usize, href));
+                }
+            }
+            Ok(Event::Eof) => break,
+            Err(_) => return body.to_string(),
+            _ => {}
+        }
+    }
+    if link_ends.is_empty() {
+        return body.to_string();
+    }
+
+    let mut annotated = String::with_capacity(body.len() + link_ends.len() * 64);
+    let mut notes = String::new();
+    let mut last = 0;
+    for (number, (end, href)) in link_ends.iter().enumerate() {
+        let number = number + 1;
+        let href = escape(href.as_str());
+        annotated.push_str(&body[last..*end]);
+        match annotation {
+            LinkAnnotation::Inline => {
+                annotated.push_str(&format!(r#" <span class="link-url">({href})</span>"#));
+            }
+            LinkAnnotation::Footnotes => {
+                annotated.push_str(&format!(
+                    r##"<sup class="link-ref"><a id="link-ref-{number}" href="#link-note-{number}">{number}</a></sup>"##
+                ));
+                notes.push_str(&format!(
+                    r##"<li id="link-note-{number}"><a href="#link-ref-{number}">↑</a> {href}</li>"##
+                ));
+            }
+        }
+        last = *end;
+    }
+    let rest = &body[last..];
+    match rest.rfind("</body>") {
+        Some(index) if !notes.is_empty() => {
+            annotated.push_str(&rest[..index]);
+            annotated.push_str(&format!(
+                r#"<section class="link-notes"><ol>{notes}</ol></section>"#
+            ));
+            annotated.push_str(&rest[index..]);
+        }
+        _ => annotated.push_str(rest),
+    }
+    annotated
+}
+
+/// Gets the `href` of the `<a>` element `element`, if it is an external link.
+fn external_href(element: &BytesStart<'_>) -> Option<String> {
+    if element.local_name().as_ref() != b"a" {
+        return None;
+    }
+    element
+        .try_get_attribute(b"href")
+        .ok()
+        .flatten()
+        .and_then(|attribute| attribute.unescape_value().ok())
+        .map(|href| href.trim().to_string())
+        .filter(|href| is_external(href))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_external_link_syntax() {
+        assert!(is_external("https://example.com"));
+        assert!(is_external("mailto:me@example.com"));
+        assert!(!is_external("c02.xhtml#id01"));
+        assert!(!is_external("images/a:b.png"));
+
+        assert_eq!(link_syntax_error("https://example.com:8080/a?b#c"), None);
+        assert_eq!(link_syntax_error("http://user@[::1]:80/"), None);
+        assert_eq!(
+            link_syntax_error("https:/example.com"),
+            Some("is missing the // before the host")
+        );
+        assert_eq!(link_syntax_error("https:///path"), Some("has no host"));
+        assert_eq!(
+            link_syntax_error("https://exa mple.com"),
+            Some("contains whitespace")
+        );
+        assert_eq!(
+            link_syntax_error("https://example.com:8o"),
+            Some("has an invalid port")
+        );
+        assert_eq!(
+            link_syntax_error("https://exa_mple.com"),
+            Some("has an invalid host")
+        );
+        assert_eq!(
+            link_syntax_error("mailto:nobody"),
+            Some("has no e-mail address")
+        );
+        assert_eq!(link_syntax_error("tel:+123"), None);
+    }
+
+    #[test]
+    fn test_external_links_annotate() {
+        let body = r##"<body><p><a href="https://a.org/?x=1&amp;y=2">A</a>, <a href="#n1">note</a> and <a href="mailto:b@c.org"><em>B</em></a>.</p></body>"##;
+        assert_eq!(
+            external_links(body.as_bytes()),
+            ["https://a.org/?x=1&y=2", "mailto:b@c.org"]
+        );
+
+        assert_eq!(
+            annotate(body, LinkAnnotation::Inline),
+            r##"<body><p><a href="https://a.org/?x=1&amp;y=2">A</a> <span class="link-url">(https://a.org/?x=1&amp;y=2)</span>, <a href="#n1">note</a> and <a href="mailto:b@c.org"><em>B</em></a> <span class="link-url">(mailto:b@c.org)</span>.</p></body>"##
+        );
+        assert_eq!(
+            annotate(body, LinkAnnotation::Footnotes),
+            concat!(
+                r##"<body><p><a href="https://a.org/?x=1&amp;y=2">A</a><sup class="link-ref"><a id="link-ref-1" href="#link-note-1">1</a></sup>, "##,
+                r##"<a href="#n1">note</a> and <a href="mailto:b@c.org"><em>B</em></a><sup class="link-ref"><a id="link-ref-2" href="#link-note-2">2</a></sup>.</p>"##,
+                r##"<section class="link-notes"><ol><li id="link-note-1"><a href="#link-ref-1">↑</a> https://a.org/?x=1&amp;y=2</li>"##,
+                r##"<li id="link-note-2"><a href="#link-ref-2">↑</a> mailto:b@c.org</li></ol></section></body>"##
+            )
+        );
+        assert_eq!(
+            annotate("<body><p>No links</p></body>", LinkAnnotation::Footnotes),
+            "<body><p>No links</p></body>"
+        );
+    }
+}
@@ -0,0 +1,196 @@
+use std::path::Path;
+
+use crate::epub::ResourceItem;
+
+/// Font weights by the style suffix of a font filename (e.g. `Merriweather-SemiBold.ttf`),
+/// longest names first so `ExtraBold` is not read as `Bold`.
+const WEIGHTS: &[(&str, u16)] = &[
+    ("extralight", 200),
+    ("ultralight", 200),
+    ("extrabold", 800),
+    ("ultrabold", 800),
+    ("semibold", 600),
+    ("demibold", 600),
+    ("regular", 400),
+    ("medium", 500),
+    ("normal", 400),
+    ("black", 900),
+    ("heavy", 900),
+    ("light", 300),
+    ("thin", 100),
+    ("bold", 700),
+    ("book", 400),
+];
+
+/// An `@font-face` rule for an embedded font.
+#[derive(Debug, PartialEq)]
+struct FontFace {
+    /// The font family name.
+    family: String,
+    /// The weight, from `100` (thin) to `900` (black).
+    weight: u16,
+    /// Whether the font is the italic style of the family.
+    italic: bool,
+    /// The filename of the font inside the `OEBPS` directory.
+    src: String,
+    /// The CSS format hint (e.g. `truetype`).
+    format: &'static str,
+}
+
+impl FontFace {
+    /// Describes the font `resource`, taking the family name from its explicit metadata or else
+    /// from its filename, and the weight and style from the suffix of its filename (e.g.
+    /// `Merriweather-BoldItalic.ttf` is the bold italic face of `Merriweather`).
+    ///
+    /// Returns `None` if the resource is not a font.
+    fn new(resource: &ResourceItem<'_>) -> Option<Self> {
+        let format = match resource.media_type() {
+            "font/ttf" | "application/x-font-ttf" => "truetype",
+            "font/otf" | "application/vnd.ms-opentype" => "opentype",
+            "font/woff" | "application/font-woff" => "woff",
+            "font/woff2" => "woff2",
+            _ => return None,
+        };
+
+        let src = resource.filename().ok()?;
+        let stem = Path::new(&src).file_stem()?.to_str()?.to_string();
+        let (name, style) = stem.rsplit_once('-').unwrap_or((&stem, ""));
+        let style = style.to_lowercase();
+        let italic = style.contains("italic") || style.contains("oblique");
+        let weight = WEIGHTS
+            .iter()
+            .find(|(weight, _)| style.contains(weight))
+            .map_or(400, |(_, weight)| *weight);
+
+        let family = match resource.font_family {
+            Some(ref family) => family.clone(),
+            None => split_words(&name.replace('_', " ")),
+        };
+
+        Some(Self {
+            family,
+            weight,
+            italic,
+            src,
+            format,
+        })
+    }
+
+    /// Writes the `@font-face` rule.
+    fn rule(&self) -> String {
+        format!(
+            "@font-face {{\n  font-family: \"{}\";\n  font-weight: {};\n  font-style: {};\n  src: url(\"{}\") format(\"{}\");\n}}\n",
+            self.family.replace(['"', '\\'], ""),
+            self.weight,
+            if self.italic { "italic" } else { "normal" },
+            self.src.replace(['"', '\\'], ""),
+            self.format
+        )
+    }
+}
+
+/// Generates the `@font-face` rules of the fonts among `resources`, skipping the fonts already
+/// mentioned by `stylesheet` (declared by hand).
+pub(crate) fn font_face_rules(resources: &[ResourceItem<'_>], stylesheet: Option<&[u8]>) -> String {
+    let stylesheet = stylesheet.map(String::from_utf8_lossy).unwrap_or_default();
+
+    resources
+        .iter()
+        .filter_map(FontFace::new)
+        .filter(|font_face| !stylesheet.contains(font_face.src.as_str()))
+        .map(|font_face| font_face.rule())
+        .collect()
+}
+
+/// Whether any of `resources` is a font with an `@font-face` rule.
+pub(crate) fn has_fonts(resources: &[ResourceItem<'_>]) -> bool {
+    resources
+        .iter()
+        .any(|resource| FontFace::new(resource).is_some())
+}
+
+/// Splits a camel case name into words (e.g. `OpenSans` into `Open Sans`).
+fn split_words(name: &str) -> String {
+    let mut words = String::with_capacity(name.len() + 4);
+    let mut previous: Option<char> = None;
+
+    for c in name.chars() {
+        if c.is_uppercase() && previous.is_some_and(char::is_lowercase) {
+            words.push(' ');
+        }
+        words.push(c);
+        previous = Some(c);
+    }
+
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::epub::{FontType, Resource};
+
+    #[test]
+    fn test_font_face_from_filename() {
+        let bold_italic = Resource::Font(
+            Path::new("fonts/Merriweather-BoldItalic.ttf"),
+            FontType::TrueType,
+        )
+        .into();
+        assert_eq!(
+            FontFace::new(&bold_italic),
+            Some(FontFace {
+                family: "Merriweather".to_string(),
+                weight: 700,
+                italic: true,
+                src: "Merriweather-BoldItalic.ttf".to_string(),
+                format: "truetype",
+            })
+        );
+
+        let face =
+            FontFace::new(&Resource::Font(Path::new("OpenSans.woff2"), FontType::Woff2).into())
+                .unwrap();
+        assert_eq!(
+            (face.family.as_str(), face.weight, face.italic),
+            ("Open Sans", 400, false)
+        );
+
+        let face = FontFace::new(
+            &Resource::Font(Path::new("Source_Serif-ExtraBold.otf"), FontType::OpenType)
+                .font_family("Source Serif Pro"),
+        )
+        .unwrap();
+        assert_eq!(
+            (face.family.as_str(), face.weight),
+            ("Source Serif Pro", 800)
+        );
+
+        assert!(
+            FontFace::new(&Resource::Raw("map.png", b"png", "image/png".to_string()).into())
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_font_face_rules() {
+        let resources = vec![
+            Resource::Font(Path::new("Lora-Regular.ttf"), FontType::TrueType).into(),
+            Resource::Font(Path::new("Lora-Italic.ttf"), FontType::TrueType).into(),
+            Resource::Raw("map.png", b"png", "image/png".to_string()).into(),
+        ];
+
+        let rules = font_face_rules(
+            &resources,
+            Some(b"@font-face { font-family: Lora; src: url(Lora-Italic.ttf); }"),
+        );
+        assert_eq!(
+            rules,
+            "@font-face {\n  font-family: \"Lora\";\n  font-weight: 400;\n  font-style: normal;\n  src: url(\"Lora-Regular.ttf\") format(\"truetype\");\n}\n"
+        );
+        assert!(has_fonts(&resources));
+        assert!(!has_fonts(&resources[2..]));
+    }
+}
@@ -0,0 +1,239 @@
+use quick_xml::escape::escape;
+
+use crate::epub::{Content, ContentBuilder, ReferenceType};
+
+/// A dedication page, generated as a [`ReferenceType::Dedication`] content without
+/// hand-written XHTML.
+///
+/// Every line of the text is a paragraph of a `<div class="dedication">`, which can be styled
+/// in the stylesheet or with [`Dedication::style`].
+///
+/// ```rust
+/// use liber::epub::{Dedication, EpubBuilder, MetadataBuilder};
+///
+/// let epub_builder = EpubBuilder::new(MetadataBuilder::title("My Book").build())
+///     .add_content(Dedication::new("For my mother,\nwho taught me to read").build());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dedication {
+    /// The title of the page. Defaults to `Dedication`.
+    title: String,
+    /// The text of the dedication, one paragraph per line.
+    text: String,
+    /// Optional inline CSS of the dedication.
+    style: Option<String>,
+}
+
+impl Dedication {
+    /// Creates a dedication page with its **text**, one paragraph per line.
+    #[must_use]
+    pub fn new<S: Into<String>>(text: S) -> Self {
+        Self {
+            title: "Dedication".to_string(),
+            text: text.into(),
+            style: None,
+        }
+    }
+
+    /// Sets the **title** of the page (listed in the guide and the table of contents).
+    ///
+    /// This is a fluent method, returning `Self`.
+    pub fn title<S: Into<String>>(mut self, title: S) -> Self {
+        self.title = title.into();
+        self
+    }
+
+    /// Sets the inline CSS of the dedication (e.g. `text-align: center; margin-top: 30%`).
+    ///
+    /// This is a fluent method, returning `Self`.
+    pub fn style<S: Into<String>>(mut self, style: S) -> Self {
+        self.style = Some(style.into());
+        self
+    }
+
+    /// Gets a [`ContentBuilder`] of the page, to set its filename or add children.
+    pub fn builder<'a>(self) -> ContentBuilder<'a> {
+        let body = format!(
+            r#"<body><div class="dedication"{}>{}</div></body>"#,
+            style_attribute(self.style.as_deref()),
+            paragraphs(&self.text)
+        );
+        ContentBuilder::generated(body, ReferenceType::Dedication(self.title))
+    }
+
+    /// Generates the page as a [`Content`].
+    pub fn build<'a>(self) -> Content<'a> {
+        self.builder().build()
+    }
+}
+
+/// An epigraph page: a quotation with its attribution, generated as a
+/// [`ReferenceType::Epigraph`] content without hand-written XHTML.
+///
+/// The quotation (one paragraph per line) is a `<blockquote>` followed by a
+/// `<p class="attribution">`, inside a `<div class="epigraph">`, which can be styled in the
+/// stylesheet or with [`Epigraph::style`].
+///
+/// ```rust
+/// use liber::epub::Epigraph;
+///
+/// let epigraph = Epigraph::new("All that is gold does not glitter,\nNot all those who wander are lost")
+///     .attribution("J. R. R. Tolkien")
+///     .source("The Fellowship of the Ring")
+///     .build();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Epigraph {
+    /// The title of the page. Defaults to `Epigraph`.
+    title: String,
+    /// The quotation, one paragraph per line.
+    quote: String,
+    /// Optional author of the quotation.
+    attribution: Option<String>,
+    /// Optional work the quotation is taken from.
+    source: Option<String>,
+    /// Optional inline CSS of the epigraph.
+    style: Option<String>,
+}
+
+impl Epigraph {
+    /// Creates an epigraph page with its **quotation**, one paragraph per line.
+    #[must_use]
+    pub fn new<S: Into<String>>(quote: S) -> Self {
+        Self {
+            title: "Epigraph".to_string(),
+            quote: quote.into(),
+            attribution: None,
+            source: None,
+            style: None,
+        }
+    }
+
+    /// Sets the **title** of the page (listed in the guide and the table of contents).
+    ///
+    /// This is a fluent method, returning `Self`.
+    pub fn title<S: Into<String>>(mut self, title: S) -> Self {
+        self.title = title.into();
+        self
+    }
+
+    /// Sets the **author** of the quotation.
+    ///
+    /// This is a fluent method, returning `Self`.
+    pub fn attribution<S: Into<String>>(mut self, attribution: S) -> Self {
+        self.attribution = Some(attribution.into());
+        self
+    }
+
+    /// Sets the **work** the quotation is taken from, cited after the author.
+    ///
+    /// This is a fluent method, returning `Self`.
+    pub fn source<S: Into<String>>(mut self, source: S) -> Self {
+        self.source = Some(source.into());
+        self
+    }
+
+    /// Sets the inline CSS of the epigraph (e.g. `margin: 20% 10% 0; font-style: italic`).
+    ///
+    /// This is a fluent method, returning `Self`.
+    pub fn style<S: Into<String>>(mut self, style: S) -> Self {
+        self.style = Some(style.into());
+        self
+    }
+
+    /// Gets a [`ContentBuilder`] of the page, to set its filename or add children.
+    pub fn builder<'a>(self) -> ContentBuilder<'a> {
+        let source = self
+            .source
+            .as_deref()
+            .map(|source| format!("<cite>{}</cite>", escape(source)));
+        let attribution = match (self.attribution.as_deref().map(escape), source) {
+            (Some(author), Some(source)) => format!("{author}, {source}"),
+            (Some(author), None) => author.into_owned(),
+            (None, Some(source)) => source,
+            (None, None) => String::new(),
+        };
+        let attribution = if attribution.is_empty() {
+            attribution
+        } else {
+            format!(r#"<p class="attribution">— {attribution}</p>"#)
+        };
+
+        let body = format!(
+            r#"<body><div class="epigraph"{}><blockquote>{}</blockquote>{attribution}</div></body>"#,
+            style_attribute(self.style.as_deref()),
+            paragraphs(&self.quote)
+        );
+        ContentBuilder::generated(body, ReferenceType::Epigraph(self.title))
+    }
+
+    /// Generates the page as a [`Content`].
+    pub fn build<'a>(self) -> Content<'a> {
+        self.builder().build()
+    }
+}
+
+/// Renders every non-blank line of `text` as a paragraph.
+fn paragraphs(text: &str) -> String {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| format!("<p>{}</p>", escape(line)))
+        .collect()
+}
+
+/// Renders the `style` attribute of the inline CSS `style`, if any.
+fn style_attribute(style: Option<&str>) -> String {
+    style
+        .map(|style| format!(r#" style="{}""#, escape(style)))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_front_matter() {
+        let dedication = Dedication::new("For my mother,\n\n  who taught me to read  ")
+            .style("text-align: center")
+            .build();
+        assert_eq!(
+            dedication.reference_type(),
+            &ReferenceType::Dedication("Dedication".to_string())
+        );
+        assert_eq!(
+            dedication.body(),
+            Some(
+                br#"<body><div class="dedication" style="text-align: center"><p>For my mother,</p><p>who taught me to read</p></div></body>"#
+                    .as_slice()
+            )
+        );
+
+        let epigraph = Epigraph::new("Not all those who wander are lost")
+            .title("Verse")
+            .attribution("J. R. R. Tolkien")
+            .source("The Lord of the Rings & more")
+            .build();
+        assert_eq!(
+            epigraph.reference_type(),
+            &ReferenceType::Epigraph("Verse".to_string())
+        );
+        assert_eq!(
+            epigraph.body(),
+            Some(
+                r#"<body><div class="epigraph"><blockquote><p>Not all those who wander are lost</p></blockquote><p class="attribution">— J. R. R. Tolkien, <cite>The Lord of the Rings &amp; more</cite></p></div></body>"#
+                    .as_bytes()
+            )
+        );
+
+        let anonymous = Epigraph::new("Quote").build();
+        assert_eq!(
+            anonymous.body(),
+            Some(
+                br#"<body><div class="epigraph"><blockquote><p>Quote</p></blockquote></div></body>"#
+                    .as_slice()
+            )
+        );
+    }
+}
@@ -0,0 +1,162 @@
+use std::collections::BTreeMap;
+
+use quick_xml::escape::escape;
+
+/// A term of the back-of-book index tagged at an anchor of a content body, with an optional
+/// sub-term (e.g. `Rivers` › `Amazon`).
+///
+/// Terms are tagged with [`ContentBuilder::add_index_term`] and the index is generated with
+/// [`EpubBuilder::index`]: an alphabetized page linking back to every occurrence.
+///
+/// ```rust
+/// use liber::epub::{ContentBuilder, IndexTerm, ReferenceType};
+///
+/// let content = ContentBuilder::new(
+///     br#"<body><p id="amazon">The Amazon is the largest river.</p></body>"#,
+///     ReferenceType::Text("Rivers".to_string()),
+/// )
+/// .add_index_term(IndexTerm::new("Rivers", "amazon").sub_term("Amazon"))
+/// .build();
+/// ```
+///
+/// [`ContentBuilder::add_index_term`]: crate::epub::ContentBuilder::add_index_term
+/// [`EpubBuilder::index`]: crate::epub::EpubBuilder::index
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexTerm {
+    /// The term, as listed in the index.
+    pub(crate) term: String,
+    /// The optional sub-term, listed under the term.
+    pub(crate) sub_term: Option<String>,
+    /// The `id` of the element of the body where the term occurs.
+    pub(crate) anchor: String,
+}
+
+impl IndexTerm {
+    /// Creates an index **term** occurring at the element of the body with the `id` **anchor**.
+    pub fn new<T: Into<String>, A: Into<String>>(term: T, anchor: A) -> Self {
+        Self {
+            term: term.into(),
+            sub_term: None,
+            anchor: anchor.into(),
+        }
+    }
+
+    /// Sets the **sub-term**, listed under the term.
+    pub fn sub_term<S: Into<String>>(mut self, sub_term: S) -> Self {
+        self.sub_term = Some(sub_term.into());
+        self
+    }
+}
+
+/// The links of an entry of the index, and of its sub-entries.
+#[derive(Debug, Default)]
+struct Entry {
+    /// The hrefs of the occurrences of the term itself.
+    hrefs: Vec<String>,
+    /// The hrefs of the occurrences of every sub-term, by sort key.
+    sub_entries: BTreeMap<(String, String), Vec<String>>,
+}
+
+/// Renders the body of the index page: its `title` as heading and the `occurrences` (terms and
+/// the filenames of their contents) alphabetized, grouped by initial letter, with numbered links
+/// back to every occurrence.
+pub(crate) fn index_body(title: &str, occurrences: &[(&IndexTerm, String)]) -> String {
+    let mut entries = BTreeMap::<(String, String), Entry>::new();
+    for (term, filename) in occurrences {
+        let href = format!("{filename}#{}", term.anchor);
+        let entry = entries.entry(sort_key(&term.term)).or_default();
+        match term.sub_term {
+            Some(ref sub_term) => entry
+                .sub_entries
+                .entry(sort_key(sub_term))
+                .or_default()
+                .push(href),
+            None => entry.hrefs.push(href),
+        }
+    }
+
+    let mut body = format!("<body><h1>{}</h1>", escape(title));
+    let mut letter = None;
+    for ((key, term), entry) in &entries {
+        let initial = key
+            .chars()
+            .next()
+            .map(|c| c.to_uppercase().collect::<String>());
+        if initial != letter {
+            if letter.is_some() {
+                body.push_str("</ul></section>");
+            }
+            let heading = initial.as_deref().unwrap_or_default();
+            body.push_str(&format!(
+                r#"<section><h2>{}</h2><ul class="index">"#,
+                escape(heading)
+            ));
+            letter = initial;
+        }
+
+        body.push_str(&format!("<li>{}{}", escape(term), links(&entry.hrefs)));
+        if !entry.sub_entries.is_empty() {
+            body.push_str("<ul>");
+            for ((_, sub_term), hrefs) in &entry.sub_entries {
+                body.push_str(&format!("<li>{}{}</li>", escape(sub_term), links(hrefs)));
+            }
+            body.push_str("</ul>");
+        }
+        body.push_str("</li>");
+    }
+    if letter.is_some() {
+        body.push_str("</ul></section>");
+    }
+    body.push_str("</body>");
+    body
+}
+
+/// Gets the key sorting `term` alphabetically regardless of case, with the term itself.
+fn sort_key(term: &str) -> (String, String) {
+    (term.to_lowercase(), term.to_string())
+}
+
+/// Renders the numbered links (`, 1, 2`) to the occurrences `hrefs` of a term.
+fn links(hrefs: &[String]) -> String {
+    hrefs
+        .iter()
+        .enumerate()
+        .map(|(number, href)| format!(r#", <a href="{}">{}</a>"#, escape(href), number + 1))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_index_body() {
+        let terms = [
+            IndexTerm::new("rivers", "nile"),
+            IndexTerm::new("Rivers", "amazon").sub_term("Amazon"),
+            IndexTerm::new("Andes", "andes"),
+            IndexTerm::new("Andes", "peaks"),
+        ];
+        let occurrences = [
+            (&terms[0], "c01.xhtml".to_string()),
+            (&terms[1], "c02.xhtml".to_string()),
+            (&terms[2], "c02.xhtml".to_string()),
+            (&terms[3], "c03.xhtml".to_string()),
+        ];
+
+        assert_eq!(
+            index_body("Index", &occurrences),
+            concat!(
+                "<body><h1>Index</h1>",
+                r#"<section><h2>A</h2><ul class="index">"#,
+                r#"<li>Andes, <a href="c02.xhtml#andes">1</a>, <a href="c03.xhtml#peaks">2</a></li>"#,
+                "</ul></section>",
+                r#"<section><h2>R</h2><ul class="index">"#,
+                r#"<li>Rivers<ul><li>Amazon, <a href="c02.xhtml#amazon">1</a></li></ul></li>"#,
+                r#"<li>rivers, <a href="c01.xhtml#nile">1</a></li>"#,
+                "</ul></section></body>"
+            )
+        );
+        assert_eq!(index_body("Index", &[]), "<body><h1>Index</h1></body>");
+    }
+}
@@ -0,0 +1,433 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use pulldown_cmark::{CowStr, Event, Options, Parser, Tag, html};
+
+use crate::epub::{
+    Content, ContentBuilder, Language, Metadata, MetadataBuilder, ReferenceType, Resource,
+    ResourceItem,
+};
+
+/// An [mdBook](https://rust-lang.github.io/mdBook/) project, with its chapters rendered to XHTML.
+///
+/// Since contents and resources only borrow their bodies and paths, the rendered chapters are
+/// owned here and the [`EpubBuilder`](crate::epub::EpubBuilder) borrows them through
+/// [`from_mdbook`](crate::epub::EpubBuilder::from_mdbook).
+#[derive(Debug, Clone)]
+pub struct MdBook {
+    /// The title of the `[book]` table of `book.toml`.
+    title: Option<String>,
+    /// The authors of the `[book]` table of `book.toml`.
+    authors: Vec<String>,
+    /// The language code of the `[book]` table of `book.toml`.
+    language: Option<String>,
+    /// The description of the `[book]` table of `book.toml`.
+    description: Option<String>,
+    /// The chapters listed in `SUMMARY.md`, in reading order.
+    chapters: Vec<MdChapter>,
+    /// The local images of the chapters as (path on disk, path relative to the source directory).
+    assets: Vec<(PathBuf, String)>,
+}
+
+/// A chapter of an [`MdBook`], rendered to XHTML.
+#[derive(Debug, Clone)]
+struct MdChapter {
+    /// The title of the chapter in `SUMMARY.md`.
+    title: String,
+    /// The filename of the chapter inside the EPUB (e.g. `guide-intro.xhtml` for `guide/intro.md`).
+    filename: String,
+    /// The rendered XHTML body.
+    body: Vec<u8>,
+    /// The nested chapters.
+    children: Vec<MdChapter>,
+}
+
+/// An entry of `SUMMARY.md`.
+#[derive(Debug, PartialEq)]
+struct SummaryItem {
+    /// The indentation of the list item (`0` for prefix and suffix chapters).
+    indent: usize,
+    /// The title of the chapter.
+    title: String,
+    /// The markdown file relative to the source directory, or `None` for a draft chapter.
+    path: Option<String>,
+}
+
+impl MdBook {
+    /// Reads the mdBook project at `root`: the `book.toml` (if any), the `SUMMARY.md` of its
+    /// source directory and every chapter listed there, nesting preserved.
+    ///
+    /// `root` may also be the source directory itself (the one holding `SUMMARY.md`). Draft
+    /// chapters (without a file) are skipped, links between chapters are rewritten to the
+    /// generated `.xhtml` files, and local images are collected as resources.
+    ///
+    /// # Errors
+    /// Returns an error if `SUMMARY.md` or a chapter cannot be read, or a
+    /// [`crate::Error::MdBook`] if `book.toml` is not valid.
+    pub fn read<P: AsRef<Path>>(root: P) -> crate::Result<Self> {
+        let root = root.as_ref();
+        let book_toml = root.join("book.toml");
+        let config = if book_toml.is_file() {
+            fs::read_to_string(&book_toml)?
+                .parse::<toml::Table>()
+                .map_err(|e| crate::Error::MdBook(format!("{}: {e}", book_toml.display())))?
+        } else {
+            toml::Table::new()
+        };
+
+        let book = config.get("book").and_then(toml::Value::as_table);
+        let setting = |key: &str| {
+            book.and_then(|book| book.get(key))
+                .and_then(toml::Value::as_str)
+                .map(str::to_string)
+        };
+        let src = match setting("src") {
+            Some(src) => root.join(src),
+            None if root.join("src/SUMMARY.md").is_file() => root.join("src"),
+            None => root.to_path_buf(),
+        };
+
+        let summary = fs::read_to_string(src.join("SUMMARY.md"))?;
+        let items = parse_summary(&summary);
+        let mut assets = Vec::new();
+        let chapters = chapters(&items, &mut 0, 0, &src, &mut assets)?;
+
+        Ok(Self {
+            title: setting("title"),
+            authors: book
+                .and_then(|book| book.get("authors"))
+                .and_then(toml::Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(|author| author.as_str().map(str::to_string))
+                .collect(),
+            language: setting("language"),
+            description: setting("description"),
+            chapters,
+            assets,
+        })
+    }
+
+    /// Gets the [`Metadata`] declared in `book.toml`: title (`Untitled` if missing), authors,
+    /// language and description.
+    pub fn metadata(&self) -> Metadata {
+        let mut builder = MetadataBuilder::title(self.title.as_deref().unwrap_or("Untitled"));
+        if !self.authors.is_empty() {
+            builder = builder.creator(self.authors.join(", "));
+        }
+        if let Some(language) = self.language.as_deref().and_then(Language::from_code) {
+            builder = builder.language(language);
+        }
+        if let Some(ref description) = self.description {
+            builder = builder.description(description);
+        }
+        builder.build()
+    }
+
+    /// Gets the chapters as contents, nesting preserved.
+    pub fn contents(&self) -> Vec<Content<'_>> {
+        self.chapters.iter().map(MdChapter::content).collect()
+    }
+
+    /// Gets the local images of the chapters as resources, each one packaged under its path
+    /// relative to the source directory.
+    pub fn resources(&self) -> Vec<ResourceItem<'_>> {
+        self.assets
+            .iter()
+            .map(|(path, name)| {
+                Resource::from_path(path)
+                    .unwrap_or(Resource::Other(
+                        path,
+                        "application/octet-stream".to_string(),
+                    ))
+                    .as_name(name)
+            })
+            .collect()
+    }
+}
+
+impl MdChapter {
+    /// Gets the chapter and its children as a [`Content`].
+    fn content(&self) -> Content<'_> {
+        ContentBuilder::new(&self.body, ReferenceType::Text(self.title.clone()))
+            .filename(&self.filename)
+            .add_children(self.children.iter().map(Self::content))
+            .build()
+    }
+}
+
+/// Parses the chapters of `SUMMARY.md`: the list items and the prefix and suffix chapters
+/// written as bare links. Headings (part titles) and separators are skipped.
+fn parse_summary(summary: &str) -> Vec<SummaryItem> {
+    summary
+        .lines()
+        .filter_map(|line| {
+            let trimmed = line.trim_start();
+            let indent = line[..line.len() - trimmed.len()]
+                .chars()
+                .map(|c| if c == '\t' { 4 } else { 1 })
+                .sum::<usize>();
+            let (indent, link) = match trimmed
+                .strip_prefix("- ")
+                .or_else(|| trimmed.strip_prefix("* "))
+            {
+                Some(link) => (indent + 1, link.trim()),
+                None if trimmed.starts_with('[') => (0, trimmed.trim_end()),
+                None => return None,
+            };
+
+            let (title, path) = link.strip_prefix('[')?.rsplit_once("](")?;
+            let path = path.strip_suffix(')')?.trim();
+            Some(SummaryItem {
+                indent,
+                title: title.to_string(),
+                path: (!path.is_empty()).then(|| path.to_string()),
+            })
+        })
+        .collect()
+}
+
+/// Renders the `items` from `position` indented at least `indent` (a level of the tree) into
+/// chapters, nesting the items indented deeper. The children of draft chapters move up a level.
+fn chapters(
+    items: &[SummaryItem],
+    position: &mut usize,
+    indent: usize,
+    src: &Path,
+    assets: &mut Vec<(PathBuf, String)>,
+) -> crate::Result<Vec<MdChapter>> {
+    let mut chapters = Vec::new();
+
+    while let Some(item) = items.get(*position).filter(|item| item.indent >= indent) {
+        *position += 1;
+        // Prefix and suffix chapters (indented `0`) are never nested under.
+        let children = match items.get(*position) {
+            Some(next) if item.indent > 0 && next.indent > item.indent => {
+                self::chapters(items, position, next.indent, src, assets)?
+            }
+            _ => Vec::new(),
+        };
+
+        match item.path {
+            Some(ref path) => chapters.push(MdChapter {
+                title: item.title.clone(),
+                filename: chapter_filename(path),
+                body: render(path, src, assets)?,
+                children,
+            }),
+            None => chapters.extend(children),
+        }
+    }
+
+    Ok(chapters)
+}
+
+/// Gets the filename inside the EPUB of the chapter at `path` (e.g. `guide-intro.xhtml` for
+/// `guide/intro.md`).
+fn chapter_filename(path: &str) -> String {
+    let path = path.trim_start_matches("./");
+    let stem = path.strip_suffix(".md").unwrap_or(path);
+    format!("{}.xhtml", stem.replace(['/', '\\'], "-"))
+}
+
+/// Renders the markdown chapter at `path` (relative to `src`) to an XHTML body, rewriting the
+/// links to other chapters and collecting its local images into `assets`.
+fn render(path: &str, src: &Path, assets: &mut Vec<(PathBuf, String)>) -> crate::Result<Vec<u8>> {
+    let markdown = fs::read_to_string(src.join(path))?;
+    let dir = Path::new(path)
+        .parent()
+        .and_then(Path::to_str)
+        .unwrap_or_default();
+
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_HEADING_ATTRIBUTES;
+    let events = Parser::new_ext(&markdown, options).map(|event| match event {
+        Event::Start(Tag::Link {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) => {
+            let dest_url = match resolve(dir, &dest_url) {
+                Some((target, fragment)) if target.ends_with(".md") => {
+                    CowStr::from(format!("{}{fragment}", chapter_filename(&target)))
+                }
+                _ => dest_url,
+            };
+            Event::Start(Tag::Link {
+                link_type,
+                dest_url,
+                title,
+                id,
+            })
+        }
+        Event::Start(Tag::Image {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) => {
+            let dest_url = match resolve(dir, &dest_url) {
+                Some((target, _)) if src.join(&target).is_file() => {
+                    if !assets.iter().any(|(_, name)| *name == target) {
+                        assets.push((src.join(&target), target.clone()));
+                    }
+                    CowStr::from(target)
+                }
+                _ => dest_url,
+            };
+            Event::Start(Tag::Image {
+                link_type,
+                dest_url,
+                title,
+                id,
+            })
+        }
+        event => event,
+    });
+
+    let mut body = String::from("<body>\n");
+    html::push_html(&mut body, events);
+    body.push_str("</body>");
+    Ok(body.into_bytes())
+}
+
+/// Resolves the local `url` of a file in the directory `dir` to its path relative to the
+/// source directory, split from its `#fragment`.
+///
+/// Returns `None` for external, absolute and fragment-only URLs.
+fn resolve<'u>(dir: &str, url: &'u str) -> Option<(String, &'u str)> {
+    if url.is_empty() || url.starts_with(['/', '#']) || url.contains(':') {
+        return None;
+    }
+
+    let (path, fragment) = url.find('#').map_or((url, ""), |i| url.split_at(i));
+    let mut segments = dir
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>();
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop()?;
+            }
+            segment => segments.push(segment),
+        }
+    }
+
+    Some((segments.join("/"), fragment))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_summary() {
+        let summary = "# Summary\n\n[Introduction](README.md)\n\n- [Guide](guide/index.md)\n    - [Setup](guide/setup.md)\n    - [Draft]()\n        - [Nested](guide/nested.md)\n\n---\n\n# Reference\n\n* [API](api.md)\n\n[Credits](credits.md)\n";
+
+        let items = parse_summary(summary);
+        assert_eq!(
+            items
+                .iter()
+                .map(|item| (item.indent, item.title.as_str(), item.path.as_deref()))
+                .collect::<Vec<_>>(),
+            vec![
+                (0, "Introduction", Some("README.md")),
+                (1, "Guide", Some("guide/index.md")),
+                (5, "Setup", Some("guide/setup.md")),
+                (5, "Draft", None),
+                (9, "Nested", Some("guide/nested.md")),
+                (1, "API", Some("api.md")),
+                (0, "Credits", Some("credits.md")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolve() {
+        assert_eq!(
+            resolve("guide", "setup.md#install"),
+            Some(("guide/setup.md".to_string(), "#install"))
+        );
+        assert_eq!(
+            resolve("guide/deep", "../../images/map.png"),
+            Some(("images/map.png".to_string(), ""))
+        );
+        assert_eq!(resolve("", "../outside.md"), None);
+        assert_eq!(resolve("", "https://example.com/a.md"), None);
+        assert_eq!(resolve("", "#top"), None);
+        assert_eq!(chapter_filename("./guide/setup.md"), "guide-setup.xhtml");
+    }
+
+    #[test]
+    fn test_mdbook_read() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("src/guide/images")).unwrap();
+        fs::write(
+            root.join("book.toml"),
+            "[book]\ntitle = \"The Guide\"\nauthors = [\"Ann\", \"Bob\"]\nlanguage = \"fr\"\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("src/SUMMARY.md"),
+            "# Summary\n\n[Intro](README.md)\n\n- [Guide](guide/index.md)\n  - [Setup](guide/setup.md)\n- [Later]()\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("src/README.md"),
+            "# Intro\n\nSee [setup](guide/setup.md#run).",
+        )
+        .unwrap();
+        fs::write(
+            root.join("src/guide/index.md"),
+            "# Guide\n\n![Map](images/map.png) and [site](https://example.com).",
+        )
+        .unwrap();
+        fs::write(
+            root.join("src/guide/setup.md"),
+            "# Setup\n\n| a |\n|---|\n| 1 |",
+        )
+        .unwrap();
+        fs::write(root.join("src/guide/images/map.png"), b"png").unwrap();
+
+        let book = MdBook::read(root).unwrap();
+        let metadata = book.metadata();
+        assert_eq!(metadata.title, "The Guide");
+        assert_eq!(metadata.creator.as_deref(), Some("Ann, Bob"));
+        assert_eq!(metadata.language.as_ref(), "fr");
+
+        let contents = book.contents();
+        assert_eq!(contents.len(), 2);
+        assert_eq!(contents[0].filename(1), "README.xhtml");
+        assert_eq!(contents[1].filename(2), "guide-index.xhtml");
+        let children = contents[1].subcontents.as_ref().unwrap();
+        assert_eq!(children.len(), 1);
+        assert_eq!(children[0].title(), "Setup");
+
+        let intro = String::from_utf8_lossy(contents[0].body().unwrap());
+        assert!(intro.contains(r#"<a href="guide-setup.xhtml#run">setup</a>"#));
+        let guide = String::from_utf8_lossy(contents[1].body().unwrap());
+        assert!(guide.contains(r#"<img src="guide/images/map.png" alt="Map" />"#));
+        assert!(guide.contains(r#"<a href="https://example.com">site</a>"#));
+        assert!(String::from_utf8_lossy(children[0].body().unwrap()).contains("<table>"));
+
+        let resources = book.resources();
+        assert_eq!(resources.len(), 1);
+        assert_eq!(resources[0].filename().unwrap(), "guide/images/map.png");
+
+        let epub = crate::epub::EpubBuilder::from_mdbook(&book)
+            .create_to_vec()
+            .unwrap();
+        let reader = crate::EpubReader::from_reader(std::io::Cursor::new(epub)).unwrap();
+        assert_eq!(reader.metadata().title, "The Guide");
+        assert_eq!(reader.contents().len(), 2);
+    }
+}
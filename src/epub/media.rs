@@ -0,0 +1,390 @@
+use std::path::Path;
+
+use quick_xml::escape::escape;
+
+use crate::epub::{
+    AudioType, Content, ContentBuilder, ImageType, ReferenceType, Resource, ResourceItem, VideoType,
+};
+
+/// The default fallback of an [`Audio`], for reading systems that cannot play it.
+const AUDIO_FALLBACK: &str = "Your reading system does not support audio.";
+
+/// The default fallback of a [`Video`], for reading systems that cannot play it.
+const VIDEO_FALLBACK: &str = "Your reading system does not support video.";
+
+/// An audio clip embedded in a content body as an `<audio>` element, with a fallback paragraph
+/// for reading systems that cannot play it.
+///
+/// Its resource is registered automatically when the content holding it is added to the book:
+/// either a page generated with [`Audio::build`], or a content with a hand-written body that
+/// embeds [`Audio::element`] and gets the resource with [`ContentBuilder::add_resource`].
+///
+/// ```rust
+/// use std::path::Path;
+///
+/// use liber::epub::{Audio, AudioType, EpubBuilder, MetadataBuilder};
+///
+/// let audio = Audio::new(Path::new("/path/to/theme.mp3"), AudioType::Mp3)
+///     .caption("The main theme, played on the piano")
+///     .fallback("Listen to the theme at the end of the book.");
+///
+/// let epub_builder = EpubBuilder::new(MetadataBuilder::title("My Book").build())
+///     .add_content(audio.build("Main theme").unwrap());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Audio<'a> {
+    /// The audio file.
+    resource: ResourceItem<'a>,
+    /// The media type of the audio file.
+    audio_type: AudioType,
+    /// Optional text shown under the player, in place of a poster.
+    caption: Option<String>,
+    /// The paragraph shown by reading systems that cannot play the audio.
+    fallback: String,
+    /// Optional `id` of the element.
+    id: Option<String>,
+}
+
+impl<'a> Audio<'a> {
+    /// Creates an audio clip from the file at `path`, of type `audio_type`.
+    #[must_use]
+    pub fn new(path: &'a Path, audio_type: AudioType) -> Self {
+        Self {
+            resource: Resource::Audio(path, audio_type.clone()).into(),
+            audio_type,
+            caption: None,
+            fallback: AUDIO_FALLBACK.to_string(),
+            id: None,
+        }
+    }
+
+    /// Sets the **filename** of the audio file inside the EPUB (see [`ResourceItem::as_name`]).
+    ///
+    /// This is a fluent method, returning `Self`.
+    pub fn as_name<S: Into<String>>(mut self, name: S) -> Self {
+        self.resource = self.resource.as_name(name);
+        self
+    }
+
+    /// Sets the **text shown under the player** (e.g. what the clip is), since an `<audio>`
+    /// has no poster image.
+    ///
+    /// This is a fluent method, returning `Self`.
+    pub fn caption<S: Into<String>>(mut self, caption: S) -> Self {
+        self.caption = Some(caption.into());
+        self
+    }
+
+    /// Sets the **fallback** paragraph shown by reading systems that cannot play the audio.
+    /// Defaults to `Your reading system does not support audio.`
+    ///
+    /// This is a fluent method, returning `Self`.
+    pub fn fallback<S: Into<String>>(mut self, fallback: S) -> Self {
+        self.fallback = fallback.into();
+        self
+    }
+
+    /// Sets the `id` of the element, so it can be the target of a content reference.
+    ///
+    /// This is a fluent method, returning `Self`.
+    pub fn id<S: Into<String>>(mut self, id: S) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    /// Gets the resource of the audio file, to attach to a content with
+    /// [`ContentBuilder::add_resource`].
+    pub fn resource(&self) -> ResourceItem<'a> {
+        self.resource.clone()
+    }
+
+    /// Gets the markup of the audio, to embed in a body: a `<div class="audio">` holding the
+    /// `<audio>` element (with its `<source>` and the fallback paragraph) and the caption, if
+    /// any.
+    ///
+    /// # Errors
+    /// Returns a [`crate::Error::FilenameNotFound`] if the path has no valid filename.
+    pub fn element(&self) -> crate::Result<String> {
+        let media_type: &str = (&self.audio_type).into();
+        let caption = self
+            .caption
+            .as_deref()
+            .map(|caption| format!(r#"<p class="caption">{}</p>"#, escape(caption)))
+            .unwrap_or_default();
+        Ok(format!(
+            r#"<div class="audio"{}><audio controls="controls"><source src="{}" type="{media_type}"/><p class="fallback">{}</p></audio>{caption}</div>"#,
+            id_attribute(self.id.as_deref()),
+            escape(&self.resource.filename()?),
+            escape(&self.fallback)
+        ))
+    }
+
+    /// Gets a [`ContentBuilder`] of a [`ReferenceType::Text`] page titled `title` holding the
+    /// audio, with its resource attached.
+    ///
+    /// # Errors
+    /// Returns a [`crate::Error::FilenameNotFound`] if the path has no valid filename.
+    pub fn builder<S: Into<String>>(self, title: S) -> crate::Result<ContentBuilder<'a>> {
+        let title = title.into();
+        let body = format!(
+            "<body><h1>{}</h1>{}</body>",
+            escape(&title),
+            self.element()?
+        );
+        Ok(ContentBuilder::generated(body, ReferenceType::Text(title)).add_resource(self.resource))
+    }
+
+    /// Generates a page titled `title` holding the audio as a [`Content`].
+    ///
+    /// # Errors
+    /// Returns a [`crate::Error::FilenameNotFound`] if the path has no valid filename.
+    pub fn build<S: Into<String>>(self, title: S) -> crate::Result<Content<'a>> {
+        self.builder(title).map(ContentBuilder::build)
+    }
+}
+
+/// A video clip embedded in a content body as a `<video>` element, with a poster image shown
+/// before it plays and a fallback for reading systems that cannot play it: the poster, followed
+/// by a paragraph.
+///
+/// Both the video and the poster are registered automatically when the content holding it is
+/// added to the book: either a page generated with [`Video::build`], or a content with a
+/// hand-written body that embeds [`Video::element`] and gets the [`Video::resources`] with
+/// [`ContentBuilder::add_resource`]. The books are EPUB 2, whose manifest has no `properties`,
+/// so the manifest entries need nothing else.
+///
+/// ```rust
+/// use std::path::Path;
+///
+/// use liber::epub::{EpubBuilder, ImageType, MetadataBuilder, Video, VideoType};
+///
+/// let video = Video::new(
+///     Path::new("/path/to/interview.mp4"),
+///     VideoType::Mp4,
+///     Path::new("/path/to/interview.jpg"),
+///     ImageType::Jpg,
+/// )
+/// .caption("An interview with the author")
+/// .fallback("Watch the interview at the end of the book.");
+///
+/// let epub_builder = EpubBuilder::new(MetadataBuilder::title("My Book").build())
+///     .add_content(video.build("Interview").unwrap());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Video<'a> {
+    /// The video file.
+    resource: ResourceItem<'a>,
+    /// The media type of the video file.
+    video_type: VideoType,
+    /// The poster image.
+    poster: ResourceItem<'a>,
+    /// Optional text shown under the player.
+    caption: Option<String>,
+    /// The paragraph shown by reading systems that cannot play the video.
+    fallback: String,
+    /// Optional `id` of the element.
+    id: Option<String>,
+}
+
+impl<'a> Video<'a> {
+    /// Creates a video clip from the file at `path`, of type `video_type`, with the image at
+    /// `poster`, of type `poster_type`, as its poster.
+    #[must_use]
+    pub fn new(
+        path: &'a Path,
+        video_type: VideoType,
+        poster: &'a Path,
+        poster_type: ImageType,
+    ) -> Self {
+        Self {
+            resource: Resource::Video(path, video_type.clone()).into(),
+            video_type,
+            poster: Resource::Image(poster, poster_type).into(),
+            caption: None,
+            fallback: VIDEO_FALLBACK.to_string(),
+            id: None,
+        }
+    }
+
+    /// Sets the **filename** of the video file inside the EPUB (see [`ResourceItem::as_name`]).
+    ///
+    /// This is a fluent method, returning `Self`.
+    pub fn as_name<S: Into<String>>(mut self, name: S) -> Self {
+        self.resource = self.resource.as_name(name);
+        self
+    }
+
+    /// Sets the **filename** of the poster image inside the EPUB (see
+    /// [`ResourceItem::as_name`]).
+    ///
+    /// This is a fluent method, returning `Self`.
+    pub fn poster_as_name<S: Into<String>>(mut self, name: S) -> Self {
+        self.poster = self.poster.as_name(name);
+        self
+    }
+
+    /// Sets the **text shown under the player** (e.g. what the clip is).
+    ///
+    /// This is a fluent method, returning `Self`.
+    pub fn caption<S: Into<String>>(mut self, caption: S) -> Self {
+        self.caption = Some(caption.into());
+        self
+    }
+
+    /// Sets the **fallback** paragraph shown under the poster by reading systems that cannot
+    /// play the video. Defaults to `Your reading system does not support video.`
+    ///
+    /// This is a fluent method, returning `Self`.
+    pub fn fallback<S: Into<String>>(mut self, fallback: S) -> Self {
+        self.fallback = fallback.into();
+        self
+    }
+
+    /// Sets the `id` of the element, so it can be the target of a content reference.
+    ///
+    /// This is a fluent method, returning `Self`.
+    pub fn id<S: Into<String>>(mut self, id: S) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    /// Gets the resources of the video file and the poster image, to attach to a content with
+    /// [`ContentBuilder::add_resource`].
+    pub fn resources(&self) -> [ResourceItem<'a>; 2] {
+        [self.resource.clone(), self.poster.clone()]
+    }
+
+    /// Gets the markup of the video, to embed in a body: a `<div class="video">` holding the
+    /// `<video>` element (with its poster, its `<source>` and the fallback) and the caption, if
+    /// any.
+    ///
+    /// # Errors
+    /// Returns a [`crate::Error::FilenameNotFound`] if a path has no valid filename.
+    pub fn element(&self) -> crate::Result<String> {
+        let media_type: &str = (&self.video_type).into();
+        let poster = escape(&self.poster.filename()?).into_owned();
+        let caption = self
+            .caption
+            .as_deref()
+            .map(|caption| format!(r#"<p class="caption">{}</p>"#, escape(caption)))
+            .unwrap_or_default();
+        Ok(format!(
+            r#"<div class="video"{}><video controls="controls" poster="{poster}"><source src="{}" type="{media_type}"/><img class="poster" src="{poster}" alt="{}"/><p class="fallback">{}</p></video>{caption}</div>"#,
+            id_attribute(self.id.as_deref()),
+            escape(&self.resource.filename()?),
+            escape(self.caption.as_deref().unwrap_or_default()),
+            escape(&self.fallback)
+        ))
+    }
+
+    /// Gets a [`ContentBuilder`] of a [`ReferenceType::Text`] page titled `title` holding the
+    /// video, with the video and poster resources attached.
+    ///
+    /// # Errors
+    /// Returns a [`crate::Error::FilenameNotFound`] if a path has no valid filename.
+    pub fn builder<S: Into<String>>(self, title: S) -> crate::Result<ContentBuilder<'a>> {
+        let title = title.into();
+        let body = format!(
+            "<body><h1>{}</h1>{}</body>",
+            escape(&title),
+            self.element()?
+        );
+        Ok(ContentBuilder::generated(body, ReferenceType::Text(title))
+            .add_resource(self.resource)
+            .add_resource(self.poster))
+    }
+
+    /// Generates a page titled `title` holding the video as a [`Content`].
+    ///
+    /// # Errors
+    /// Returns a [`crate::Error::FilenameNotFound`] if a path has no valid filename.
+    pub fn build<S: Into<String>>(self, title: S) -> crate::Result<Content<'a>> {
+        self.builder(title).map(ContentBuilder::build)
+    }
+}
+
+/// Renders the `id` attribute of `id`, if any.
+fn id_attribute(id: Option<&str>) -> String {
+    id.map(|id| format!(r#" id="{}""#, escape(id)))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_audio() {
+        let audio = Audio::new(Path::new("/music/theme.ogg"), AudioType::Ogg)
+            .as_name("audio/theme & variations.ogg")
+            .caption("The theme")
+            .id("theme");
+        assert_eq!(
+            audio.element().unwrap(),
+            concat!(
+                r#"<div class="audio" id="theme"><audio controls="controls">"#,
+                r#"<source src="audio/theme &amp; variations.ogg" type="audio/ogg"/>"#,
+                r#"<p class="fallback">Your reading system does not support audio.</p></audio>"#,
+                r#"<p class="caption">The theme</p></div>"#
+            )
+        );
+
+        let mut page = audio.clone().fallback("No audio").build("Theme").unwrap();
+        assert_eq!(
+            page.reference_type(),
+            &ReferenceType::Text("Theme".to_string())
+        );
+        assert!(
+            String::from_utf8_lossy(page.body().unwrap())
+                .starts_with(r#"<body><h1>Theme</h1><div class="audio" id="theme">"#)
+        );
+        assert_eq!(page.take_resources(), [audio.resource()]);
+        assert!(page.take_resources().is_empty());
+
+        assert!(
+            Audio::new(Path::new("/"), AudioType::Mp3)
+                .element()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_video() {
+        let video = Video::new(
+            Path::new("/films/interview.webm"),
+            VideoType::Webm,
+            Path::new("/films/still.png"),
+            ImageType::Png,
+        )
+        .poster_as_name("images/still.png")
+        .caption("Q & A");
+        assert_eq!(
+            video.element().unwrap(),
+            concat!(
+                r#"<div class="video"><video controls="controls" poster="images/still.png">"#,
+                r#"<source src="interview.webm" type="video/webm"/>"#,
+                r#"<img class="poster" src="images/still.png" alt="Q &amp; A"/>"#,
+                r#"<p class="fallback">Your reading system does not support video.</p></video>"#,
+                r#"<p class="caption">Q &amp; A</p></div>"#
+            )
+        );
+
+        let mut page = video.clone().id("interview").build("Interview").unwrap();
+        assert!(
+            String::from_utf8_lossy(page.body().unwrap())
+                .starts_with(r#"<body><h1>Interview</h1><div class="video" id="interview">"#)
+        );
+        assert_eq!(page.take_resources(), video.resources());
+
+        assert!(
+            Video::new(
+                Path::new("/films/interview.mp4"),
+                VideoType::Mp4,
+                Path::new("/"),
+                ImageType::Jpg,
+            )
+            .element()
+            .is_err()
+        );
+    }
+}
@@ -3,10 +3,12 @@ use std::fmt::Display;
 use chrono::{DateTime, Utc};
 use uuid::Uuid;
 
+use crate::epub::{AgeRange, Audience, Certification, Conformance, unicode};
+
 /// Core structure holding all necessary descriptive information about a resource (e.g., a book).
 ///
 /// Use the [`MetadataBuilder`] to create instances of this struct.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Metadata {
     /// The primary title of the resource.
     pub title: String,
@@ -20,12 +22,30 @@ pub struct Metadata {
     pub contributor: Option<String>,
     /// The entity responsible for making the resource available.
     pub publisher: Option<String>,
+    /// The imprint (brand) of the publisher the resource is published under.
+    pub imprint: Option<String>,
+    /// The place (usually the city) where the resource is published.
+    pub publication_place: Option<String>,
     /// The date of the resource's publication or creation. Defaults to the current UTC time when created via `new()`.
     pub date: Option<DateTime<Utc>>,
     /// Keywords or phrases describing the content of the resource.
     pub subject: Option<String>,
     /// A short summary or description of the resource's content.
     pub description: Option<String>,
+    /// Optional intended audience of the resource (e.g. children).
+    pub audience: Option<Audience>,
+    /// Optional education level the resource is meant for (e.g. `Grade 5`).
+    pub education_level: Option<String>,
+    /// Optional typical age range of the readers of the resource.
+    pub age_range: Option<AgeRange>,
+    /// Optional accessibility conformance claim (e.g. EPUB Accessibility 1.1 with WCAG 2.1 AA).
+    pub conforms_to: Option<Conformance>,
+    /// Optional party that certified the accessibility conformance claim.
+    pub certification: Option<Certification>,
+    /// Whether `date` still holds the current UTC time set by `new()`.
+    pub(crate) generated_date: bool,
+    /// Whether `identifier` still holds the random UUID set by the builder.
+    pub(crate) generated_identifier: bool,
 }
 
 impl Metadata {
@@ -40,82 +60,83 @@ impl Metadata {
             creator: None,
             contributor: None,
             publisher: None,
+            imprint: None,
+            publication_place: None,
             date: Some(Utc::now()),
             subject: None,
             description: None,
+            audience: None,
+            education_level: None,
+            age_range: None,
+            conforms_to: None,
+            certification: None,
+            generated_date: true,
+            generated_identifier: true,
         }
     }
 
-    /// Generates the XML representation for the **title** element.
-    pub(crate) fn title_as_metadata_xml(&self) -> String {
-        format!("<dc:title>{}</dc:title>", self.title)
-    }
-
-    /// Generates the XML representation for the **creator** element, including the `opf:role="aut"` attribute.
-    ///
-    /// Returns `None` if the creator is not set.
-    pub(crate) fn creator_as_metadata_xml(&self) -> Option<String> {
-        Some(format!(
-            r#"<dc:creator opf:role="aut">{}</dc:creator>"#,
-            self.creator.as_ref()?
-        ))
-    }
-
-    /// Generates the XML representation for the **contributor** element, including the `opf:role="trl"` attribute.
-    ///
-    /// Returns `None` if the contributor is not set.
-    pub(crate) fn contributor_as_metadata_xml(&self) -> Option<String> {
-        Some(format!(
-            r#"<dc:contributor opf:role="trl">{}</dc:contributor>"#,
-            self.contributor.as_ref()?
-        ))
+    /// Converts the textual fields to Unicode Normalization Form C.
+    pub(crate) fn normalize_unicode(&mut self) {
+        unicode::nfc(&mut self.title);
+        self.creator
+            .iter_mut()
+            .chain(self.contributor.iter_mut())
+            .chain(self.publisher.iter_mut())
+            .chain(self.imprint.iter_mut())
+            .chain(self.publication_place.iter_mut())
+            .chain(self.subject.iter_mut())
+            .chain(self.description.iter_mut())
+            .chain(self.education_level.iter_mut())
+            .for_each(unicode::nfc);
+        self.certification
+            .iter_mut()
+            .for_each(Certification::normalize_unicode);
     }
 
-    /// Generates the XML representation for the **publisher** element.
+    /// Replaces the values generated at creation time, so that building twice gives the same result.
     ///
-    /// Returns `None` if the publisher is not set.
-    pub(crate) fn publisher_as_metadata_xml(&self) -> Option<String> {
-        Some(format!(
-            "<dc:publisher>{}</dc:publisher>",
-            self.publisher.as_ref()?
-        ))
-    }
-
-    /// Generates the XML representation for the **date** element, formatted as YYYY-MM-DD.
-    ///
-    /// Returns `None` if the date is not set.
-    pub(crate) fn date_as_metadata_xml(&self) -> Option<String> {
-        Some(format!(
-            r#"<dc:date opf:event="publication">{}</dc:date>"#,
-            self.date?.format("%Y-%m-%d")
-        ))
-    }
+    /// The current UTC time set as `date` is dropped, and the random UUID set as `identifier` is
+    /// replaced by a name-based UUID derived from the title and creator. Values set explicitly are kept.
+    pub(crate) fn make_deterministic(&mut self) {
+        if self.generated_date {
+            self.date = None;
+            self.generated_date = false;
+        }
 
-    /// Generates the XML representation for the **subject** element.
-    ///
-    /// Returns `None` if the subject is not set.
-    pub(crate) fn subject_as_metadata_xml(&self) -> Option<String> {
-        Some(format!(
-            "<dc:subject>{}</dc:subject>",
-            self.subject.as_ref()?
-        ))
+        if self.generated_identifier {
+            let name = format!(
+                "{}\n{}",
+                self.title,
+                self.creator.as_deref().unwrap_or_default()
+            );
+            self.identifier =
+                Identifier::UUID(Uuid::new_v5(&Uuid::NAMESPACE_OID, name.as_bytes()).to_string());
+            self.generated_identifier = false;
+        }
     }
 
-    /// Generates the XML representation for the **description** element.
+    /// Derives the metadata of one part of a split book.
     ///
-    /// Returns `None` if the description is not set.
-    pub(crate) fn description_as_metadata_xml(&self) -> Option<String> {
-        Some(format!(
-            "<dc:description>{}</dc:description>",
-            self.description.as_ref()?
-        ))
+    /// The title of the part is appended to the book title, and the identifier is replaced by a
+    /// name-based UUID derived from the book identifier and the part `number`, so every part is a
+    /// distinct publication and splitting twice gives the same identifiers.
+    pub(crate) fn part(&self, number: usize, part_title: &str) -> Metadata {
+        let name = format!("{}\n{number}", String::from(&self.identifier));
+        Metadata {
+            title: format!("{}: {part_title}", self.title),
+            identifier: Identifier::UUID(
+                Uuid::new_v5(&Uuid::NAMESPACE_OID, name.as_bytes()).to_string(),
+            ),
+            generated_identifier: false,
+            ..self.clone()
+        }
     }
 }
 
 /// A builder for easily constructing [`Metadata`] structs.
 ///
 /// This uses a **fluent interface** to set optional fields before finalizing the structure with `build()`.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct MetadataBuilder(Metadata);
 
 impl MetadataBuilder {
@@ -138,6 +159,7 @@ impl MetadataBuilder {
     /// Sets the unique **identifier** for the resource (e.g., UUID or ISBN).
     pub fn identifier(mut self, identifier: Identifier) -> Self {
         self.0.identifier = identifier;
+        self.0.generated_identifier = false;
         self
     }
 
@@ -159,9 +181,24 @@ impl MetadataBuilder {
         self
     }
 
+    /// Sets the **imprint** the resource is published under, declared as
+    /// `schema:publisherImprint`.
+    pub fn imprint<S: Into<String>>(mut self, imprint: S) -> Self {
+        self.0.imprint = Some(imprint.into());
+        self
+    }
+
+    /// Sets the **publication place** of the resource (e.g. `London`), declared as
+    /// `schema:locationCreated`.
+    pub fn publication_place<S: Into<String>>(mut self, publication_place: S) -> Self {
+        self.0.publication_place = Some(publication_place.into());
+        self
+    }
+
     /// Sets the publication **date** using a specific `DateTime<Utc>`.
     pub fn date(mut self, date: DateTime<Utc>) -> Self {
         self.0.date = Some(date);
+        self.0.generated_date = false;
         self
     }
 
@@ -177,6 +214,41 @@ impl MetadataBuilder {
         self
     }
 
+    /// Sets the intended **audience** of the resource, declared as `dcterms:audience`.
+    pub fn audience(mut self, audience: Audience) -> Self {
+        self.0.audience = Some(audience);
+        self
+    }
+
+    /// Sets the **education level** the resource is meant for (e.g. `Grade 5` or `Higher
+    /// education`), declared as `dcterms:educationLevel`.
+    pub fn education_level<S: Into<String>>(mut self, education_level: S) -> Self {
+        self.0.education_level = Some(education_level.into());
+        self
+    }
+
+    /// Sets the typical **age range** of the readers of the resource, declared as
+    /// `schema:typicalAgeRange`, so children's titles carry the classification retailers ask for.
+    pub fn age_range(mut self, age_range: AgeRange) -> Self {
+        self.0.age_range = Some(age_range);
+        self
+    }
+
+    /// Sets the **accessibility conformance** claim of the resource, declared as
+    /// `dcterms:conformsTo`, so reading systems and retailers can tell which accessibility
+    /// standard it meets.
+    pub fn conforms_to(mut self, conformance: Conformance) -> Self {
+        self.0.conforms_to = Some(conformance);
+        self
+    }
+
+    /// Sets the party that **certified** the accessibility conformance claim, declared as
+    /// `a11y:certifiedBy` (with its credential and report, if any).
+    pub fn certification(mut self, certification: Certification) -> Self {
+        self.0.certification = Some(certification);
+        self
+    }
+
     /// Consumes the builder and returns the final [`Metadata`] instance.
     pub fn build(self) -> Metadata {
         self.0
@@ -184,7 +256,7 @@ impl MetadataBuilder {
 }
 
 /// Represents the primary language of the resource content, using its corresponding **ISO 639-1** code.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub enum Language {
     Arabic,
     Bulgarian,
@@ -236,6 +308,64 @@ pub enum Language {
 }
 
 impl Language {
+    /// Selects the language from its ISO 639-1 code (e.g. `en`), ignoring any region subtag
+    /// (e.g. `en-US`) and the case.
+    ///
+    /// Returns `None` if the code does not match a supported language.
+    pub fn from_code(code: &str) -> Option<Self> {
+        let code = code.split(['-', '_']).next()?.to_ascii_lowercase();
+        [
+            Self::Arabic,
+            Self::Bulgarian,
+            Self::Chinese,
+            Self::Croatian,
+            Self::Czech,
+            Self::Danish,
+            Self::Dutch,
+            Self::English,
+            Self::Estonian,
+            Self::Finnish,
+            Self::French,
+            Self::Greek,
+            Self::German,
+            Self::Hebrew,
+            Self::Hungarian,
+            Self::Icelandic,
+            Self::Indonesian,
+            Self::Irish,
+            Self::Italian,
+            Self::Japanese,
+            Self::Korean,
+            Self::Latvian,
+            Self::Lithuanian,
+            Self::Macedonian,
+            Self::Malay,
+            Self::Maltese,
+            Self::Norwegian,
+            Self::Persian,
+            Self::Polish,
+            Self::Portuguese,
+            Self::Romanian,
+            Self::Russian,
+            Self::Serbian,
+            Self::Slovak,
+            Self::Slovenian,
+            Self::Spanish,
+            Self::Swahili,
+            Self::Swedish,
+            Self::Tagalog,
+            Self::Thai,
+            Self::Turkish,
+            Self::Ukrainian,
+            Self::Urdu,
+            Self::Vietnamese,
+            Self::Welsh,
+            Self::Yiddish,
+        ]
+        .into_iter()
+        .find(|language| language.as_ref() == code)
+    }
+
     /// Generates the XML representation for the **language** element.
     ///
     /// The language code (e.g., `en`, `fr`) is used as the content.
@@ -299,7 +429,7 @@ impl AsRef<str> for Language {
 }
 
 /// Represents a unique identifier for the resource, typically a UUID or ISBN.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Identifier {
     /// A standard **UUID** (Universally Unique Identifier).
     UUID(String),
@@ -307,27 +437,6 @@ pub enum Identifier {
     ISBN(String),
 }
 
-impl Identifier {
-    /// Generates the XML representation for the **identifier** element.
-    ///
-    /// The scheme (`UUID` or `ISBN`) and the URN value are included.
-    pub(crate) fn as_metadata_xml(&self) -> String {
-        format!(
-            r#"<dc:identifier id="BookId" opf:scheme="{}">{}</dc:identifier>"#,
-            self,
-            std::string::String::from(self)
-        )
-    }
-
-    /// Generates the XML representation for the **TOC (Table of Contents)** metadata, typically used for DTB UID.
-    pub(crate) fn as_toc_xml(&self) -> String {
-        format!(
-            r#"<meta name="dtb:uid" content="{}"/>"#,
-            std::string::String::from(self)
-        )
-    }
-}
-
 /// Converts the identifier into its URN (Uniform Resource Name) format, e.g., `urn:uuid:...` or `urn:isbn:...`.
 impl From<&Identifier> for String {
     fn from(value: &Identifier) -> Self {
@@ -345,6 +454,69 @@ impl Default for Identifier {
     }
 }
 
+impl Identifier {
+    /// Checks the identifier, returning a [`crate::Error::EmptyIdentifier`] if it is empty, or
+    /// a [`crate::Error::InvalidIsbn`] if it is an ISBN without 10 or 13 digits or whose check
+    /// digit does not match.
+    pub(crate) fn problem(&self) -> Option<crate::Error> {
+        let value = match self {
+            Self::UUID(value) | Self::ISBN(value) => value.trim(),
+        };
+        if value.is_empty() {
+            return Some(crate::Error::EmptyIdentifier);
+        }
+        match self {
+            Self::ISBN(isbn) if !is_valid_isbn(isbn) => {
+                Some(crate::Error::InvalidIsbn(isbn.clone()))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Whether `isbn` (with optional hyphens or spaces) has 10 or 13 digits and a matching check
+/// digit (`X` stands for 10 as the last character of an ISBN-10).
+fn is_valid_isbn(isbn: &str) -> bool {
+    let chars: Vec<char> = isbn
+        .chars()
+        .filter(|c| *c != '-' && !c.is_whitespace())
+        .collect();
+    let digit = |(position, c): (usize, &char)| match c {
+        'X' | 'x' if position == 9 && chars.len() == 10 => Some(10),
+        _ => c.to_digit(10),
+    };
+    let Some(digits) = chars
+        .iter()
+        .enumerate()
+        .map(digit)
+        .collect::<Option<Vec<u32>>>()
+    else {
+        return false;
+    };
+
+    match digits.len() {
+        10 => {
+            digits
+                .iter()
+                .zip((1..=10).rev())
+                .map(|(digit, weight)| digit * weight)
+                .sum::<u32>()
+                % 11
+                == 0
+        }
+        13 => {
+            digits
+                .iter()
+                .zip([1, 3].into_iter().cycle())
+                .map(|(digit, weight)| digit * weight)
+                .sum::<u32>()
+                % 10
+                == 0
+        }
+        _ => false,
+    }
+}
+
 /// Displays the identifier scheme (`UUID` or `ISBN`).
 impl Display for Identifier {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -402,6 +574,8 @@ mod tests {
             .identifier(identifier)
             .creator(creator)
             .publisher(publisher)
+            .imprint("Picador")
+            .publication_place("London")
             .subject(subject)
             .description(description)
             .build();
@@ -409,6 +583,8 @@ mod tests {
         assert_eq!(metadata.creator, Some(creator.to_string()));
         assert_eq!(metadata.contributor, None);
         assert_eq!(metadata.publisher, Some(publisher.to_string()));
+        assert_eq!(metadata.imprint.as_deref(), Some("Picador"));
+        assert_eq!(metadata.publication_place.as_deref(), Some("London"));
         assert!(metadata.date.is_some());
         assert_eq!(metadata.subject, Some(subject.to_string()));
         assert_eq!(metadata.description, Some(description.to_string()));
@@ -425,4 +601,54 @@ mod tests {
             _ => panic!("Default identifier was not a UUID"),
         }
     }
+
+    #[test]
+    fn test_metadata_make_deterministic() {
+        let deterministic = |title: &str| {
+            let mut metadata = MetadataBuilder::title(title).creator("Author").build();
+            metadata.make_deterministic();
+            metadata
+        };
+
+        let metadata = deterministic("Title");
+        assert!(metadata.date.is_none());
+        assert_eq!(
+            String::from(&metadata.identifier),
+            String::from(&deterministic("Title").identifier)
+        );
+        assert_ne!(
+            String::from(&metadata.identifier),
+            String::from(&deterministic("Other").identifier)
+        );
+
+        let date = Utc::now();
+        let mut metadata = MetadataBuilder::title("Title")
+            .date(date)
+            .identifier(Identifier::ISBN("9780000000000".to_string()))
+            .build();
+        metadata.make_deterministic();
+
+        assert_eq!(metadata.date, Some(date));
+        assert_eq!(String::from(&metadata.identifier), "urn:isbn:9780000000000");
+    }
+
+    #[test]
+    fn test_identifier_problem() {
+        let isbn = |value: &str| Identifier::ISBN(value.to_string()).problem();
+
+        assert!(get_test_identifier().problem().is_none());
+        assert!(isbn("0-306-40615-2").is_none());
+        assert!(isbn("0-8044-2957-X").is_none());
+        assert!(Identifier::default().problem().is_none());
+        assert!(matches!(
+            isbn("978-3-16-148410-1"),
+            Some(crate::Error::InvalidIsbn(isbn)) if isbn == "978-3-16-148410-1"
+        ));
+        assert!(isbn("12345").is_some());
+        assert!(isbn("X-306-40615-2").is_some());
+        assert!(matches!(
+            Identifier::UUID(" ".to_string()).problem(),
+            Some(crate::Error::EmptyIdentifier)
+        ));
+    }
 }
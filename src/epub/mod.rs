@@ -1,11 +1,87 @@
+mod accessibility;
+mod audience;
+mod calibre;
+mod captioned;
+mod comic;
 mod content;
 mod content_reference;
+mod content_source;
+mod cover;
 mod epub_builder;
+mod external_link;
+mod font_face;
+mod front_matter;
+mod index_term;
+#[cfg(all(feature = "mdbook", not(target_arch = "wasm32")))]
+mod mdbook;
+mod media;
 mod metadata;
+mod nav_list;
+mod numbering;
+mod plugin;
+mod poem;
+mod profile;
+mod punctuation;
 mod resource;
+#[cfg(not(target_arch = "wasm32"))]
+mod resource_dir;
+#[cfg(feature = "crypto")]
+mod signer;
+#[cfg(feature = "async")]
+mod spawner;
+mod text_transform;
+mod theme;
+mod typography;
+mod unicode;
+mod watermark;
 
+pub use accessibility::*;
+pub use audience::*;
+pub use calibre::CalibreMetadata;
+pub use comic::{ComicBuilder, FixedLayout};
 pub use content::*;
 pub use content_reference::*;
+#[cfg(feature = "async")]
+pub use content_source::AsyncContentSource;
+pub use content_source::ContentSource;
+pub use cover::GeneratedCover;
+pub(crate) use cover::{
+    COVER_PAGE_FILENAME, GENERATED_COVER_FILENAME, GENERATED_COVER_MEDIA_TYPE, image_dimensions,
+};
 pub use epub_builder::*;
+pub use external_link::{DeadLink, LinkAnnotation};
+pub(crate) use external_link::{external_links, link_syntax_error};
+pub use front_matter::{Dedication, Epigraph};
+pub use index_term::IndexTerm;
+#[cfg(all(feature = "mdbook", not(target_arch = "wasm32")))]
+pub use mdbook::MdBook;
+pub use media::{Audio, Video};
 pub use metadata::*;
+pub use nav_list::*;
+pub(crate) use numbering::Numbering;
+pub use numbering::{BookPart, NumberingStyle};
+pub use plugin::EpubPlugin;
+pub use poem::Poem;
+pub use profile::Profile;
 pub use resource::*;
+#[cfg(not(target_arch = "wasm32"))]
+pub use resource_dir::ResourceDir;
+#[cfg(feature = "crypto")]
+pub use signer::PackageSigner;
+#[cfg(feature = "async")]
+pub(crate) use spawner::run_blocking;
+#[cfg(feature = "async")]
+pub use spawner::{InlineSpawner, Spawner, TokioSpawner};
+pub use text_transform::TextTransform;
+pub use theme::Theme;
+pub use typography::Typography;
+pub use watermark::{Watermark, WatermarkLocation};
+
+/// Appends `items` to the optional list `list`, leaving it `None` if there are none, so
+/// optional lists are never empty.
+pub(crate) fn extend_list<T, I: IntoIterator<Item = T>>(list: &mut Option<Vec<T>>, items: I) {
+    let mut items = items.into_iter().peekable();
+    if items.peek().is_some() {
+        list.get_or_insert_with(Vec::new).extend(items);
+    }
+}
@@ -0,0 +1,251 @@
+use std::borrow::Cow;
+
+use crate::epub::{BookPart, Numbering, NumberingStyle, unicode};
+
+/// The kind of page of a [`PageTarget`], as defined by the NCX specification.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PageType {
+    /// A page of the front matter, usually numbered with roman numerals (e.g. `xii`).
+    Front,
+    /// A page of the body, numbered with arabic numerals (e.g. `12`).
+    #[default]
+    Normal,
+    /// A page without a number in sequence (e.g. a plate).
+    Special,
+}
+
+impl PageType {
+    /// Gets the numbering style of the generated labels of this kind of page: the style of the
+    /// matching [`BookPart`] if it is set, else lowercase roman numerals for the front matter
+    /// and arabic numerals for the rest.
+    fn numbering_style(&self, numbering: &Numbering) -> NumberingStyle {
+        match self {
+            Self::Front => numbering
+                .style(BookPart::FrontMatter)
+                .unwrap_or(NumberingStyle::LowerRoman),
+            Self::Normal => numbering.style(BookPart::BodyMatter).unwrap_or_default(),
+            Self::Special => NumberingStyle::Arabic,
+        }
+    }
+
+    /// Gets the position of this kind of page in an array of counters.
+    fn index(&self) -> usize {
+        match self {
+            Self::Front => 0,
+            Self::Normal => 1,
+            Self::Special => 2,
+        }
+    }
+}
+
+impl AsRef<str> for PageType {
+    fn as_ref(&self) -> &str {
+        match self {
+            Self::Front => "front",
+            Self::Normal => "normal",
+            Self::Special => "special",
+        }
+    }
+}
+
+/// A page of the print edition, listed in the `pageList` of the `toc.ncx` so reading systems
+/// can show and go to print page numbers.
+///
+/// The target is an href relative to the content directory, usually an anchor placed in a
+/// content body where the page begins (e.g. `c03.xhtml#page12`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageTarget {
+    /// The page label shown by reading systems (e.g. `12` or `xii`). If `None`, it is
+    /// generated from the position of the page among the pages of its type.
+    pub(crate) label: Option<String>,
+    /// The href of the beginning of the page.
+    pub(crate) src: String,
+    /// The kind of page.
+    pub(crate) page_type: PageType,
+}
+
+impl PageTarget {
+    /// Creates a [`PageType::Normal`] page target with its **label** and the **href** where the page begins.
+    pub fn new<L: Into<String>, S: Into<String>>(label: L, src: S) -> Self {
+        Self {
+            label: Some(label.into()),
+            src: src.into(),
+            page_type: PageType::default(),
+        }
+    }
+
+    /// Creates a [`PageType::Normal`] page target with the **href** where the page begins, and
+    /// a label numbered automatically (e.g. `12`, or `xii` for a [`PageType::Front`] page), in
+    /// the style set by [`crate::epub::EpubBuilder::numbering`].
+    pub fn numbered<S: Into<String>>(src: S) -> Self {
+        Self {
+            label: None,
+            src: src.into(),
+            page_type: PageType::default(),
+        }
+    }
+
+    /// Sets the **kind of page** (front matter, body or special).
+    ///
+    /// This is a fluent method, returning `Self`.
+    pub fn page_type(mut self, page_type: PageType) -> Self {
+        self.page_type = page_type;
+        self
+    }
+
+    /// Gets the label of the page and, for a [`PageType::Normal`] page with a numbered label
+    /// or labelled with an arabic numeral, its page number.
+    ///
+    /// `number` is the position of the page among the pages of its type, starting at `1`.
+    fn label_and_value(&self, number: usize, numbering: &Numbering) -> (Cow<'_, str>, Option<u32>) {
+        match self.label {
+            Some(ref label) => (
+                Cow::Borrowed(label),
+                (self.page_type == PageType::Normal)
+                    .then(|| label.parse().ok())
+                    .flatten()
+                    .filter(|value| *value > 0),
+            ),
+            None => (
+                Cow::Owned(self.page_type.numbering_style(numbering).format(number)),
+                (self.page_type == PageType::Normal)
+                    .then(|| u32::try_from(number).ok())
+                    .flatten(),
+            ),
+        }
+    }
+
+    /// Converts the label to Unicode Normalization Form C.
+    pub(crate) fn normalize_unicode(&mut self) {
+        self.label.iter_mut().for_each(unicode::nfc);
+    }
+}
+
+/// Gets the label and the page number (see [`PageTarget::label_and_value`]) of every page of
+/// `page_list`, numbering the pages without a label within their type.
+pub(crate) fn page_labels<'p>(
+    page_list: &'p [PageTarget],
+    numbering: &Numbering,
+) -> Vec<(Cow<'p, str>, Option<u32>)> {
+    let mut numbers = [0; 3];
+    page_list
+        .iter()
+        .map(|page_target| {
+            let number = &mut numbers[page_target.page_type.index()];
+            *number += 1;
+            page_target.label_and_value(*number, numbering)
+        })
+        .collect()
+}
+
+/// A location listed in a [`NavList`] (e.g. an illustration), with its label.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NavTarget {
+    /// The label shown by reading systems.
+    pub(crate) label: String,
+    /// The href of the location (e.g. `c02.xhtml#figure-1`).
+    pub(crate) src: String,
+}
+
+impl NavTarget {
+    /// Creates a navigation target with its **label** and the **href** of its location.
+    pub fn new<L: Into<String>, S: Into<String>>(label: L, src: S) -> Self {
+        Self {
+            label: label.into(),
+            src: src.into(),
+        }
+    }
+}
+
+/// A secondary list of locations of the `toc.ncx` besides the table of contents (e.g. a list of
+/// illustrations, of tables, or of maps).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NavList {
+    /// The heading of the list (e.g. `List of Illustrations`).
+    pub(crate) label: String,
+    /// The locations of the list, in reading order.
+    pub(crate) targets: Vec<NavTarget>,
+}
+
+impl NavList {
+    /// Creates an empty navigation list with its **heading**.
+    pub fn new<L: Into<String>>(label: L) -> Self {
+        Self {
+            label: label.into(),
+            targets: Vec::new(),
+        }
+    }
+
+    /// Adds a single [`NavTarget`] to the list.
+    ///
+    /// This is a fluent method, returning `Self`.
+    pub fn add_target(mut self, target: NavTarget) -> Self {
+        self.targets.push(target);
+        self
+    }
+
+    /// Adds a collection of [`NavTarget`]s to the list.
+    ///
+    /// Accepts any iterable (e.g. a `Vec` or a mapped iterator).
+    /// This is a fluent method, returning `Self`.
+    pub fn add_targets<I: IntoIterator<Item = NavTarget>>(mut self, targets: I) -> Self {
+        self.targets.extend(targets);
+        self
+    }
+
+    /// Converts the heading and the labels of the targets to Unicode Normalization Form C.
+    pub(crate) fn normalize_unicode(&mut self) {
+        unicode::nfc(&mut self.label);
+        for target in &mut self.targets {
+            unicode::nfc(&mut target.label);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_labels() {
+        let mut numbering = Numbering::default();
+        numbering.set_style(BookPart::BodyMatter, NumberingStyle::UpperRoman);
+
+        let page_list = [
+            PageTarget::numbered("c01.xhtml").page_type(PageType::Front),
+            PageTarget::numbered("c01.xhtml#p2").page_type(PageType::Front),
+            PageTarget::new("12", "c02.xhtml#p12"),
+            PageTarget::new("0", "c02.xhtml#p0"),
+            PageTarget::new("A-1", "c02.xhtml#a1"),
+            PageTarget::new("12", "c03.xhtml").page_type(PageType::Special),
+            PageTarget::numbered("c04.xhtml"),
+        ];
+        let labels = page_labels(&page_list, &numbering)
+            .into_iter()
+            .map(|(label, value)| (label.into_owned(), value))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            labels,
+            vec![
+                ("i".to_string(), None),
+                ("ii".to_string(), None),
+                ("12".to_string(), Some(12)),
+                ("0".to_string(), None),
+                ("A-1".to_string(), None),
+                ("12".to_string(), None),
+                ("IV".to_string(), Some(4)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_nav_list_add_targets() {
+        let nav_list = NavList::new("Illustrations")
+            .add_target(NavTarget::new("Map", "c01.xhtml#map"))
+            .add_targets(vec![NavTarget::new("Plan", "c02.xhtml#plan")]);
+
+        assert_eq!(nav_list.targets.len(), 2);
+        assert_eq!(nav_list.targets[1].src, "c02.xhtml#plan");
+    }
+}
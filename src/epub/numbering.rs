@@ -0,0 +1,117 @@
+/// The part of a book a content unit belongs to, derived from its [`crate::epub::ReferenceType`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BookPart {
+    /// The sections preceding the main text: foreword, preface and acknowledgements.
+    FrontMatter,
+    /// The main text (the chapters).
+    BodyMatter,
+    /// The sections following the main text: notes, glossary, bibliography and index.
+    BackMatter,
+}
+
+/// A style of numbers, used for the auto-generated labels of the table of contents and of the
+/// page list (see [`crate::epub::EpubBuilder::numbering`]).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NumberingStyle {
+    /// Arabic numerals: `1`, `2`, `3`...
+    #[default]
+    Arabic,
+    /// Lowercase roman numerals: `i`, `ii`, `iii`...
+    LowerRoman,
+    /// Uppercase roman numerals: `I`, `II`, `III`...
+    UpperRoman,
+}
+
+impl NumberingStyle {
+    /// Formats `number` in this style.
+    ///
+    /// Roman numerals are only written up to `3999`; larger numbers (and `0`) fall back to
+    /// arabic numerals.
+    pub fn format(&self, number: usize) -> String {
+        match self {
+            Self::Arabic => number.to_string(),
+            Self::LowerRoman => roman(number).unwrap_or_else(|| number.to_string()),
+            Self::UpperRoman => roman(number)
+                .map(|numeral| numeral.to_uppercase())
+                .unwrap_or_else(|| number.to_string()),
+        }
+    }
+}
+
+/// The numbering styles of the parts of a book. Parts without a style are not numbered.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Numbering {
+    /// The style of the front matter.
+    front_matter: Option<NumberingStyle>,
+    /// The style of the body matter.
+    body_matter: Option<NumberingStyle>,
+    /// The style of the back matter.
+    back_matter: Option<NumberingStyle>,
+}
+
+impl Numbering {
+    /// Gets the numbering style of `part`, if it is numbered.
+    pub(crate) fn style(&self, part: BookPart) -> Option<NumberingStyle> {
+        match part {
+            BookPart::FrontMatter => self.front_matter,
+            BookPart::BodyMatter => self.body_matter,
+            BookPart::BackMatter => self.back_matter,
+        }
+    }
+
+    /// Sets the numbering style of `part`.
+    pub(crate) fn set_style(&mut self, part: BookPart, style: NumberingStyle) {
+        match part {
+            BookPart::FrontMatter => self.front_matter = Some(style),
+            BookPart::BodyMatter => self.body_matter = Some(style),
+            BookPart::BackMatter => self.back_matter = Some(style),
+        }
+    }
+}
+
+/// Writes `number` in lowercase roman numerals, if it is between `1` and `3999`.
+fn roman(mut number: usize) -> Option<String> {
+    const NUMERALS: [(usize, &str); 13] = [
+        (1000, "m"),
+        (900, "cm"),
+        (500, "d"),
+        (400, "cd"),
+        (100, "c"),
+        (90, "xc"),
+        (50, "l"),
+        (40, "xl"),
+        (10, "x"),
+        (9, "ix"),
+        (5, "v"),
+        (4, "iv"),
+        (1, "i"),
+    ];
+
+    if !(1..4000).contains(&number) {
+        return None;
+    }
+
+    let mut numeral = String::new();
+    for (value, symbol) in NUMERALS {
+        while number >= value {
+            numeral.push_str(symbol);
+            number -= value;
+        }
+    }
+    Some(numeral)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_numbering_style_format() {
+        assert_eq!(NumberingStyle::Arabic.format(12), "12");
+        assert_eq!(NumberingStyle::LowerRoman.format(4), "iv");
+        assert_eq!(NumberingStyle::LowerRoman.format(1994), "mcmxciv");
+        assert_eq!(NumberingStyle::UpperRoman.format(49), "XLIX");
+        assert_eq!(NumberingStyle::UpperRoman.format(0), "0");
+        assert_eq!(NumberingStyle::LowerRoman.format(4000), "4000");
+    }
+}
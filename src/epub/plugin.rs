@@ -0,0 +1,72 @@
+use crate::epub::Metadata;
+
+/// A hook into the creation of an EPUB, registered with
+/// [`EpubBuilder::add_plugin`](crate::epub::EpubBuilder::add_plugin), to apply custom
+/// transformations to the generated files.
+///
+/// Every callback has a default implementation doing nothing, so a plugin only implements the
+/// ones it needs. Callbacks run in the order the plugins were registered, and any error aborts
+/// the creation of the EPUB.
+///
+/// ```rust
+/// use liber::epub::{EpubBuilder, EpubPlugin, MetadataBuilder};
+///
+/// /// Credits the publisher in every chapter.
+/// struct Footer;
+///
+/// impl EpubPlugin for Footer {
+///     fn chapter_rendered(&self, _filepath: &str, xhtml: &mut String) -> liber::Result<()> {
+///         *xhtml = xhtml.replace("</body>", "<p>Published by ACME</p></body>");
+///         Ok(())
+///     }
+/// }
+///
+/// let epub_builder = EpubBuilder::new(MetadataBuilder::title("My Book").build()).add_plugin(&Footer);
+/// ```
+pub trait EpubPlugin: Sync {
+    /// Gets the name of the plugin, used in debug output.
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+
+    /// Called once the metadata is final (normalized), before any file is generated.
+    ///
+    /// # Errors
+    /// Any error aborts the creation of the EPUB.
+    fn metadata_finalized(&self, _metadata: &mut Metadata) -> crate::Result<()> {
+        Ok(())
+    }
+
+    /// Called for the XHTML file of every content unit once it is rendered, with its path
+    /// inside the archive (e.g. `OEBPS/c01.xhtml`).
+    ///
+    /// # Errors
+    /// Any error aborts the creation of the EPUB.
+    fn chapter_rendered(&self, _filepath: &str, _xhtml: &mut String) -> crate::Result<()> {
+        Ok(())
+    }
+
+    /// Called with the package document (`content.opf`) once its metadata, manifest, spine and
+    /// guide are assembled, before it is formatted.
+    ///
+    /// # Errors
+    /// Any error aborts the creation of the EPUB.
+    fn manifest_assembled(&self, _content_opf: &mut String) -> crate::Result<()> {
+        Ok(())
+    }
+
+    /// Called before every entry of the archive is written (except the `mimetype`, which is
+    /// fixed by the specification), with its path and its final content.
+    ///
+    /// # Errors
+    /// Any error aborts the creation of the EPUB.
+    fn before_entry(&self, _filepath: &str, _bytes: &mut Vec<u8>) -> crate::Result<()> {
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for dyn EpubPlugin + '_ {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
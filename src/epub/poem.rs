@@ -0,0 +1,163 @@
+use quick_xml::escape::escape;
+
+use crate::epub::{Content, ContentBuilder, ReferenceType};
+
+/// The deepest indentation level of a line of a [`Poem`].
+const MAX_INDENT: usize = 6;
+
+/// A poem, generated as a [`ReferenceType::Text`] content whose line breaks and indentation are
+/// kept as markup (not as a `<pre>` block), so reading systems can reflow long lines.
+///
+/// The text is split into stanzas at blank lines. Every line is a `<p class="line">` of a
+/// `<div class="stanza">`, inside a `<div class="poem">`; lines starting with whitespace (two
+/// spaces or a tab per level, up to six levels) get the class `indent1`, `indent2`... Append
+/// [`Poem::STYLESHEET`] to the stylesheet for hanging indents (wrapped lines are indented
+/// further than their start).
+///
+/// ```rust
+/// use liber::epub::{EpubBuilder, MetadataBuilder, Poem};
+///
+/// let stylesheet = [b"body { font-family: serif; }\n".as_slice(), Poem::STYLESHEET.as_bytes()].concat();
+/// let epub_builder = EpubBuilder::new(MetadataBuilder::title("Verses").build())
+///     .stylesheet(&stylesheet)
+///     .add_content(
+///         Poem::new("The Tyger", "Tyger Tyger, burning bright,\n  In the forests of the night;")
+///             .poet("William Blake")
+///             .build(),
+///     );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Poem {
+    /// The title of the poem.
+    title: String,
+    /// The text of the poem: one line per line, stanzas separated by blank lines.
+    text: String,
+    /// Optional author of the poem.
+    poet: Option<String>,
+}
+
+impl Poem {
+    /// The CSS preset of the generated markup: stanzas separated by a blank line and hanging
+    /// indents for the lines and their indentation levels.
+    pub const STYLESHEET: &'static str = ".poem { margin: 1em 0; }
+.poem .stanza { margin-bottom: 1em; }
+.poem .line { margin: 0; padding-left: 2em; text-indent: -2em; text-align: left; }
+.poem .indent1 { padding-left: 3em; }
+.poem .indent2 { padding-left: 4em; }
+.poem .indent3 { padding-left: 5em; }
+.poem .indent4 { padding-left: 6em; }
+.poem .indent5 { padding-left: 7em; }
+.poem .indent6 { padding-left: 8em; }
+.poet { font-style: italic; text-align: right; }
+";
+
+    /// Creates a poem with its **title** and its **text**: one line per line, stanzas separated
+    /// by blank lines, indentation as leading spaces or tabs.
+    #[must_use]
+    pub fn new<T: Into<String>, S: Into<String>>(title: T, text: S) -> Self {
+        Self {
+            title: title.into(),
+            text: text.into(),
+            poet: None,
+        }
+    }
+
+    /// Sets the **author** of the poem, shown under the title.
+    ///
+    /// This is a fluent method, returning `Self`.
+    pub fn poet<S: Into<String>>(mut self, poet: S) -> Self {
+        self.poet = Some(poet.into());
+        self
+    }
+
+    /// Gets a [`ContentBuilder`] of the poem, to set its filename or add children.
+    pub fn builder<'a>(self) -> ContentBuilder<'a> {
+        let poet = self
+            .poet
+            .as_deref()
+            .map(|poet| format!(r#"<p class="poet">{}</p>"#, escape(poet)))
+            .unwrap_or_default();
+        let body = format!(
+            r#"<body><h1>{}</h1>{poet}<div class="poem">{}</div></body>"#,
+            escape(&self.title),
+            stanzas(&self.text)
+        );
+        ContentBuilder::generated(body, ReferenceType::Text(self.title))
+    }
+
+    /// Generates the poem as a [`Content`].
+    pub fn build<'a>(self) -> Content<'a> {
+        self.builder().build()
+    }
+}
+
+/// Renders the stanzas of `text` (separated by blank lines) and their lines.
+fn stanzas(text: &str) -> String {
+    let mut stanzas = String::new();
+    let mut in_stanza = false;
+    for line in text.lines() {
+        let verse = line.trim();
+        if verse.is_empty() {
+            if in_stanza {
+                stanzas.push_str("</div>");
+                in_stanza = false;
+            }
+            continue;
+        }
+        if !in_stanza {
+            stanzas.push_str(r#"<div class="stanza">"#);
+            in_stanza = true;
+        }
+
+        let indent = indent_level(line);
+        let class = match indent {
+            0 => "line".to_string(),
+            indent => format!("line indent{indent}"),
+        };
+        stanzas.push_str(&format!(r#"<p class="{class}">{}</p>"#, escape(verse)));
+    }
+    if in_stanza {
+        stanzas.push_str("</div>");
+    }
+    stanzas
+}
+
+/// Gets the indentation level of `line`: one per two leading spaces or per tab, up to
+/// [`MAX_INDENT`].
+fn indent_level(line: &str) -> usize {
+    let width = line
+        .chars()
+        .take_while(|c| c.is_whitespace())
+        .map(|c| if c == '\t' { 2 } else { 1 })
+        .sum::<usize>();
+    (width / 2).min(MAX_INDENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_poem() {
+        let poem = Poem::new(
+            "The Tyger",
+            "\nTyger Tyger, burning bright,\n  In the forests of the night;\n\n\n\tWhat immortal hand & eye,\n                 Could frame\n",
+        )
+        .poet("William Blake")
+        .build();
+
+        assert_eq!(
+            poem.reference_type(),
+            &ReferenceType::Text("The Tyger".to_string())
+        );
+        assert_eq!(
+            String::from_utf8_lossy(poem.body().unwrap()),
+            concat!(
+                r#"<body><h1>The Tyger</h1><p class="poet">William Blake</p><div class="poem">"#,
+                r#"<div class="stanza"><p class="line">Tyger Tyger, burning bright,</p><p class="line indent1">In the forests of the night;</p></div>"#,
+                r#"<div class="stanza"><p class="line indent1">What immortal hand &amp; eye,</p><p class="line indent6">Could frame</p></div>"#,
+                "</div></body>"
+            )
+        );
+    }
+}
@@ -0,0 +1,103 @@
+use std::fmt::Display;
+
+use crate::epub::{Identifier, Metadata};
+
+/// A retailer the EPUB is built for, toggling its vendor files, cover constraints, compression
+/// and metadata requirements in one switch with
+/// [`EpubBuilder::profile`](crate::epub::EpubBuilder::profile).
+///
+/// The cover constraints and the metadata requirements are reported as warnings by
+/// [`EpubBuilder::create_with_warnings`](crate::epub::EpubBuilder::create_with_warnings).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Profile {
+    /// Any reading system: no vendor files, no requirements.
+    #[default]
+    Generic,
+    /// Apple Books: the iBooks display options file (embedded fonts), an SVG cover page and a
+    /// cover at least 1400 pixels wide.
+    Apple,
+    /// Kobo: an SVG cover page, a cover at least 1400 pixels wide and a description.
+    Kobo,
+    /// Google Play Books: a cover at least 640 pixels wide, a description, a publisher and an
+    /// ISBN.
+    Google,
+}
+
+impl Profile {
+    /// Whether the retailer reads `META-INF/com.apple.ibooks.display-options.xml`.
+    pub fn display_options(self) -> bool {
+        self == Self::Apple
+    }
+
+    /// Whether the retailer shows a cover page scaling the cover inside an SVG wrapper.
+    pub fn svg_cover_page(self) -> bool {
+        matches!(self, Self::Apple | Self::Kobo)
+    }
+
+    /// Gets the minimum width in pixels of the cover image the retailer accepts, if any.
+    pub fn min_cover_width(self) -> Option<u32> {
+        match self {
+            Self::Generic => None,
+            Self::Apple | Self::Kobo => Some(1400),
+            Self::Google => Some(640),
+        }
+    }
+
+    /// Gets the metadata fields the retailer requires that `metadata` lacks.
+    pub(crate) fn missing_metadata(self, metadata: &Metadata) -> Vec<&'static str> {
+        let isbn = matches!(metadata.identifier, Identifier::ISBN(_));
+        let required = match self {
+            Self::Generic => [].as_slice(),
+            Self::Apple => &[("dc:creator", metadata.creator.is_some())],
+            Self::Kobo => &[
+                ("dc:creator", metadata.creator.is_some()),
+                ("dc:description", metadata.description.is_some()),
+            ],
+            Self::Google => &[
+                ("dc:creator", metadata.creator.is_some()),
+                ("dc:description", metadata.description.is_some()),
+                ("dc:publisher", metadata.publisher.is_some()),
+                ("dc:identifier (ISBN)", isbn),
+            ],
+        };
+        required
+            .iter()
+            .filter(|(_, present)| !present)
+            .map(|(field, _)| *field)
+            .collect()
+    }
+}
+
+/// Displays the name of the retailer.
+impl Display for Profile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Generic => "generic",
+            Self::Apple => "Apple Books",
+            Self::Kobo => "Kobo",
+            Self::Google => "Google Play Books",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::epub::MetadataBuilder;
+
+    #[test]
+    fn test_profile_missing_metadata() {
+        let metadata = MetadataBuilder::title("Title").creator("Author").build();
+
+        assert!(Profile::Generic.missing_metadata(&metadata).is_empty());
+        assert!(Profile::Apple.missing_metadata(&metadata).is_empty());
+        assert_eq!(
+            Profile::Kobo.missing_metadata(&metadata),
+            vec!["dc:description"]
+        );
+        assert_eq!(
+            Profile::Google.missing_metadata(&metadata),
+            vec!["dc:description", "dc:publisher", "dc:identifier (ISBN)"]
+        );
+    }
+}
@@ -0,0 +1,150 @@
+/// Elements whose text is kept as is: code, preformatted text, scripts and styles.
+const VERBATIM: &[&str] = &["code", "pre", "kbd", "samp", "var", "script", "style"];
+
+/// Inline elements, which do not break the text: a quote right after them continues the text
+/// before them.
+const INLINE: &[&str] = &[
+    "a", "abbr", "b", "bdi", "bdo", "cite", "code", "dfn", "em", "i", "kbd", "mark", "q", "s",
+    "samp", "small", "span", "strong", "sub", "sup", "u", "var",
+];
+
+/// Converts the straight quotes, double (or triple) hyphens and triple dots of the text nodes of
+/// the XHTML `text` to typographic quotes (`“”‘’`), em dashes (`—`) and ellipses (`…`).
+///
+/// Tags, attributes, comments, CDATA sections and the text of code, preformatted text, scripts
+/// and styles are left untouched. A quote is opening at the start of a block or after
+/// whitespace, a bracket, a dash or another opening quote, and closing (or an apostrophe)
+/// otherwise.
+pub(crate) fn smarten(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    let mut verbatim_depth = 0usize;
+    let mut previous = None;
+
+    while !rest.is_empty() {
+        if rest.starts_with('<') {
+            let end = markup_end(rest);
+            let markup = &rest[..end];
+            result.push_str(markup);
+            if let Some((name, closing)) = element(markup) {
+                if VERBATIM.contains(&name.as_str()) {
+                    if closing {
+                        verbatim_depth = verbatim_depth.saturating_sub(1);
+                    } else if !markup.ends_with("/>") {
+                        verbatim_depth += 1;
+                    }
+                }
+                if !INLINE.contains(&name.as_str()) {
+                    previous = None;
+                }
+            }
+            rest = &rest[end..];
+        } else {
+            let end = rest.find('<').unwrap_or(rest.len());
+            if verbatim_depth > 0 {
+                result.push_str(&rest[..end]);
+                previous = rest[..end].chars().last().or(previous);
+            } else {
+                smarten_run(&rest[..end], &mut previous, &mut result);
+            }
+            rest = &rest[end..];
+        }
+    }
+    result
+}
+
+/// Converts the punctuation of the text node `run` into `result`, `previous` being the last
+/// character of the text before it in the same block, if any.
+fn smarten_run(run: &str, previous: &mut Option<char>, result: &mut String) {
+    let mut chars = run.chars().peekable();
+    while let Some(c) = chars.next() {
+        let smart = match c {
+            '"' if opens(*previous) => '“',
+            '"' => '”',
+            '\'' if opens(*previous) && !chars.peek().is_some_and(char::is_ascii_digit) => '‘',
+            '\'' => '’',
+            '-' | '.' => {
+                let mut count = 1;
+                while chars.next_if_eq(&c).is_some() {
+                    count += 1;
+                }
+                match (c, count) {
+                    ('-', 2 | 3) => '—',
+                    ('.', 3) => '…',
+                    _ => {
+                        result.extend(std::iter::repeat_n(c, count));
+                        *previous = Some(c);
+                        continue;
+                    }
+                }
+            }
+            c => c,
+        };
+        result.push(smart);
+        *previous = Some(smart);
+    }
+}
+
+/// Checks whether a quote after the character `previous` (`None` at the start of a block) is an
+/// opening one.
+fn opens(previous: Option<char>) -> bool {
+    previous.is_none_or(|previous| previous.is_whitespace() || "([{<“‘—–-/".contains(previous))
+}
+
+/// Gets the end of the markup (tag, comment, CDATA section or processing instruction) at the
+/// start of `text`, ignoring the `>` in quoted attribute values.
+fn markup_end(text: &str) -> usize {
+    for (start, end) in [("<!--", "-->"), ("<![CDATA[", "]]>"), ("<?", "?>")] {
+        if let Some(markup) = text.strip_prefix(start) {
+            return markup
+                .find(end)
+                .map_or(text.len(), |index| start.len() + index + end.len());
+        }
+    }
+
+    let mut quote = None;
+    for (index, c) in text.char_indices().skip(1) {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(open), c) if c == open => quote = None,
+            (None, '>') => return index + 1,
+            _ => {}
+        }
+    }
+    text.len()
+}
+
+/// Gets the lowercase name of the element of the tag `markup` and whether it is a closing tag,
+/// or `None` if it is not a tag.
+fn element(markup: &str) -> Option<(String, bool)> {
+    let tag = markup.strip_prefix('<')?;
+    let (tag, closing) = tag
+        .strip_prefix('/')
+        .map_or((tag, false), |tag| (tag, true));
+    let name = tag
+        .split(|c: char| c.is_whitespace() || c == '/' || c == '>')
+        .next()
+        .filter(|name| !name.is_empty() && !name.starts_with(['!', '?']))?;
+    Some((name.to_ascii_lowercase(), closing))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_smarten() {
+        assert_eq!(
+            smarten(
+                r#"<body><p class="a">"It's the '90s -- isn't it?" she said...</p><p>'Yes,' <em>"he"</em> said--- "<a href="x">no</a>".</p></body>"#
+            ),
+            r#"<body><p class="a">“It’s the ’90s — isn’t it?” she said…</p><p>‘Yes,’ <em>“he”</em> said— “<a href="x">no</a>”.</p></body>"#
+        );
+        assert_eq!(
+            smarten(
+                r#"<p>A "<code>x = "y"</code>" <pre>a -- b...</pre><!-- "c" -- --><br/>"d" ---- ..</p>"#
+            ),
+            r#"<p>A “<code>x = "y"</code>” <pre>a -- b...</pre><!-- "c" -- --><br/>“d” ---- ..</p>"#
+        );
+    }
+}
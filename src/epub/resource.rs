@@ -1,11 +1,26 @@
-use std::{ffi::OsStr, fmt::Display, fs, path::Path};
+#[cfg(feature = "sniff")]
+use std::sync::OnceLock;
+use std::{fmt::Display, fs, path::Path};
 
-use crate::output::file_content::FileContent;
+#[cfg(feature = "async")]
+use crate::epub::{Spawner, run_blocking};
+#[cfg(feature = "http")]
+use crate::output::http;
+use crate::{
+    ResultExt,
+    epub::unicode,
+    output::file_content::{FileBytes, FileContent},
+};
+
+/// The size from which resource files are memory-mapped instead of read, when the **`mmap`
+/// feature** is enabled.
+#[cfg(feature = "mmap")]
+pub(crate) const MMAP_MIN_SIZE: u64 = 16 * 1024 * 1024;
 
 /// Represents the common image file types supported for inclusion as resources.
 ///
 /// This enum automatically maps to the correct **MIME (media) type**.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ImageType {
     /// JPEG image format, mapping to `image/jpeg`.
     Jpg,
@@ -17,6 +32,21 @@ pub enum ImageType {
     Svg,
 }
 
+impl ImageType {
+    /// Selects the image type from the extension of `path` (`.jpg`, `.jpeg`, `.png`, `.gif`, `.svg`).
+    ///
+    /// Returns `None` if the path has no extension or the extension is not a known image format.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match media_type_from_extension(path)? {
+            "image/jpeg" => Some(Self::Jpg),
+            "image/png" => Some(Self::Png),
+            "image/gif" => Some(Self::Gif),
+            "image/svg+xml" => Some(Self::Svg),
+            _ => None,
+        }
+    }
+}
+
 /// Implements conversion from `ImageType` to its standard MIME type string slice.
 impl From<&ImageType> for &str {
     fn from(value: &ImageType) -> Self {
@@ -29,111 +59,681 @@ impl From<&ImageType> for &str {
     }
 }
 
+/// Represents the font file formats supported for inclusion as resources.
+///
+/// This enum automatically maps to the correct **MIME (media) type**.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum FontType {
+    /// TrueType font format, mapping to `font/ttf`.
+    TrueType,
+    /// OpenType font format, mapping to `font/otf`.
+    #[default]
+    OpenType,
+    /// Web Open Font Format, mapping to `font/woff`.
+    Woff,
+    /// Web Open Font Format 2.0, mapping to `font/woff2`.
+    Woff2,
+}
+
+impl FontType {
+    /// Selects the font type from the extension of `path` (`.ttf`, `.otf`, `.woff`, `.woff2`).
+    ///
+    /// Returns `None` if the path has no extension or the extension is not a known font format.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match media_type_from_extension(path)? {
+            "font/ttf" => Some(Self::TrueType),
+            "font/otf" => Some(Self::OpenType),
+            "font/woff" => Some(Self::Woff),
+            "font/woff2" => Some(Self::Woff2),
+            _ => None,
+        }
+    }
+}
+
+/// Implements conversion from `FontType` to its standard MIME type string slice.
+impl From<&FontType> for &str {
+    fn from(value: &FontType) -> Self {
+        match value {
+            FontType::TrueType => "font/ttf",
+            FontType::OpenType => "font/otf",
+            FontType::Woff => "font/woff",
+            FontType::Woff2 => "font/woff2",
+        }
+    }
+}
+
+/// Represents the audio file formats supported for inclusion as resources.
+///
+/// This enum automatically maps to the correct **MIME (media) type**.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum AudioType {
+    /// MPEG Audio Layer III, mapping to `audio/mpeg`.
+    #[default]
+    Mp3,
+    /// AAC audio in an MPEG-4 container (`.m4a`, `.aac`), mapping to `audio/mp4`.
+    M4a,
+    /// Vorbis audio in an Ogg container, mapping to `audio/ogg`.
+    Ogg,
+    /// Opus audio in an Ogg container, mapping to `audio/ogg; codecs=opus`.
+    Opus,
+    /// Waveform audio, mapping to `audio/wav`.
+    Wav,
+}
+
+impl AudioType {
+    /// Selects the audio type from the extension of `path` (`.mp3`, `.m4a`, `.ogg`, `.opus`, `.wav`...).
+    ///
+    /// Returns `None` if the path has no extension or the extension is not a known audio format.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match media_type_from_extension(path)? {
+            "audio/mpeg" => Some(Self::Mp3),
+            "audio/mp4" => Some(Self::M4a),
+            "audio/ogg" => Some(Self::Ogg),
+            "audio/ogg; codecs=opus" => Some(Self::Opus),
+            "audio/wav" => Some(Self::Wav),
+            _ => None,
+        }
+    }
+}
+
+/// Implements conversion from `AudioType` to its standard MIME type string slice.
+impl From<&AudioType> for &str {
+    fn from(value: &AudioType) -> Self {
+        match value {
+            AudioType::Mp3 => "audio/mpeg",
+            AudioType::M4a => "audio/mp4",
+            AudioType::Ogg => "audio/ogg",
+            AudioType::Opus => "audio/ogg; codecs=opus",
+            AudioType::Wav => "audio/wav",
+        }
+    }
+}
+
+/// Represents the video file formats supported for inclusion as resources.
+///
+/// This enum automatically maps to the correct **MIME (media) type**.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum VideoType {
+    /// MPEG-4 video, mapping to `video/mp4`.
+    #[default]
+    Mp4,
+    /// WebM video, mapping to `video/webm`.
+    Webm,
+}
+
+impl VideoType {
+    /// Selects the video type from the extension of `path` (`.mp4`, `.m4v`, `.webm`).
+    ///
+    /// Returns `None` if the path has no extension or the extension is not a known video format.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match media_type_from_extension(path)? {
+            "video/mp4" => Some(Self::Mp4),
+            "video/webm" => Some(Self::Webm),
+            _ => None,
+        }
+    }
+}
+
+/// Implements conversion from `VideoType` to its standard MIME type string slice.
+impl From<&VideoType> for &str {
+    fn from(value: &VideoType) -> Self {
+        match value {
+            VideoType::Mp4 => "video/mp4",
+            VideoType::Webm => "video/webm",
+        }
+    }
+}
+
 /// Represents a single external file resource (like an image, font, or video)
 /// that must be included in the final output file.
 ///
 /// The `'a` lifetime indicates that the resource only holds a reference to the file's path.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Resource<'a> {
     /// An image resource, holding a reference to the file path and its type.
     Image(&'a Path, ImageType),
-    /// A font resource, holding a reference to the file path. Assumed to be **OpenType**.
-    Font(&'a Path),
-    /// An audio resource, holding a reference to the file path. Assumed to be **MPEG Audio (MP3)**.
-    Audio(&'a Path),
-    /// A video resource, holding a reference to the file path. Assumed to be **MP4**.
-    Video(&'a Path),
+    /// A font resource, holding a reference to the file path and its type.
+    Font(&'a Path, FontType),
+    /// An audio resource, holding a reference to the file path and its type.
+    Audio(&'a Path, AudioType),
+    /// A video resource, holding a reference to the file path and its type.
+    Video(&'a Path, VideoType),
+    /// Any other file allowed in the manifest (e.g. `application/json`, `text/javascript`, PLS lexicons),
+    /// holding a reference to the file path and its explicit media type.
+    Other(&'a Path, String),
+    /// An in-memory file, holding a reference to its filename inside the EPUB (e.g. `images/map.png`),
+    /// its content and its explicit media type.
+    ///
+    /// This is useful for generated files, or files read from an existing EPUB with the
+    /// [`EpubReader`](crate::EpubReader).
+    Raw(&'a str, &'a [u8], String),
+    /// A remote file, holding a reference to its URL and its explicit media type.
+    ///
+    /// It is downloaded when the EPUB is created and embedded under the last segment of the URL path.
+    /// This variant is only available when the **`http` feature** is enabled.
+    #[cfg(feature = "http")]
+    Url(&'a str, String),
 }
 
 impl<'a> Resource<'a> {
+    /// Creates a resource for `path`, inferring its variant and type from the file extension.
+    ///
+    /// Extensions that do not map to an [`ImageType`], [`FontType`], [`AudioType`] or [`VideoType`]
+    /// but have a known media type (e.g. `.webp`, `.css`, `.js`) become a [`Resource::Other`].
+    /// Returns `None` if the path has no extension or the extension is unknown.
+    pub fn from_path(path: &'a Path) -> Option<Self> {
+        if let Some(image_type) = ImageType::from_path(path) {
+            Some(Self::Image(path, image_type))
+        } else if let Some(font_type) = FontType::from_path(path) {
+            Some(Self::Font(path, font_type))
+        } else if let Some(audio_type) = AudioType::from_path(path) {
+            Some(Self::Audio(path, audio_type))
+        } else if let Some(video_type) = VideoType::from_path(path) {
+            Some(Self::Video(path, video_type))
+        } else {
+            media_type_from_extension(path)
+                .map(|media_type| Self::Other(path, media_type.to_string()))
+        }
+    }
+
     /// Gets the appropriate **MIME media type** string for the resource variant.
     ///
     /// This is required for manifest generation (e.g., in EPUB).
     pub(crate) fn media_type(&self) -> &str {
         match self {
             Resource::Image(_, img_type) => img_type.into(),
-            Resource::Font(path) => {
-                if path.extension() == Some(OsStr::new("ttf")) {
-                    "application/x-font-ttf"
-                } else {
-                    "application/vnd.ms-opentype"
-                }
+            Resource::Font(_, font_type) => font_type.into(),
+            Resource::Audio(_, audio_type) => audio_type.into(),
+            Resource::Video(_, video_type) => video_type.into(),
+            Resource::Other(_, media_type) | Resource::Raw(_, _, media_type) => media_type,
+            #[cfg(feature = "http")]
+            Resource::Url(_, media_type) => media_type,
+        }
+    }
+
+    /// Wraps this resource into a [`ResourceItem`] whose manifest media type is `media_type`
+    /// instead of the one derived from the resource type.
+    pub fn with_media_type<S: Into<String>>(self, media_type: S) -> ResourceItem<'a> {
+        ResourceItem::from(self).with_media_type(media_type)
+    }
+
+    /// Wraps this resource into a [`ResourceItem`] packaged as `name` inside the EPUB
+    /// instead of the basename of its source path.
+    pub fn as_name<S: Into<String>>(self, name: S) -> ResourceItem<'a> {
+        ResourceItem::from(self).as_name(name)
+    }
+
+    /// Wraps this resource into a [`ResourceItem`] mangled with the IDPF font obfuscation algorithm.
+    /// See [`ResourceItem::obfuscate`].
+    pub fn obfuscate(self) -> ResourceItem<'a> {
+        ResourceItem::from(self).obfuscate()
+    }
+
+    /// Wraps this resource into a [`ResourceItem`] whose generated `@font-face` rule declares
+    /// the `font_family` name. See [`ResourceItem::font_family`].
+    pub fn font_family<S: Into<String>>(self, font_family: S) -> ResourceItem<'a> {
+        ResourceItem::from(self).font_family(font_family)
+    }
+
+    /// Builds a [`crate::Error::InvalidResource`] for this resource with the given `reason`.
+    fn invalid(&self, reason: impl Display) -> crate::Error {
+        crate::Error::InvalidResource {
+            resource: self.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+
+    /// Converts an error reading the file of this resource into a
+    /// [`crate::Error::ResourceNotFound`] if the file does not exist, or a
+    /// [`crate::Error::Io`] otherwise.
+    fn read_error(&self, error: std::io::Error) -> crate::Error {
+        if error.kind() == std::io::ErrorKind::NotFound {
+            crate::Error::ResourceNotFound {
+                path: self.path().to_path_buf(),
             }
-            Resource::Audio(_) => "audio/mpeg",
-            Resource::Video(_) => "video/mp4",
+        } else {
+            crate::Error::Io(error)
         }
     }
 
-    /// Reads the file content synchronously and wraps it in a [`FileContent`] structure.
+    /// Loads the file content synchronously (see [`FileBytes`]) and wraps it in a
+    /// [`FileContent`] structure.
     ///
     /// The output path is prefixed with `OEBPS/` and the filename.
     ///
     /// # Errors
     /// Returns an error if the file cannot be read or if the filename cannot be extracted.
-    pub(crate) fn file_content(&self) -> crate::Result<FileContent<String, Vec<u8>>> {
-        match self {
-            Self::Image(path, _) | Self::Font(path) | Self::Audio(path) | Self::Video(path) => Ok(
-                FileContent::new(format!("OEBPS/{}", self.filename()?), fs::read(path)?),
-            ),
-        }
+    pub(crate) fn file_content(&self) -> crate::Result<FileContent<String, FileBytes>> {
+        let bytes = match self {
+            #[cfg(feature = "http")]
+            Self::Url(url, _) => http::get(url)?.into(),
+            Self::Raw(_, bytes, _) => bytes.to_vec().into(),
+            _ => read_file(self.path()).map_err(|e| self.read_error(e))?,
+        };
+
+        Ok(FileContent::new(
+            format!("OEBPS/{}", self.filename()?),
+            bytes,
+        ))
     }
 
-    /// Reads the file content asynchronously (using `tokio::fs`) and wraps it in a [`FileContent`] structure.
+    /// Loads the file content asynchronously (running the blocking work with `spawner`, see
+    /// [`Spawner`]) and wraps it in a [`FileContent`] structure.
     ///
     /// This method is only compiled when the **`async` feature** is enabled.
     ///
     /// # Errors
     /// Returns an error if the file cannot be read asynchronously or if the filename cannot be extracted.
     #[cfg(feature = "async")]
-    pub(crate) async fn async_file_content(&self) -> crate::Result<FileContent<String, Vec<u8>>> {
-        match self {
-            Self::Image(path, _) | Self::Font(path) | Self::Audio(path) | Self::Video(path) => {
-                Ok(FileContent::new(
-                    format!("OEBPS/{}", self.filename()?),
-                    tokio::fs::read(path).await?,
-                ))
+    pub(crate) async fn async_file_content(
+        &self,
+        spawner: Option<&dyn Spawner>,
+    ) -> crate::Result<FileContent<String, FileBytes>> {
+        let bytes = match self {
+            #[cfg(feature = "http-async")]
+            Self::Url(url, _) => http::async_get(url).await?.into(),
+            #[cfg(all(feature = "http", not(feature = "http-async")))]
+            Self::Url(url, _) => {
+                let url = url.to_string();
+                run_blocking(spawner, move || http::get(&url))
+                    .await??
+                    .into()
             }
+            Self::Raw(_, bytes, _) => bytes.to_vec().into(),
+            _ => {
+                let path = self.path().to_path_buf();
+                run_blocking(spawner, move || read_file(&path))
+                    .await?
+                    .map_err(|e| self.read_error(e))?
+            }
+        };
+
+        Ok(FileContent::new(
+            format!("OEBPS/{}", self.filename()?),
+            bytes,
+        ))
+    }
+
+    /// Detects the **MIME media type** from the file's magic bytes.
+    ///
+    /// Returns `None` if the file cannot be read or its content is not recognized, and always
+    /// for [`Resource::Other`], [`Resource::Raw`] and `Resource::Url`, whose media types are explicit.
+    /// This method is only compiled when the **`sniff` feature** is enabled.
+    #[cfg(feature = "sniff")]
+    pub(crate) fn sniffed_media_type(&self) -> Option<&'static str> {
+        match self {
+            Self::Other(..) | Self::Raw(..) => return None,
+            #[cfg(feature = "http")]
+            Self::Url(..) => return None,
+            _ => {}
+        }
+
+        let kind = infer::get_from_path(self.path()).ok()??;
+        Some(media_type_for_extension(kind.extension()).unwrap_or(kind.mime_type()))
+    }
+
+    /// Gets the path of the file on disk, the filename as a path for [`Resource::Raw`], or the
+    /// URL as a path for `Resource::Url`.
+    fn path(&self) -> &'a Path {
+        match self {
+            Self::Raw(name, ..) => Path::new(*name),
+            Self::Image(path, _)
+            | Self::Font(path, _)
+            | Self::Audio(path, _)
+            | Self::Video(path, _)
+            | Self::Other(path, _) => path,
+            #[cfg(feature = "http")]
+            Self::Url(url, _) => Path::new(*url),
         }
     }
 
     /// Extracts the final filename (e.g., `image.png`) from the full path reference.
     ///
+    /// The filename of a [`Resource::Raw`] is used as is, so it may contain subdirectories.
+    ///
     /// # Errors
     /// Returns a [`crate::Error::FilenameNotFound`] if the path does not contain a valid filename.
     pub(crate) fn filename(&self) -> crate::Result<String> {
-        match self {
-            Self::Image(path, _) | Self::Font(path) | Self::Audio(path) | Self::Video(path) => {
-                let filename = path
-                    .file_name()
-                    .and_then(|filename| filename.to_str())
-                    .ok_or(crate::Error::FilenameNotFound(self.to_string()))?;
+        let filename = match self {
+            #[cfg(feature = "http")]
+            Self::Url(url, _) => url_filename(url),
+            Self::Raw(name, ..) => Some(*name).filter(|name| !name.is_empty()),
+            _ => self
+                .path()
+                .file_name()
+                .and_then(|filename| filename.to_str()),
+        }
+        .ok_or(crate::Error::FilenameNotFound(self.to_string()))?;
+
+        Ok(filename.to_string())
+    }
+}
 
-                Ok(filename.to_string())
+/// A [`Resource`] together with the options used when packaging it into the EPUB.
+///
+/// Every [`Resource`] converts into a `ResourceItem` with default options, so it can be
+/// passed anywhere a `ResourceItem` is expected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceItem<'a> {
+    /// The underlying file resource.
+    pub(crate) resource: Resource<'a>,
+    /// An optional media type replacing the one derived from the resource type.
+    media_type: Option<String>,
+    /// An optional in-archive filename replacing the basename of the source path.
+    name: Option<String>,
+    /// Whether the content is mangled with the IDPF font obfuscation algorithm.
+    pub(crate) obfuscated: bool,
+    /// An optional font family name for the generated `@font-face` rule, replacing the one
+    /// derived from the filename.
+    pub(crate) font_family: Option<String>,
+    /// The media type sniffed from the file content when it disagrees with the one derived
+    /// from the resource type, detected once.
+    #[cfg(feature = "sniff")]
+    sniffed_media_type: SniffedMediaType,
+}
+
+/// The cached result of sniffing the media type of a resource file, detected on first use.
+///
+/// It is derived from the resource, so it is ignored when comparing resource items.
+#[cfg(feature = "sniff")]
+#[derive(Debug, Clone, Default)]
+struct SniffedMediaType(OnceLock<Option<&'static str>>);
+
+#[cfg(feature = "sniff")]
+impl PartialEq for SniffedMediaType {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+#[cfg(feature = "sniff")]
+impl Eq for SniffedMediaType {}
+
+impl<'a> ResourceItem<'a> {
+    /// Converts the in-archive filename to Unicode Normalization Form C, e.g. for files named
+    /// on macOS, whose file system stores decomposed characters.
+    pub(crate) fn normalize_unicode(&mut self) {
+        if let Ok(filename) = self.filename() {
+            let normalized = unicode::nfc_str(&filename);
+            if normalized != filename {
+                self.name = Some(normalized.into_owned());
             }
         }
     }
 
-    /// Generates the **XML `<item>` tag** used in the package manifest (e.g., EPUB's `content.opf`).
+    /// Sets the manifest **media type**, overriding the one derived from the resource type.
+    pub fn with_media_type<S: Into<String>>(mut self, media_type: S) -> Self {
+        self.media_type = Some(media_type.into());
+        self
+    }
+
+    /// Sets the **filename** used inside the EPUB (e.g. `cover-final.jpg`), so it can differ from
+    /// the basename of the source path.
     ///
-    /// Returns `None` if the filename cannot be extracted.
-    pub(crate) fn as_manifest_xml(&self) -> Option<String> {
-        Some(format!(
-            r#"<item id="{filename}" href="{filename}" media-type="{media_type}"/>"#,
-            filename = self.filename().ok()?,
-            media_type = self.media_type()
-        ))
+    /// This avoids collisions when different source directories contain files with the same name.
+    pub fn as_name<S: Into<String>>(mut self, name: S) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Mangles the content with the **IDPF font obfuscation** algorithm when packaging it,
+    /// declaring it in `META-INF/encryption.xml`.
+    ///
+    /// This is intended for fonts whose license requires them not to be trivially extractable
+    /// from the EPUB. The key is derived from the metadata identifier, so reading systems can
+    /// restore the original font.
+    pub fn obfuscate(mut self) -> Self {
+        self.obfuscated = true;
+        self
+    }
+
+    /// Sets the **font family** name of the `@font-face` rule generated for this font (see
+    /// [`crate::epub::EpubBuilder::font_faces`]), instead of deriving it from the filename.
+    pub fn font_family<S: Into<String>>(mut self, font_family: S) -> Self {
+        self.font_family = Some(font_family.into());
+        self
+    }
+
+    /// Gets the media type declared in the manifest, preferring the override when set.
+    ///
+    /// With the **`sniff` feature** enabled, the file content is inspected as well, only the
+    /// first time: when it disagrees with the media type derived from the resource type, a
+    /// warning is logged once and the sniffed media type wins.
+    pub fn media_type(&self) -> &str {
+        if let Some(ref media_type) = self.media_type {
+            return media_type;
+        }
+
+        let declared = self.resource.media_type();
+
+        #[cfg(feature = "sniff")]
+        if let Some(sniffed) = *self.sniffed_media_type.0.get_or_init(|| {
+            let sniffed = self.resource.sniffed_media_type()?;
+            (declared.split(';').next().map(str::trim) != Some(sniffed)).then(|| {
+                log::warn!(
+                    "Resource '{}' is declared as '{declared}' but its content is '{sniffed}'",
+                    self.resource
+                );
+                sniffed
+            })
+        }) {
+            return sniffed;
+        }
+
+        declared
+    }
+
+    /// Reads the underlying file synchronously. See [`Resource::file_content`].
+    ///
+    /// The output path uses the custom name when set.
+    pub(crate) fn file_content(&self) -> crate::Result<FileContent<String, FileBytes>> {
+        let mut file_content = self
+            .resource
+            .file_content()
+            .with_context(|| format!("resource '{}'", self.resource))?;
+        if let Some(ref name) = self.name {
+            file_content.filepath = format!("OEBPS/{name}");
+        }
+        Ok(file_content)
+    }
+
+    /// Reads the underlying file asynchronously. See [`Resource::async_file_content`].
+    ///
+    /// The output path uses the custom name when set.
+    #[cfg(feature = "async")]
+    pub(crate) async fn async_file_content(
+        &self,
+        spawner: Option<&dyn Spawner>,
+    ) -> crate::Result<FileContent<String, FileBytes>> {
+        let mut file_content = self
+            .resource
+            .async_file_content(spawner)
+            .await
+            .with_context(|| format!("resource '{}'", self.resource))?;
+        if let Some(ref name) = self.name {
+            file_content.filepath = format!("OEBPS/{name}");
+        }
+        Ok(file_content)
+    }
+
+    /// Gets the final filename inside the EPUB: the custom name when set, otherwise
+    /// the basename of the source path (see [`Resource::filename`]).
+    ///
+    /// # Errors
+    /// Returns a [`crate::Error::FilenameNotFound`] if the source path has no valid basename.
+    pub fn filename(&self) -> crate::Result<String> {
+        match self.name {
+            Some(ref name) => Ok(name.clone()),
+            None => self.resource.filename(),
+        }
+    }
+
+    /// Checks that the source file exists, is readable and is not empty, without loading it.
+    ///
+    /// Returns a [`crate::Error::ResourceNotFound`] if the file does not exist, or else a
+    /// [`crate::Error::InvalidResource`] describing the problem, if any. Remote resources are
+    /// not checked, and in-memory resources are only checked for emptiness.
+    pub(crate) fn problem(&self) -> Option<crate::Error> {
+        #[cfg(feature = "http")]
+        if let Resource::Url(..) = self.resource {
+            return None;
+        }
+
+        if let Resource::Raw(_, bytes, _) = self.resource {
+            return bytes
+                .is_empty()
+                .then(|| self.resource.invalid("file is empty"));
+        }
+
+        // There is no file system to read the other resources from
+        #[cfg(target_arch = "wasm32")]
+        let problem = self
+            .resource
+            .invalid("only in-memory resources (Resource::Raw) are supported on wasm32");
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let problem = {
+            let path = self.resource.path();
+            match fs::metadata(path) {
+                Err(e) => self.resource.read_error(e),
+                Ok(metadata) if !metadata.is_file() => self.resource.invalid("not a file"),
+                Ok(metadata) if metadata.len() == 0 => self.resource.invalid("file is empty"),
+                Ok(_) => self.resource.read_error(fs::File::open(path).err()?),
+            }
+        };
+
+        Some(problem)
+    }
+
+    /// Gets the manifest **id** of the resource, derived from its filename inside the EPUB.
+    ///
+    /// See [`manifest_id`].
+    pub(crate) fn manifest_id(&self) -> crate::Result<String> {
+        Ok(manifest_id(&self.filename()?))
+    }
+}
+
+impl<'a> From<Resource<'a>> for ResourceItem<'a> {
+    fn from(resource: Resource<'a>) -> Self {
+        Self {
+            resource,
+            media_type: None,
+            name: None,
+            obfuscated: false,
+            font_family: None,
+            #[cfg(feature = "sniff")]
+            sniffed_media_type: SniffedMediaType::default(),
+        }
+    }
+}
+
+/// Extracts the last segment of the path of `url`, ignoring the query and the fragment
+/// (e.g. `map.png` for `https://cdn.example.com/assets/map.png?v=2`).
+///
+/// Returns `None` if the URL has no path or its path ends with `/`.
+#[cfg(feature = "http")]
+fn url_filename(url: &str) -> Option<&str> {
+    let url = url.split(['?', '#']).next()?;
+    let (_, path) = url
+        .split_once("://")
+        .unwrap_or(("", url))
+        .1
+        .split_once('/')?;
+    path.rsplit('/')
+        .next()
+        .filter(|segment| !segment.is_empty())
+}
+
+/// Turns a filename inside the EPUB (e.g. `images/map 1.png`) into a valid manifest id (an XML `NCName`).
+///
+/// Characters other than ASCII letters, digits, `-`, `_` and `.` are replaced by `_`, and an `_`
+/// is prepended when the name does not start with a letter or `_`. Plain filenames like
+/// `image.png` are kept as they are.
+pub(crate) fn manifest_id(filename: &str) -> String {
+    let id = filename
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+
+    if id.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        id
+    } else {
+        format!("_{id}")
     }
 }
 
+/// Maps the extension of a path (case-insensitive) to its standard **MIME media type**.
+///
+/// Returns `None` if the path has no extension or the extension is unknown.
+fn media_type_from_extension(path: &Path) -> Option<&'static str> {
+    media_type_for_extension(&path.extension()?.to_str()?.to_ascii_lowercase())
+}
+
+/// Maps a lowercase file extension to its standard **MIME media type**.
+fn media_type_for_extension(extension: &str) -> Option<&'static str> {
+    let media_type = match extension {
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "mp3" => "audio/mpeg",
+        "ogg" | "oga" => "audio/ogg",
+        "opus" => "audio/ogg; codecs=opus",
+        "m4a" | "aac" => "audio/mp4",
+        "wav" => "audio/wav",
+        "mp4" | "m4v" => "video/mp4",
+        "webm" => "video/webm",
+        "css" => "text/css",
+        "js" => "text/javascript",
+        "json" => "application/json",
+        "xhtml" => "application/xhtml+xml",
+        "smil" => "application/smil+xml",
+        "pls" => "application/pls+xml",
+        _ => return None,
+    };
+
+    Some(media_type)
+}
+
 /// Implements display for [`Resource`], outputting the file's full path string.
 impl Display for Resource<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::Image(path, _) | Self::Font(path) | Self::Audio(path) | Self::Video(path) => {
-                write!(f, "{}", path.to_str().unwrap_or_default())
-            }
-        }
+        write!(f, "{}", self.path().to_str().unwrap_or_default())
+    }
+}
+
+/// Loads the resource file at `path`, without reading it: it is copied to the archive in chunks
+/// when written, or memory-mapped when the **`mmap` feature** is enabled and the file is at
+/// least [`MMAP_MIN_SIZE`] bytes long.
+///
+/// The file is opened here, so a missing or unreadable file is reported before anything is
+/// written.
+fn read_file(path: &Path) -> std::io::Result<FileBytes> {
+    let file = fs::File::open(path)?;
+    let len = file.metadata()?.len();
+
+    #[cfg(feature = "mmap")]
+    if len >= MMAP_MIN_SIZE {
+        // SAFETY: the mapping is only read while the EPUB is created. As documented on the
+        // `mmap` feature, resource files must not be modified meanwhile.
+        return unsafe { memmap2::Mmap::map(&file) }.map(FileBytes::Mapped);
     }
+
+    Ok(FileBytes::File {
+        path: path.to_path_buf(),
+        len,
+    })
 }
 
 #[cfg(test)]
@@ -168,15 +768,214 @@ mod tests {
     fn test_resource_media_type_other() {
         let path = Path::new("test.otf");
         assert_eq!(
-            Resource::Font(path).media_type(),
-            "application/vnd.ms-opentype"
+            Resource::Font(path, FontType::OpenType).media_type(),
+            "font/otf"
         );
 
         let path = Path::new("test.mp3");
-        assert_eq!(Resource::Audio(path).media_type(), "audio/mpeg");
+        assert_eq!(
+            Resource::Audio(path, AudioType::Mp3).media_type(),
+            "audio/mpeg"
+        );
 
         let path = Path::new("test.mp4");
-        assert_eq!(Resource::Video(path).media_type(), "video/mp4");
+        assert_eq!(
+            Resource::Video(path, VideoType::Mp4).media_type(),
+            "video/mp4"
+        );
+    }
+
+    #[test]
+    fn test_resource_media_type_custom() {
+        let path = Path::new("data/lexicon.pls");
+        let resource = Resource::Other(path, "application/pls+xml".to_string());
+        assert_eq!(resource.media_type(), "application/pls+xml");
+        assert_eq!(resource.filename().unwrap(), "lexicon.pls");
+
+        assert_eq!(
+            ResourceItem::from(resource).manifest_id().unwrap(),
+            "lexicon.pls"
+        );
+    }
+
+    #[test]
+    fn test_resource_raw() {
+        let item = ResourceItem::from(Resource::Raw(
+            "images/map.png",
+            b"png",
+            "image/png".to_string(),
+        ));
+        assert_eq!(item.media_type(), "image/png");
+        assert_eq!(item.manifest_id().unwrap(), "images_map.png");
+        assert!(item.problem().is_none());
+        assert_eq!(
+            item.file_content().unwrap(),
+            FileContent::new("OEBPS/images/map.png".to_string(), b"png".to_vec().into())
+        );
+
+        let item = ResourceItem::from(Resource::Raw("empty.bin", b"", "x/y".to_string()));
+        assert_eq!(
+            item.problem().unwrap().to_string(),
+            "'empty.bin': file is empty"
+        );
+        assert!(
+            Resource::Raw("", b"x", "x/y".to_string())
+                .filename()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_video_type_from_path() {
+        assert!(matches!(
+            VideoType::from_path(Path::new("clip.mp4")),
+            Some(VideoType::Mp4)
+        ));
+        assert!(matches!(
+            VideoType::from_path(Path::new("clip.WEBM")),
+            Some(VideoType::Webm)
+        ));
+        assert!(VideoType::from_path(Path::new("track.mp3")).is_none());
+
+        let path = Path::new("clip.webm");
+        let resource = Resource::Video(path, VideoType::from_path(path).unwrap_or_default());
+        assert_eq!(resource.media_type(), "video/webm");
+    }
+
+    #[test]
+    fn test_audio_type_from_path() {
+        assert!(matches!(
+            AudioType::from_path(Path::new("track.mp3")),
+            Some(AudioType::Mp3)
+        ));
+        assert!(matches!(
+            AudioType::from_path(Path::new("track.M4A")),
+            Some(AudioType::M4a)
+        ));
+        assert!(matches!(
+            AudioType::from_path(Path::new("track.aac")),
+            Some(AudioType::M4a)
+        ));
+        assert!(matches!(
+            AudioType::from_path(Path::new("track.ogg")),
+            Some(AudioType::Ogg)
+        ));
+        assert!(matches!(
+            AudioType::from_path(Path::new("track.opus")),
+            Some(AudioType::Opus)
+        ));
+        assert!(matches!(
+            AudioType::from_path(Path::new("track.wav")),
+            Some(AudioType::Wav)
+        ));
+        assert!(AudioType::from_path(Path::new("clip.webm")).is_none());
+
+        let path = Path::new("track.opus");
+        let resource = Resource::Audio(path, AudioType::from_path(path).unwrap_or_default());
+        assert_eq!(resource.media_type(), "audio/ogg; codecs=opus");
+    }
+
+    #[test]
+    fn test_font_type_from_path() {
+        assert!(matches!(
+            FontType::from_path(Path::new("font.TTF")),
+            Some(FontType::TrueType)
+        ));
+        assert!(matches!(
+            FontType::from_path(Path::new("font.otf")),
+            Some(FontType::OpenType)
+        ));
+        assert!(matches!(
+            FontType::from_path(Path::new("font.woff")),
+            Some(FontType::Woff)
+        ));
+        assert!(matches!(
+            FontType::from_path(Path::new("font.woff2")),
+            Some(FontType::Woff2)
+        ));
+        assert!(FontType::from_path(Path::new("font.png")).is_none());
+        assert!(FontType::from_path(Path::new("no_extension")).is_none());
+
+        let path = Path::new("font.woff2");
+        let resource = Resource::Font(path, FontType::from_path(path).unwrap_or_default());
+        assert_eq!(resource.media_type(), "font/woff2");
+    }
+
+    #[test]
+    fn test_resource_item_media_type_override() {
+        let item = Resource::Font(Path::new("font.otf"), FontType::OpenType)
+            .with_media_type("application/vnd.ms-opentype");
+        assert_eq!(item.media_type(), "application/vnd.ms-opentype");
+        assert_eq!(item.manifest_id().unwrap(), "font.otf");
+
+        let item = ResourceItem::from(Resource::Audio(Path::new("track.ogg"), AudioType::Ogg));
+        assert_eq!(item.media_type(), "audio/ogg");
+    }
+
+    #[test]
+    fn test_resource_item_as_name() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let content: Vec<u8> = vec![0x11, 0x22];
+        let file_path = create_temp_file(temp_dir.path(), "image1.png", &content);
+
+        let item = Resource::Image(&file_path, ImageType::Png).as_name("cover-final.png");
+        assert_eq!(item.filename().unwrap(), "cover-final.png");
+        assert_eq!(item.manifest_id().unwrap(), "cover-final.png");
+        let file_content = item.file_content().unwrap();
+        assert_eq!(file_content.filepath, "OEBPS/cover-final.png");
+        assert_eq!(file_content.bytes.into_vec().unwrap(), content);
+
+        let item = ResourceItem::from(Resource::Image(&file_path, ImageType::Png));
+        assert_eq!(item.filename().unwrap(), "image1.png");
+    }
+
+    #[test]
+    fn test_resource_from_path() {
+        let resource = Resource::from_path(Path::new("img/a.JPG")).unwrap();
+        assert!(matches!(resource, Resource::Image(_, ImageType::Jpg)));
+
+        let resource = Resource::from_path(Path::new("a.woff")).unwrap();
+        assert!(matches!(resource, Resource::Font(_, FontType::Woff)));
+
+        let resource = Resource::from_path(Path::new("a.webp")).unwrap();
+        assert_eq!(resource.media_type(), "image/webp");
+
+        let resource = Resource::from_path(Path::new("style/extra.css")).unwrap();
+        assert_eq!(resource.media_type(), "text/css");
+
+        assert!(Resource::from_path(Path::new("a.unknown")).is_none());
+        assert!(Resource::from_path(Path::new("README")).is_none());
+    }
+
+    #[test]
+    fn test_manifest_id() {
+        assert_eq!(manifest_id("image.png"), "image.png");
+        assert_eq!(manifest_id("_font-1.otf"), "_font-1.otf");
+        assert_eq!(manifest_id("images/map 1.png"), "images_map_1.png");
+        assert_eq!(manifest_id("1.png"), "_1.png");
+        assert_eq!(manifest_id("ñandú.jpg"), "_and_.jpg");
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn test_resource_url() {
+        let resource = Resource::Url(
+            "https://cdn.example.com/assets/map.png?v=2#top",
+            "image/png".to_string(),
+        );
+        assert_eq!(resource.filename().unwrap(), "map.png");
+        assert_eq!(resource.media_type(), "image/png");
+        assert_eq!(
+            resource.to_string(),
+            "https://cdn.example.com/assets/map.png?v=2#top"
+        );
+
+        assert_eq!(
+            url_filename("https://example.com/a/font.otf"),
+            Some("font.otf")
+        );
+        assert_eq!(url_filename("https://example.com"), None);
+        assert_eq!(url_filename("https://example.com/assets/"), None);
     }
 
     #[test]
@@ -190,7 +989,7 @@ mod tests {
         assert_eq!(resource.filename().unwrap(), "just_a_file.gif");
 
         let path = Path::new("assets/font.ttf");
-        let resource = Resource::Font(path);
+        let resource = Resource::Font(path, FontType::TrueType);
         assert_eq!(resource.filename().unwrap(), "font.ttf");
     }
 
@@ -205,23 +1004,62 @@ mod tests {
 
         let file_content = resource.file_content().unwrap();
 
-        let expected_filepath = format!("OEBPS/{}", filename);
-        let expected_content = FileContent::new(expected_filepath, content);
+        let expected_content = FileContent::new(
+            format!("OEBPS/{}", filename),
+            FileBytes::File {
+                path: file_path.clone(),
+                len: content.len() as u64,
+            },
+        );
 
         assert_eq!(file_content, expected_content);
+        assert_eq!(file_content.bytes.into_vec().unwrap(), content);
     }
 
     #[test]
     fn test_resource_file_content_io_error() {
         let non_existent_path = Path::new("non_existent_file_for_test.mp4");
-        let resource = Resource::Video(non_existent_path);
+        let resource = Resource::Video(non_existent_path, VideoType::Mp4);
 
         match resource.file_content() {
-            Err(e) => assert!(matches!(e, crate::Error::Io(_))),
-            _ => panic!("Expected Io error when reading non-existent file"),
+            Err(crate::Error::ResourceNotFound { path }) => assert_eq!(path, non_existent_path),
+            other => {
+                panic!("Expected ResourceNotFound when reading non-existent file, got {other:?}")
+            }
         }
     }
 
+    #[test]
+    #[cfg(feature = "sniff")]
+    fn test_resource_item_media_type_sniffed() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let png = [0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A];
+
+        let file_path = create_temp_file(temp_dir.path(), "image.jpg", &png);
+        let item = ResourceItem::from(Resource::Image(&file_path, ImageType::Jpg));
+        assert_eq!(item.media_type(), "image/png");
+
+        let file_path = create_temp_file(temp_dir.path(), "no_extension", b"OggS\0\x02");
+        let item = ResourceItem::from(Resource::Audio(&file_path, AudioType::Mp3));
+        assert_eq!(item.media_type(), "audio/ogg");
+
+        let item = ResourceItem::from(Resource::Audio(&file_path, AudioType::Opus));
+        assert_eq!(item.media_type(), "audio/ogg; codecs=opus");
+
+        let item = Resource::Audio(&file_path, AudioType::Mp3).with_media_type("audio/opus");
+        assert_eq!(item.media_type(), "audio/opus");
+
+        // The content is sniffed once, on the first call
+        let item = ResourceItem::from(Resource::Audio(&file_path, AudioType::Mp3));
+        assert_eq!(item.media_type(), "audio/ogg");
+        std::fs::remove_file(&file_path).unwrap();
+        assert_eq!(item.media_type(), "audio/ogg");
+        assert_eq!(
+            item,
+            ResourceItem::from(Resource::Audio(&file_path, AudioType::Mp3))
+        );
+    }
+
     #[test]
     fn test_resource_display_trait() {
         let path = Path::new("/some/long/path/file.svg");
@@ -229,7 +1067,33 @@ mod tests {
         assert_eq!(format!("{}", resource), "/some/long/path/file.svg");
 
         let path = Path::new("font.otf");
-        let resource = Resource::Font(path);
+        let resource = Resource::Font(path, FontType::OpenType);
         assert_eq!(format!("{}", resource), "font.otf");
     }
+
+    #[test]
+    #[cfg(feature = "mmap")]
+    fn test_resource_file_content_mmap() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let small = create_temp_file(temp_dir.path(), "small.mp3", b"id3");
+        let large = temp_dir.path().join("large.mp3");
+        std::fs::File::create(&large)
+            .unwrap()
+            .set_len(MMAP_MIN_SIZE)
+            .unwrap();
+
+        let mut file_content = Resource::Audio(&large, AudioType::Mp3)
+            .file_content()
+            .unwrap();
+        assert!(matches!(file_content.bytes, FileBytes::Mapped(_)));
+        assert_eq!(file_content.bytes.len() as u64, MMAP_MIN_SIZE);
+
+        file_content.bytes.to_mut().unwrap()[0] = 1;
+        assert!(matches!(file_content.bytes, FileBytes::Owned(_)));
+
+        let file_content = Resource::Audio(&small, AudioType::Mp3)
+            .file_content()
+            .unwrap();
+        assert!(matches!(file_content.bytes, FileBytes::File { len: 3, .. }));
+    }
 }
@@ -0,0 +1,152 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::epub::{Resource, ResourceItem};
+
+/// The files found by walking a directory, ready to be registered as resources.
+///
+/// Since resources only borrow their paths, the walked paths are owned here and the
+/// [`EpubBuilder`](crate::epub::EpubBuilder) borrows them through
+/// [`add_resources_from_dir`](crate::epub::EpubBuilder::add_resources_from_dir).
+#[derive(Debug, Clone)]
+pub struct ResourceDir {
+    /// The walked files as (path on disk, path relative to the root using `/` separators).
+    entries: Vec<(PathBuf, String)>,
+}
+
+impl ResourceDir {
+    /// Walks `root` recursively, collecting every file in a stable (sorted) order.
+    ///
+    /// Hidden files and directories (starting with `.`) are skipped.
+    ///
+    /// # Errors
+    /// Returns an error if a directory cannot be read, or a [`crate::Error::FilenameNotFound`]
+    /// if a file name is not valid UTF-8.
+    pub fn read<P: AsRef<Path>>(root: P) -> crate::Result<Self> {
+        let mut entries = Vec::new();
+        walk(root.as_ref(), "", &mut entries)?;
+        entries.sort_by(|(_, a), (_, b)| a.cmp(b));
+        Ok(Self { entries })
+    }
+
+    /// Gets the walked files as resources, each one packaged under its relative subpath
+    /// (e.g. `OEBPS/images/map.png` for `<root>/images/map.png`).
+    ///
+    /// The resource variant and media type are inferred from the file extension (see
+    /// [`Resource::from_path`]); files with an unknown extension are registered as
+    /// `application/octet-stream`.
+    pub fn resources(&self) -> Vec<ResourceItem<'_>> {
+        self.entries
+            .iter()
+            .map(|(path, name)| {
+                Resource::from_path(path)
+                    .unwrap_or(Resource::Other(
+                        path,
+                        "application/octet-stream".to_string(),
+                    ))
+                    .as_name(name)
+            })
+            .collect()
+    }
+
+    /// Gets the number of walked files.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if no file was found.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Recursively pushes the files of `dir` into `entries`, prefixing their names with `prefix`.
+fn walk(dir: &Path, prefix: &str, entries: &mut Vec<(PathBuf, String)>) -> crate::Result {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let filename = entry
+            .file_name()
+            .into_string()
+            .map_err(|_| crate::Error::FilenameNotFound(path.display().to_string()))?;
+
+        if filename.starts_with('.') {
+            continue;
+        }
+
+        let name = format!("{prefix}{filename}");
+        if entry.file_type()?.is_dir() {
+            walk(&path, &format!("{name}/"), entries)?;
+        } else {
+            entries.push((path, name));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resource_dir_read() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("images/maps")).unwrap();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::write(root.join("images/maps/world-map.png"), b"png").unwrap();
+        fs::write(root.join("images/cover.jpg"), b"jpg").unwrap();
+        fs::write(root.join("font.woff2"), b"woff2").unwrap();
+        fs::write(root.join("data.bin"), b"bin").unwrap();
+        fs::write(root.join(".DS_Store"), b"hidden").unwrap();
+        fs::write(root.join(".git/HEAD"), b"hidden").unwrap();
+
+        let dir = ResourceDir::read(root).unwrap();
+        assert_eq!(dir.len(), 4);
+
+        let manifest = dir
+            .resources()
+            .iter()
+            .map(|item| {
+                (
+                    item.manifest_id().unwrap(),
+                    item.filename().unwrap(),
+                    item.media_type().to_string(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let expected = [
+            ("data.bin", "data.bin", "application/octet-stream"),
+            ("font.woff2", "font.woff2", "font/woff2"),
+            ("images_cover.jpg", "images/cover.jpg", "image/jpeg"),
+            (
+                "images_maps_world-map.png",
+                "images/maps/world-map.png",
+                "image/png",
+            ),
+        ];
+        assert_eq!(
+            manifest,
+            expected.map(|(id, href, media_type)| (
+                id.to_string(),
+                href.to_string(),
+                media_type.to_string()
+            ))
+        );
+
+        let file_content = dir.resources()[3].file_content().unwrap();
+        assert_eq!(file_content.filepath, "OEBPS/images/maps/world-map.png");
+        assert_eq!(file_content.bytes.into_vec().unwrap(), b"png");
+    }
+
+    #[test]
+    fn test_resource_dir_read_missing() {
+        assert!(ResourceDir::read("/path/that/does/not/exist").is_err());
+        let temp_dir = tempfile::tempdir().unwrap();
+        assert!(ResourceDir::read(temp_dir.path()).unwrap().is_empty());
+    }
+}
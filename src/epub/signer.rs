@@ -0,0 +1,54 @@
+/// Signs the package with a caller-provided key, registered with
+/// [`EpubBuilder::sign`](crate::epub::EpubBuilder::sign), to generate the XML digital
+/// signature of `META-INF/signatures.xml`.
+///
+/// liber computes the SHA-256 digests of the signed entries and canonicalizes the
+/// `<SignedInfo>` element; the signer only signs those bytes with its key (held in memory, or
+/// in a hardware module or remote service), so any signature algorithm and crypto library can
+/// be used.
+///
+/// ```rust
+/// use liber::epub::{EpubBuilder, MetadataBuilder, PackageSigner};
+///
+/// /// Signs with an RSA key, using any crypto library.
+/// struct RsaSigner;
+///
+/// impl PackageSigner for RsaSigner {
+///     fn signature_method(&self) -> &str {
+///         "http://www.w3.org/2001/04/xmldsig-more#rsa-sha256"
+///     }
+///
+///     fn sign(&self, signed_info: &[u8]) -> liber::Result<Vec<u8>> {
+///         // e.g. `rsa::pkcs1v15::SigningKey::<Sha256>::sign(signed_info).to_vec()`
+///         Ok(signed_info.to_vec())
+///     }
+/// }
+///
+/// let epub_builder = EpubBuilder::new(MetadataBuilder::title("My Book").build()).sign(&RsaSigner);
+/// ```
+///
+/// This trait is only available when the **`crypto` feature** is enabled.
+pub trait PackageSigner: Sync {
+    /// Gets the URI of the signature algorithm, declared as the `<SignatureMethod>` (e.g.
+    /// `http://www.w3.org/2001/04/xmldsig-more#rsa-sha256`).
+    fn signature_method(&self) -> &str;
+
+    /// Signs the canonical (C14N) bytes of the `<SignedInfo>` element, returning the raw
+    /// signature value.
+    ///
+    /// # Errors
+    /// Any error aborts the creation of the EPUB.
+    fn sign(&self, signed_info: &[u8]) -> crate::Result<Vec<u8>>;
+
+    /// Gets the DER-encoded X.509 certificate of the signing key, if any, declared in the
+    /// `<KeyInfo>` of the signature so readers can verify it.
+    fn certificate(&self) -> Option<&[u8]> {
+        None
+    }
+}
+
+impl std::fmt::Debug for dyn PackageSigner + '_ {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PackageSigner({})", self.signature_method())
+    }
+}
@@ -0,0 +1,114 @@
+use futures::channel::oneshot;
+
+/// Runs the blocking work of the async API (reading resource files and formatting the XML
+/// files) away from the async executor, so the async API works on any runtime.
+///
+/// Register one with [`EpubBuilder::spawner`](crate::epub::EpubBuilder::spawner). Without one,
+/// [`TokioSpawner`] is used inside a Tokio runtime and [`InlineSpawner`] elsewhere.
+///
+/// ```rust
+/// use liber::epub::Spawner;
+///
+/// /// Runs the blocking work on a new thread, e.g. for a runtime without a blocking pool.
+/// struct ThreadSpawner;
+///
+/// impl Spawner for ThreadSpawner {
+///     fn spawn_blocking(&self, task: Box<dyn FnOnce() + Send>) {
+///         std::thread::spawn(task);
+///     }
+/// }
+/// ```
+///
+/// This trait is only available when the **`async` feature** is enabled.
+pub trait Spawner: Sync {
+    /// Starts `task` on a thread where blocking is allowed (e.g. with
+    /// `tokio::task::spawn_blocking`, `async_std::task::spawn_blocking` or `smol::unblock`),
+    /// without waiting for it to finish.
+    fn spawn_blocking(&self, task: Box<dyn FnOnce() + Send>);
+}
+
+/// A [`Spawner`] running the blocking work on the blocking thread pool of the current Tokio
+/// runtime.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokioSpawner;
+
+impl Spawner for TokioSpawner {
+    fn spawn_blocking(&self, task: Box<dyn FnOnce() + Send>) {
+        tokio::task::spawn_blocking(task);
+    }
+}
+
+/// A [`Spawner`] running the blocking work right away on the current thread, for
+/// executor-less environments (e.g. `futures::executor::block_on`).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct InlineSpawner;
+
+impl Spawner for InlineSpawner {
+    fn spawn_blocking(&self, task: Box<dyn FnOnce() + Send>) {
+        task();
+    }
+}
+
+impl std::fmt::Debug for dyn Spawner + '_ {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Spawner")
+    }
+}
+
+/// Runs the blocking function `f` with `spawner`, or with the default spawner if `None` (see
+/// [`Spawner`]), and waits for its result.
+///
+/// # Errors
+/// Returns a [`crate::Error::BlockingTaskCanceled`] if the task is dropped before finishing
+/// (e.g. because it panicked).
+pub(crate) async fn run_blocking<T, F>(spawner: Option<&dyn Spawner>, f: F) -> crate::Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    let spawner = spawner.unwrap_or(if tokio::runtime::Handle::try_current().is_ok() {
+        &TokioSpawner
+    } else {
+        &InlineSpawner
+    });
+
+    let (sender, receiver) = oneshot::channel();
+    spawner.spawn_blocking(Box::new(move || {
+        let _ = sender.send(f());
+    }));
+    receiver
+        .await
+        .map_err(|_| crate::Error::BlockingTaskCanceled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Dropping;
+
+    impl Spawner for Dropping {
+        fn spawn_blocking(&self, _task: Box<dyn FnOnce() + Send>) {}
+    }
+
+    #[test]
+    fn test_run_blocking_without_runtime() {
+        let result = futures::executor::block_on(run_blocking(None, || 6 * 7));
+        assert_eq!(result.unwrap(), 42);
+
+        let result = futures::executor::block_on(run_blocking(Some(&Dropping), || 6 * 7));
+        assert!(matches!(result, Err(crate::Error::BlockingTaskCanceled)));
+    }
+
+    #[tokio::test]
+    async fn test_run_blocking_with_tokio() {
+        let caller = std::thread::current().id();
+        let current = || std::thread::current().id();
+
+        assert_ne!(run_blocking(None, current).await.unwrap(), caller);
+        assert_eq!(
+            run_blocking(Some(&InlineSpawner), current).await.unwrap(),
+            caller
+        );
+    }
+}
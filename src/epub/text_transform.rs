@@ -0,0 +1,128 @@
+use std::{fmt, sync::Arc};
+
+/// How a [`TextTransform`] rewrites a body.
+#[derive(Clone)]
+enum Rewrite {
+    /// A closure returning the rewritten body.
+    Closure(Arc<dyn Fn(&str) -> String + Send + Sync>),
+    /// A regular expression whose matches are replaced, expanding `$1`, `$name`...
+    #[cfg(feature = "regex")]
+    Regex(regex::Regex, String),
+}
+
+/// A transform of the body of every content, registered with
+/// [`EpubBuilder::add_text_transform`](crate::epub::EpubBuilder::add_text_transform) and run
+/// when its XHTML is generated, so the source files need no preprocessing (e.g. normalizing
+/// the spacing of units or stripping editorial comments).
+///
+/// Transforms run in registration order, before the other rewrites of the body (smart
+/// punctuation, typography, DPUB-ARIA roles). They see the whole XHTML body, markup included.
+/// Bodies given as complete XHTML documents are left untouched.
+///
+/// ```rust
+/// use liber::epub::{EpubBuilder, MetadataBuilder, TextTransform};
+///
+/// let epub_builder = EpubBuilder::new(MetadataBuilder::title("My Book").build())
+///     .add_text_transform(TextTransform::new("strip notes", |body| {
+///         body.replace("<!-- TODO -->", "")
+///     }));
+/// ```
+#[derive(Clone)]
+pub struct TextTransform {
+    /// The name of the transform, used in debug output.
+    name: String,
+    /// How the transform rewrites a body.
+    rewrite: Rewrite,
+}
+
+impl TextTransform {
+    /// Creates a transform named `name` from a closure returning the rewritten body.
+    #[must_use]
+    pub fn new<S, F>(name: S, transform: F) -> Self
+    where
+        S: Into<String>,
+        F: Fn(&str) -> String + Send + Sync + 'static,
+    {
+        Self {
+            name: name.into(),
+            rewrite: Rewrite::Closure(Arc::new(transform)),
+        }
+    }
+
+    /// Creates a transform replacing every match of the regular expression `pattern` with
+    /// `replacement`, where `$1` or `$name` expand to the captured groups (see
+    /// [`regex::Regex::replace_all`]). It is named after the pattern.
+    ///
+    /// ```rust
+    /// use liber::epub::TextTransform;
+    ///
+    /// // A narrow no-break space between a number and its unit
+    /// let units = TextTransform::regex(r"(\d) (km|kg|cm)\b", "$1\u{202F}$2").unwrap();
+    /// ```
+    ///
+    /// This method requires the **`regex` feature** to be enabled.
+    ///
+    /// # Errors
+    /// Returns an error if `pattern` is not a valid regular expression.
+    #[cfg(feature = "regex")]
+    pub fn regex<S: Into<String>>(pattern: &str, replacement: S) -> crate::Result<Self> {
+        Ok(Self {
+            name: pattern.to_string(),
+            rewrite: Rewrite::Regex(regex::Regex::new(pattern)?, replacement.into()),
+        })
+    }
+
+    /// Gets the name of the transform.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Applies the transform to the XHTML `body`.
+    pub(crate) fn apply(&self, body: &str) -> String {
+        match self.rewrite {
+            Rewrite::Closure(ref transform) => transform(body),
+            #[cfg(feature = "regex")]
+            Rewrite::Regex(ref regex, ref replacement) => {
+                regex.replace_all(body, replacement.as_str()).into_owned()
+            }
+        }
+    }
+}
+
+impl fmt::Debug for TextTransform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("TextTransform").field(&self.name).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_transform_apply() {
+        let transform = TextTransform::new("strip comments", |body| {
+            body.replace("<!-- ed: check -->", "")
+        });
+        assert_eq!(transform.name(), "strip comments");
+        assert_eq!(
+            transform.apply("<body><p>Text<!-- ed: check --></p></body>"),
+            "<body><p>Text</p></body>"
+        );
+        assert_eq!(
+            format!("{transform:?}"),
+            r#"TextTransform("strip comments")"#
+        );
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_text_transform_regex() {
+        let transform = TextTransform::regex(r"(\d+) ?(km|kg)\b", "$1\u{A0}$2").unwrap();
+        assert_eq!(
+            transform.apply("<p>5 km and 10kg, not 3 kgs</p>"),
+            "<p>5\u{A0}km and 10\u{A0}kg, not 3 kgs</p>"
+        );
+        assert!(TextTransform::regex("(unclosed", "").is_err());
+    }
+}
@@ -0,0 +1,104 @@
+/// A built-in stylesheet preset, so a book reads well without a hand-written stylesheet.
+///
+/// Set with [`EpubBuilder::theme`](crate::epub::EpubBuilder::theme): used alone as the
+/// stylesheet (`style.css`), or followed by the one set with
+/// [`EpubBuilder::stylesheet`](crate::epub::EpubBuilder::stylesheet), whose rules override the
+/// theme's.
+///
+/// The presets only use relative sizes, so the font size chosen in the reading system is kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Theme {
+    /// A serif body text with indented paragraphs, like a printed novel.
+    Classic,
+    /// A sans-serif body text with spaced paragraphs, like a web page.
+    Modern,
+    /// Black text on a white background with underlined links and bold headings, for low
+    /// vision readers.
+    HighContrast,
+    /// Dark brown text on a sepia background, turning to light text on a dark background when
+    /// the reading system is in night mode.
+    Sepia,
+}
+
+/// The rules shared by every theme: headings, images, figures, tables and quotations.
+const BASE: &str = "\
+h1, h2, h3, h4, h5, h6 { line-height: 1.2; page-break-after: avoid; break-after: avoid; }
+h1 { font-size: 1.6em; margin: 2em 0 1em; text-align: center; }
+h2 { font-size: 1.3em; margin: 1.5em 0 0.75em; }
+h3 { font-size: 1.1em; margin: 1.2em 0 0.6em; }
+img, svg { max-width: 100%; height: auto; }
+figure { margin: 1em 0; text-align: center; page-break-inside: avoid; break-inside: avoid; }
+figcaption, caption { font-size: 0.9em; font-style: italic; margin-top: 0.5em; }
+table { border-collapse: collapse; margin: 1em auto; }
+th, td { border: 1px solid currentColor; padding: 0.25em 0.5em; }
+blockquote { margin: 1em 2em; }
+";
+
+/// The rules of [`Theme::Classic`].
+const CLASSIC: &str = "\
+body { font-family: Georgia, \"Times New Roman\", serif; line-height: 1.5; margin: 0 5%; text-align: justify; hyphens: auto; -webkit-hyphens: auto; }
+p { margin: 0; text-indent: 1.5em; }
+h1 + p, h2 + p, h3 + p, hr + p, blockquote + p { text-indent: 0; }
+hr { border: none; margin: 1.5em 0; text-align: center; }
+hr::after { content: \"* * *\"; }
+";
+
+/// The rules of [`Theme::Modern`].
+const MODERN: &str = "\
+body { font-family: \"Helvetica Neue\", Arial, sans-serif; line-height: 1.6; margin: 0 5%; }
+p { margin: 0 0 1em; }
+h1, h2, h3 { font-weight: 600; text-align: left; }
+hr { border: none; border-top: 1px solid #999; margin: 2em 20%; }
+";
+
+/// The rules of [`Theme::HighContrast`].
+const HIGH_CONTRAST: &str = "\
+body { font-family: Verdana, Arial, sans-serif; line-height: 1.7; margin: 0 4%; color: #000; background-color: #fff; }
+p { margin: 0 0 1em; }
+h1, h2, h3, h4, h5, h6 { font-weight: bold; }
+a { color: #00e; text-decoration: underline; }
+hr { border: none; border-top: 2px solid #000; margin: 2em 0; }
+";
+
+/// The rules of [`Theme::Sepia`].
+const SEPIA: &str = "\
+body { font-family: Georgia, serif; line-height: 1.5; margin: 0 5%; color: #5b4636; background-color: #f4ecd8; }
+p { margin: 0; text-indent: 1.5em; }
+h1 + p, h2 + p, h3 + p { text-indent: 0; }
+a { color: #8b4513; }
+@media (prefers-color-scheme: dark) {
+  body { color: #e8dcc8; background-color: #1e1a16; }
+  a { color: #d2a679; }
+}
+";
+
+impl Theme {
+    /// Gets the CSS of the theme.
+    pub fn css(self) -> String {
+        let rules = match self {
+            Self::Classic => CLASSIC,
+            Self::Modern => MODERN,
+            Self::HighContrast => HIGH_CONTRAST,
+            Self::Sepia => SEPIA,
+        };
+        [rules, BASE].concat()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_theme_css() {
+        let classic = Theme::Classic.css();
+        assert!(classic.starts_with("body { font-family: Georgia"));
+        assert!(classic.contains("img, svg { max-width: 100%; height: auto; }"));
+        assert!(
+            Theme::Sepia
+                .css()
+                .contains("@media (prefers-color-scheme: dark)")
+        );
+        assert_ne!(Theme::Modern.css(), Theme::HighContrast.css());
+    }
+}
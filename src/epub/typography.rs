@@ -0,0 +1,273 @@
+use std::mem::{self, Discriminant};
+
+use quick_xml::escape::escape;
+
+use crate::epub::ReferenceType;
+
+/// Opt-in typographic touches applied to the bodies of some types of content when their XHTML
+/// is generated, with the CSS they need appended to the stylesheet: a drop cap on the first
+/// letter of the first paragraph, a small-caps lead-in on its first words, and an ornament
+/// replacing the section breaks (`<hr/>`).
+///
+/// Set with [`EpubBuilder::typography`](crate::epub::EpubBuilder::typography). It applies to
+/// the chapters ([`ReferenceType::Text`]) unless other types are chosen with
+/// [`Typography::apply_to`].
+///
+/// ```rust
+/// use liber::epub::{EpubBuilder, MetadataBuilder, ReferenceType, Typography};
+///
+/// let typography = Typography::new()
+///     .drop_caps(true)
+///     .small_caps_lead_in(3)
+///     .ornament("❦")
+///     .apply_to(ReferenceType::Text(String::new()))
+///     .apply_to(ReferenceType::Preface(String::new()));
+/// let epub_builder =
+///     EpubBuilder::new(MetadataBuilder::title("My Book").build()).typography(typography);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Typography {
+    /// Whether the first letter of the first paragraph is a drop cap.
+    drop_caps: bool,
+    /// The number of words of the first paragraph set in small caps.
+    lead_in_words: usize,
+    /// Optional ornament replacing the section breaks.
+    ornament: Option<String>,
+    /// The types of content the typography applies to. Defaults to the chapters.
+    reference_types: Option<Vec<Discriminant<ReferenceType>>>,
+}
+
+impl Typography {
+    /// Creates a typography without any touch, for the chapters.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether the first letter of the first paragraph (with the punctuation before it,
+    /// e.g. an opening quote) is a drop cap, wrapped in a `<span class="drop-cap">`.
+    ///
+    /// This is a fluent method, returning `Self`.
+    pub fn drop_caps(mut self, drop_caps: bool) -> Self {
+        self.drop_caps = drop_caps;
+        self
+    }
+
+    /// Sets the number of **words** of the first paragraph set in small caps, wrapped in a
+    /// `<span class="lead-in">` (after the drop cap, if any). `0` disables it.
+    ///
+    /// This is a fluent method, returning `Self`.
+    pub fn small_caps_lead_in(mut self, words: usize) -> Self {
+        self.lead_in_words = words;
+        self
+    }
+
+    /// Sets the **ornament** (e.g. `❦` or `* * *`) replacing the section breaks (`<hr/>`), as a
+    /// centered `<p class="ornament" role="separator">`.
+    ///
+    /// This is a fluent method, returning `Self`.
+    pub fn ornament<S: Into<String>>(mut self, ornament: S) -> Self {
+        self.ornament = Some(ornament.into());
+        self
+    }
+
+    /// Applies the typography to the contents of the type of `reference_type` (its title is
+    /// ignored), replacing the default of the chapters on the first call.
+    ///
+    /// This is a fluent method, returning `Self`.
+    pub fn apply_to(mut self, reference_type: ReferenceType) -> Self {
+        let discriminant = mem::discriminant(&reference_type);
+        let reference_types = self.reference_types.get_or_insert_with(Vec::new);
+        if !reference_types.contains(&discriminant) {
+            reference_types.push(discriminant);
+        }
+        self
+    }
+
+    /// Gets the CSS of the enabled touches.
+    pub fn css(&self) -> String {
+        let mut css = String::new();
+        if self.drop_caps {
+            css.push_str(".drop-cap { float: left; font-size: 3.2em; line-height: 0.8; margin: 0.1em 0.1em 0 0; }\n");
+        }
+        if self.lead_in_words > 0 {
+            css.push_str(".lead-in { font-variant: small-caps; letter-spacing: 0.05em; }\n");
+        }
+        if self.ornament.is_some() {
+            css.push_str(".ornament { text-align: center; text-indent: 0; margin: 1em 0; }\n");
+        }
+        css
+    }
+
+    /// Checks whether the typography applies to the contents of type `reference_type`.
+    pub(crate) fn applies_to(&self, reference_type: &ReferenceType) -> bool {
+        match self.reference_types {
+            Some(ref reference_types) => {
+                reference_types.contains(&mem::discriminant(reference_type))
+            }
+            None => matches!(reference_type, ReferenceType::Text(_)),
+        }
+    }
+
+    /// Applies the enabled touches to the XHTML `body`.
+    pub(crate) fn apply(&self, body: &str) -> String {
+        let mut body = match first_text(body) {
+            Some(start) if self.drop_caps || self.lead_in_words > 0 => self.lead(body, start),
+            _ => body.to_string(),
+        };
+        if let Some(ref ornament) = self.ornament {
+            let separator = format!(
+                r#"<p class="ornament" role="separator">{}</p>"#,
+                escape(ornament)
+            );
+            body = body
+                .replace("<hr/>", &separator)
+                .replace("<hr />", &separator);
+        }
+        body
+    }
+
+    /// Wraps the drop cap and the lead-in of the text of `body` starting at `start` (the first
+    /// text of the first paragraph), up to the next tag.
+    fn lead(&self, body: &str, start: usize) -> String {
+        let run = &body[start..];
+        let run = &run[..run.find('<').unwrap_or(run.len())];
+
+        let cap_end = if self.drop_caps { cap_end(run) } else { 0 };
+        let lead_end = match self.lead_in_words {
+            0 => cap_end,
+            words => word_end(run, words).max(cap_end),
+        };
+
+        let mut lead = String::with_capacity(body.len() + 64);
+        lead.push_str(&body[..start]);
+        if cap_end > 0 {
+            lead.push_str(&format!(
+                r#"<span class="drop-cap">{}</span>"#,
+                &run[..cap_end]
+            ));
+        }
+        if lead_end > cap_end {
+            lead.push_str(&format!(
+                r#"<span class="lead-in">{}</span>"#,
+                &run[cap_end..lead_end]
+            ));
+        }
+        lead.push_str(&body[start + lead_end..]);
+        lead
+    }
+}
+
+/// Finds the first text of the first paragraph (`<p>`) of `body`, skipping the inline tags
+/// before it. Returns `None` if the paragraph starts with no text.
+fn first_text(body: &str) -> Option<usize> {
+    let mut from = 0;
+    let paragraph = loop {
+        let index = from + body[from..].find("<p")?;
+        let next = body[index + 2..].chars().next();
+        if matches!(next, Some('>' | '/') | Some(' ' | '\t' | '\n' | '\r')) {
+            break index;
+        }
+        from = index + 2;
+    };
+
+    let mut index = paragraph + body[paragraph..].find('>')? + 1;
+    loop {
+        let rest = &body[index..];
+        let skipped = rest.len() - rest.trim_start().len();
+        index += skipped;
+        let rest = &body[index..];
+        if rest.starts_with("</") || rest.is_empty() {
+            return None;
+        }
+        if rest.starts_with('<') {
+            index += rest.find('>')? + 1;
+        } else {
+            return Some(index);
+        }
+    }
+}
+
+/// Gets the end of the drop cap of the text `run`: the punctuation and entities before its first
+/// letter or digit, and that letter. Returns `0` if it has none.
+fn cap_end(run: &str) -> usize {
+    let mut end = 0;
+    while end < run.len() {
+        let rest = &run[end..];
+        if rest.starts_with('&') {
+            end += rest.find(';').map_or(rest.len(), |semicolon| semicolon + 1);
+            continue;
+        }
+        let c = rest.chars().next().unwrap_or_default();
+        if c.is_whitespace() {
+            return 0;
+        }
+        end += c.len_utf8();
+        if c.is_alphanumeric() {
+            return end;
+        }
+    }
+    0
+}
+
+/// Gets the end of the first `words` words of the text `run` (or of the whole run).
+fn word_end(run: &str, words: usize) -> usize {
+    let mut count = 0;
+    let mut in_word = false;
+    for (index, c) in run.char_indices() {
+        if c.is_whitespace() {
+            if in_word {
+                count += 1;
+                if count == words {
+                    return index;
+                }
+            }
+            in_word = false;
+        } else {
+            in_word = true;
+        }
+    }
+    run.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_typography_apply() {
+        let typography = Typography::new()
+            .drop_caps(true)
+            .small_caps_lead_in(3)
+            .ornament("* * *");
+
+        assert_eq!(
+            typography.apply(
+                "<body><h1>One</h1><p class=\"first\">\n  <em>&#8220;It was a dark night,</em> she said.</p><hr/><p>Then</p></body>"
+            ),
+            concat!(
+                r#"<body><h1>One</h1><p class="first">"#,
+                "\n  ",
+                r#"<em><span class="drop-cap">&#8220;I</span><span class="lead-in">t was a</span> dark night,</em> she said.</p>"#,
+                r#"<p class="ornament" role="separator">* * *</p><p>Then</p></body>"#
+            )
+        );
+        assert_eq!(
+            Typography::new()
+                .small_caps_lead_in(5)
+                .apply("<body><pre>x</pre><p>Short text</p></body>"),
+            r#"<body><pre>x</pre><p><span class="lead-in">Short text</span></p></body>"#
+        );
+        assert_eq!(
+            typography.apply("<body><p><img src=\"a.png\"/></p></body>"),
+            "<body><p><img src=\"a.png\"/></p></body>"
+        );
+
+        assert!(typography.applies_to(&ReferenceType::Text("One".to_string())));
+        assert!(!typography.applies_to(&ReferenceType::Preface("Preface".to_string())));
+        let typography = typography.apply_to(ReferenceType::Preface(String::new()));
+        assert!(!typography.applies_to(&ReferenceType::Text("One".to_string())));
+        assert!(typography.applies_to(&ReferenceType::Preface("Preface".to_string())));
+        assert_eq!(typography.css().lines().count(), 3);
+    }
+}
@@ -0,0 +1,34 @@
+use std::borrow::Cow;
+
+use unicode_normalization::{UnicodeNormalization, is_nfc};
+
+/// Converts `text` to Unicode Normalization Form C (precomposed characters) in place.
+pub(crate) fn nfc(text: &mut String) {
+    if !is_nfc(text) {
+        *text = text.nfc().collect();
+    }
+}
+
+/// Gets `text` in Unicode Normalization Form C, borrowing it if it is already normalized.
+pub(crate) fn nfc_str(text: &str) -> Cow<'_, str> {
+    if is_nfc(text) {
+        Cow::Borrowed(text)
+    } else {
+        Cow::Owned(text.nfc().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nfc() {
+        let mut text = "Cafe\u{301}".to_string();
+        nfc(&mut text);
+        assert_eq!(text, "Caf\u{e9}");
+
+        assert!(matches!(nfc_str("Caf\u{e9}"), Cow::Borrowed(_)));
+        assert_eq!(nfc_str("n\u{303}and\u{fa}"), "\u{f1}and\u{fa}");
+    }
+}
@@ -0,0 +1,172 @@
+use quick_xml::escape::escape;
+
+use crate::epub::EpubPlugin;
+
+/// A place of the generated files where a [`Watermark`] is injected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WatermarkLocation {
+    /// Hidden, as `<meta name="watermark"/>` in the package metadata.
+    PackageMetadata,
+    /// Hidden, as `<meta name="watermark"/>` in the header of every XHTML file.
+    ContentHeads,
+    /// Visible, as a `<p class="watermark">` line at the end of the content with this filename
+    /// (e.g. `colophon.xhtml`).
+    Colophon(String),
+    /// Visible, as a `<p class="watermark">` line at the end of every content.
+    EveryContent,
+}
+
+/// A buyer-specific string (e.g. `Licensed to jane@example.com, order 1234`) injected into
+/// defined locations across the generated files, so storefronts can produce personalized
+/// copies from one builder.
+///
+/// A watermark is an [`EpubPlugin`]: register it on a clone of the builder of every copy.
+///
+/// ```rust
+/// use liber::epub::{EpubBuilder, MetadataBuilder, Watermark, WatermarkLocation};
+///
+/// let epub_builder = EpubBuilder::new(MetadataBuilder::title("My Book").build());
+///
+/// let watermark = Watermark::new("Licensed to jane@example.com, order 1234")
+///     .add_location(WatermarkLocation::Colophon("colophon.xhtml".to_string()));
+/// let copy = epub_builder.clone().add_plugin(&watermark);
+/// ```
+///
+/// The visible line can be styled in the stylesheet with the `watermark` class.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Watermark {
+    /// The buyer-specific string.
+    pub text: String,
+    /// The locations of the watermark. Defaults to the package metadata.
+    pub locations: Vec<WatermarkLocation>,
+}
+
+impl Watermark {
+    /// Creates the watermark `text`, declared in the package metadata.
+    #[must_use]
+    pub fn new<S: Into<String>>(text: S) -> Self {
+        Self {
+            text: text.into(),
+            locations: vec![WatermarkLocation::PackageMetadata],
+        }
+    }
+
+    /// Adds a **location** where the watermark is injected.
+    pub fn add_location(mut self, location: WatermarkLocation) -> Self {
+        if !self.locations.contains(&location) {
+            self.locations.push(location);
+        }
+        self
+    }
+
+    /// Sets the **locations** where the watermark is injected, replacing the default one.
+    pub fn locations(mut self, locations: Vec<WatermarkLocation>) -> Self {
+        self.locations = locations;
+        self
+    }
+
+    /// Checks whether the watermark is injected at `location`.
+    fn at(&self, location: &WatermarkLocation) -> bool {
+        self.locations.contains(location)
+    }
+
+    /// Gets the hidden `<meta>` element of the watermark.
+    fn meta(&self) -> String {
+        format!(
+            r#"<meta name="watermark" content="{}"/>"#,
+            escape(&self.text)
+        )
+    }
+}
+
+impl EpubPlugin for Watermark {
+    fn name(&self) -> &str {
+        "Watermark"
+    }
+
+    fn chapter_rendered(&self, filepath: &str, xhtml: &mut String) -> crate::Result<()> {
+        if self.at(&WatermarkLocation::ContentHeads) {
+            insert_before(xhtml, "</head>", &self.meta());
+        }
+
+        let filename = filepath.rsplit('/').next().unwrap_or(filepath);
+        let visible = self.at(&WatermarkLocation::EveryContent)
+            || self.at(&WatermarkLocation::Colophon(filename.to_string()));
+        if visible {
+            let line = format!(r#"<p class="watermark">{}</p>"#, escape(&self.text));
+            insert_before(xhtml, "</body>", &line);
+        }
+        Ok(())
+    }
+
+    fn manifest_assembled(&self, content_opf: &mut String) -> crate::Result<()> {
+        if self.at(&WatermarkLocation::PackageMetadata) {
+            insert_before(content_opf, "</metadata>", &self.meta());
+        }
+        Ok(())
+    }
+}
+
+/// Inserts `fragment` before the last occurrence of the closing tag `end`, if any.
+fn insert_before(xml: &mut String, end: &str, fragment: &str) {
+    if let Some(index) = xml.rfind(end) {
+        xml.insert_str(index, fragment);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::epub::{ContentBuilder, EpubBuilder, MetadataBuilder, ReferenceType};
+
+    #[test]
+    fn test_watermark() {
+        let content = |filename: &str| {
+            ContentBuilder::new(
+                b"<body><p>Text</p></body>",
+                ReferenceType::Text(filename.to_string()),
+            )
+            .filename(filename)
+            .build()
+        };
+        let epub_builder = EpubBuilder::new(MetadataBuilder::title("Title").build())
+            .pretty_print(false)
+            .add_content(content("c01.xhtml"))
+            .add_content(content("colophon.xhtml"));
+
+        let watermark = Watermark::new("Licensed to <jane@example.com>")
+            .add_location(WatermarkLocation::ContentHeads)
+            .add_location(WatermarkLocation::Colophon("colophon.xhtml".to_string()));
+        let files = epub_builder
+            .add_plugin(&watermark)
+            .files()
+            .unwrap()
+            .collect::<crate::Result<Vec<_>>>()
+            .unwrap();
+        let file = |filepath: &str| {
+            let file = files.iter().find(|file| file.filepath == filepath).unwrap();
+            String::from_utf8(file.bytes.as_slice().unwrap().to_vec()).unwrap()
+        };
+
+        let meta = r#"<meta name="watermark" content="Licensed to &lt;jane@example.com&gt;"/>"#;
+        let line = r#"<p class="watermark">Licensed to &lt;jane@example.com&gt;</p></body>"#;
+        assert!(file("OEBPS/content.opf").contains(&format!("{meta}</metadata>")));
+        assert!(file("OEBPS/c01.xhtml").contains(&format!("{meta}</head>")));
+        assert!(!file("OEBPS/c01.xhtml").contains(line));
+        assert!(file("OEBPS/colophon.xhtml").contains(line));
+
+        let watermark =
+            Watermark::new("Order 1234").locations(vec![WatermarkLocation::EveryContent]);
+        let mut xhtml = "<html><head></head><body><p>Text</p></body></html>".to_string();
+        watermark
+            .chapter_rendered("OEBPS/c01.xhtml", &mut xhtml)
+            .unwrap();
+        assert_eq!(
+            xhtml,
+            r#"<html><head></head><body><p>Text</p><p class="watermark">Order 1234</p></body></html>"#
+        );
+        let mut content_opf = "<metadata></metadata>".to_string();
+        watermark.manifest_assembled(&mut content_opf).unwrap();
+        assert_eq!(content_opf, "<metadata></metadata>");
+    }
+}
@@ -0,0 +1,1242 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io::{Read, Seek},
+};
+#[cfg(not(target_arch = "wasm32"))]
+use std::{fs::File, path::Path};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use zip::ZipArchive;
+
+use crate::{
+    epub::{
+        AgeRange, Audience, Certification, Conformance, Content, ContentBuilder, ContentReference,
+        EpubBuilder, Identifier, Language, Metadata, ReferenceType, Resource, ResourceItem,
+    },
+    input::xml::Element,
+    output::obfuscation,
+};
+
+/// A file of the package manifest read from an existing EPUB (an image, a font...).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EpubResource {
+    /// The path of the file relative to the package document (e.g. `images/map.png`).
+    pub filename: String,
+    /// The media type declared in the manifest.
+    pub media_type: String,
+    /// The content of the file, de-obfuscated if it was mangled with the IDPF font obfuscation algorithm.
+    pub bytes: Vec<u8>,
+    /// Whether the file was mangled with the IDPF font obfuscation algorithm.
+    pub obfuscated: bool,
+}
+
+impl EpubResource {
+    /// Gets the file as an in-memory [`Resource::Raw`], keeping its filename and media type,
+    /// and marked to be obfuscated again if it was.
+    pub fn resource(&self) -> ResourceItem<'_> {
+        let resource = ResourceItem::from(Resource::Raw(
+            &self.filename,
+            &self.bytes,
+            self.media_type.clone(),
+        ));
+        if self.obfuscated {
+            resource.obfuscate()
+        } else {
+            resource
+        }
+    }
+}
+
+/// A content document read from an existing EPUB, with its place in the table of contents.
+#[derive(Debug, Clone)]
+struct ParsedContent {
+    /// The path of the file relative to the package document (e.g. `c01.xhtml`).
+    filename: String,
+    /// The semantic type and title (from the guide, or else from the NCX) of the document.
+    reference_type: ReferenceType,
+    /// The NCX label, when it differs from the guide title.
+    toc_label: Option<String>,
+    /// The `<body>` element of the document.
+    body: Vec<u8>,
+    /// The NCX entries pointing to anchors inside the document.
+    content_references: Vec<ContentReference>,
+    /// The nested content documents.
+    subcontents: Vec<ParsedContent>,
+}
+
+impl ParsedContent {
+    /// Rebuilds the [`Content`] model, borrowing the body.
+    fn content(&self) -> Content<'_> {
+        let mut builder = ContentBuilder::new(&self.body, self.reference_type.clone())
+            .filename(self.filename.clone());
+
+        if let Some(ref toc_label) = self.toc_label {
+            builder = builder.toc_label(toc_label.clone());
+        }
+
+        if !self.content_references.is_empty() {
+            builder = builder.add_content_references(self.content_references.clone());
+        }
+
+        if !self.subcontents.is_empty() {
+            builder = builder.add_children(self.subcontents.iter().map(Self::content));
+        }
+
+        builder.build()
+    }
+}
+
+/// An entry of the NCX `navMap`.
+#[derive(Debug)]
+struct NavPoint {
+    /// The text of the `navLabel`.
+    label: String,
+    /// The referenced file, relative to the package document.
+    file: String,
+    /// The referenced anchor inside `file`, if any.
+    fragment: Option<String>,
+    /// The nested entries.
+    children: Vec<NavPoint>,
+}
+
+/// An existing EPUB file, parsed back into the model used to build one.
+///
+/// The reader opens the archive, locates the package document through `META-INF/container.xml`,
+/// and reads the metadata, the manifest, the spine, the guide and the NCX table of contents.
+/// All files are loaded into memory, so the archive is not needed afterwards.
+///
+/// # Example
+/// ```no_run
+/// use liber::EpubReader;
+///
+/// let reader = EpubReader::open("book.epub")?;
+/// println!("{} has {} contents", reader.metadata().title, reader.contents().len());
+/// # Ok::<(), liber::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct EpubReader {
+    /// The metadata of the package document.
+    metadata: Metadata,
+    /// The first stylesheet of the manifest, if any.
+    stylesheet: Option<Vec<u8>>,
+    /// The cover image, if declared.
+    cover_image: Option<EpubResource>,
+    /// The other files of the manifest, in manifest order.
+    resources: Vec<EpubResource>,
+    /// The content documents, structured after the NCX table of contents.
+    contents: Vec<ParsedContent>,
+}
+
+impl EpubReader {
+    /// Opens and parses the EPUB file at `path`.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read, is not a zip archive, or is not a valid EPUB
+    /// (see [`EpubReader::from_reader`]).
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn open<P: AsRef<Path>>(path: P) -> crate::Result<Self> {
+        Self::from_reader(File::open(path)?)
+    }
+
+    /// Parses an EPUB archive from any seekable reader (e.g. a `Cursor` over its bytes).
+    ///
+    /// # Errors
+    /// Returns a [`crate::Error::Zip`] if the archive or one of its files cannot be read, a
+    /// [`crate::Error::XmlParser`] if a package file is not well formed, or a
+    /// [`crate::Error::InvalidEpub`] if a mandatory part (container, package document, title) is missing.
+    pub fn from_reader<R: Read + Seek>(reader: R) -> crate::Result<Self> {
+        let mut archive = ZipArchive::new(reader)?;
+
+        let (base, package) = read_package(&mut archive)?;
+        let metadata_element = package
+            .child("metadata")
+            .ok_or_else(|| invalid("the package document has no metadata"))?;
+        let metadata = parse_metadata(metadata_element, package.attribute("unique-identifier"))?;
+
+        // Archive paths of the obfuscated files, and the key to restore them
+        let obfuscated_files = match archive.index_for_name("META-INF/encryption.xml") {
+            Some(_) => {
+                let encryption =
+                    Element::parse(&read_string(&mut archive, "META-INF/encryption.xml")?)?;
+                encryption
+                    .children_named("EncryptedData")
+                    .filter(|data| {
+                        data.child("EncryptionMethod")
+                            .and_then(|method| method.attribute("Algorithm"))
+                            == Some(obfuscation::ALGORITHM)
+                    })
+                    .filter_map(|data| {
+                        let uri = data
+                            .child("CipherData")?
+                            .child("CipherReference")?
+                            .attribute("URI")?;
+                        Some(percent_decode(uri))
+                    })
+                    .collect::<HashSet<_>>()
+            }
+            None => HashSet::new(),
+        };
+        let key = obfuscation::key(
+            unique_identifier(metadata_element, package.attribute("unique-identifier"))
+                .map(|identifier| identifier.text.as_str())
+                .unwrap_or_default(),
+        );
+
+        // Manifest items as id -> (href, media type, properties)
+        let manifest = package
+            .child("manifest")
+            .map(|manifest| {
+                manifest
+                    .children_named("item")
+                    .filter_map(|item| {
+                        Some((
+                            item.attribute("id")?.to_string(),
+                            (
+                                percent_decode(item.attribute("href")?),
+                                item.attribute("media-type").unwrap_or_default().to_string(),
+                                item.attribute("properties").unwrap_or_default().to_string(),
+                            ),
+                        ))
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        let href_of = |id: &str| {
+            manifest
+                .iter()
+                .find(|(item_id, _)| item_id == id)
+                .map(|(_, (href, ..))| href.clone())
+        };
+
+        let spine = package.child("spine");
+        let spine_files = spine
+            .map(|spine| {
+                spine
+                    .children_named("itemref")
+                    .filter_map(|itemref| href_of(itemref.attribute("idref")?))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        let ncx_file = spine
+            .and_then(|spine| spine.attribute("toc"))
+            .and_then(href_of)
+            .or_else(|| {
+                manifest
+                    .iter()
+                    .find(|(_, (_, media_type, _))| media_type == "application/x-dtbncx+xml")
+                    .map(|(_, (href, ..))| href.clone())
+            });
+
+        let cover_file = cover_item(&package)
+            .and_then(|item| item.attribute("href"))
+            .map(percent_decode);
+
+        let stylesheet_file = manifest
+            .iter()
+            .find(|(_, (_, media_type, _))| media_type == "text/css")
+            .map(|(_, (href, ..))| href.clone());
+
+        // Guide references as href -> type
+        let guide = package
+            .child("guide")
+            .map(|guide| {
+                guide
+                    .children_named("reference")
+                    .filter_map(|reference| {
+                        Some((
+                            percent_decode(reference.attribute("href")?.split('#').next()?),
+                            (
+                                reference.attribute("type")?.to_string(),
+                                reference.attribute("title").unwrap_or_default().to_string(),
+                            ),
+                        ))
+                    })
+                    .collect::<HashMap<_, _>>()
+            })
+            .unwrap_or_default();
+
+        let nav_points = match ncx_file {
+            Some(ref ncx_file) => {
+                let ncx =
+                    Element::parse(&read_string(&mut archive, &format!("{base}{ncx_file}"))?)?;
+                ncx.child("navMap")
+                    .map(|nav_map| parse_nav_points(nav_map, ncx_file))
+                    .unwrap_or_default()
+            }
+            None => Vec::new(),
+        };
+
+        let mut bodies = HashMap::new();
+        for file in &spine_files {
+            let xhtml = read_string(&mut archive, &format!("{base}{file}"))?;
+            bodies.insert(file.clone(), body(&xhtml));
+        }
+
+        let mut used = HashSet::new();
+        let mut contents = contents_from_nav_points(&nav_points, &mut bodies, &guide, &mut used);
+
+        // Documents of the spine missing from the NCX become top-level contents
+        for file in &spine_files {
+            if let Some(body) = bodies.remove(file) {
+                let (ref_type, title) = guide
+                    .get(file)
+                    .cloned()
+                    .unwrap_or(("text".to_string(), file.clone()));
+                contents.push(ParsedContent {
+                    filename: file.clone(),
+                    reference_type: ReferenceType::from_type_and_title(&ref_type, title),
+                    toc_label: None,
+                    body,
+                    content_references: Vec::new(),
+                    subcontents: Vec::new(),
+                });
+            }
+        }
+        let spine_index = |file: &str| spine_files.iter().position(|spine_file| spine_file == file);
+        contents.sort_by_key(|content| spine_index(&content.filename));
+
+        let mut stylesheet = None;
+        let mut cover_image = None;
+        let mut resources = Vec::new();
+        for (_, (href, media_type, _)) in &manifest {
+            if spine_files.contains(href) || Some(href) == ncx_file.as_ref() {
+                continue;
+            }
+
+            let path = format!("{base}{href}");
+            let mut bytes = read_bytes(&mut archive, &path)?;
+            let obfuscated = obfuscated_files.contains(&path);
+            if obfuscated {
+                obfuscation::obfuscate(&key, &mut bytes);
+            }
+
+            if stylesheet.is_none() && Some(href) == stylesheet_file.as_ref() {
+                stylesheet = Some(bytes);
+                continue;
+            }
+
+            let resource = EpubResource {
+                filename: href.clone(),
+                media_type: media_type.clone(),
+                bytes,
+                obfuscated,
+            };
+            if cover_image.is_none() && Some(href) == cover_file.as_ref() {
+                cover_image = Some(resource);
+            } else {
+                resources.push(resource);
+            }
+        }
+
+        Ok(Self {
+            metadata,
+            stylesheet,
+            cover_image,
+            resources,
+            contents,
+        })
+    }
+
+    /// Gets the metadata of the book.
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Gets the metadata of the book mutably, e.g. to fix a typo before [`EpubReader::to_builder`].
+    pub fn metadata_mut(&mut self) -> &mut Metadata {
+        &mut self.metadata
+    }
+
+    /// Gets the stylesheet of the book: the first `text/css` file of the manifest.
+    pub fn stylesheet(&self) -> Option<&[u8]> {
+        self.stylesheet.as_deref()
+    }
+
+    /// Gets the cover image, declared by the `cover` meta (EPUB 2) or the `cover-image`
+    /// manifest property (EPUB 3).
+    pub fn cover_image(&self) -> Option<&EpubResource> {
+        self.cover_image.as_ref()
+    }
+
+    /// Gets the other files of the manifest (images, fonts, audio...), in manifest order.
+    ///
+    /// The content documents, the NCX, the stylesheet and the cover image are not included.
+    pub fn resources(&self) -> &[EpubResource] {
+        &self.resources
+    }
+
+    /// Rebuilds the content documents in spine order, nested after the NCX table of contents.
+    ///
+    /// Each [`Content`] keeps its original filename (subdirectories included, e.g.
+    /// `Text/ch1.xhtml`, so relative links between files still resolve) and holds the `<body>`
+    /// of the document; NCX entries pointing to anchors become [`ContentReference`]s, and the
+    /// reference type comes from the guide.
+    pub fn contents(&self) -> Vec<Content<'_>> {
+        self.contents.iter().map(ParsedContent::content).collect()
+    }
+
+    /// Creates an [`EpubBuilder`] holding the whole book (metadata, stylesheet, cover image,
+    /// resources and contents), borrowing the files read.
+    ///
+    /// The builder API can then be used to edit the book before writing it back out, e.g.
+    /// replacing the cover with [`EpubBuilder::cover_image`] or adding contents. To remove or
+    /// reorder contents, build a new [`EpubBuilder`] from [`EpubReader::contents`] instead.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use liber::EpubReader;
+    ///
+    /// let mut reader = EpubReader::open("book.epub")?;
+    /// reader.metadata_mut().title = "The Fixed Title".to_string();
+    /// reader.to_builder().create(&mut std::fs::File::create("fixed.epub")?)?;
+    /// # Ok::<(), liber::Error>(())
+    /// ```
+    pub fn to_builder(&self) -> EpubBuilder<'_> {
+        let mut builder = EpubBuilder::new(self.metadata.clone());
+
+        if let Some(ref stylesheet) = self.stylesheet {
+            builder = builder.stylesheet(stylesheet);
+        }
+
+        if let Some(ref cover_image) = self.cover_image {
+            builder = builder.cover_image_item(cover_image.resource());
+        }
+
+        if !self.resources.is_empty() {
+            builder = builder.add_resources(self.resources.iter().map(EpubResource::resource));
+        }
+
+        if !self.contents.is_empty() {
+            builder = builder.add_contents(self.contents());
+        }
+
+        builder
+    }
+}
+
+/// Reads the metadata of the EPUB file at `path`, without loading its contents nor resources.
+///
+/// Only `META-INF/container.xml` and the package document are decompressed, which makes this
+/// much faster than [`EpubReader::open`] for library or catalog tooling.
+///
+/// # Example
+/// ```no_run
+/// let metadata = liber::inspect("book.epub")?;
+/// println!("{} by {}", metadata.title, metadata.creator.unwrap_or_default());
+/// # Ok::<(), liber::Error>(())
+/// ```
+///
+/// # Errors
+/// Returns the same errors as [`EpubReader::open`].
+#[cfg(not(target_arch = "wasm32"))]
+pub fn inspect<P: AsRef<Path>>(path: P) -> crate::Result<Metadata> {
+    let mut archive = ZipArchive::new(File::open(path)?)?;
+    let (_, package) = read_package(&mut archive)?;
+    let metadata = package
+        .child("metadata")
+        .ok_or_else(|| invalid("the package document has no metadata"))?;
+
+    parse_metadata(metadata, package.attribute("unique-identifier"))
+}
+
+/// Reads the cover image of the EPUB file at `path`, without loading its contents nor the
+/// other resources.
+///
+/// The cover is located through the `cover` meta of the package metadata (EPUB 2) or the
+/// `cover-image` property of a manifest item (EPUB 3).
+///
+/// # Example
+/// ```no_run
+/// if let Some(cover) = liber::extract_cover("book.epub")? {
+///     std::fs::write(format!("cover-{}", cover.filename.replace('/', "_")), cover.bytes)?;
+/// }
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// # Returns
+/// Returns `None` if the EPUB does not declare a cover image.
+///
+/// # Errors
+/// Returns the same errors as [`EpubReader::open`], or a [`crate::Error::Zip`] if the declared
+/// cover image is missing from the archive.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn extract_cover<P: AsRef<Path>>(path: P) -> crate::Result<Option<EpubResource>> {
+    let mut archive = ZipArchive::new(File::open(path)?)?;
+    let (base, package) = read_package(&mut archive)?;
+
+    let Some((href, media_type)) = cover_item(&package).and_then(|item| {
+        Some((
+            percent_decode(item.attribute("href")?),
+            item.attribute("media-type").unwrap_or_default().to_string(),
+        ))
+    }) else {
+        return Ok(None);
+    };
+
+    Ok(Some(EpubResource {
+        bytes: read_bytes(&mut archive, &format!("{base}{href}"))?,
+        filename: href,
+        media_type,
+        obfuscated: false,
+    }))
+}
+
+/// Finds the manifest `<item>` of the cover image, declared by the `cover` meta (whose content
+/// is the item id, or its href for some producers) or by the EPUB 3 `cover-image` property.
+fn cover_item(package: &Element) -> Option<&Element> {
+    let manifest = package.child("manifest")?;
+
+    package
+        .child("metadata")
+        .and_then(|metadata| {
+            metadata
+                .children_named("meta")
+                .find(|meta| meta.attribute("name") == Some("cover"))
+        })
+        .and_then(|meta| meta.attribute("content"))
+        .and_then(|content| {
+            manifest.children_named("item").find(|item| {
+                item.attribute("id") == Some(content) || item.attribute("href") == Some(content)
+            })
+        })
+        .or_else(|| {
+            manifest.children_named("item").find(|item| {
+                item.attribute("properties").is_some_and(|properties| {
+                    properties.split_whitespace().any(|p| p == "cover-image")
+                })
+            })
+        })
+}
+
+/// Locates the package document through `META-INF/container.xml` and parses it.
+///
+/// Returns the directory of the package document inside the archive (e.g. `OEBPS/`), which
+/// the manifest hrefs are relative to, and its root `<package>` element.
+fn read_package<R: Read + Seek>(archive: &mut ZipArchive<R>) -> crate::Result<(String, Element)> {
+    let container = Element::parse(&read_string(archive, "META-INF/container.xml")?)?;
+    let opf_path = container
+        .child("rootfiles")
+        .and_then(|rootfiles| rootfiles.child("rootfile"))
+        .and_then(|rootfile| rootfile.attribute("full-path"))
+        .ok_or_else(|| invalid("container.xml does not declare a package document"))?
+        .to_string();
+    let base = opf_path
+        .rsplit_once('/')
+        .map(|(base, _)| format!("{base}/"))
+        .unwrap_or_default();
+
+    Ok((base, Element::parse(&read_string(archive, &opf_path)?)?))
+}
+
+/// Creates a [`crate::Error::InvalidEpub`] with `message`.
+fn invalid(message: &str) -> crate::Error {
+    crate::Error::InvalidEpub(message.to_string())
+}
+
+/// Reads the file `name` of the archive.
+pub(super) fn read_bytes<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    name: &str,
+) -> crate::Result<Vec<u8>> {
+    let mut file = archive.by_name(name)?;
+    let mut bytes = Vec::with_capacity(file.size() as usize);
+    file.read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Reads the file `name` of the archive as UTF-8 text.
+pub(super) fn read_string<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    name: &str,
+) -> crate::Result<String> {
+    Ok(String::from_utf8(read_bytes(archive, name)?)?)
+}
+
+/// Decodes the `%XX` escapes of an URL path (e.g. `my%20file.xhtml`).
+pub(super) fn percent_decode(href: &str) -> String {
+    let bytes = href.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(byte) = href
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            decoded.push(byte);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).unwrap_or_else(|_| href.to_string())
+}
+
+/// Extracts the `<body>` element of an XHTML document, or the whole document if it has none.
+fn body(xhtml: &str) -> Vec<u8> {
+    match (xhtml.find("<body"), xhtml.rfind("</body>")) {
+        (Some(start), Some(end)) if start < end => xhtml[start..end + "</body>".len()].into(),
+        _ => xhtml.trim().into(),
+    }
+}
+
+/// Reads the Dublin Core elements of the package `<metadata>`.
+///
+/// # Arguments
+/// * `metadata`: The `<metadata>` element.
+/// * `unique_identifier`: The id of the `<dc:identifier>` declared as the package unique identifier.
+fn parse_metadata(metadata: &Element, unique_identifier: Option<&str>) -> crate::Result<Metadata> {
+    let text = |name: &str| {
+        metadata
+            .child(name)
+            .map(|element| element.text.clone())
+            .filter(|text| !text.is_empty())
+    };
+
+    let title = text("title").ok_or_else(|| invalid("the package metadata has no title"))?;
+
+    let identifier = self::unique_identifier(metadata, unique_identifier).map(|identifier| {
+        let scheme = identifier.attribute("scheme").unwrap_or_default();
+        let value = identifier.text.as_str();
+        if let Some(isbn) = value.strip_prefix("urn:isbn:") {
+            Identifier::ISBN(isbn.to_string())
+        } else if scheme.eq_ignore_ascii_case("isbn") {
+            Identifier::ISBN(value.to_string())
+        } else {
+            Identifier::UUID(value.strip_prefix("urn:uuid:").unwrap_or(value).to_string())
+        }
+    });
+
+    let date = metadata
+        .children_named("date")
+        .find(|date| date.attribute("event") == Some("publication"))
+        .or_else(|| metadata.child("date"))
+        .and_then(|date| parse_date(&date.text));
+
+    Ok(Metadata {
+        title,
+        language: text("language")
+            .and_then(|code| Language::from_code(&code))
+            .unwrap_or_default(),
+        generated_identifier: identifier.is_none(),
+        identifier: identifier.unwrap_or_default(),
+        creator: text("creator"),
+        contributor: text("contributor"),
+        publisher: text("publisher"),
+        imprint: meta(metadata, "schema:publisherImprint"),
+        publication_place: meta(metadata, "schema:locationCreated"),
+        date,
+        subject: text("subject"),
+        description: text("description"),
+        audience: meta(metadata, "dcterms:audience").map(|audience| Audience::parse(&audience)),
+        education_level: meta(metadata, "dcterms:educationLevel"),
+        age_range: meta(metadata, "schema:typicalAgeRange").and_then(|r| AgeRange::parse(&r)),
+        conforms_to: meta(metadata, "dcterms:conformsTo").map(|c| Conformance::parse(&c)),
+        certification: meta(metadata, "a11y:certifiedBy").map(|certified_by| Certification {
+            certified_by,
+            credential: meta(metadata, "a11y:certifierCredential"),
+            report: meta(metadata, "a11y:certifierReport"),
+        }),
+        generated_date: false,
+    })
+}
+
+/// Gets the value of the `<meta>` named `name`: its `content` attribute (EPUB 2), or its text
+/// when declared as a `property` (EPUB 3).
+fn meta(metadata: &Element, name: &str) -> Option<String> {
+    metadata.children_named("meta").find_map(|meta| {
+        if meta.attribute("name") == Some(name) {
+            meta.attribute("content").map(str::to_string)
+        } else if meta.attribute("property") == Some(name) {
+            Some(meta.text.clone())
+        } else {
+            None
+        }
+    })
+}
+
+/// Gets the `<dc:identifier>` declared as the package unique identifier, or the first one.
+fn unique_identifier<'a>(metadata: &'a Element, id: Option<&str>) -> Option<&'a Element> {
+    metadata
+        .children_named("identifier")
+        .find(|identifier| identifier.attribute("id") == id)
+        .or_else(|| metadata.child("identifier"))
+}
+
+/// Parses a W3CDTF date (`YYYY`, `YYYY-MM`, `YYYY-MM-DD` or a full RFC 3339 timestamp).
+fn parse_date(date: &str) -> Option<DateTime<Utc>> {
+    if let Ok(date_time) = DateTime::parse_from_rfc3339(date) {
+        return Some(date_time.with_timezone(&Utc));
+    }
+
+    let mut parts = date.splitn(3, '-').map(str::parse::<u32>);
+    let year = parts.next()?.ok()?;
+    let month = parts.next().unwrap_or(Ok(1)).ok()?;
+    let day = parts.next().unwrap_or(Ok(1)).ok()?;
+
+    Some(
+        NaiveDate::from_ymd_opt(year.try_into().ok()?, month, day)?
+            .and_hms_opt(0, 0, 0)?
+            .and_utc(),
+    )
+}
+
+/// Reads the `<navPoint>` children of `parent`, recursively.
+///
+/// The `src` of each entry is resolved against the directory of the NCX (`ncx_file`), so it
+/// becomes relative to the package document like the manifest hrefs.
+fn parse_nav_points(parent: &Element, ncx_file: &str) -> Vec<NavPoint> {
+    let ncx_base = ncx_file
+        .rsplit_once('/')
+        .map(|(base, _)| format!("{base}/"))
+        .unwrap_or_default();
+
+    parent
+        .children_named("navPoint")
+        .filter_map(|nav_point| {
+            let src = nav_point.child("content")?.attribute("src")?;
+            let (file, fragment) = match src.split_once('#') {
+                Some((file, fragment)) => (file, Some(fragment.to_string())),
+                None => (src, None),
+            };
+
+            Some(NavPoint {
+                label: nav_point
+                    .child("navLabel")
+                    .and_then(|nav_label| nav_label.child("text"))
+                    .map(|text| text.text.clone())
+                    .unwrap_or_default(),
+                file: format!("{ncx_base}{}", percent_decode(file)),
+                fragment,
+                children: parse_nav_points(nav_point, ncx_file),
+            })
+        })
+        .collect()
+}
+
+/// Builds the content tree from the NCX entries.
+///
+/// The first entry pointing to a document of the spine creates its content; nested entries
+/// pointing to anchors of the same document become content references, and nested entries
+/// pointing to other documents become subcontents. Entries pointing to documents already used
+/// (or outside the spine) are skipped, keeping their children.
+///
+/// # Arguments
+/// * `nav_points`: The NCX entries of the current level.
+/// * `bodies`: The bodies of the spine documents not used yet, by file.
+/// * `guide`: The guide references as file -> (type, title).
+/// * `used`: The documents already used.
+fn contents_from_nav_points(
+    nav_points: &[NavPoint],
+    bodies: &mut HashMap<String, Vec<u8>>,
+    guide: &HashMap<String, (String, String)>,
+    used: &mut HashSet<String>,
+) -> Vec<ParsedContent> {
+    let mut contents = Vec::new();
+
+    for nav_point in nav_points {
+        let Some(body) = bodies.remove(&nav_point.file) else {
+            contents.extend(contents_from_nav_points(
+                &nav_point.children,
+                bodies,
+                guide,
+                used,
+            ));
+            continue;
+        };
+        used.insert(nav_point.file.clone());
+
+        let (same_file, other_files): (Vec<_>, Vec<_>) = nav_point
+            .children
+            .iter()
+            .partition(|child| child.file == nav_point.file && child.fragment.is_some());
+
+        let (ref_type, title) = match guide.get(&nav_point.file) {
+            Some((ref_type, title)) if !title.is_empty() => (ref_type.as_str(), title.as_str()),
+            Some((ref_type, _)) => (ref_type.as_str(), nav_point.label.as_str()),
+            None => ("text", nav_point.label.as_str()),
+        };
+
+        let mut subcontents = Vec::new();
+        for child in other_files {
+            subcontents.extend(contents_from_nav_points(
+                std::slice::from_ref(child),
+                bodies,
+                guide,
+                used,
+            ));
+        }
+
+        contents.push(ParsedContent {
+            filename: nav_point.file.clone(),
+            reference_type: ReferenceType::from_type_and_title(ref_type, title.to_string()),
+            toc_label: (title != nav_point.label).then(|| nav_point.label.clone()),
+            body,
+            content_references: same_file
+                .into_iter()
+                .map(|child| content_reference(child, &nav_point.file))
+                .collect(),
+            subcontents,
+        });
+    }
+
+    contents
+}
+
+/// Creates the content reference of an NCX entry pointing to an anchor of `file`, with the
+/// nested entries pointing to anchors of the same file as children.
+fn content_reference(nav_point: &NavPoint, file: &str) -> ContentReference {
+    let mut reference = ContentReference::new(nav_point.label.clone());
+    if let Some(ref fragment) = nav_point.fragment {
+        reference = reference.id(fragment.clone());
+    }
+
+    let children = nav_point
+        .children
+        .iter()
+        .filter(|child| child.file == file && child.fragment.is_some())
+        .map(|child| content_reference(child, file))
+        .collect::<Vec<_>>();
+
+    if children.is_empty() {
+        reference
+    } else {
+        reference.add_children(children)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use chrono::TimeZone;
+    use zip::{ZipWriter, write::SimpleFileOptions};
+
+    use super::*;
+    use crate::epub::{
+        AgeRange, Audience, Certification, Conformance, EpubBuilder, FontType, ImageType,
+        MetadataBuilder, Resource, WcagLevel, WcagVersion, XhtmlOptions,
+    };
+
+    #[test]
+    fn test_epub_reader_round_trip() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let cover = temp_dir.path().join("cover.png");
+        let font = temp_dir.path().join("font.ttf");
+        std::fs::write(&cover, b"png").unwrap();
+        std::fs::write(&font, b"ttf").unwrap();
+
+        let bytes = EpubBuilder::new(
+            MetadataBuilder::title("Pride & Prejudice")
+                .creator("Jane Austen")
+                .language(Language::French)
+                .identifier(Identifier::ISBN("9780000000000".to_string()))
+                .date(Utc.with_ymd_and_hms(1813, 1, 28, 0, 0, 0).unwrap())
+                .imprint("Egerton")
+                .publication_place("London")
+                .audience(Audience::YoungAdult)
+                .education_level("Secondary school")
+                .age_range(AgeRange::from_age(14))
+                .build(),
+        )
+        .stylesheet(b"body {}")
+        .cover_image(&cover, ImageType::Png)
+        .add_resource(Resource::Font(&font, FontType::TrueType))
+        .add_content(
+            ContentBuilder::new(
+                br#"<body><h1>Chapter 1</h1><h2 id="s1">Section 1.1</h2></body>"#,
+                ReferenceType::Text("Chapter 1".to_string()),
+            )
+            .add_content_reference(ContentReference::new("Section 1.1").id("s1"))
+            .add_child(
+                ContentBuilder::new(
+                    b"<body><h1>Chapter 1.1</h1></body>",
+                    ReferenceType::Text("Chapter 1.1".to_string()),
+                )
+                .build(),
+            )
+            .build(),
+        )
+        .add_content(
+            ContentBuilder::new(
+                b"<body><h1>Notes</h1></body>",
+                ReferenceType::Notes("Notes".to_string()),
+            )
+            .build(),
+        )
+        .create_to_vec()
+        .unwrap();
+
+        let reader = EpubReader::from_reader(Cursor::new(bytes)).unwrap();
+
+        let metadata = reader.metadata();
+        assert_eq!(metadata.title, "Pride & Prejudice");
+        assert_eq!(metadata.creator.as_deref(), Some("Jane Austen"));
+        assert!(matches!(metadata.language, Language::French));
+        assert!(
+            matches!(metadata.identifier, Identifier::ISBN(ref isbn) if isbn == "9780000000000")
+        );
+        assert_eq!(
+            metadata.date,
+            Some(Utc.with_ymd_and_hms(1813, 1, 28, 0, 0, 0).unwrap())
+        );
+        assert_eq!(metadata.imprint.as_deref(), Some("Egerton"));
+        assert_eq!(metadata.publication_place.as_deref(), Some("London"));
+        assert_eq!(metadata.audience, Some(Audience::YoungAdult));
+        assert_eq!(
+            metadata.education_level.as_deref(),
+            Some("Secondary school")
+        );
+        assert_eq!(metadata.age_range, Some(AgeRange::from_age(14)));
+
+        assert_eq!(reader.stylesheet(), Some(b"body {}".as_slice()));
+        assert_eq!(
+            reader.cover_image(),
+            Some(&EpubResource {
+                filename: "cover.png".to_string(),
+                media_type: "image/png".to_string(),
+                bytes: b"png".to_vec(),
+                obfuscated: false,
+            })
+        );
+        assert_eq!(reader.resources().len(), 1);
+        assert_eq!(reader.resources()[0].filename, "font.ttf");
+
+        let contents = reader.contents();
+        assert_eq!(contents.len(), 2);
+        assert_eq!(contents[0].filename(0), "c01.xhtml");
+        assert_eq!(contents[0].title(), "Chapter 1");
+        let content_references = contents[0].content_references.as_ref().unwrap();
+        assert_eq!(content_references[0].title, "Section 1.1");
+        assert_eq!(
+            content_references[0].reference_name("c01.xhtml", 1),
+            "c01.xhtml#s1"
+        );
+        let subcontents = contents[0].subcontents.as_ref().unwrap();
+        assert_eq!(subcontents[0].filename(0), "c02.xhtml");
+        assert!(
+            matches!(contents[1].reference_type, ReferenceType::Notes(ref title) if title == "Notes")
+        );
+
+        let file = contents[1]
+            .render(
+                3,
+                XhtmlOptions {
+                    add_stylesheet: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert!(file.bytes.contains("<h1>Notes</h1>"));
+        assert!(file.bytes.ends_with("</body></html>"));
+    }
+
+    #[test]
+    fn test_epub_reader_to_builder() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let font = temp_dir.path().join("font.otf");
+        let new_cover = temp_dir.path().join("new-cover.jpg");
+        std::fs::write(&font, b"original font bytes").unwrap();
+        std::fs::write(&new_cover, b"jpg").unwrap();
+
+        let bytes = EpubBuilder::new(MetadataBuilder::title("Teh Title").build())
+            .add_resource(Resource::Font(&font, FontType::OpenType).obfuscate())
+            .add_content(
+                ContentBuilder::new(
+                    b"<body><h1>Chapter 1</h1></body>",
+                    ReferenceType::Text("Chapter 1".to_string()),
+                )
+                .build(),
+            )
+            .add_content(
+                ContentBuilder::new(
+                    b"<body><h1>The Road</h1></body>",
+                    ReferenceType::Text("Text".to_string()),
+                )
+                .toc_label("Chapter Two: The Road")
+                .build(),
+            )
+            .create_to_vec()
+            .unwrap();
+
+        let mut reader = EpubReader::from_reader(Cursor::new(bytes)).unwrap();
+        assert!(reader.resources()[0].obfuscated);
+        assert_eq!(reader.resources()[0].bytes, b"original font bytes");
+
+        reader.metadata_mut().title = "The Title".to_string();
+        let bytes = reader
+            .to_builder()
+            .cover_image(&new_cover, ImageType::Jpg)
+            .create_to_vec()
+            .unwrap();
+
+        let edited = EpubReader::from_reader(Cursor::new(bytes)).unwrap();
+        assert_eq!(edited.metadata().title, "The Title");
+        assert_eq!(
+            String::from(&edited.metadata().identifier),
+            String::from(&reader.metadata().identifier)
+        );
+        assert_eq!(edited.cover_image().unwrap().filename, "new-cover.jpg");
+        assert_eq!(edited.resources(), reader.resources());
+        assert_eq!(edited.contents().len(), 2);
+        assert_eq!(edited.contents()[0].title(), "Chapter 1");
+        assert_eq!(edited.contents()[0].toc_label(), "Chapter 1");
+        assert_eq!(edited.contents()[1].title(), "Text");
+        assert_eq!(edited.contents()[1].toc_label(), "Chapter Two: The Road");
+    }
+
+    #[test]
+    fn test_epub_reader_without_ncx() {
+        let mut bytes = Vec::new();
+        let mut zip = ZipWriter::new(Cursor::new(&mut bytes));
+        let options = SimpleFileOptions::default();
+        let files = [
+            (
+                "META-INF/container.xml",
+                r#"<container><rootfiles><rootfile full-path="book/package.opf"/></rootfiles></container>"#,
+            ),
+            (
+                "book/package.opf",
+                r#"<package version="3.0" unique-identifier="uid"><metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+                <dc:identifier id="uid">urn:uuid:1234</dc:identifier><dc:title>Untitled</dc:title><dc:language>pt-BR</dc:language><dc:date>2020</dc:date></metadata>
+                <manifest><item id="t" href="text/one%20two.xhtml" media-type="application/xhtml+xml"/><item id="c" href="img/c.jpg" media-type="image/jpeg" properties="cover-image"/></manifest>
+                <spine><itemref idref="t"/></spine></package>"#,
+            ),
+            (
+                "book/text/one two.xhtml",
+                r#"<html><head><title>One</title></head><body class="x"><p>One</p></body></html>"#,
+            ),
+            ("book/img/c.jpg", "jpg"),
+        ];
+        for (name, content) in files {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+
+        let reader = EpubReader::from_reader(Cursor::new(bytes)).unwrap();
+        assert!(
+            matches!(reader.metadata().identifier, Identifier::UUID(ref uuid) if uuid == "1234")
+        );
+        assert!(matches!(reader.metadata().language, Language::Portuguese));
+        assert_eq!(
+            reader.metadata().date,
+            Some(Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap())
+        );
+        assert_eq!(reader.cover_image().unwrap().filename, "img/c.jpg");
+        assert!(reader.resources().is_empty());
+
+        let contents = reader.contents();
+        assert_eq!(contents.len(), 1);
+        assert_eq!(contents[0].filename(0), "text/one two.xhtml");
+        let file = contents[0].render(1, XhtmlOptions::default()).unwrap();
+        assert!(
+            file.bytes
+                .ends_with(r#"<body class="x"><p>One</p></body></html>"#)
+        );
+    }
+
+    /// Writes an EPUB laid out in subdirectories, like most EPUB editors do:
+    /// `OEBPS/Text/ch1.xhtml` linking `OEBPS/Styles/main.css`.
+    fn nested_epub() -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut zip = ZipWriter::new(Cursor::new(&mut bytes));
+        let options = SimpleFileOptions::default();
+        let files = [
+            (
+                "META-INF/container.xml",
+                r#"<container><rootfiles><rootfile full-path="OEBPS/content.opf"/></rootfiles></container>"#,
+            ),
+            (
+                "OEBPS/content.opf",
+                r#"<package version="2.0" unique-identifier="uid"><metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+                <dc:identifier id="uid">urn:uuid:1234</dc:identifier><dc:title>Nested</dc:title><dc:language>en</dc:language></metadata>
+                <manifest><item id="s" href="Styles/main.css" media-type="text/css"/><item id="t" href="Text/ch1.xhtml" media-type="application/xhtml+xml"/></manifest>
+                <spine><itemref idref="t"/></spine></package>"#,
+            ),
+            ("OEBPS/Styles/main.css", "p { margin: 0; }"),
+            (
+                "OEBPS/Text/ch1.xhtml",
+                r#"<html><head><title>One</title><link href="../Styles/main.css" rel="stylesheet" type="text/css"/></head><body><p>One</p></body></html>"#,
+            ),
+        ];
+        for (name, content) in files {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_epub_reader_subdirectories() {
+        let reader = EpubReader::from_reader(Cursor::new(nested_epub())).unwrap();
+        assert_eq!(reader.stylesheet(), Some(b"p { margin: 0; }".as_slice()));
+
+        let contents = reader.contents();
+        assert_eq!(contents[0].filename(0), "Text/ch1.xhtml");
+        let file = contents[0]
+            .render(
+                1,
+                XhtmlOptions {
+                    add_stylesheet: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(file.filepath, "OEBPS/Text/ch1.xhtml");
+        assert!(
+            file.bytes
+                .contains(r#"<link href="../style.css" rel="stylesheet" type="text/css"/>"#)
+        );
+    }
+
+    #[test]
+    fn test_epub_reader_to_builder_subdirectories() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("nested.epub");
+        std::fs::write(&path, nested_epub()).unwrap();
+
+        let mut reader = EpubReader::open(&path).unwrap();
+        reader.metadata_mut().title = "Nested, Edited".to_string();
+        let bytes = reader.to_builder().create_to_vec().unwrap();
+
+        let edited = EpubReader::from_reader(Cursor::new(&bytes)).unwrap();
+        assert_eq!(edited.metadata().title, "Nested, Edited");
+        assert_eq!(edited.stylesheet(), Some(b"p { margin: 0; }".as_slice()));
+        assert_eq!(edited.contents()[0].filename(0), "Text/ch1.xhtml");
+
+        // The stylesheet link of the chapter, resolved against its directory, finds the file.
+        let mut archive = ZipArchive::new(Cursor::new(&bytes)).unwrap();
+        let chapter = read_string(&mut archive, "OEBPS/Text/ch1.xhtml").unwrap();
+        assert!(chapter.contains("<p>One</p>"));
+        let href = chapter
+            .split(r#"<link href=""#)
+            .nth(1)
+            .and_then(|link| link.split('"').next())
+            .unwrap();
+        let mut path = vec!["OEBPS", "Text"];
+        for segment in href.split('/') {
+            if segment == ".." {
+                path.pop();
+            } else {
+                path.push(segment);
+            }
+        }
+        assert_eq!(path.join("/"), "OEBPS/style.css");
+        assert_eq!(
+            read_bytes(&mut archive, &path.join("/")).unwrap(),
+            b"p { margin: 0; }"
+        );
+    }
+
+    #[test]
+    fn test_inspect() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("book.epub");
+        EpubBuilder::new(
+            MetadataBuilder::title("Catalogued")
+                .creator("Author")
+                .subject("Fiction")
+                .build(),
+        )
+        .add_content(
+            ContentBuilder::new(
+                b"<body><p>Text</p></body>",
+                ReferenceType::Text("Chapter".to_string()),
+            )
+            .build(),
+        )
+        .create(&mut File::create(&path).unwrap())
+        .unwrap();
+
+        let metadata = inspect(&path).unwrap();
+        assert_eq!(metadata.title, "Catalogued");
+        assert_eq!(metadata.creator.as_deref(), Some("Author"));
+        assert_eq!(metadata.subject.as_deref(), Some("Fiction"));
+        assert!(inspect(temp_dir.path().join("missing.epub")).is_err());
+    }
+
+    #[test]
+    fn test_epub_reader_accessibility_metadata() {
+        let certification = Certification::new("Accessibility Testers")
+            .credential("Certified Assessor")
+            .report("https://a11y.example/report");
+        let bytes = EpubBuilder::new(
+            MetadataBuilder::title("Accessible")
+                .conforms_to(Conformance::EpubA11y11(WcagVersion::V2_1, WcagLevel::AA))
+                .certification(certification.clone())
+                .build(),
+        )
+        .render_content_opf()
+        .unwrap();
+        assert!(bytes.contains(
+            r#"<meta name="dcterms:conformsTo" content="EPUB Accessibility 1.1 - WCAG 2.1 Level AA"/>"#
+        ));
+        assert!(
+            bytes.contains(r#"<meta name="a11y:certifiedBy" content="Accessibility Testers"/>"#)
+        );
+
+        let bytes = EpubBuilder::new(
+            MetadataBuilder::title("Accessible")
+                .conforms_to(Conformance::EpubA11y10(WcagLevel::A))
+                .certification(certification.clone())
+                .build(),
+        )
+        .create_to_vec()
+        .unwrap();
+        let reader = EpubReader::from_reader(Cursor::new(bytes)).unwrap();
+        assert_eq!(
+            reader.metadata().conforms_to,
+            Some(Conformance::EpubA11y10(WcagLevel::A))
+        );
+        assert_eq!(reader.metadata().certification, Some(certification));
+    }
+
+    #[test]
+    fn test_extract_cover() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let cover = temp_dir.path().join("front.jpg");
+        std::fs::write(&cover, b"jpg").unwrap();
+
+        let with_cover = temp_dir.path().join("with-cover.epub");
+        EpubBuilder::new(MetadataBuilder::title("Title").build())
+            .cover_image(&cover, ImageType::Jpg)
+            .create(&mut File::create(&with_cover).unwrap())
+            .unwrap();
+
+        let without_cover = temp_dir.path().join("without-cover.epub");
+        EpubBuilder::new(MetadataBuilder::title("Title").build())
+            .create(&mut File::create(&without_cover).unwrap())
+            .unwrap();
+
+        let cover = extract_cover(&with_cover).unwrap().unwrap();
+        assert_eq!(cover.filename, "front.jpg");
+        assert_eq!(cover.media_type, "image/jpeg");
+        assert_eq!(cover.bytes, b"jpg");
+        assert!(extract_cover(&without_cover).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_cover_item_epub3_property() {
+        let package = Element::parse(
+            r#"<package><metadata/><manifest>
+            <item id="a" href="a.png" media-type="image/png"/>
+            <item id="b" href="b.png" media-type="image/png" properties="nav cover-image"/>
+            </manifest></package>"#,
+        )
+        .unwrap();
+        assert_eq!(cover_item(&package).unwrap().attribute("id"), Some("b"));
+    }
+
+    #[test]
+    fn test_epub_reader_invalid() {
+        assert!(EpubReader::from_reader(Cursor::new(b"not a zip".to_vec())).is_err());
+
+        let mut bytes = Vec::new();
+        ZipWriter::new(Cursor::new(&mut bytes)).finish().unwrap();
+        assert!(EpubReader::from_reader(Cursor::new(bytes)).is_err());
+    }
+}
@@ -0,0 +1,723 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    io::{Read, Seek},
+};
+#[cfg(not(target_arch = "wasm32"))]
+use std::{fs::File, path::Path};
+
+use zip::{CompressionMethod, ZipArchive};
+
+use crate::{
+    epub::UNIQUE_GUIDE_TYPES,
+    input::{reader, xml::Element},
+};
+
+/// The guide reference types defined by the OPF 2.0.1 specification, besides `other.*`.
+const GUIDE_TYPES: &[&str] = &[
+    "acknowledgements",
+    "bibliography",
+    "colophon",
+    "copyright-page",
+    "cover",
+    "dedication",
+    "epigraph",
+    "foreword",
+    "glossary",
+    "index",
+    "loi",
+    "lot",
+    "notes",
+    "preface",
+    "text",
+    "title-page",
+    "toc",
+];
+
+/// A problem found by [`validate`] (or [`crate::epub::EpubBuilder::validate`]) in an EPUB.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationIssue {
+    /// The path of the offending file inside the archive (e.g. `OEBPS/content.opf`).
+    pub path: String,
+    /// A human readable description of the problem.
+    pub message: String,
+}
+
+impl ValidationIssue {
+    fn new<P: Into<String>, M: Into<String>>(path: P, message: M) -> Self {
+        Self {
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// Checks the EPUB file at `path` for the structural problems most commonly reported by
+/// epubcheck, without needing Java:
+///
+/// - the `mimetype` file must be the first entry, stored uncompressed, with `application/epub+zip`.
+/// - `META-INF/container.xml` must declare a package document present in the archive.
+/// - manifest ids must be unique, and every manifest href must exist in the archive.
+/// - every file of the archive (besides `mimetype` and `META-INF`) must be in the manifest.
+/// - the spine must not be empty, must reference manifest items only once, and its `toc`
+///   must be the NCX item.
+/// - guide references must have a known type and point to manifest items, with at most one
+///   `cover`, `toc` and `title-page`.
+/// - the NCX navigation points must point to manifest items, and to existing anchors.
+/// - the XHTML files must be well formed, and their intra-book links (`href`) must point to
+///   existing files and anchors, e.g. a mistyped [`crate::epub::ContentReference::id`].
+///
+/// This is not a replacement for epubcheck: the XHTML contents are not validated against
+/// their schema.
+///
+/// # Example
+/// ```no_run
+/// for issue in liber::validate("book.epub")? {
+///     eprintln!("{issue}");
+/// }
+/// # Ok::<(), liber::Error>(())
+/// ```
+///
+/// # Returns
+/// Returns every problem found, or an empty vector if the EPUB is valid.
+///
+/// # Errors
+/// Returns a [`crate::Error`] if the file cannot be read or is not a ZIP archive.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn validate<P: AsRef<Path>>(path: P) -> crate::Result<Vec<ValidationIssue>> {
+    validate_archive(File::open(path)?)
+}
+
+/// Checks an EPUB archive read from `reader`. See [`validate`].
+pub(crate) fn validate_archive<R: Read + Seek>(reader: R) -> crate::Result<Vec<ValidationIssue>> {
+    let mut archive = ZipArchive::new(reader)?;
+    let mut issues = Vec::new();
+
+    check_mimetype(&mut archive, &mut issues)?;
+
+    let Some(opf_path) = container_rootfile(&mut archive, &mut issues) else {
+        return Ok(issues);
+    };
+    let Some(package) = parse_file(&mut archive, &opf_path, &mut issues) else {
+        return Ok(issues);
+    };
+    let base = directory(&opf_path);
+
+    let names = (0..archive.len())
+        .filter_map(|index| archive.name_for_index(index).map(str::to_string))
+        .collect::<Vec<_>>();
+
+    let items = check_manifest(&package, &base, &opf_path, &names, &mut issues);
+    check_undeclared_files(&names, &items, &opf_path, &mut issues);
+    let ncx = check_spine(&package, &items, &opf_path, &mut issues);
+    check_guide(&package, &base, &items, &opf_path, &mut issues);
+
+    let documents = read_documents(&mut archive, &items, &names, &mut issues);
+
+    if let Some(ncx_path) = ncx.filter(|ncx_path| names.contains(ncx_path))
+        && let Some(ncx) = parse_file(&mut archive, &ncx_path, &mut issues)
+    {
+        let ncx_base = directory(&ncx_path);
+        check_nav_points(&ncx, &ncx_base, &items, &documents, &ncx_path, &mut issues);
+    }
+
+    check_links(&items, &names, &documents, &mut issues);
+
+    Ok(issues)
+}
+
+/// A manifest `<item>`, with its href resolved to a path inside the archive.
+struct Item {
+    id: String,
+    path: String,
+    media_type: String,
+}
+
+/// The anchors (`id` attributes) and the link targets (`href` attributes) of an XHTML file.
+#[derive(Default)]
+struct Document {
+    ids: HashSet<String>,
+    hrefs: Vec<String>,
+}
+
+impl Document {
+    fn new(root: &Element) -> Self {
+        let mut document = Self::default();
+        document.collect(root);
+        document
+    }
+
+    fn collect(&mut self, element: &Element) {
+        if let Some(id) = element.attribute("id") {
+            self.ids.insert(id.to_string());
+        }
+        if let Some(href) = element.attribute("href") {
+            self.hrefs.push(href.to_string());
+        }
+        for child in &element.children {
+            self.collect(child);
+        }
+    }
+}
+
+/// Checks that `mimetype` is the first entry, stored, with the EPUB media type.
+fn check_mimetype<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    issues: &mut Vec<ValidationIssue>,
+) -> crate::Result<()> {
+    if archive.is_empty() {
+        issues.push(ValidationIssue::new("mimetype", "the archive is empty"));
+        return Ok(());
+    }
+
+    let mut first = archive.by_index(0)?;
+    if first.name() != "mimetype" {
+        issues.push(ValidationIssue::new(
+            "mimetype",
+            format!(
+                "must be the first file of the archive, found {}",
+                first.name()
+            ),
+        ));
+        return Ok(());
+    }
+    if first.compression() != CompressionMethod::Stored {
+        issues.push(ValidationIssue::new(
+            "mimetype",
+            "must be stored uncompressed",
+        ));
+    }
+
+    let mut content = Vec::new();
+    first.read_to_end(&mut content)?;
+    if content != b"application/epub+zip" {
+        issues.push(ValidationIssue::new(
+            "mimetype",
+            "must contain exactly application/epub+zip",
+        ));
+    }
+    Ok(())
+}
+
+/// Gets the path of the package document declared in `META-INF/container.xml`.
+fn container_rootfile<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    issues: &mut Vec<ValidationIssue>,
+) -> Option<String> {
+    let path = "META-INF/container.xml";
+    if archive.index_for_name(path).is_none() {
+        issues.push(ValidationIssue::new(path, "is missing"));
+        return None;
+    }
+
+    let rootfile = parse_file(archive, path, issues)?
+        .child("rootfiles")
+        .and_then(|rootfiles| rootfiles.child("rootfile"))
+        .and_then(|rootfile| rootfile.attribute("full-path"))
+        .map(str::to_string);
+
+    match rootfile {
+        Some(rootfile) if archive.index_for_name(&rootfile).is_some() => Some(rootfile),
+        Some(rootfile) => {
+            issues.push(ValidationIssue::new(
+                path,
+                format!("declares the package document {rootfile}, which is missing"),
+            ));
+            None
+        }
+        None => {
+            issues.push(ValidationIssue::new(
+                path,
+                "does not declare a package document",
+            ));
+            None
+        }
+    }
+}
+
+/// Reads and parses the XML file `path`, reporting it as an issue if it is not well formed.
+fn parse_file<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    path: &str,
+    issues: &mut Vec<ValidationIssue>,
+) -> Option<Element> {
+    match reader::read_string(archive, path).and_then(|xml| Element::parse(&xml)) {
+        Ok(element) => Some(element),
+        Err(e) => {
+            issues.push(ValidationIssue::new(path, format!("cannot be parsed: {e}")));
+            None
+        }
+    }
+}
+
+/// Checks the manifest ids are unique and its hrefs exist, returning its items.
+fn check_manifest(
+    package: &Element,
+    base: &str,
+    opf_path: &str,
+    names: &[String],
+    issues: &mut Vec<ValidationIssue>,
+) -> Vec<Item> {
+    let Some(manifest) = package.child("manifest") else {
+        issues.push(ValidationIssue::new(opf_path, "has no manifest"));
+        return Vec::new();
+    };
+
+    let mut ids = HashSet::new();
+    let mut items = Vec::new();
+    for item in manifest.children_named("item") {
+        let (Some(id), Some(href)) = (item.attribute("id"), item.attribute("href")) else {
+            issues.push(ValidationIssue::new(
+                opf_path,
+                "manifest item without id or href",
+            ));
+            continue;
+        };
+
+        if !ids.insert(id) {
+            issues.push(ValidationIssue::new(
+                opf_path,
+                format!("duplicate manifest id {id}"),
+            ));
+        }
+
+        let path = resolve(base, href);
+        if !names.contains(&path) {
+            issues.push(ValidationIssue::new(
+                opf_path,
+                format!("manifest item {id} points to the missing file {path}"),
+            ));
+        }
+
+        items.push(Item {
+            id: id.to_string(),
+            path,
+            media_type: item.attribute("media-type").unwrap_or_default().to_string(),
+        });
+    }
+    items
+}
+
+/// Checks every file of the archive, besides the container files, is in the manifest.
+fn check_undeclared_files(
+    names: &[String],
+    items: &[Item],
+    opf_path: &str,
+    issues: &mut Vec<ValidationIssue>,
+) {
+    names
+        .iter()
+        .filter(|name| {
+            *name != "mimetype"
+                && *name != opf_path
+                && !name.starts_with("META-INF/")
+                && !name.ends_with('/')
+        })
+        .filter(|name| !items.iter().any(|item| &item.path == *name))
+        .for_each(|name| {
+            issues.push(ValidationIssue::new(
+                name.as_str(),
+                "is not declared in the manifest",
+            ))
+        });
+}
+
+/// Checks the spine itemrefs, returning the path of the NCX referenced by its `toc`.
+fn check_spine(
+    package: &Element,
+    items: &[Item],
+    opf_path: &str,
+    issues: &mut Vec<ValidationIssue>,
+) -> Option<String> {
+    let Some(spine) = package.child("spine") else {
+        issues.push(ValidationIssue::new(opf_path, "has no spine"));
+        return None;
+    };
+
+    let mut idrefs = HashSet::new();
+    for itemref in spine.children_named("itemref") {
+        let idref = itemref.attribute("idref").unwrap_or_default();
+        if !items.iter().any(|item| item.id == idref) {
+            issues.push(ValidationIssue::new(
+                opf_path,
+                format!("spine itemref {idref} is not in the manifest"),
+            ));
+        } else if !idrefs.insert(idref) {
+            issues.push(ValidationIssue::new(
+                opf_path,
+                format!("spine references {idref} more than once"),
+            ));
+        }
+    }
+    if idrefs.is_empty() {
+        issues.push(ValidationIssue::new(opf_path, "the spine is empty"));
+    }
+
+    let toc = spine.attribute("toc")?;
+    match items.iter().find(|item| item.id == toc) {
+        Some(item) if item.media_type == "application/x-dtbncx+xml" => Some(item.path.clone()),
+        Some(_) => {
+            issues.push(ValidationIssue::new(
+                opf_path,
+                format!("spine toc {toc} is not an NCX document"),
+            ));
+            None
+        }
+        None => {
+            issues.push(ValidationIssue::new(
+                opf_path,
+                format!("spine toc {toc} is not in the manifest"),
+            ));
+            None
+        }
+    }
+}
+
+/// Checks the guide references have a known type, not repeating the types a book has at most
+/// one of, and point to manifest items.
+fn check_guide(
+    package: &Element,
+    base: &str,
+    items: &[Item],
+    opf_path: &str,
+    issues: &mut Vec<ValidationIssue>,
+) {
+    let Some(guide) = package.child("guide") else {
+        return;
+    };
+
+    let mut unique_types = HashSet::new();
+    for reference in guide.children_named("reference") {
+        let ref_type = reference.attribute("type").unwrap_or_default();
+        if !GUIDE_TYPES.contains(&ref_type) && !ref_type.starts_with("other.") {
+            issues.push(ValidationIssue::new(
+                opf_path,
+                format!("unknown guide reference type {ref_type:?}"),
+            ));
+        }
+        if UNIQUE_GUIDE_TYPES.contains(&ref_type) && !unique_types.insert(ref_type) {
+            issues.push(ValidationIssue::new(
+                opf_path,
+                format!("duplicate guide reference type {ref_type:?}"),
+            ));
+        }
+
+        let href = reference.attribute("href").unwrap_or_default();
+        let path = resolve(base, file_part(href));
+        if !items.iter().any(|item| item.path == path) {
+            issues.push(ValidationIssue::new(
+                opf_path,
+                format!("guide reference {href} is not in the manifest"),
+            ));
+        }
+    }
+}
+
+/// Checks the NCX navigation points, page targets and navigation targets (recursively) point
+/// to manifest items, and to existing anchors when they have a fragment.
+fn check_nav_points(
+    parent: &Element,
+    base: &str,
+    items: &[Item],
+    documents: &HashMap<String, Document>,
+    ncx_path: &str,
+    issues: &mut Vec<ValidationIssue>,
+) {
+    for child in &parent.children {
+        if matches!(child.name.as_str(), "navPoint" | "pageTarget" | "navTarget")
+            && let Some(src) = child
+                .child("content")
+                .and_then(|content| content.attribute("src"))
+        {
+            let path = resolve(base, file_part(src));
+            if !items.iter().any(|item| item.path == path) {
+                issues.push(ValidationIssue::new(
+                    ncx_path,
+                    format!("navigation point {src} is not in the manifest"),
+                ));
+            } else if let Some(anchor) = missing_anchor(&path, src, documents) {
+                issues.push(ValidationIssue::new(
+                    ncx_path,
+                    format!("navigation point {src} points to the missing anchor #{anchor}"),
+                ));
+            }
+        }
+        check_nav_points(child, base, items, documents, ncx_path, issues);
+    }
+}
+
+/// Reads the anchors and links of every XHTML file of the manifest, reporting the files that
+/// are not well formed.
+fn read_documents<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    items: &[Item],
+    names: &[String],
+    issues: &mut Vec<ValidationIssue>,
+) -> HashMap<String, Document> {
+    items
+        .iter()
+        .filter(|item| item.media_type == "application/xhtml+xml" && names.contains(&item.path))
+        .filter_map(|item| {
+            let root = parse_file(archive, &item.path, issues)?;
+            Some((item.path.clone(), Document::new(&root)))
+        })
+        .collect()
+}
+
+/// Checks every intra-book `href` of the XHTML files resolves to an existing file and anchor.
+fn check_links(
+    items: &[Item],
+    names: &[String],
+    documents: &HashMap<String, Document>,
+    issues: &mut Vec<ValidationIssue>,
+) {
+    for item in items {
+        let Some(document) = documents.get(&item.path) else {
+            continue;
+        };
+        let base = directory(&item.path);
+
+        for href in document.hrefs.iter().filter(|href| !is_external(href)) {
+            let path = match file_part(href) {
+                "" => item.path.clone(),
+                file => resolve(&base, file),
+            };
+
+            if !names.contains(&path) {
+                issues.push(ValidationIssue::new(
+                    item.path.as_str(),
+                    format!("link {href} points to the missing file {path}"),
+                ));
+            } else if let Some(anchor) = missing_anchor(&path, href, documents) {
+                issues.push(ValidationIssue::new(
+                    item.path.as_str(),
+                    format!("link {href} points to the missing anchor #{anchor}"),
+                ));
+            }
+        }
+    }
+}
+
+/// Gets the fragment of `href` if the XHTML file `path` it points to has no such anchor.
+fn missing_anchor<'a>(
+    path: &str,
+    href: &'a str,
+    documents: &HashMap<String, Document>,
+) -> Option<&'a str> {
+    let (_, anchor) = href.split_once('#')?;
+    let document = documents.get(path)?;
+    (!anchor.is_empty() && !document.ids.contains(anchor)).then_some(anchor)
+}
+
+/// Whether `href` points outside the book (e.g. `https://...`, `mailto:...`).
+fn is_external(href: &str) -> bool {
+    href.starts_with("//")
+        || href
+            .split_once(':')
+            .is_some_and(|(scheme, _)| !scheme.contains(['/', '#', '?']))
+}
+
+/// Resolves the relative (percent-encoded) `href` against the archive directory `base`,
+/// handling `.` and `..` segments.
+fn resolve(base: &str, href: &str) -> String {
+    let href = reader::percent_decode(href);
+    let mut segments = base
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>();
+    for segment in href.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+    segments.join("/")
+}
+
+/// Gets the directory of the archive path `path`, with a trailing slash (empty at the root).
+fn directory(path: &str) -> String {
+    path.rsplit_once('/')
+        .map(|(directory, _)| format!("{directory}/"))
+        .unwrap_or_default()
+}
+
+/// Gets an href without its `#fragment`.
+fn file_part(href: &str) -> &str {
+    href.split_once('#').map_or(href, |(file, _)| file)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use zip::{ZipWriter, write::SimpleFileOptions};
+
+    use super::*;
+    use crate::epub::{
+        ContentBuilder, ContentReference, EpubBuilder, MetadataBuilder, ReferenceType,
+    };
+
+    fn zip(files: &[(&str, &str, CompressionMethod)]) -> Cursor<Vec<u8>> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, content, compression) in files {
+            writer
+                .start_file(
+                    *name,
+                    SimpleFileOptions::default().compression_method(*compression),
+                )
+                .unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+        }
+        Cursor::new(writer.finish().unwrap().into_inner())
+    }
+
+    #[test]
+    fn test_validate_generated_epub() {
+        let bytes = EpubBuilder::new(MetadataBuilder::title("Valid").build())
+            .stylesheet(b"body {}")
+            .add_content(
+                ContentBuilder::new(
+                    b"<body><h1>Chapter 1</h1></body>",
+                    ReferenceType::Text("Chapter 1".to_string()),
+                )
+                .build(),
+            )
+            .create_to_vec()
+            .unwrap();
+
+        assert!(validate_archive(Cursor::new(bytes)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_validate_broken_epub() {
+        let container = r#"<container><rootfiles><rootfile full-path="OEBPS/content.opf"/></rootfiles></container>"#;
+        let opf = r#"<package>
+            <manifest>
+                <item id="c01" href="c01.xhtml" media-type="application/xhtml+xml"/>
+                <item id="c01" href="missing.xhtml" media-type="application/xhtml+xml"/>
+                <item id="ncx" href="toc.ncx" media-type="application/x-dtbncx+xml"/>
+            </manifest>
+            <spine toc="ncx"><itemref idref="c01"/><itemref idref="c02"/></spine>
+            <guide>
+                <reference type="start" title="Start" href="c03.xhtml#top"/>
+                <reference type="toc" title="Contents" href="c01.xhtml"/>
+                <reference type="toc" title="Contents" href="c01.xhtml"/>
+            </guide>
+        </package>"#;
+        let ncx = r#"<ncx><navMap><navPoint><content src="c04.xhtml"/></navPoint></navMap></ncx>"#;
+
+        let issues = validate_archive(zip(&[
+            ("mimetype", "application/epub", CompressionMethod::Deflated),
+            (
+                "META-INF/container.xml",
+                container,
+                CompressionMethod::Stored,
+            ),
+            ("OEBPS/content.opf", opf, CompressionMethod::Stored),
+            ("OEBPS/c01.xhtml", "<html/>", CompressionMethod::Stored),
+            ("OEBPS/toc.ncx", ncx, CompressionMethod::Stored),
+            ("OEBPS/extra.css", "", CompressionMethod::Stored),
+        ]))
+        .unwrap()
+        .into_iter()
+        .map(|issue| issue.to_string())
+        .collect::<Vec<_>>();
+
+        assert_eq!(
+            issues,
+            vec![
+                "mimetype: must be stored uncompressed",
+                "mimetype: must contain exactly application/epub+zip",
+                "OEBPS/content.opf: duplicate manifest id c01",
+                "OEBPS/content.opf: manifest item c01 points to the missing file OEBPS/missing.xhtml",
+                "OEBPS/extra.css: is not declared in the manifest",
+                "OEBPS/content.opf: spine itemref c02 is not in the manifest",
+                "OEBPS/content.opf: unknown guide reference type \"start\"",
+                "OEBPS/content.opf: guide reference c03.xhtml#top is not in the manifest",
+                "OEBPS/content.opf: duplicate guide reference type \"toc\"",
+                "OEBPS/toc.ncx: navigation point c04.xhtml is not in the manifest",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_dead_links() {
+        let issues = EpubBuilder::new(MetadataBuilder::title("Links").build())
+            .add_content(
+                ContentBuilder::new(
+                    br##"<body><h1>Chapter 1</h1><h2 id="s1-1">Section 1.1</h2><p>
+                        <a href="#s1-1">ok</a> <a href="./c02.xhtml">ok</a>
+                        <a href="https://example.com/#s1">ok</a> <a href="mailto:me@example.com">ok</a>
+                        <a href="c02.xhtml#nope">dead</a> <a href="../missing.xhtml">dead</a>
+                    </p></body>"##,
+                    ReferenceType::Text("Chapter 1".to_string()),
+                )
+                .add_content_reference(ContentReference::new("Section 1.1").id("s1-l"))
+                .build(),
+            )
+            .add_content(
+                ContentBuilder::new(
+                    b"<body><h1>Chapter 2</h1></body>",
+                    ReferenceType::Text("Chapter 2".to_string()),
+                )
+                .build(),
+            )
+            .validate_archive()
+            .unwrap()
+            .into_iter()
+            .map(|issue| issue.to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            issues,
+            vec![
+                "OEBPS/toc.ncx: navigation point c01.xhtml#s1-l points to the missing anchor #s1-l",
+                "OEBPS/c01.xhtml: link c02.xhtml#nope points to the missing anchor #nope",
+                "OEBPS/c01.xhtml: link ../missing.xhtml points to the missing file missing.xhtml",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolve() {
+        assert_eq!(resolve("OEBPS/", "c01.xhtml"), "OEBPS/c01.xhtml");
+        assert_eq!(
+            resolve("OEBPS/text/", "../images/a%20b.png"),
+            "OEBPS/images/a b.png"
+        );
+        assert_eq!(resolve("", "./toc.ncx"), "toc.ncx");
+    }
+
+    #[test]
+    fn test_validate_misplaced_mimetype() {
+        let issues = validate_archive(zip(&[
+            (
+                "META-INF/container.xml",
+                "<container/>",
+                CompressionMethod::Stored,
+            ),
+            (
+                "mimetype",
+                "application/epub+zip",
+                CompressionMethod::Stored,
+            ),
+        ]))
+        .unwrap();
+
+        assert_eq!(issues.len(), 2);
+        assert_eq!(
+            issues[0].message,
+            "must be the first file of the archive, found META-INF/container.xml"
+        );
+        assert_eq!(issues[1].message, "does not declare a package document");
+    }
+}
@@ -0,0 +1,177 @@
+use quick_xml::{Reader, escape, events::Event, name::QName};
+
+/// An XML element parsed into memory, with its attributes, child elements and text.
+///
+/// Element and attribute names are stored without their namespace prefix (e.g. `title`
+/// for `dc:title`, `scheme` for `opf:scheme`), which is enough to read EPUB package files.
+#[derive(Debug, Default)]
+pub struct Element {
+    /// The local name of the element.
+    pub name: String,
+    /// The attributes as (local name, unescaped value), in document order.
+    pub attributes: Vec<(String, String)>,
+    /// The child elements, in document order.
+    pub children: Vec<Element>,
+    /// The unescaped text directly contained in the element, trimmed.
+    pub text: String,
+}
+
+impl Element {
+    /// Parses `xml` and returns its root element.
+    ///
+    /// # Errors
+    /// Returns a `crate::Error::XmlParser` if the document is not well formed, or a
+    /// `crate::Error::InvalidEpub` if it has no root element.
+    pub fn parse(xml: &str) -> crate::Result<Element> {
+        let mut reader = Reader::from_str(xml);
+        let mut stack: Vec<Element> = Vec::new();
+        let mut root = None;
+
+        loop {
+            let event = reader
+                .read_event()
+                .map_err(|e| crate::Error::XmlParser(reader.buffer_position(), e))?;
+
+            match event {
+                Event::Start(start) => stack.push(Element::new(&start)?),
+                Event::Empty(start) => push_child(&mut stack, &mut root, Element::new(&start)?),
+                Event::End(_) => {
+                    if let Some(mut element) = stack.pop() {
+                        element.text = element.text.trim().to_string();
+                        push_child(&mut stack, &mut root, element);
+                    }
+                }
+                Event::Text(text) => {
+                    if let Some(element) = stack.last_mut() {
+                        element
+                            .text
+                            .push_str(&text.decode().map_err(quick_xml::Error::from)?);
+                    }
+                }
+                Event::CData(cdata) => {
+                    if let Some(element) = stack.last_mut() {
+                        element
+                            .text
+                            .push_str(&cdata.decode().map_err(quick_xml::Error::from)?);
+                    }
+                }
+                Event::GeneralRef(reference) => {
+                    if let Some(element) = stack.last_mut() {
+                        match reference.resolve_char_ref()? {
+                            Some(ch) => element.text.push(ch),
+                            None => {
+                                let name = reference.decode().map_err(quick_xml::Error::from)?;
+                                let resolved = escape::resolve_predefined_entity(&name)
+                                    .map(str::to_string)
+                                    .unwrap_or_else(|| format!("&{name};"));
+                                element.text.push_str(&resolved);
+                            }
+                        }
+                    }
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+
+        root.ok_or_else(|| crate::Error::InvalidEpub("XML document without root element".into()))
+    }
+
+    /// Creates an element, without children nor text, from its start tag.
+    fn new(start: &quick_xml::events::BytesStart<'_>) -> crate::Result<Element> {
+        let attributes = start
+            .attributes()
+            .map(|attribute| {
+                let attribute = attribute.map_err(quick_xml::Error::from)?;
+                Ok((
+                    local_name(attribute.key),
+                    attribute.unescape_value()?.into_owned(),
+                ))
+            })
+            .collect::<crate::Result<Vec<_>>>()?;
+
+        Ok(Element {
+            name: local_name(start.name()),
+            attributes,
+            children: Vec::new(),
+            text: String::new(),
+        })
+    }
+
+    /// Gets the value of the attribute `name` (a local name), if present.
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Gets the first child element called `name` (a local name), if present.
+    pub fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    /// Iterates over the child elements called `name` (a local name).
+    pub fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+}
+
+/// Appends `element` to the element on top of `stack`, or makes it the root if the stack is empty.
+fn push_child(stack: &mut [Element], root: &mut Option<Element>, element: Element) {
+    match stack.last_mut() {
+        Some(parent) => parent.children.push(element),
+        None => {
+            root.get_or_insert(element);
+        }
+    }
+}
+
+/// Gets a qualified name without its namespace prefix, as a `String`.
+fn local_name(name: QName<'_>) -> String {
+    String::from_utf8_lossy(name.local_name().as_ref()).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_element_parse() {
+        let root = Element::parse(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+            <package xmlns="http://www.idpf.org/2007/opf">
+                <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+                    <dc:title> Pride &amp; Prejudice &#x2014; <![CDATA[<1813>]]> </dc:title>
+                    <dc:identifier opf:scheme="ISBN">urn:isbn:123</dc:identifier>
+                </metadata>
+                <manifest><item id="a" href="a.xhtml"/></manifest>
+            </package>"#,
+        )
+        .unwrap();
+
+        assert_eq!(root.name, "package");
+        let metadata = root.child("metadata").unwrap();
+        assert_eq!(
+            metadata.child("title").unwrap().text,
+            "Pride & Prejudice \u{2014} <1813>"
+        );
+        assert_eq!(
+            metadata.child("identifier").unwrap().attribute("scheme"),
+            Some("ISBN")
+        );
+        assert_eq!(
+            root.child("manifest")
+                .unwrap()
+                .children_named("item")
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_element_parse_invalid() {
+        assert!(Element::parse("<package><metadata></package>").is_err());
+        assert!(Element::parse("").is_err());
+    }
+}
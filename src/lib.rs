@@ -15,8 +15,8 @@
 //! use std::path::Path;
 //!
 //! use liber::epub::{
-//!     ContentBuilder, ContentReference, EpubBuilder, ImageType, MetadataBuilder, ReferenceType,
-//!     Resource,
+//!     ContentBuilder, ContentReference, EpubBuilder, FontType, ImageType, MetadataBuilder,
+//!     ReferenceType, Resource,
 //! };
 //!
 //! fn main() {
@@ -27,7 +27,7 @@
 //! }
 //!
 //! fn create() -> Result<(), Box<dyn std::error::Error>> {
-//!     let mut file = std::fs::File::create("book.epub")?;
+//!     let mut file = std::fs::File::create(std::env::temp_dir().join("book.epub"))?;
 //!     let title = "My Book";
 //!
 //!     let contents = vec![
@@ -53,7 +53,7 @@
 //!     let epub_builder = EpubBuilder::new(MetadataBuilder::title(title).creator("author").build())
 //!         .stylesheet("body {}".as_bytes())
 //!         .cover_image(Path::new("/path/to/img.jpg"), ImageType::Jpg)
-//!         .add_resource(Resource::Font(Path::new("/path/to/some_font.otf")))
+//!         .add_resource(Resource::Font(Path::new("/path/to/some_font.otf"), FontType::OpenType))
 //!         .add_content(
 //!             ContentBuilder::new(
 //!                 r#"<body><h1>Chapter 1</h1><h2 id="id01">Section 1.1</h2><h2 id="id02">Section 1.1.1</h2><h2 id="id03">Section 1.2</h2></body>"#.as_bytes(),
@@ -74,8 +74,29 @@
 //! ## Modules & Re-exports
 //!
 //! - [`epub`] — Core types to model the epub.
-//! - [`epub::Content`], [`epub::ContentReference`], [`epub::Resource`], [`epub::Language`], [`epub::Identifier`], [`epub::Metadata`] — Main data structures.
+//! - [`epub::Content`], [`epub::ContentReference`], [`epub::Resource`], [`epub::ResourceItem`], [`epub::GeneratedCover`], [`epub::ResourceDir`], [`epub::Language`], [`epub::Identifier`], [`epub::Metadata`] — Main data structures.
 //! - [`epub::EpubBuilder`], [`epub::ContentBuilder`], [`epub::MetadataBuilder`] — Builders.
+//! - [`epub::ComicBuilder`], [`epub::FixedLayout`] — Pre-paginated books with one page per image (e.g. comics, or manga turned right to left).
+//! - [`epub::ContentSource`] — Produces content bodies on demand (e.g. from a database) while the EPUB is created.
+//! - [`epub::EpubPlugin`] — Hooks into the build pipeline to transform the generated files.
+//! - [`epub::Audio`] — `<audio>` elements with a fallback, registering their resource with the content.
+//! - [`epub::Video`] — `<video>` elements with a poster image and a fallback, registering both resources with the content.
+//! - [`epub::LinkAnnotation`] — External links followed by their URL, inline or as numbered link notes.
+//! - [`epub::TextTransform`] — Ordered closure or regular expression (`regex` feature) rewrites of every body.
+//! - [`epub::Theme`] — Built-in stylesheet presets, used alone or under the stylesheet of the book.
+//! - [`epub::Typography`] — Drop caps, small-caps lead-ins and section-break ornaments, per type of content.
+//! - [`epub::Profile`] — Builds the book for a retailer (Apple Books, Kobo, Google Play Books) in one switch.
+//! - [`epub::Dedication`], [`epub::Epigraph`] — Generate the dedication and epigraph pages of the front matter.
+//! - [`epub::Poem`] — Generates poems keeping their line breaks and indentation, with a CSS preset.
+//! - [`epub::IndexTerm`] — Tags the terms of the generated back-of-book index (see [`epub::EpubBuilder::index`]).
+//! - [`epub::Watermark`] — Injects a buyer-specific string into personalized copies of the book.
+//! - [`EpubReader`], [`EpubResource`] — Parse existing EPUB files back into the model.
+//! - [`inspect`], [`extract_cover`] — Read only the metadata, or the cover image, of an existing EPUB file.
+//! - [`validate`], [`ValidationIssue`] — Check an existing EPUB file for common structural problems.
+//! - [`Checksum`] — The SHA-256 checksums of the archive entries returned by [`epub::EpubBuilder::create_with_checksums`].
+//! - [`Warning`] — Non-fatal quality issues returned by [`epub::EpubBuilder::create_with_warnings`].
+//! - [`OpdsLinks`] — The links of the OPDS catalog entry of the book (see [`epub::EpubBuilder::render_opds_entry`]).
+//! - [`EpubFiles`], [`FileContent`], [`FileBytes`] — The generated files, one by one, for custom packaging (see [`epub::EpubBuilder::files`]).
 //!
 //! ## Error Handling
 //!
@@ -84,16 +105,57 @@
 //!
 //! ## Feature Flags
 //!
-//! - `async` — Enables the asynchronous API (`search`).
+//! - `async` — Enables the asynchronous API, usable on any runtime (see `epub::Spawner`).
+//! - `cli` — Builds the `liber-cli` binary, creating an EPUB from a TOML manifest.
+//! - `crypto` — Signs the package with a caller-provided key (`epub::PackageSigner`), generating `META-INF/signatures.xml`.
+//! - `http` — Downloads remote resources (`Resource::Url`) when creating the EPUB, with `ureq` (run with the `epub::Spawner` by the asynchronous API).
+//! - `http-async` — Enables `http` and `async`, downloading remote resources natively asynchronously with `reqwest` in the asynchronous API, which needs a Tokio runtime.
+//! - `mdbook` — Imports an mdBook project (`SUMMARY.md` and its markdown chapters) with `EpubBuilder::from_mdbook`.
+//! - `mmap` — Memory-maps resource files of 16 MiB or more instead of reading them, reducing peak memory for large audio or video resources. The files must not be modified while the EPUB is created.
+//! - `onix` — Generates the ONIX 3.0 product record of the book with `EpubBuilder::render_onix`.
+//! - `image` — Downscales and compresses the cover (and optionally other images) with `ImageOptions`.
+//! - `rayon` — Loads resources and renders chapters in parallel in the synchronous API (see `EpubBuilder::parallel_rendering`).
+//! - `regex` — Rewrites the bodies with regular expressions (`TextTransform::regex`).
+//! - `sniff` — Detects resource media types from the file content, logging a warning when it disagrees with the extension.
+//!
+//! ## WebAssembly
+//!
+//! The synchronous API compiles for `wasm32-unknown-unknown`, e.g. for browser-based EPUB makers.
+//! There is no file system there: only in-memory resources ([`epub::Resource::Raw`]) are
+//! accepted, the EPUB is created with [`epub::EpubBuilder::create_to_vec`] (or any writer), and
+//! existing EPUBs are read with [`EpubReader::from_reader`]. The APIs working on paths
+//! (`ResourceDir`, `create_dir`, `EpubReader::open`, `inspect`, `extract_cover`, `validate`) and
+//! the `async` and `mmap` features are not available.
 //!
 //! ## License
 //!
 //! This is free software, published under the [MIT License](https://mit-license.org/).
 
+#[cfg(all(feature = "async", target_arch = "wasm32"))]
+compile_error!("the `async` feature (tokio) is not supported on wasm32 targets");
+
+#[cfg(all(feature = "mmap", target_arch = "wasm32"))]
+compile_error!("the `mmap` feature is not supported on wasm32 targets");
+
 pub mod epub;
+mod input;
 mod output;
 
+pub use input::reader::{EpubReader, EpubResource};
+#[cfg(not(target_arch = "wasm32"))]
+pub use input::reader::{extract_cover, inspect};
+pub use input::validation::ValidationIssue;
+#[cfg(not(target_arch = "wasm32"))]
+pub use input::validation::validate;
 pub use output::creator::ZipCompression;
+pub use output::file_content::{FileBytes, FileContent};
+pub use output::files::EpubFiles;
+pub use output::manifest::Checksum;
+pub use output::opds::OpdsLinks;
+#[cfg(feature = "image")]
+pub use output::optimizer::ImageOptions;
+pub use output::sink::PlannedFile;
+pub use output::warning::Warning;
 
 /// Error type for all fallible operations in this crate.
 #[derive(thiserror::Error, Debug)]
@@ -112,6 +174,27 @@ pub enum Error {
     #[error(transparent)]
     TokioJoinError(#[from] tokio::task::JoinError),
 
+    #[cfg(feature = "async")]
+    #[error("A blocking task of the async API was dropped before finishing")]
+    BlockingTaskCanceled,
+
+    #[cfg(feature = "http")]
+    #[error(transparent)]
+    Http(#[from] ureq::Error),
+
+    #[cfg(feature = "http-async")]
+    #[error(transparent)]
+    AsyncHttp(#[from] reqwest::Error),
+
+    #[cfg(feature = "image")]
+    #[error(transparent)]
+    Image(#[from] image::ImageError),
+
+    /// An invalid pattern given to `TextTransform::regex` (requires the **`regex` feature**).
+    #[cfg(feature = "regex")]
+    #[error(transparent)]
+    Regex(#[from] regex::Error),
+
     #[error(transparent)]
     Utf8(#[from] std::str::Utf8Error),
 
@@ -127,11 +210,100 @@ pub enum Error {
     #[error("Content filename must end with '.xhtml'. Got '{0}'")]
     ContentFilename(String),
 
+    #[error("Invalid EPUB: {0}")]
+    InvalidEpub(String),
+
+    #[error("The book has no contents, so its spine is empty")]
+    EmptySpine,
+
+    #[error("The book identifier is empty")]
+    EmptyIdentifier,
+
+    #[error("'{0}' is not a valid ISBN-10 or ISBN-13")]
+    InvalidIsbn(String),
+
+    #[error("'{filename}': filename used by both '{first}' and '{second}'")]
+    DuplicateFilename {
+        filename: String,
+        first: String,
+        second: String,
+    },
+
+    #[error("'{filename}': anchor '#{anchor}' is missing from the body")]
+    MissingAnchor { filename: String, anchor: String },
+
+    #[error("'{filename}': anchor '#{anchor}' is used by more than one content reference")]
+    DuplicateAnchor { filename: String, anchor: String },
+
+    #[error("'{filename}': anchor '#{anchor}' is not a valid id")]
+    InvalidAnchor { filename: String, anchor: String },
+
+    #[error("'{0}': the spine order lists a missing content")]
+    MissingSpineItem(String),
+
+    #[error("'{0}': the spine order lists this content more than once")]
+    DuplicateSpineItem(String),
+
+    #[error("'{src}': target points to the missing content '{filename}'")]
+    MissingTarget { src: String, filename: String },
+
+    #[error("'{}': resource not found", .path.display())]
+    ResourceNotFound { path: std::path::PathBuf },
+
+    #[error("'{resource}': {reason}")]
+    InvalidResource { resource: String, reason: String },
+
+    #[error("Invalid resources:\n{}", .0.join("\n"))]
+    InvalidResources(Vec<String>),
+
     #[error("Error at position {0}: {1:?}")]
     XmlParser(u64, quick_xml::Error),
+
+    #[cfg(feature = "async")]
+    #[error("Content '{0}' has an async source, which only the async API can produce")]
+    AsyncContentSource(String),
+
+    #[cfg(feature = "mdbook")]
+    #[error("Invalid mdBook: {0}")]
+    MdBook(String),
+
+    /// An error identified by the file it occurred on, e.g. `content 'Chapter 3' (OEBPS/c03.xhtml)`
+    /// or `resource 'images/map.png'`.
+    #[error("{context}: {source}")]
+    Context {
+        context: String,
+        #[source]
+        source: Box<Error>,
+    },
+}
+
+impl Error {
+    /// Gets the underlying error, without the context identifying the file it occurred on
+    /// (see [`Error::Context`]).
+    pub fn root_cause(&self) -> &Error {
+        match self {
+            Self::Context { source, .. } => source.root_cause(),
+            _ => self,
+        }
+    }
 }
 
 /// A convenient alias for `Result` with the crate's [`Error`] type.
 ///
 /// Defaults to `()` for the success type if not specified.
 pub type Result<T = ()> = std::result::Result<T, Error>;
+
+/// Adds the file an error occurred on to the errors of a [`Result`].
+pub(crate) trait ResultExt<T> {
+    /// Wraps the error, if any, in an [`Error::Context`] built by `context`.
+    fn with_context<C: std::fmt::Display>(self, context: impl FnOnce() -> C) -> Result<T>;
+}
+
+impl<T, E: Into<Error>> ResultExt<T> for std::result::Result<T, E> {
+    fn with_context<C: std::fmt::Display>(self, context: impl FnOnce() -> C) -> Result<T> {
+        self.map_err(|e| Error::Context {
+            context: context().to_string(),
+            source: Box::new(e.into()),
+        })
+    }
+}
@@ -1,20 +1,27 @@
-use std::io::{Cursor, Write};
+use std::io::Write;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
 
-use zip::{
-    CompressionMethod, ZipWriter,
-    write::{FileOptions, SimpleFileOptions},
-};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
 
+#[cfg(not(target_arch = "wasm32"))]
+use crate::output::sink::DirSink;
 use crate::{
-    epub::Epub,
+    ResultExt,
+    epub::{Content, Epub, EpubPlugin, ResourceItem, XhtmlOptions},
     output::{
-        file_content::{self, FileContent},
+        file_content::{self, FileBytes, FileContent},
+        files::{EpubFiles, is_mimetype},
+        manifest::Checksum,
+        sink::{Sink, ZipSink},
+        warning::{self, CoverConstraint, Warning},
         xml,
     },
 };
 
 /// Defines the compression method used when creating the EPUB ZIP archive.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub enum ZipCompression {
     /// Use **Deflated** compression. This is generally preferred for smaller file sizes.
     Deflated,
@@ -25,30 +32,26 @@ pub enum ZipCompression {
 }
 
 /// A builder responsible for creating and writing all components of an EPUB book
-/// into a standard ZIP archive format.
+/// into a [`Sink`]: a standard ZIP archive format, or an unpacked directory.
 ///
 /// This struct manages the final serialization step, taking the high-level
 /// `Epub` data structure and writing all necessary files (`.opf`, `.ncx`, `.xhtml`, etc.)
-/// to an underlying writer.
+/// to the sink.
 #[derive(Debug)]
-pub struct EpubFile<'a, W> {
+pub struct EpubFile<'a, S> {
     /// The source data structure containing all metadata and content of the EPUB.
     epub: Epub<'a>,
-    /// The file options (including compression method) used for writing files into the ZIP archive.
-    options: FileOptions<'a, ()>,
-    /// The external writer where the final compressed EPUB bytes will be written to.
-    writer: W,
-    /// The internal ZIP writer, buffering the content before flushing to `self.writer`.
-    zip_writer: ZipWriter<Cursor<Vec<u8>>>,
+    /// The destination of the generated files.
+    sink: S,
 }
 
-impl<'a, W> EpubFile<'a, W>
+impl<'a, W> EpubFile<'a, ZipSink<'a, W>>
 where
     W: Write + Send,
 {
-    /// Creates a new `EpubFile` builder.
+    /// Creates a new `EpubFile` builder writing a ZIP archive.
     ///
-    /// This sets up the internal ZIP writer and configures the file options
+    /// This sets up the streaming ZIP writer and configures the file options
     /// based on the chosen compression method.
     ///
     /// # Arguments
@@ -56,121 +59,195 @@ where
     /// * `epub`: The EPUB data structure to be written.
     /// * `writer`: The output stream (e.g., a `File` or `Vec<u8>`) where the final `.epub` bytes will go.
     /// * `compression`: The default compression method to use for the files inside the ZIP archive.
-    pub fn new(epub: Epub<'a>, writer: W, compression: ZipCompression) -> EpubFile<'a, W> {
-        let compression = match compression {
-            ZipCompression::Stored => CompressionMethod::Stored,
-            ZipCompression::Deflated => CompressionMethod::Deflated,
-        };
-
-        Self {
-            epub,
-            writer,
-            options: SimpleFileOptions::default()
-                .compression_method(compression)
-                .unix_permissions(0o755),
-            zip_writer: ZipWriter::new(Cursor::new(Vec::new())),
-        }
+    pub fn new(epub: Epub<'a>, writer: W, compression: ZipCompression) -> Self {
+        let sink = ZipSink::new(&epub, writer, compression);
+        Self::with_sink(epub, sink)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<'a> EpubFile<'a, DirSink> {
+    /// Creates a new `EpubFile` builder writing an unpacked directory tree below `root`.
+    pub fn new_dir(epub: Epub<'a>, root: &Path) -> Self {
+        Self::with_sink(epub, DirSink::new(root))
     }
+}
 
-    /// Generates all necessary EPUB files, zips them up, and writes the final
-    /// archive to the output writer provided during initialization.
+impl<'a, S: Sink> EpubFile<'a, S> {
+    /// Creates a new `EpubFile` builder writing to any [`Sink`].
+    ///
+    /// The Unicode text of `epub` is normalized when the files are generated, unless
+    /// normalization is disabled.
+    pub fn with_sink(epub: Epub<'a>, sink: S) -> Self {
+        Self { epub, sink }
+    }
+
+    /// Generates all necessary EPUB files and writes them to the sink provided during initialization.
+    ///
+    /// The files are generated by [`EpubFiles`], in this order:
+    /// 1. The mandatory fixed files (`mimetype`, always first and stored, `container.xml`).
+    /// 2. The optional files (stylesheet, cover image, generic resources).
+    /// 3. The XHTML files of all contents.
+    /// 4. The central XML files (`content.opf` and `toc.ncx`), formatted unless
+    ///    pretty-printing is disabled.
+    /// 5. The build manifest (`META-INF/liber-manifest.json`), if enabled.
+    ///
+    /// The sink is then finalized (e.g. writing the central directory of the ZIP archive).
     ///
-    /// The process involves:
-    /// 1. Adding mandatory fixed files (`mimetype`, `container.xml`).
-    /// 2. Adding optional files (stylesheet, cover image, generic resources).
-    /// 3. Generating and adding all content XHTML files.
-    /// 4. Generating, formatting, and adding the central XML files (`content.opf` and `toc.ncx`).
-    /// 5. Finalizing the internal ZIP archive and writing the resulting bytes to the
-    ///    external `writer`.
+    /// Every file is written to the sink as soon as it is generated, so the whole archive is
+    /// never held in memory.
     ///
     /// # Returns
     ///
     /// Returns `crate::Result<()>` indicating success or failure in any step
     /// (file generation, XML formatting, or ZIP writing).
-    pub fn create(mut self) -> crate::Result<()> {
-        // 1. Add mandatory files
-        self.add_file(file_content::mimetype())?;
-        self.add_file(file_content::container())?;
-        self.add_file(file_content::display_options())?;
-
-        // 2. Add optional files (stylesheet, cover image, resources)
-        if let Some(stylesheet) = self.epub.stylesheet {
-            self.add_file(FileContent::new("OEBPS/style.css", stylesheet))?;
-        }
-
-        if let Some(ref cover_image) = self.epub.cover_image {
-            self.add_file(cover_image.file_content()?)?;
-        }
-
-        if let Some(ref resources) = self.epub.resources {
-            let contents = resources
-                .iter()
-                .map(|resource| resource.file_content())
-                .collect::<crate::Result<Vec<FileContent<String, Vec<u8>>>>>()?;
-
-            self.add_files(contents)?;
-        }
+    pub fn create(self) -> crate::Result<()> {
+        self.create_with_warnings().map(|_| ())
+    }
 
-        // 3. Generate and add content XHTML files
-        if let Some(ref contents) = self.epub.contents {
-            let mut file_number: usize = 0;
-            let mut file_contents: Vec<FileContent<String, String>> = Vec::new();
-            for content in contents {
-                let res = content.file_content(&mut file_number, self.epub.stylesheet.is_some())?;
-                file_contents.extend(res);
+    /// Generates all the EPUB files like [`EpubFile::create`], returning the non-fatal quality
+    /// issues found along the way (see [`Warning`]).
+    pub fn create_with_warnings(self) -> crate::Result<Vec<Warning>> {
+        let files = EpubFiles::new(self.epub)?;
+        let mut warnings = warning::model_warnings(files.epub());
+        let cover_constraint = CoverConstraint::new(files.epub());
+
+        write_files(self.sink, files, |file| {
+            warnings.extend(warning::size_warning(&file.filepath, file.bytes.len()));
+            if let Some(ref cover_constraint) = cover_constraint {
+                warnings.extend(cover_constraint.check(file)?);
             }
+            Ok(())
+        })?;
+        Ok(warnings)
+    }
 
-            self.add_files(file_contents)?;
-        }
+    /// Generates all the EPUB files like [`EpubFile::create`], returning the SHA-256 checksum
+    /// of every entry of the archive, in the order they are written.
+    pub fn create_with_checksums(self) -> crate::Result<Vec<Checksum>> {
+        let mut checksums = Vec::new();
+        write_files(self.sink, EpubFiles::new(self.epub)?, |file| {
+            checksums.push(Checksum::new(file)?);
+            Ok(())
+        })?;
+        Ok(checksums)
+    }
+}
 
-        // 4. Generate, format, and add OPF and NCX files
-        let mut content_opf = file_content::content_opf(&self.epub)?;
-        content_opf.format(xml::format(&content_opf.bytes)?);
-        self.add_file(content_opf)?;
+/// Writes every file of `files` to `sink`, passing it to `inspect` first, then finalizes the
+/// sink.
+fn write_files<S: Sink>(
+    mut sink: S,
+    files: EpubFiles<'_>,
+    mut inspect: impl FnMut(&FileContent<String, FileBytes>) -> crate::Result,
+) -> crate::Result {
+    for file in files {
+        let file = file?;
+        inspect(&file)
+            .and_then(|_| sink.write_file(&file.filepath, &file.bytes, is_mimetype(&file.filepath)))
+            .with_context(|| format!("entry '{}'", file.filepath))?;
+    }
+    sink.finish()
+}
 
-        let mut toc_ncx = file_content::toc_ncx(&self.epub)?;
-        toc_ncx.format(xml::format(&toc_ncx.bytes)?);
-        self.add_file(toc_ncx)?;
+/// Identifies the plugin and the file of an error returned by an [`EpubPlugin`] callback.
+pub(crate) fn plugin_context(plugin: &dyn EpubPlugin, filepath: &str) -> String {
+    format!("plugin '{}' on '{filepath}'", plugin.name())
+}
 
-        // 5. Finalize ZIP and flush to external writer
-        let buffer = self.zip_writer.finish()?;
-        self.writer.write_all(&buffer.into_inner())?;
+/// Loads the content of every resource, in parallel when the **`rayon` feature** is enabled.
+///
+/// The returned contents keep the order of `resources`.
+pub(crate) fn load_resources(
+    resources: &[ResourceItem<'_>],
+) -> crate::Result<Vec<FileContent<String, FileBytes>>> {
+    #[cfg(feature = "rayon")]
+    let resources = resources.par_iter();
+    #[cfg(not(feature = "rayon"))]
+    let resources = resources.iter();
+
+    resources.map(ResourceItem::file_content).collect()
+}
 
-        Ok(())
+/// Generates the `content.opf` file, formatted unless pretty-printing is disabled.
+pub(crate) fn render_content_opf(epub: &Epub<'_>) -> crate::Result<FileContent<String, String>> {
+    let mut content_opf = file_content::content_opf(epub)?;
+    for plugin in epub.plugins() {
+        plugin
+            .manifest_assembled(&mut content_opf.bytes)
+            .with_context(|| plugin_context(*plugin, &content_opf.filepath))?;
+    }
+    if epub.pretty_print {
+        content_opf.format(xml::format(&content_opf.bytes)?);
     }
+    Ok(content_opf)
+}
 
-    /// Adds a single `FileContent` item to the internal ZIP archive.
-    ///
-    /// This starts a new file entry in the ZIP using the configured compression
-    /// options and writes the file's content bytes.
-    ///
-    /// # Arguments
-    ///
-    /// * `file_content`: The structure holding the file path and content bytes.
-    fn add_file<F, B>(&mut self, file_content: FileContent<F, B>) -> crate::Result<()>
-    where
-        F: ToString,
-        B: AsRef<[u8]>,
-    {
-        self.zip_writer
-            .start_file(file_content.filepath.to_string(), self.options)?;
-        self.zip_writer.write_all(file_content.bytes.as_ref())?;
-        Ok(())
+/// Generates the `content.opf` and `toc.ncx` files (see [`render_content_opf`] and
+/// [`render_toc_ncx`]), both at once in parallel when `parallel` is set and the **`rayon`
+/// feature** is enabled.
+pub(crate) fn render_package(
+    epub: &Epub<'_>,
+    #[cfg_attr(not(feature = "rayon"), allow(unused_variables))] parallel: bool,
+) -> crate::Result<[FileContent<String, String>; 2]> {
+    #[cfg(feature = "rayon")]
+    let (content_opf, toc_ncx) = if parallel {
+        rayon::join(|| render_content_opf(epub), || render_toc_ncx(epub))
+    } else {
+        (render_content_opf(epub), render_toc_ncx(epub))
+    };
+    #[cfg(not(feature = "rayon"))]
+    let (content_opf, toc_ncx) = (render_content_opf(epub), render_toc_ncx(epub));
+
+    Ok([content_opf?, toc_ncx?])
+}
+
+/// Generates the `toc.ncx` file, formatted unless pretty-printing is disabled.
+pub(crate) fn render_toc_ncx(epub: &Epub<'_>) -> crate::Result<FileContent<String, String>> {
+    let mut toc_ncx = file_content::toc_ncx(epub)?;
+    if epub.pretty_print {
+        toc_ncx.format(xml::format(&toc_ncx.bytes)?);
     }
+    Ok(toc_ncx)
+}
 
-    /// Adds a vector of `FileContent` items to the internal ZIP archive.
-    ///
-    /// # Arguments
-    ///
-    /// * `file_contents`: A vector of file contents to add to the archive.
-    fn add_files<F, B>(&mut self, file_contents: Vec<FileContent<F, B>>) -> crate::Result<()>
-    where
-        F: ToString,
-        B: AsRef<[u8]>,
-    {
-        for fc in file_contents {
-            self.add_file(fc)?;
+/// Renders the XHTML files of all contents (and their subcontents), every file in parallel when
+/// `parallel` is set and the **`rayon` feature** is enabled.
+///
+/// Files are numbered sequentially in book order, counting from `number`, either way, so the
+/// output is the same. The rendered files are then passed to the `plugins`, in book order.
+pub(crate) fn render_contents(
+    contents: &[Content<'_>],
+    number: usize,
+    options: XhtmlOptions<'_>,
+    plugins: &[&dyn EpubPlugin],
+    #[cfg_attr(not(feature = "rayon"), allow(unused_variables))] parallel: bool,
+) -> crate::Result<Vec<FileContent<String, String>>> {
+    let documents = Content::documents(contents, number);
+    let render = |&(content, number): &(&Content<'_>, usize)| content.render(number, options);
+
+    #[cfg(feature = "rayon")]
+    let file_contents = if parallel {
+        documents.par_iter().map(render).collect()
+    } else {
+        documents
+            .iter()
+            .map(render)
+            .collect::<crate::Result<Vec<_>>>()
+    };
+    #[cfg(not(feature = "rayon"))]
+    let file_contents = documents
+        .iter()
+        .map(render)
+        .collect::<crate::Result<Vec<_>>>();
+
+    let mut file_contents = file_contents?;
+    for file_content in &mut file_contents {
+        for plugin in plugins {
+            plugin
+                .chapter_rendered(&file_content.filepath, &mut file_content.bytes)
+                .with_context(|| plugin_context(*plugin, &file_content.filepath))?;
         }
-        Ok(())
     }
+    Ok(file_contents)
 }
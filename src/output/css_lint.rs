@@ -0,0 +1,233 @@
+use crate::output::warning::Warning;
+
+/// Declarations (a property and its values) that break common reading systems, with the reason.
+const DECLARATIONS: &[(&str, &[&str], &str)] = &[
+    (
+        "position",
+        &["fixed", "sticky"],
+        "is ignored or breaks pagination in most reading systems",
+    ),
+    (
+        "display",
+        &["flex", "inline-flex", "grid", "inline-grid"],
+        "is not supported by RMSDK (Adobe Digital Editions) nor older Kindles",
+    ),
+];
+
+/// Value functions that RMSDK drops the whole declaration for.
+const FUNCTIONS: &[&str] = &["calc(", "var(", "min(", "max(", "clamp("];
+
+/// Viewport units, resolved inconsistently (or not at all) by paginated reading systems.
+const VIEWPORT_UNITS: &[&str] = &["vw", "vh", "vmin", "vmax"];
+
+/// Selector parts that RMSDK does not support, dropping the whole rule.
+const SELECTORS: &[&str] = &[
+    ":not(",
+    ":nth-child(",
+    ":nth-last-child(",
+    ":nth-of-type(",
+    ":nth-last-of-type(",
+    ":last-child",
+    ":first-of-type",
+    ":last-of-type",
+    ":only-child",
+    ":only-of-type",
+    ":empty",
+    "^=",
+    "$=",
+    "*=",
+];
+
+/// The reason of the warnings about unsupported selectors.
+const SELECTOR_REASON: &str =
+    "is not supported by RMSDK (Adobe Digital Editions), which drops the rule";
+
+/// The kind of block of a stylesheet being scanned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Block {
+    /// A block of rules: the top level, or a conditional group rule like `@media`.
+    Rules,
+    /// A block of declarations: a style rule, or an at-rule like `@font-face`.
+    Declarations,
+}
+
+/// Collects the [`Warning::IncompatibleCss`] of the stylesheet `css` at `path`: declarations
+/// (`position: fixed`, `display: flex`...), viewport units, value functions (`calc()`,
+/// `var()`...) and selectors (`:not()`, `:nth-child()`...) known to break common reading
+/// systems, with the line they are on.
+pub(crate) fn css_warnings(path: &str, css: &str) -> Vec<Warning> {
+    let css = strip_comments(css);
+    let mut warnings = Vec::new();
+    let mut blocks = vec![Block::Rules];
+    let mut buffer = String::new();
+    let mut line = 1;
+    let mut start_line = 1;
+
+    for c in css.chars() {
+        match c {
+            '{' => {
+                let prelude = buffer.trim();
+                let block = match prelude.strip_prefix('@') {
+                    Some(at_rule) if is_group_rule(at_rule) => Block::Rules,
+                    Some(_) => Block::Declarations,
+                    None => {
+                        selector_warnings(path, start_line, prelude, &mut warnings);
+                        Block::Declarations
+                    }
+                };
+                blocks.push(block);
+                buffer.clear();
+            }
+            ';' | '}' if blocks.last() == Some(&Block::Declarations) => {
+                declaration_warnings(path, start_line, buffer.trim(), &mut warnings);
+                buffer.clear();
+                if c == '}' {
+                    blocks.pop();
+                }
+            }
+            '}' => {
+                if blocks.len() > 1 {
+                    blocks.pop();
+                }
+                buffer.clear();
+            }
+            ';' => buffer.clear(),
+            c => {
+                if buffer.trim().is_empty() && !c.is_whitespace() {
+                    start_line = line;
+                }
+                buffer.push(c);
+            }
+        }
+        if c == '\n' {
+            line += 1;
+        }
+    }
+
+    warnings
+}
+
+/// Checks whether the at-rule `at_rule` (without the `@`) holds rules rather than
+/// declarations.
+fn is_group_rule(at_rule: &str) -> bool {
+    ["media", "supports", "document", "layer", "container"]
+        .iter()
+        .any(|name| at_rule.starts_with(name))
+}
+
+/// Collects the warnings of the selector list `selectors`, on `line`.
+fn selector_warnings(path: &str, line: usize, selectors: &str, warnings: &mut Vec<Warning>) {
+    for selector in selectors.split(',') {
+        if let Some(part) = SELECTORS.iter().find(|part| selector.contains(*part)) {
+            warnings.push(Warning::IncompatibleCss {
+                path: path.to_string(),
+                line,
+                construct: format!("selector {:?}: unsupported {part:?}", selector.trim()),
+                reason: SELECTOR_REASON.to_string(),
+            });
+        }
+    }
+}
+
+/// Collects the warnings of the `declaration` (`property: value`), on `line`.
+fn declaration_warnings(path: &str, line: usize, declaration: &str, warnings: &mut Vec<Warning>) {
+    let Some((property, value)) = declaration.split_once(':') else {
+        return;
+    };
+    let property = property.trim().to_ascii_lowercase();
+    let value = value.trim().to_ascii_lowercase();
+    let value = value.trim_end_matches("!important").trim();
+    let mut warn = |reason: &str| {
+        warnings.push(Warning::IncompatibleCss {
+            path: path.to_string(),
+            line,
+            construct: format!("{property}: {value}"),
+            reason: reason.to_string(),
+        });
+    };
+
+    if let Some((.., reason)) = DECLARATIONS
+        .iter()
+        .find(|(name, values, _)| property == *name && values.contains(&value))
+    {
+        warn(reason);
+    }
+    if property.starts_with("--") {
+        warn("custom properties are not supported by RMSDK (Adobe Digital Editions)");
+    } else if FUNCTIONS.iter().any(|function| value.contains(function)) {
+        warn("is not supported by RMSDK (Adobe Digital Editions), which drops the declaration");
+    } else if has_viewport_unit(value) {
+        warn("viewport units are not supported by RMSDK and resolve inconsistently when paginated");
+    }
+}
+
+/// Checks whether `value` has a length in a viewport unit (e.g. `50vh`).
+fn has_viewport_unit(value: &str) -> bool {
+    value
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == '.' || c == '-'))
+        .any(|token| {
+            VIEWPORT_UNITS.iter().any(|unit| {
+                token
+                    .strip_suffix(unit)
+                    .is_some_and(|number| !number.is_empty() && number.parse::<f64>().is_ok())
+            })
+        })
+}
+
+/// Replaces the comments of `css` with spaces, keeping their line breaks so lines still count.
+fn strip_comments(css: &str) -> String {
+    let mut stripped = String::with_capacity(css.len());
+    let mut rest = css;
+    while let Some(start) = rest.find("/*") {
+        stripped.push_str(&rest[..start]);
+        let comment_end = rest[start + 2..].find("*/").map(|end| start + 2 + end + 2);
+        let comment = &rest[start..comment_end.unwrap_or(rest.len())];
+        stripped.extend(comment.chars().map(|c| if c == '\n' { '\n' } else { ' ' }));
+        rest = &rest[comment_end.unwrap_or(rest.len())..];
+    }
+    stripped.push_str(rest);
+    stripped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::epub::Theme;
+
+    #[test]
+    fn test_css_warnings() {
+        let css = "/* position: fixed; */
+header { position: fixed; top: 0 }
+@media (min-width: 600px) {
+  .cover { height: 100vh; width: calc(100% - 2em); }
+}
+@font-face { font-family: Serif; src: url(fonts/serif.otf); }
+li:nth-child(2n), p { display: FLEX !important; margin: 0 1.5em; --gap: 1em; }
+";
+        let warnings = css_warnings("style.css", css)
+            .into_iter()
+            .map(|warning| warning.to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            warnings,
+            [
+                "style.css:2: position: fixed is ignored or breaks pagination in most reading systems",
+                "style.css:4: height: 100vh viewport units are not supported by RMSDK and resolve inconsistently when paginated",
+                "style.css:4: width: calc(100% - 2em) is not supported by RMSDK (Adobe Digital Editions), which drops the declaration",
+                "style.css:7: selector \"li:nth-child(2n)\": unsupported \":nth-child(\" is not supported by RMSDK (Adobe Digital Editions), which drops the rule",
+                "style.css:7: display: flex is not supported by RMSDK (Adobe Digital Editions) nor older Kindles",
+                "style.css:7: --gap: 1em custom properties are not supported by RMSDK (Adobe Digital Editions)",
+            ]
+        );
+
+        for theme in [
+            Theme::Classic,
+            Theme::Modern,
+            Theme::HighContrast,
+            Theme::Sepia,
+        ] {
+            assert!(css_warnings("style.css", &theme.css()).is_empty());
+        }
+    }
+}
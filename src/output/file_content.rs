@@ -1,4 +1,25 @@
-use crate::epub::{Content, ContentReference, Epub, ReferenceType};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fs,
+    io::{BufReader, Read, Write},
+    path::PathBuf,
+};
+
+use quick_xml::{
+    Writer,
+    events::{BytesDecl, BytesEnd, BytesStart, BytesText, Event},
+};
+
+use crate::{
+    epub::{
+        BookPart, COVER_PAGE_FILENAME, CalibreMetadata, Certification, Content, ContentReference,
+        Epub, FixedLayout, GENERATED_COVER_FILENAME, GENERATED_COVER_MEDIA_TYPE, NavList,
+        Numbering, PageTarget, ReferenceType, ResourceItem, UNIQUE_GUIDE_TYPES, manifest_id,
+        page_labels,
+    },
+    output::obfuscation,
+};
 
 /// A generic struct representing a file within the EPUB archive.
 ///
@@ -15,14 +36,13 @@ pub struct FileContent<F, B> {
 impl<F, B> FileContent<F, B>
 where
     F: Into<String>,
-    B: AsRef<[u8]>,
 {
     /// Creates a new `FileContent` instance.
     ///
     /// # Arguments
     ///
     /// * `filepath`: The path of the file. Must be convertible to `String`.
-    /// * `bytes`: The content of the file. Must be convertible to a byte slice.
+    /// * `bytes`: The content of the file (e.g. a `String`, a byte slice or [`FileBytes`]).
     pub fn new(filepath: F, bytes: B) -> FileContent<F, B> {
         Self { filepath, bytes }
     }
@@ -37,6 +57,192 @@ where
     }
 }
 
+#[cfg(feature = "async")]
+impl<F> FileContent<F, String> {
+    /// Asynchronously formats the XML content (see [`crate::output::xml::async_format`]),
+    /// moving it to the blocking task and back instead of copying it.
+    ///
+    /// This method is only available when the **`async` feature** is enabled.
+    pub(crate) async fn async_format(
+        mut self,
+        spawner: Option<&dyn crate::epub::Spawner>,
+    ) -> crate::Result<Self> {
+        self.bytes = crate::output::xml::async_format(self.bytes, spawner).await?;
+        Ok(self)
+    }
+}
+
+/// The size of the chunks resource files are copied in, so memory use is bounded by it
+/// rather than by the size of the files: the ZIP entries are passed on to the writer as they
+/// are copied, by both the synchronous and the asynchronous API.
+pub(crate) const COPY_CHUNK_SIZE: usize = 64 * 1024;
+
+/// The bytes of a generated file: in memory, a resource file copied in chunks when it is
+/// written, or a memory-mapped resource file (**`mmap` feature**).
+pub enum FileBytes {
+    /// Bytes in memory.
+    Owned(Vec<u8>),
+    /// A resource file, read in chunks of bounded size when it is written to the archive.
+    File {
+        /// The path of the file.
+        path: PathBuf,
+        /// The size of the file in bytes, when it was loaded.
+        len: u64,
+    },
+    /// A memory-mapped resource file, read by the OS as it is written to the archive.
+    #[cfg(feature = "mmap")]
+    Mapped(memmap2::Mmap),
+}
+
+impl FileBytes {
+    /// Gets the size of the bytes.
+    pub fn len(&self) -> u64 {
+        match self {
+            Self::Owned(bytes) => bytes.len() as u64,
+            Self::File { len, .. } => *len,
+            #[cfg(feature = "mmap")]
+            Self::Mapped(mmap) => mmap.len() as u64,
+        }
+    }
+
+    /// Checks whether there are no bytes.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Gets the bytes if they are in memory (or mapped), without reading a resource file.
+    pub fn as_slice(&self) -> Option<&[u8]> {
+        match self {
+            Self::Owned(bytes) => Some(bytes),
+            Self::File { .. } => None,
+            #[cfg(feature = "mmap")]
+            Self::Mapped(mmap) => Some(mmap),
+        }
+    }
+
+    /// Gets the bytes, reading them into memory first if they come from a resource file.
+    ///
+    /// # Errors
+    /// Returns an error if the resource file cannot be read.
+    pub fn load(&mut self) -> std::io::Result<&[u8]> {
+        if let Self::File { path, .. } = self {
+            *self = Self::Owned(fs::read(path)?);
+        }
+        Ok(self.as_slice().unwrap_or_default())
+    }
+
+    /// Gets a mutable vector of the bytes, copying them into memory first if they come from a
+    /// resource file or are mapped.
+    ///
+    /// # Errors
+    /// Returns an error if the resource file cannot be read.
+    pub fn to_mut(&mut self) -> std::io::Result<&mut Vec<u8>> {
+        match self {
+            Self::Owned(bytes) => Ok(bytes),
+            Self::File { path, .. } => {
+                *self = Self::Owned(fs::read(path)?);
+                self.to_mut()
+            }
+            #[cfg(feature = "mmap")]
+            Self::Mapped(mmap) => {
+                *self = Self::Owned(mmap.to_vec());
+                self.to_mut()
+            }
+        }
+    }
+
+    /// Converts the bytes into a vector, reading or copying them into memory if needed.
+    ///
+    /// # Errors
+    /// Returns an error if the resource file cannot be read.
+    pub fn into_vec(self) -> std::io::Result<Vec<u8>> {
+        match self {
+            Self::Owned(bytes) => Ok(bytes),
+            Self::File { path, .. } => fs::read(path),
+            #[cfg(feature = "mmap")]
+            Self::Mapped(mmap) => Ok(mmap.to_vec()),
+        }
+    }
+
+    /// Gets a reader of the bytes, opening the resource file if they come from one.
+    ///
+    /// # Errors
+    /// Returns an error if the resource file cannot be opened.
+    pub fn reader(&self) -> std::io::Result<Box<dyn Read + Send + '_>> {
+        match self {
+            Self::File { path, .. } => Ok(Box::new(fs::File::open(path)?)),
+            _ => Ok(Box::new(self.as_slice().unwrap_or_default())),
+        }
+    }
+
+    /// Copies the bytes to `writer`, in chunks of bounded size if they come from a resource
+    /// file, returning the number of bytes copied.
+    ///
+    /// # Errors
+    /// Returns an error if the resource file cannot be read or `writer` fails.
+    pub fn copy_to<W: Write + ?Sized>(&self, writer: &mut W) -> std::io::Result<u64> {
+        match self.as_slice() {
+            Some(bytes) => writer.write_all(bytes).map(|_| bytes.len() as u64),
+            None => {
+                let mut reader = BufReader::with_capacity(COPY_CHUNK_SIZE, self.reader()?);
+                std::io::copy(&mut reader, writer)
+            }
+        }
+    }
+}
+
+impl From<Vec<u8>> for FileBytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self::Owned(bytes)
+    }
+}
+
+/// Bytes in memory (or mapped) are equal when their content is; bytes of resource files are
+/// equal when their paths and sizes are.
+impl PartialEq for FileBytes {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (
+                Self::File { path, len },
+                Self::File {
+                    path: other_path,
+                    len: other_len,
+                },
+            ) => path == other_path && len == other_len,
+            _ => self.as_slice().is_some() && self.as_slice() == other.as_slice(),
+        }
+    }
+}
+
+impl Eq for FileBytes {}
+
+impl PartialEq<[u8]> for FileBytes {
+    fn eq(&self, other: &[u8]) -> bool {
+        self.as_slice() == Some(other)
+    }
+}
+
+impl PartialEq<Vec<u8>> for FileBytes {
+    fn eq(&self, other: &Vec<u8>) -> bool {
+        self.as_slice() == Some(other.as_slice())
+    }
+}
+
+impl std::fmt::Debug for FileBytes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Owned(bytes) => f.debug_tuple("Owned").field(bytes).finish(),
+            Self::File { path, len } => f
+                .debug_struct("File")
+                .field("path", path)
+                .field("len", len)
+                .finish(),
+            #[cfg(feature = "mmap")]
+            Self::Mapped(mmap) => write!(f, "Mapped({} bytes)", mmap.len()),
+        }
+    }
+}
+
 /// Creates a `FileContent` for the mandatory EPUB **container.xml** file.
 ///
 /// This file specifies the location of the OPF package document.
@@ -64,68 +270,131 @@ pub fn mimetype<'a>() -> FileContent<&'a str, &'a [u8]> {
 /// Creates a `FileContent` for the **com.apple.ibooks.display-options.xml** file.
 ///
 /// This is a non-mandatory file used by iBooks to specify display options,
-/// in this case, enabling specified fonts.
-pub fn display_options<'a>() -> FileContent<&'a str, &'a [u8]> {
+/// in this case, enabling specified fonts (and the fixed layout of pre-paginated books).
+pub fn display_options(fixed_layout: bool) -> FileContent<&'static str, String> {
+    let fixed_layout = if fixed_layout {
+        "\n\t\t<option name=\"fixed-layout\">true</option>"
+    } else {
+        ""
+    };
     FileContent::new(
         "META-INF/com.apple.ibooks.display-options.xml",
-        r#"<?xml version="1.0" encoding="utf-8"?>
+        format!(
+            r#"<?xml version="1.0" encoding="utf-8"?>
 <display_options>
 	<platform name="*">
-		<option name="specified-fonts">true</option>
+		<option name="specified-fonts">true</option>{fixed_layout}
 	</platform>
 </display_options>
         "#
-        .as_bytes(),
+        ),
     )
 }
 
-/// A helper struct for efficiently building the content of XML files as a `String`.
+/// Creates a `FileContent` for the **META-INF/encryption.xml** file.
+///
+/// This file declares every resource mangled with the IDPF font obfuscation algorithm,
+/// so reading systems know to de-obfuscate them.
+///
+/// # Returns
 ///
-/// It wraps a single `String` and provides methods for appending various values,
-/// including conditional and optional strings, which is useful for generating XML dynamically.
-#[derive(Debug)]
-pub struct ContentBuilder(String);
+/// Returns `None` if no resource is obfuscated.
+pub fn encryption(epub: &Epub<'_>) -> Option<FileContent<&'static str, String>> {
+    let encrypted_data = epub
+        .resources
+        .as_ref()?
+        .iter()
+        .filter(|resource| resource.obfuscated)
+        .filter_map(|resource| resource.filename().ok())
+        .map(|filename| {
+            format!(
+                r#"<enc:EncryptedData><enc:EncryptionMethod Algorithm="{}"/><enc:CipherData><enc:CipherReference URI="OEBPS/{filename}"/></enc:CipherData></enc:EncryptedData>"#,
+                obfuscation::ALGORITHM
+            )
+        })
+        .collect::<String>();
 
-impl ContentBuilder {
-    /// Appends a string-like value to the builder's content.
-    ///
-    /// # Type Parameters
-    ///
-    /// * `S`: Any type that can be converted into a `String` (e.g., `&str` or `String`).
-    pub fn add<S: Into<String>>(&mut self, value: S) {
-        self.0.push_str(&value.into());
+    if encrypted_data.is_empty() {
+        return None;
     }
 
-    /// Appends an optional string-like value to the builder's content if it is `Some`.
-    ///
-    /// If `value` is `None`, nothing is appended.
-    ///
-    /// # Type Parameters
-    ///
-    /// * `S`: Any type that can be converted into a `String`.
-    pub fn add_optional<S: Into<String>>(&mut self, value: Option<S>) {
-        if let Some(value) = value {
-            self.0.push_str(&value.into());
-        }
+    Some(FileContent::new(
+        "META-INF/encryption.xml",
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?><encryption xmlns="urn:oasis:names:tc:opendocument:xmlns:container" xmlns:enc="http://www.w3.org/2001/04/xmlenc#">{encrypted_data}</encryption>"#
+        ),
+    ))
+}
+
+/// A helper struct for building the content of XML files on top of `quick_xml::Writer` events.
+///
+/// Text and attribute values are escaped by the writer, so titles or names containing
+/// characters such as `&` or `<` always produce well-formed XML.
+pub struct XmlBuilder(Writer<Vec<u8>>);
+
+impl XmlBuilder {
+    /// Creates a new builder, starting the document with the XML declaration, with `capacity`
+    /// bytes preallocated for the whole document.
+    pub fn with_capacity(capacity: usize) -> crate::Result<Self> {
+        let mut writer = Writer::new(Vec::with_capacity(capacity));
+        writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+        Ok(Self(writer))
     }
 
-    /// Appends a specific string-like value only if the condition-providing `Option` is `Some`.
-    ///
-    /// This is useful for including fixed XML tags only when a related field exists.
-    ///
-    /// # Type Parameters
+    /// Writes a `<!DOCTYPE ...>` declaration with the given (unescaped) content.
+    pub fn doctype(&mut self, doctype: &str) -> crate::Result {
+        self.0
+            .write_event(Event::DocType(BytesText::from_escaped(doctype)))?;
+        Ok(())
+    }
+
+    /// Opens the element `name` with the given attributes. It must be closed with [`Self::end`].
+    pub fn start(&mut self, name: &str, attributes: &[(&str, &str)]) -> crate::Result {
+        self.0.write_event(Event::Start(
+            BytesStart::new(name).with_attributes(attributes.iter().copied()),
+        ))?;
+        Ok(())
+    }
+
+    /// Closes the element `name`.
+    pub fn end(&mut self, name: &str) -> crate::Result {
+        self.0.write_event(Event::End(BytesEnd::new(name)))?;
+        Ok(())
+    }
+
+    /// Writes the self-closing element `name` with the given attributes.
+    pub fn empty(&mut self, name: &str, attributes: &[(&str, &str)]) -> crate::Result {
+        self.0.write_event(Event::Empty(
+            BytesStart::new(name).with_attributes(attributes.iter().copied()),
+        ))?;
+        Ok(())
+    }
+
+    /// Writes the element `name` with the given attributes, holding only `text`.
+    pub fn text(&mut self, name: &str, attributes: &[(&str, &str)], text: &str) -> crate::Result {
+        self.start(name, attributes)?;
+        self.0.write_event(Event::Text(BytesText::new(text)))?;
+        self.end(name)
+    }
+
+    /// Writes the element `name` with the given attributes, holding only `text` if it is `Some`.
     ///
-    /// * `S`: Any type that can be converted into a `String`.
-    /// * `T`: The inner type of the condition `Option`.
-    pub fn add_if_some<T, S: Into<String>>(&mut self, value: S, some: Option<T>) {
-        if some.is_some() {
-            self.0.push_str(&value.into());
+    /// If `text` is `None`, nothing is written.
+    pub fn text_optional(
+        &mut self,
+        name: &str,
+        attributes: &[(&str, &str)],
+        text: Option<&str>,
+    ) -> crate::Result {
+        match text {
+            Some(text) => self.text(name, attributes, text),
+            None => Ok(()),
         }
     }
 
     /// Consumes the builder and returns the assembled content as a `String`.
-    pub fn build(self) -> String {
-        self.0
+    pub fn build(self) -> crate::Result<String> {
+        Ok(String::from_utf8(self.0.into_inner())?)
     }
 }
 
@@ -144,79 +413,245 @@ impl ContentBuilder {
 /// "OEBPS/content.opf" with the generated XML content.
 pub fn content_opf(epub: &Epub<'_>) -> crate::Result<FileContent<String, String>> {
     let metadata = &epub.metadata;
+    let mut xml = XmlBuilder::with_capacity(content_opf_capacity(epub))?;
+    let mut filename = String::new();
+
+    xml.start(
+        "package",
+        &[
+            ("version", "2.0"),
+            ("unique-identifier", "BookId"),
+            ("xmlns", "http://www.idpf.org/2007/opf"),
+        ],
+    )?;
 
-    let mut content_builder = ContentBuilder(String::from(
-        r#"<?xml version="1.0" encoding="utf-8"?><package version="2.0" unique-identifier="BookId" xmlns="http://www.idpf.org/2007/opf">
-        <metadata xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:opf="http://www.idpf.org/2007/opf">"#,
-    ));
-
-    content_builder.add(metadata.title_as_metadata_xml());
-    content_builder.add(metadata.language.as_metadata_xml());
-    content_builder.add(metadata.identifier.as_metadata_xml());
-    content_builder.add_optional(metadata.creator_as_metadata_xml());
-    content_builder.add_optional(metadata.contributor_as_metadata_xml());
-    content_builder.add_optional(metadata.publisher_as_metadata_xml());
-    content_builder.add_optional(metadata.date_as_metadata_xml());
-    content_builder.add_optional(metadata.subject_as_metadata_xml());
-    content_builder.add_optional(metadata.description_as_metadata_xml());
-    content_builder.add_optional(epub.cover_image_as_metadata_xml());
-    content_builder.add(
-        r#"</metadata><manifest><item id="ncx" href="toc.ncx" media-type="application/x-dtbncx+xml" />"#,
-    );
-
-    content_builder.add_if_some(
-        r#"<item id="style.css" href="style.css" media-type="text/css"/>"#,
-        epub.stylesheet.as_ref(),
-    );
-
-    content_builder.add_optional(epub.cover_image_as_manifest_xml());
+    // Metadata
+    xml.start(
+        "metadata",
+        &[
+            ("xmlns:dc", "http://purl.org/dc/elements/1.1/"),
+            ("xmlns:opf", "http://www.idpf.org/2007/opf"),
+        ],
+    )?;
+    xml.text("dc:title", &[], &metadata.title)?;
+    xml.text("dc:language", &[], metadata.language.as_ref())?;
+    xml.text(
+        "dc:identifier",
+        &[
+            ("id", "BookId"),
+            ("opf:scheme", &metadata.identifier.to_string()),
+        ],
+        &String::from(&metadata.identifier),
+    )?;
+    xml.text_optional(
+        "dc:creator",
+        &[("opf:role", "aut")],
+        metadata.creator.as_deref(),
+    )?;
+    xml.text_optional(
+        "dc:contributor",
+        &[("opf:role", "trl")],
+        metadata.contributor.as_deref(),
+    )?;
+    xml.text_optional("dc:publisher", &[], metadata.publisher.as_deref())?;
+    for (name, content) in [
+        ("schema:publisherImprint", &metadata.imprint),
+        ("schema:locationCreated", &metadata.publication_place),
+    ] {
+        if let Some(content) = content {
+            xml.empty("meta", &[("name", name), ("content", content)])?;
+        }
+    }
+    xml.text_optional(
+        "dc:date",
+        &[("opf:event", "publication")],
+        metadata
+            .date
+            .map(|date| date.format("%Y-%m-%d").to_string())
+            .as_deref(),
+    )?;
+    xml.text_optional("dc:subject", &[], metadata.subject.as_deref())?;
+    xml.text_optional("dc:description", &[], metadata.description.as_deref())?;
+    for (name, content) in [
+        (
+            "dcterms:audience",
+            metadata.audience.as_ref().map(ToString::to_string),
+        ),
+        ("dcterms:educationLevel", metadata.education_level.clone()),
+        (
+            "schema:typicalAgeRange",
+            metadata.age_range.map(|range| range.to_string()),
+        ),
+    ] {
+        if let Some(ref content) = content {
+            xml.empty("meta", &[("name", name), ("content", content)])?;
+        }
+    }
+    if let Some(ref conformance) = metadata.conforms_to {
+        xml.empty(
+            "meta",
+            &[
+                ("name", "dcterms:conformsTo"),
+                ("content", &conformance.to_string()),
+            ],
+        )?;
+    }
+    for (name, content) in metadata.certification.iter().flat_map(Certification::metas) {
+        xml.empty("meta", &[("name", name), ("content", content)])?;
+    }
+    if let Some(cover_image_id) = epub.cover_image_id() {
+        xml.empty("meta", &[("name", "cover"), ("content", &cover_image_id)])?;
+    }
+    if let Some(ref generator) = epub.generator {
+        xml.empty("meta", &[("name", "generator"), ("content", generator)])?;
+    }
+    for (name, content) in epub.calibre.iter().flat_map(CalibreMetadata::metas) {
+        xml.empty("meta", &[("name", &name), ("content", &content)])?;
+    }
+    for (name, content) in epub.fixed_layout.iter().flat_map(FixedLayout::metas) {
+        xml.empty("meta", &[("name", name), ("content", &content)])?;
+    }
+    xml.end("metadata")?;
+
+    // Manifest
+    xml.start("manifest", &[])?;
+    manifest_item(&mut xml, "ncx", "toc.ncx", "application/x-dtbncx+xml")?;
+
+    if epub.has_stylesheet() {
+        manifest_item(&mut xml, "style.css", "style.css", "text/css")?;
+    }
+
+    match epub.cover_image {
+        Some(ref cover_image) => resource_manifest_item(&mut xml, cover_image)?,
+        None if epub.generated_cover.is_some() => manifest_item(
+            &mut xml,
+            GENERATED_COVER_FILENAME,
+            GENERATED_COVER_FILENAME,
+            GENERATED_COVER_MEDIA_TYPE,
+        )?,
+        None => {}
+    }
 
     if let Some(ref resources) = epub.resources {
         for resource in resources {
-            content_builder.add_optional(resource.as_manifest_xml());
+            resource_manifest_item(&mut xml, resource)?;
         }
     }
 
-    create_content_chain(
-        &mut 0,
-        &mut content_builder,
-        epub.contents.as_deref(),
-        |filename, _| {
-            format!(
-                r#"<item id="{filename}" href="{filename}" media-type="application/xhtml+xml"/>"#
-            )
-        },
-    )?;
-
-    content_builder.add(r#"</manifest><spine toc="ncx">"#);
+    if epub.has_cover_page() {
+        manifest_item(
+            &mut xml,
+            COVER_PAGE_FILENAME,
+            COVER_PAGE_FILENAME,
+            "application/xhtml+xml",
+        )?;
+    }
 
     create_content_chain(
         &mut 0,
-        &mut content_builder,
+        &mut xml,
+        &mut filename,
         epub.contents.as_deref(),
-        |filename, _| format!(r#"<itemref idref="{filename}"/>"#),
+        &mut |xml, filename, _| manifest_item(xml, filename, filename, "application/xhtml+xml"),
     )?;
-
-    content_builder.add(r#"</spine><guide>"#);
-
+    xml.end("manifest")?;
+
+    // Spine
+    if epub
+        .fixed_layout
+        .is_some_and(|layout| layout.is_right_to_left())
+    {
+        xml.start(
+            "spine",
+            &[("toc", "ncx"), ("page-progression-direction", "rtl")],
+        )?;
+    } else {
+        xml.start("spine", &[("toc", "ncx")])?;
+    }
+    if epub.has_cover_page() {
+        xml.empty("itemref", &[("idref", COVER_PAGE_FILENAME)])?;
+    }
+    if epub.spine_order.is_some() {
+        for filename in epub.spine() {
+            xml.empty("itemref", &[("idref", &filename)])?;
+        }
+    } else {
+        create_content_chain(
+            &mut 0,
+            &mut xml,
+            &mut filename,
+            epub.contents.as_deref(),
+            &mut |xml, filename, _| xml.empty("itemref", &[("idref", filename)]),
+        )?;
+    }
+    xml.end("spine")?;
+
+    // Guide, with only the first reference of the types a book has at most one of
+    let mut unique_types = Vec::new();
+    xml.start("guide", &[])?;
+    if epub.has_cover_page() {
+        unique_types.push("cover");
+        xml.empty(
+            "reference",
+            &[
+                ("type", "cover"),
+                ("title", "Cover"),
+                ("href", COVER_PAGE_FILENAME),
+            ],
+        )?;
+    }
     create_content_chain(
         &mut 0,
-        &mut content_builder,
+        &mut xml,
+        &mut filename,
         epub.contents.as_deref(),
-        |filename, reference_type| {
+        &mut |xml, filename, reference_type| {
             let (ref_type, title) = reference_type.type_and_title();
-            format!(r#"<reference type="{ref_type}" title="{title}" href="{filename}"/>"#,)
+            if let Some(unique_type) = UNIQUE_GUIDE_TYPES.iter().find(|t| **t == ref_type) {
+                if unique_types.contains(unique_type) {
+                    return Ok(());
+                }
+                unique_types.push(unique_type);
+            }
+            xml.empty(
+                "reference",
+                &[("type", ref_type), ("title", title), ("href", filename)],
+            )
         },
     )?;
+    xml.end("guide")?;
 
-    content_builder.add(r#"</guide></package>"#);
+    xml.end("package")?;
 
     Ok(FileContent::new(
         "OEBPS/content.opf".to_string(),
-        content_builder.build(),
+        xml.build()?,
     ))
 }
 
+/// Writes an `<item>` element of the package manifest.
+fn manifest_item(xml: &mut XmlBuilder, id: &str, href: &str, media_type: &str) -> crate::Result {
+    xml.empty(
+        "item",
+        &[("id", id), ("href", href), ("media-type", media_type)],
+    )
+}
+
+/// Writes the `<item>` element of the package manifest declaring `resource`.
+///
+/// Nothing is written if the filename of the resource cannot be extracted.
+fn resource_manifest_item(xml: &mut XmlBuilder, resource: &ResourceItem<'_>) -> crate::Result {
+    match resource.filename() {
+        Ok(filename) => manifest_item(
+            xml,
+            &manifest_id(&filename),
+            &filename,
+            resource.media_type(),
+        ),
+        Err(_) => Ok(()),
+    }
+}
+
 /// A recursive private helper function used by `content_opf` to traverse the
 /// hierarchical content structure (`epub.contents`) and generate repeated XML
 /// elements (manifest items, spine references, or guide references).
@@ -224,10 +659,11 @@ pub fn content_opf(epub: &Epub<'_>) -> crate::Result<FileContent<String, String>
 /// # Arguments
 ///
 /// * `file_number`: A mutable counter to assign unique filenames/IDs to content documents.
-/// * `cb`: A mutable reference to the `ContentBuilder` to append the generated XML.
+/// * `xml`: A mutable reference to the `XmlBuilder` to write the generated XML to.
+/// * `filename`: A buffer reused for the generated filenames.
 /// * `contents`: An `Option` containing a slice of the current level of `Content` to process.
-/// * `f`: A function pointer that takes the generated filename and its `ReferenceType` and
-///   returns the specific XML element string to be added (e.g., a `<item>` tag).
+/// * `f`: A function that takes the builder, the generated filename and its
+///   `ReferenceType`, and writes the specific XML element (e.g., an `<item>` tag).
 ///
 /// # Returns
 ///
@@ -235,21 +671,22 @@ pub fn content_opf(epub: &Epub<'_>) -> crate::Result<FileContent<String, String>
 /// (not ending with `.xhtml`).
 fn create_content_chain(
     file_number: &mut usize,
-    cb: &mut ContentBuilder,
+    xml: &mut XmlBuilder,
+    filename: &mut String,
     contents: Option<&[Content<'_>]>,
-    f: fn(String, &ReferenceType) -> String,
+    f: &mut dyn FnMut(&mut XmlBuilder, &str, &ReferenceType) -> crate::Result,
 ) -> crate::Result {
     if let Some(contents) = contents {
         for con in contents {
             *file_number += 1;
-            let filename = con.filename(*file_number).into_owned();
-            if !filename.ends_with(".xhtml") {
-                return Err(crate::Error::ContentFilename(filename));
+            let name = con.filename_in(*file_number, filename);
+            if !name.ends_with(".xhtml") {
+                return Err(crate::Error::ContentFilename(name.to_string()));
             }
 
-            cb.add(f(filename, &con.reference_type));
+            f(xml, name, &con.reference_type)?;
 
-            create_content_chain(file_number, cb, con.subcontents.as_deref(), f)?;
+            create_content_chain(file_number, xml, filename, con.subcontents.as_deref(), f)?;
         }
     }
     Ok(())
@@ -258,7 +695,8 @@ fn create_content_chain(
 /// Generates the **toc.ncx** (Navigation Control File for XML) file for the EPUB.
 ///
 /// This file defines the EPUB's table of contents, including the hierarchical
-/// structure of the book's sections and subsections (`navMap`).
+/// structure of the book's sections and subsections (`navMap`), followed by the
+/// print page targets (`pageList`) and the secondary lists (`navList`), if any.
 ///
 /// # Arguments
 ///
@@ -270,164 +708,489 @@ fn create_content_chain(
 /// "OEBPS/toc.ncx" with the generated XML content.
 pub fn toc_ncx(epub: &Epub<'_>) -> crate::Result<FileContent<String, String>> {
     let metadata = &epub.metadata;
+    let mut xml = XmlBuilder::with_capacity(toc_ncx_capacity(epub))?;
 
-    let mut content_builder = ContentBuilder(String::from(
-        r#"<?xml version="1.0" encoding="UTF-8"?><!DOCTYPE ncx PUBLIC "-//NISO//DTD ncx 2005-1//EN" "http://www.daisy.org/z3986/2005/ncx-2005-1.dtd">
-        <ncx xmlns="http://www.daisy.org/z3986/2005/ncx/" version="2005-1"><head>"#,
-    ));
+    xml.doctype(
+        r#"ncx PUBLIC "-//NISO//DTD ncx 2005-1//EN" "http://www.daisy.org/z3986/2005/ncx-2005-1.dtd""#,
+    )?;
+    xml.start(
+        "ncx",
+        &[
+            ("xmlns", "http://www.daisy.org/z3986/2005/ncx/"),
+            ("version", "2005-1"),
+        ],
+    )?;
 
-    content_builder.add(metadata.identifier.as_toc_xml());
-    content_builder.add(epub.level_as_toc_xml());
+    let page_list = epub.page_list.as_deref().unwrap_or_default();
+    let page_labels = page_labels(page_list, &epub.numbering);
+    let max_page_number = page_labels
+        .iter()
+        .filter_map(|(_, value)| *value)
+        .max()
+        .unwrap_or(0);
+
+    xml.start("head", &[])?;
+    for (name, content) in [
+        ("dtb:uid", String::from(&metadata.identifier)),
+        ("dtb:depth", toc_depth(epub).to_string()),
+        ("dtb:totalPageCount", page_list.len().to_string()),
+        ("dtb:maxPageNumber", max_page_number.to_string()),
+    ] {
+        xml.empty("meta", &[("name", name), ("content", &content)])?;
+    }
+    xml.end("head")?;
+
+    xml.start("docTitle", &[])?;
+    xml.text("text", &[], &metadata.title)?;
+    xml.end("docTitle")?;
+
+    let mut nav_map = NavMap {
+        xml: &mut xml,
+        play_order: 0,
+        play_orders: HashMap::new(),
+        max_depth: epub.toc_depth.unwrap_or(usize::MAX),
+        flat: epub.flat_toc,
+        numbering: epub.numbering,
+        part_numbers: HashMap::new(),
+        id: String::new(),
+        number: String::new(),
+    };
 
-    content_builder.add(format!(r#"<meta name="dtb:totalPageCount" content="0"/><meta name="dtb:maxPageNumber" content="0"/></head>
-                        <docTitle><text>{}</text></docTitle><navMap>"#, metadata.title));
+    nav_map.xml.start("navMap", &[])?;
+    if let Some(ref contents) = epub.contents {
+        nav_map.contents(&mut 0, contents, 1)?;
+    }
+    nav_map.xml.end("navMap")?;
 
-    content_builder.add_optional(
-        epub.contents
-            .as_ref()
-            .map(|contents| contents_to_nav_point(&mut 0, &mut 0, contents)),
-    );
+    if !page_list.is_empty() {
+        nav_map.page_list(page_list, &page_labels)?;
+    }
+    for (list_number, nav_list) in epub.nav_lists.iter().flatten().enumerate() {
+        nav_map.nav_list(list_number + 1, nav_list)?;
+    }
 
-    content_builder.add(r#"</navMap></ncx>"#);
+    xml.end("ncx")?;
 
-    Ok(FileContent::new(
-        "OEBPS/toc.ncx".to_string(),
-        content_builder.build(),
-    ))
+    Ok(FileContent::new("OEBPS/toc.ncx".to_string(), xml.build()?))
 }
 
-/// A recursive private helper function to generate the `navPoint` elements for the `toc.ncx` file.
-///
-/// It traverses the hierarchical content structure and creates the corresponding
-/// nested `<navPoint>` XML tags for the table of contents.
-///
-/// # Arguments
-///
-/// * `play_order`: A mutable counter used to generate the unique sequential `playOrder` attribute.
-/// * `contents`: A slice of `Content` items at the current hierarchy level.
-///
-/// # Returns
-///
-/// Returns an `Option<String>`: `Some(String)` containing the generated XML for the
-/// navigation points, or `None` if the input slice is empty.
-fn contents_to_nav_point(
-    play_order: &mut usize,
-    file_number: &mut usize,
-    contents: &[Content<'_>],
-) -> String {
-    let mut result = String::new();
-    for content in contents {
-        *play_order += 1;
-        let current_play_order = *play_order;
-
-        *file_number += 1;
-        let filename = &content.filename(*file_number);
-
-        let nav_point = format!(
-            r#"<navPoint id="navPoint-{current_play_order}" playOrder="{current_play_order}">
-            <navLabel><text>{text}</text></navLabel>
-            <content src="{filename}"/>{content_references}{subs}</navPoint>"#,
-            text = content.title(),
-            content_references = content
-                .content_references
-                .as_ref()
-                .map(|content_references| content_references_to_nav_point(
-                    (current_play_order, filename),
-                    play_order,
-                    "",
-                    content_references,
-                    &mut 0
-                ))
-                .unwrap_or_default(),
-            subs = content
+/// The estimated size of the fixed parts of the `content.opf` and `toc.ncx` files (declaration,
+/// namespaces, head and metadata elements), in bytes.
+pub(crate) const XML_BASE_CAPACITY: usize = 1024;
+
+/// The estimated size of the elements written for every content document in the `content.opf`
+/// file (manifest item, spine itemref and guide reference), in bytes, without its title.
+const OPF_CONTENT_CAPACITY: usize = 192;
+
+/// The estimated size of the manifest item of a resource in the `content.opf` file, in bytes,
+/// without its id and href.
+const OPF_RESOURCE_CAPACITY: usize = 64;
+
+/// The estimated size of a navigation point or target of the `toc.ncx` file, in bytes, without
+/// its label and `src`.
+const NCX_NAV_POINT_CAPACITY: usize = 128;
+
+/// Estimates the size of the `content.opf` file of `epub` from its model, so the document is
+/// written into a buffer allocated once, even for books with thousands of chapters.
+fn content_opf_capacity(epub: &Epub<'_>) -> usize {
+    let contents = epub.contents.as_deref().unwrap_or_default();
+    let resources = epub
+        .resources
+        .iter()
+        .flatten()
+        .map(|resource| OPF_RESOURCE_CAPACITY + 2 * resource.filename().map_or(0, |f| f.len()))
+        .sum::<usize>();
+
+    XML_BASE_CAPACITY
+        + epub.metadata.description.as_ref().map_or(0, String::len)
+        + resources
+        + contents_capacity(contents, OPF_CONTENT_CAPACITY)
+}
+
+/// Estimates the size of the `toc.ncx` file of `epub` from its model (see
+/// [`content_opf_capacity`]).
+fn toc_ncx_capacity(epub: &Epub<'_>) -> usize {
+    let contents = epub.contents.as_deref().unwrap_or_default();
+    let references = contents.iter().map(references_capacity).sum::<usize>();
+    let targets = epub
+        .page_list
+        .iter()
+        .flatten()
+        .map(|target| NCX_NAV_POINT_CAPACITY + target.src.len())
+        .chain(epub.nav_lists.iter().flatten().flat_map(|nav_list| {
+            nav_list
+                .targets
+                .iter()
+                .map(|target| NCX_NAV_POINT_CAPACITY + target.label.len() + target.src.len())
+        }))
+        .sum::<usize>();
+
+    XML_BASE_CAPACITY + contents_capacity(contents, NCX_NAV_POINT_CAPACITY) + references + targets
+}
+
+/// Sums the estimated size of the elements written for `contents` and their subcontents, with
+/// `capacity` bytes for each one plus the length of its title.
+fn contents_capacity(contents: &[Content<'_>], capacity: usize) -> usize {
+    contents
+        .iter()
+        .map(|content| {
+            capacity
+                + content.reference_type.type_and_title().1.len()
+                + contents_capacity(content.subcontents.as_deref().unwrap_or_default(), capacity)
+        })
+        .sum()
+}
+
+/// Sums the estimated size of the navigation points of the content references of `content`
+/// and its subcontents.
+fn references_capacity(content: &Content<'_>) -> usize {
+    content
+        .content_references
+        .iter()
+        .flatten()
+        .map(|reference| reference.count() * (NCX_NAV_POINT_CAPACITY + reference.title.len()))
+        .chain(
+            content
                 .subcontents
-                .as_ref()
-                .map(|s| contents_to_nav_point(play_order, file_number, s))
-                .unwrap_or_default(),
-        );
-        result.push_str(&nav_point);
-    }
+                .iter()
+                .flatten()
+                .map(references_capacity),
+        )
+        .sum()
+}
 
-    result
+/// Writes `args` into `buffer` (cleared first) and returns it, so the generated attributes of
+/// repeated elements reuse one allocation.
+fn write_in<'b>(buffer: &'b mut String, args: std::fmt::Arguments<'_>) -> &'b str {
+    buffer.clear();
+    let _ = std::fmt::Write::write_fmt(buffer, args);
+    buffer
 }
 
-/// A recursive private helper function to generate nested `navPoint` elements
-/// for **content references** (i.e., internal links/subheadings within a single XHTML file).
-///
-/// This function is called from `contents_to_nav_point` to handle the deeper
-/// hierarchy of links within a specific file.
-///
-/// # Arguments
-///
-/// * `current_xhtml`: A tuple containing the unique index and filename of the current XHTML file.
-/// * `play_order`: A mutable counter to continue the sequential `playOrder` across all entries.
-/// * `toc_index`: A string representing the current hierarchical index path (e.g., "1-2-").
-/// * `content_references`: A slice of `ContentReference` items to process.
-/// * `link_number`: A mutable counter to generate unique link IDs/names within the file.
-///
-/// # Returns
-///
-/// Returns an `Option<String>`: `Some(String)` containing the generated XML for the
-/// reference navigation points, or `None` if the input slice is empty.
-fn content_references_to_nav_point(
-    current_xhtml: (usize, &str),
-    play_order: &mut usize,
-    toc_index: &str,
-    content_references: &[ContentReference],
-    link_number: &mut usize,
-) -> String {
-    let mut result = String::new();
-
-    let (prefix, mut toc_number) = toc_index
-        .rsplit_once('-')
-        .map(|(prefix, number)| (prefix, number.parse::<usize>().unwrap_or(0)))
-        .unwrap_or(("", 0));
-
-    for content_reference in content_references {
-        *link_number += 1;
-        let current_link = *link_number;
-
-        toc_number += 1;
-        let current_toc = format!("{prefix}-{toc_number}");
-
-        *play_order += 1;
-        let current_play_order = *play_order;
-
-        let nav_point = format!(
-            r#"<navPoint id="navPoint-{xhtml_number}{current_toc}" playOrder="{current_play_order}">
-            <navLabel><text>{text}</text></navLabel>
-            <content src="{src}"/>{subcontent_references}</navPoint>"#,
-            xhtml_number = current_xhtml.0,
-            text = content_reference.title,
-            src = content_reference.reference_name(current_xhtml.1, current_link),
-            subcontent_references = content_reference
-                .subcontent_references
-                .as_ref()
-                .map(|subcontent_references| content_references_to_nav_point(
-                    current_xhtml,
-                    play_order,
-                    &format!("{current_toc}-"),
-                    subcontent_references,
-                    link_number,
+/// Gets the depth of the navigation map: the nesting of the contents and their references,
+/// limited by the `toc_depth` setting, or `1` for a flat table of contents.
+fn toc_depth(epub: &Epub<'_>) -> usize {
+    let max_depth = if epub.flat_toc {
+        1
+    } else {
+        epub.toc_depth.unwrap_or(usize::MAX)
+    };
+    epub.level().min(max_depth)
+}
+
+/// Writes a `<navPoint>` element, leaving it open for nested navigation points.
+fn start_nav_point(
+    xml: &mut XmlBuilder,
+    id: &str,
+    play_order: &str,
+    text: &str,
+    src: &str,
+) -> crate::Result {
+    xml.start("navPoint", &[("id", id), ("playOrder", play_order)])?;
+    xml.start("navLabel", &[])?;
+    xml.text("text", &[], text)?;
+    xml.end("navLabel")?;
+    xml.empty("content", &[("src", src)])
+}
+
+/// Writes the `navPoint` elements of the `navMap` of the `toc.ncx` file.
+struct NavMap<'x> {
+    /// The builder of the `toc.ncx` file.
+    xml: &'x mut XmlBuilder,
+    /// A counter used to generate the unique sequential `playOrder` attribute.
+    play_order: usize,
+    /// The `playOrder` of every written navigation point, by `src`, shared by the page and
+    /// navigation targets pointing to the same location.
+    play_orders: HashMap<String, usize>,
+    /// The maximum nesting of the navigation points (`1` for the top-level contents only).
+    max_depth: usize,
+    /// Whether all the navigation points are written at one level, in reading order.
+    flat: bool,
+    /// The numbering styles of the labels of the top-level contents, by part of the book.
+    numbering: Numbering,
+    /// The number of the last numbered top-level content of each part of the book.
+    part_numbers: HashMap<BookPart, usize>,
+    /// A buffer reused for the generated `id` attributes.
+    id: String,
+    /// A buffer reused for the `playOrder` attributes.
+    number: String,
+}
+
+impl NavMap<'_> {
+    /// Recursively generates the `navPoint` elements of `contents`, at nesting level `depth`.
+    ///
+    /// It traverses the hierarchical content structure and writes the corresponding
+    /// nested `<navPoint>` XML tags for the table of contents, down to `max_depth`
+    /// (as siblings if the map is `flat`).
+    ///
+    /// # Arguments
+    ///
+    /// * `file_number`: A mutable counter used to name the content documents.
+    /// * `contents`: A slice of `Content` items at the current hierarchy level.
+    /// * `depth`: The nesting level of `contents`, starting at `1`.
+    fn contents(
+        &mut self,
+        file_number: &mut usize,
+        contents: &[Content<'_>],
+        depth: usize,
+    ) -> crate::Result {
+        for content in contents {
+            self.play_order += 1;
+            let current_play_order = self.play_order;
+
+            *file_number += 1;
+            let filename = &content.filename(*file_number);
+
+            let label = self.content_label(content, depth);
+            start_nav_point(
+                self.xml,
+                write_in(&mut self.id, format_args!("navPoint-{current_play_order}")),
+                write_in(&mut self.number, format_args!("{current_play_order}")),
+                &label,
+                filename,
+            )?;
+            self.play_orders
+                .entry(filename.to_string())
+                .or_insert(current_play_order);
+            if self.flat {
+                self.xml.end("navPoint")?;
+            }
+
+            if depth < self.max_depth {
+                if let Some(ref content_references) = content.content_references {
+                    self.content_references(
+                        (current_play_order, filename),
+                        "",
+                        content_references,
+                        &mut 0,
+                        depth + 1,
+                    )?;
+                }
+
+                if let Some(ref subcontents) = content.subcontents {
+                    self.contents(file_number, subcontents, depth + 1)?;
+                }
+            } else if let Some(ref subcontents) = content.subcontents {
+                // The subcontents are left out, but their files keep their numbers
+                *file_number += subcontents.iter().map(Content::file_count).sum::<usize>();
+            }
+
+            if !self.flat {
+                self.xml.end("navPoint")?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Recursively generates nested `navPoint` elements for **content references** (i.e.,
+    /// internal links/subheadings within a single XHTML file), at nesting level `depth`.
+    ///
+    /// This function is called from `contents` to handle the deeper hierarchy of links within
+    /// a specific file.
+    ///
+    /// # Arguments
+    ///
+    /// * `current_xhtml`: A tuple containing the unique index and filename of the current XHTML file.
+    /// * `toc_index`: A string representing the current hierarchical index path (e.g., "1-2-").
+    /// * `content_references`: A slice of `ContentReference` items to process.
+    /// * `link_number`: A mutable counter to generate unique link IDs/names within the file.
+    /// * `depth`: The nesting level of `content_references`.
+    fn content_references(
+        &mut self,
+        current_xhtml: (usize, &str),
+        toc_index: &str,
+        content_references: &[ContentReference],
+        link_number: &mut usize,
+        depth: usize,
+    ) -> crate::Result {
+        let (prefix, mut toc_number) = toc_index
+            .rsplit_once('-')
+            .map(|(prefix, number)| (prefix, number.parse::<usize>().unwrap_or(0)))
+            .unwrap_or(("", 0));
+
+        for content_reference in content_references {
+            *link_number += 1;
+            let current_link = *link_number;
+
+            toc_number += 1;
+            let current_toc = format!("{prefix}-{toc_number}");
+
+            self.play_order += 1;
+            let current_play_order = self.play_order;
+
+            let src = content_reference.reference_name(current_xhtml.1, current_link);
+            start_nav_point(
+                self.xml,
+                write_in(
+                    &mut self.id,
+                    format_args!("navPoint-{}{current_toc}", current_xhtml.0),
+                ),
+                write_in(&mut self.number, format_args!("{current_play_order}")),
+                &content_reference.title,
+                &src,
+            )?;
+            self.play_orders.entry(src).or_insert(current_play_order);
+            if self.flat {
+                self.xml.end("navPoint")?;
+            }
+
+            if let Some(ref subcontent_references) = content_reference.subcontent_references {
+                if depth < self.max_depth {
+                    self.content_references(
+                        current_xhtml,
+                        &format!("{current_toc}-"),
+                        subcontent_references,
+                        link_number,
+                        depth + 1,
+                    )?;
+                } else {
+                    // The references are left out, but the next ones keep their generated ids
+                    *link_number += subcontent_references
+                        .iter()
+                        .map(ContentReference::count)
+                        .sum::<usize>();
+                }
+            }
+
+            if !self.flat {
+                self.xml.end("navPoint")?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Gets the label of the navigation point of `content`, prefixed with its number when it is
+    /// a top-level content of a numbered part of the book.
+    fn content_label<'c>(&mut self, content: &'c Content<'_>, depth: usize) -> Cow<'c, str> {
+        let style = content
+            .reference_type
+            .book_part()
+            .filter(|_| depth == 1)
+            .and_then(|part| Some((part, self.numbering.style(part)?)));
+
+        match style {
+            Some((part, style)) => {
+                let number = self.part_numbers.entry(part).or_insert(0);
+                *number += 1;
+                Cow::Owned(format!(
+                    "{}. {}",
+                    style.format(*number),
+                    content.toc_label()
                 ))
-                .unwrap_or_default()
-        );
-        result.push_str(&nav_point);
+            }
+            None => Cow::Borrowed(content.toc_label()),
+        }
+    }
+
+    /// Writes the `pageList` element with a `pageTarget` for every print page, with the labels
+    /// and page numbers of `page_labels`.
+    ///
+    /// The `value` attribute is only written for the normal pages with a page number.
+    fn page_list(
+        &mut self,
+        page_list: &[PageTarget],
+        page_labels: &[(Cow<'_, str>, Option<u32>)],
+    ) -> crate::Result {
+        self.xml.start("pageList", &[])?;
+        for (number, (page_target, (label, value))) in page_list.iter().zip(page_labels).enumerate()
+        {
+            let value = value.map(|value| value.to_string());
+            let play_order = self.target_play_order(&page_target.src);
+            let id = write_in(&mut self.id, format_args!("pageTarget-{}", number + 1));
+            let play_order = write_in(&mut self.number, format_args!("{play_order}"));
+
+            let mut attributes = vec![("id", id), ("type", page_target.page_type.as_ref())];
+            if let Some(ref value) = value {
+                attributes.push(("value", value));
+            }
+            attributes.push(("playOrder", play_order));
+
+            self.xml.start("pageTarget", &attributes)?;
+            self.target_label_and_content(label, &page_target.src)?;
+            self.xml.end("pageTarget")?;
+        }
+        self.xml.end("pageList")
     }
 
-    result
+    /// Writes a `navList` element, with its heading and a `navTarget` for every location.
+    ///
+    /// # Arguments
+    ///
+    /// * `list_number`: The position of the list, starting at `1`, used to generate unique ids.
+    /// * `nav_list`: The list to write.
+    fn nav_list(&mut self, list_number: usize, nav_list: &NavList) -> crate::Result {
+        self.xml.start("navList", &[])?;
+        self.xml.start("navLabel", &[])?;
+        self.xml.text("text", &[], &nav_list.label)?;
+        self.xml.end("navLabel")?;
+        for (number, nav_target) in nav_list.targets.iter().enumerate() {
+            let play_order = self.target_play_order(&nav_target.src);
+            let id = write_in(
+                &mut self.id,
+                format_args!("navTarget-{list_number}-{}", number + 1),
+            );
+            let play_order = write_in(&mut self.number, format_args!("{play_order}"));
+
+            self.xml
+                .start("navTarget", &[("id", id), ("playOrder", play_order)])?;
+            self.target_label_and_content(&nav_target.label, &nav_target.src)?;
+            self.xml.end("navTarget")?;
+        }
+        self.xml.end("navList")
+    }
+
+    /// Writes the `navLabel` and the `content` of a page or navigation target.
+    fn target_label_and_content(&mut self, label: &str, src: &str) -> crate::Result {
+        self.xml.start("navLabel", &[])?;
+        self.xml.text("text", &[], label)?;
+        self.xml.end("navLabel")?;
+        self.xml.empty("content", &[("src", src)])
+    }
+
+    /// Gets the `playOrder` of a target: the one of the navigation point (or previous target)
+    /// with the same `src`, or the next one in the sequence.
+    fn target_play_order(&mut self, src: &str) -> usize {
+        *self.play_orders.entry(src.to_string()).or_insert_with(|| {
+            self.play_order += 1;
+            self.play_order
+        })
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::{collections::HashMap, path::Path};
+
     use crate::epub::{
-        ContentBuilder, ContentReference, EpubBuilder, Identifier, MetadataBuilder, ReferenceType,
+        BookPart, ContentBuilder, ContentReference, EpubBuilder, FontType, Identifier,
+        MetadataBuilder, NavList, NavTarget, NumberingStyle, PageTarget, PageType, ReferenceType,
+        Resource,
     };
 
-    use super::{content_references_to_nav_point, contents_to_nav_point, toc_ncx};
+    use super::{
+        NavMap, Numbering, XmlBuilder, content_opf, content_opf_capacity, display_options,
+        encryption, toc_ncx, toc_ncx_capacity,
+    };
 
-    fn cleaner(xml: String) -> String {
-        xml.replace("\n", "").replace(" ".repeat(12).as_str(), "")
+    fn body(xml: XmlBuilder) -> String {
+        xml.build()
+            .unwrap()
+            .trim_start_matches(r#"<?xml version="1.0" encoding="UTF-8"?>"#)
+            .to_string()
+    }
+
+    #[test]
+    fn test_display_options() {
+        let options = display_options(false).bytes;
+        assert!(options.contains(r#"<option name="specified-fonts">true</option>"#));
+        assert!(!options.contains("fixed-layout"));
+
+        let options = display_options(true).bytes;
+        assert!(options.contains(
+            "<option name=\"specified-fonts\">true</option>\n\t\t<option name=\"fixed-layout\">true</option>\n\t</platform>"
+        ));
     }
 
     #[test]
@@ -459,7 +1222,7 @@ mod tests {
 
         assert_eq!(file_content.filepath, "OEBPS/toc.ncx");
 
-        let content = cleaner(file_content.bytes);
+        let content = file_content.bytes;
         assert!(content.contains(r#"<meta name="dtb:uid" content="urn:uuid:mock-epub-id"/>"#));
         assert!(content.contains(r#"<meta name="dtb:depth" content="1"/>"#));
         assert!(content.contains(r#"<docTitle><text>Title</text></docTitle>"#));
@@ -487,7 +1250,7 @@ mod tests {
     }
 
     #[test]
-    fn test_contents_to_nav_point_nested() {
+    fn test_nav_map_contents_nested() {
         let mock_epub = EpubBuilder::new(MetadataBuilder::title("Title").build())
             .add_content(
                 ContentBuilder::new(
@@ -516,16 +1279,24 @@ mod tests {
                 .build(),
             );
 
-        let mut play_order = 0;
-        let mut file_number = 0;
-
-        let result = contents_to_nav_point(
-            &mut play_order,
-            &mut file_number,
-            &mock_epub.0.contents.unwrap(),
-        );
-
-        let xml = cleaner(result);
+        let mut xml = XmlBuilder::with_capacity(0).unwrap();
+        let mut nav_map = NavMap {
+            xml: &mut xml,
+            play_order: 0,
+            play_orders: HashMap::new(),
+            max_depth: usize::MAX,
+            flat: false,
+            numbering: Numbering::default(),
+            part_numbers: HashMap::new(),
+            id: String::new(),
+            number: String::new(),
+        };
+        nav_map
+            .contents(&mut 0, &mock_epub.0.contents.unwrap(), 1)
+            .unwrap();
+        let play_order = nav_map.play_order;
+
+        let xml = body(xml);
 
         assert!(xml.contains(r#"<navPoint id="navPoint-1" playOrder="1"><navLabel><text>Main Chapter</text></navLabel><content src="c01.xhtml"/>"#));
         assert!(xml.contains(r#"<navPoint id="navPoint-2" playOrder="2"><navLabel><text>Section 1.1</text></navLabel><content src="c02.xhtml"/></navPoint>"#));
@@ -536,7 +1307,7 @@ mod tests {
     }
 
     #[test]
-    fn test_contents_to_nav_point_with_references() {
+    fn test_nav_map_contents_with_references() {
         let mock_epub = EpubBuilder::new(MetadataBuilder::title("With Refs").build()).add_content(
             ContentBuilder::new(
                 "<body><h1>Chapter with Refs</h1></body>".as_bytes(),
@@ -549,16 +1320,24 @@ mod tests {
             .build(),
         );
 
-        let mut play_order = 0;
-        let mut file_number = 0;
-
-        let result = contents_to_nav_point(
-            &mut play_order,
-            &mut file_number,
-            &mock_epub.0.contents.unwrap(),
-        );
-
-        let xml = cleaner(result);
+        let mut xml = XmlBuilder::with_capacity(0).unwrap();
+        let mut nav_map = NavMap {
+            xml: &mut xml,
+            play_order: 0,
+            play_orders: HashMap::new(),
+            max_depth: usize::MAX,
+            flat: false,
+            numbering: Numbering::default(),
+            part_numbers: HashMap::new(),
+            id: String::new(),
+            number: String::new(),
+        };
+        nav_map
+            .contents(&mut 0, &mock_epub.0.contents.unwrap(), 1)
+            .unwrap();
+        let play_order = nav_map.play_order;
+
+        let xml = body(xml);
 
         assert!(xml.contains(r#"<navPoint id="navPoint-1" playOrder="1"><navLabel><text>Chapter with Refs</text></navLabel><content src="c01.xhtml"/>"#));
         assert!(xml.contains(r#"<navPoint id="navPoint-1-1" playOrder="2"><navLabel><text>Ref A</text></navLabel><content src="c01.xhtml#id01"/></navPoint>"#));
@@ -567,7 +1346,7 @@ mod tests {
     }
 
     #[test]
-    fn test_content_references_to_nav_point_nested() {
+    fn test_nav_map_content_references_nested() {
         let content_references = vec![
             ContentReference::new("Level 1 Ref 1").add_child(
                 ContentReference::new("Level 2 Ref 1")
@@ -576,18 +1355,32 @@ mod tests {
             ContentReference::new("Level 1 Ref 2").id("four"),
         ];
 
-        let mut play_order = 10;
         let mut link_number = 0;
 
-        let result = content_references_to_nav_point(
-            (5, "some.xhtml"),
-            &mut play_order,
-            "",
-            &content_references,
-            &mut link_number,
-        );
+        let mut xml = XmlBuilder::with_capacity(0).unwrap();
+        let mut nav_map = NavMap {
+            xml: &mut xml,
+            play_order: 10,
+            play_orders: HashMap::new(),
+            max_depth: usize::MAX,
+            flat: false,
+            numbering: Numbering::default(),
+            part_numbers: HashMap::new(),
+            id: String::new(),
+            number: String::new(),
+        };
+        nav_map
+            .content_references(
+                (5, "some.xhtml"),
+                "",
+                &content_references,
+                &mut link_number,
+                1,
+            )
+            .unwrap();
+        let play_order = nav_map.play_order;
 
-        let xml = cleaner(result);
+        let xml = body(xml);
 
         assert!(xml.contains(r#"<navPoint id="navPoint-5-1" playOrder="11"><navLabel><text>Level 1 Ref 1</text></navLabel><content src="some.xhtml#id01"/>"#));
         assert!(xml.contains(r#"<navPoint id="navPoint-5-1-1" playOrder="12"><navLabel><text>Level 2 Ref 1</text></navLabel><content src="some.xhtml#id02"/>"#));
@@ -596,4 +1389,289 @@ mod tests {
         assert_eq!(play_order, 14);
         assert_eq!(link_number, 4);
     }
+
+    #[test]
+    fn test_toc_ncx_toc_depth() {
+        let mock_epub = EpubBuilder::new(MetadataBuilder::title("Depth").build())
+            .toc_depth(2)
+            .add_content(
+                ContentBuilder::new(
+                    r#"<body><h2 id="id01">1.1</h2><h3 id="id02">1.1.1</h3><h2 id="id03">1.2</h2></body>"#
+                        .as_bytes(),
+                    ReferenceType::Text("Chapter 1".to_string()),
+                )
+                .add_content_reference(
+                    ContentReference::new("1.1").add_child(ContentReference::new("1.1.1")),
+                )
+                .add_content_reference(ContentReference::new("1.2"))
+                .add_child(
+                    ContentBuilder::new(
+                        "<body><h1>Part 1.3</h1></body>".as_bytes(),
+                        ReferenceType::Text("1.3".to_string()),
+                    )
+                    .add_child(
+                        ContentBuilder::new(
+                            "<body><h1>Part 1.3.1</h1></body>".as_bytes(),
+                            ReferenceType::Text("1.3.1".to_string()),
+                        )
+                        .build(),
+                    )
+                    .build(),
+                )
+                .build(),
+            )
+            .add_content(
+                ContentBuilder::new(
+                    "<body><h1>Chapter 2</h1></body>".as_bytes(),
+                    ReferenceType::Text("Chapter 2".to_string()),
+                )
+                .build(),
+            );
+
+        let content = toc_ncx(&mock_epub.0).unwrap().bytes;
+
+        assert!(content.contains(r#"<meta name="dtb:depth" content="2"/>"#));
+        assert!(content.contains(r#"<content src="c01.xhtml#id01"/></navPoint>"#));
+        assert!(!content.contains("1.1.1"));
+        assert!(
+            content.contains(
+                r#"<navLabel><text>1.2</text></navLabel><content src="c01.xhtml#id03"/>"#
+            )
+        );
+        assert!(content.contains(
+            r#"<navLabel><text>1.3</text></navLabel><content src="c02.xhtml"/></navPoint>"#
+        ));
+        assert!(!content.contains("1.3.1"));
+        assert!(
+            content.contains(
+                r#"<navLabel><text>Chapter 2</text></navLabel><content src="c04.xhtml"/>"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_toc_ncx_flat_toc() {
+        let mock_epub = EpubBuilder::new(MetadataBuilder::title("Flat").build())
+            .flat_toc(true)
+            .add_content(
+                ContentBuilder::new(
+                    r#"<body><h2 id="id01">1.1</h2><h3 id="id02">1.1.1</h3></body>"#.as_bytes(),
+                    ReferenceType::Text("Chapter 1".to_string()),
+                )
+                .add_content_reference(
+                    ContentReference::new("1.1").add_child(ContentReference::new("1.1.1")),
+                )
+                .add_child(
+                    ContentBuilder::new(
+                        "<body><h1>Part 1.2</h1></body>".as_bytes(),
+                        ReferenceType::Text("1.2".to_string()),
+                    )
+                    .build(),
+                )
+                .build(),
+            );
+
+        let content = toc_ncx(&mock_epub.0).unwrap().bytes;
+
+        assert!(content.contains(r#"<meta name="dtb:depth" content="1"/>"#));
+        assert!(content.contains(concat!(
+            r#"<navMap><navPoint id="navPoint-1" playOrder="1"><navLabel><text>Chapter 1</text></navLabel><content src="c01.xhtml"/></navPoint>"#,
+            r#"<navPoint id="navPoint-1-1" playOrder="2"><navLabel><text>1.1</text></navLabel><content src="c01.xhtml#id01"/></navPoint>"#,
+            r#"<navPoint id="navPoint-1-1-1" playOrder="3"><navLabel><text>1.1.1</text></navLabel><content src="c01.xhtml#id02"/></navPoint>"#,
+            r#"<navPoint id="navPoint-4" playOrder="4"><navLabel><text>1.2</text></navLabel><content src="c02.xhtml"/></navPoint></navMap>"#,
+        )));
+    }
+
+    #[test]
+    fn test_toc_ncx_page_list_and_nav_list() {
+        let mock_epub = EpubBuilder::new(MetadataBuilder::title("Pages").build())
+            .add_content(
+                ContentBuilder::new(
+                    r#"<body><span id="page1"/><img id="map" src="map.png" alt="Map"/></body>"#
+                        .as_bytes(),
+                    ReferenceType::Text("Chapter 1".to_string()),
+                )
+                .build(),
+            )
+            .add_page_target(PageTarget::new("i", "c01.xhtml").page_type(PageType::Front))
+            .add_page_targets(vec![
+                PageTarget::new("1", "c01.xhtml#page1"),
+                PageTarget::new("2", "c01.xhtml#page2"),
+            ])
+            .add_nav_list(
+                NavList::new("Illustrations").add_target(NavTarget::new("Map", "c01.xhtml#map")),
+            );
+
+        let content = toc_ncx(&mock_epub.0).unwrap().bytes;
+
+        assert!(content.contains(r#"<meta name="dtb:totalPageCount" content="3"/>"#));
+        assert!(content.contains(r#"<meta name="dtb:maxPageNumber" content="2"/>"#));
+        assert!(content.contains(concat!(
+            r#"</navMap><pageList>"#,
+            r#"<pageTarget id="pageTarget-1" type="front" playOrder="1"><navLabel><text>i</text></navLabel><content src="c01.xhtml"/></pageTarget>"#,
+            r#"<pageTarget id="pageTarget-2" type="normal" value="1" playOrder="2"><navLabel><text>1</text></navLabel><content src="c01.xhtml#page1"/></pageTarget>"#,
+            r#"<pageTarget id="pageTarget-3" type="normal" value="2" playOrder="3"><navLabel><text>2</text></navLabel><content src="c01.xhtml#page2"/></pageTarget>"#,
+            r#"</pageList><navList><navLabel><text>Illustrations</text></navLabel>"#,
+            r#"<navTarget id="navTarget-1-1" playOrder="4"><navLabel><text>Map</text></navLabel><content src="c01.xhtml#map"/></navTarget>"#,
+            r#"</navList></ncx>"#,
+        )));
+    }
+
+    #[test]
+    fn test_toc_ncx_numbering() {
+        let content = |ref_type: ReferenceType| {
+            ContentBuilder::new("<body><p>Text</p></body>".as_bytes(), ref_type).build()
+        };
+        let mock_epub = EpubBuilder::new(MetadataBuilder::title("Numbered").build())
+            .numbering(BookPart::FrontMatter, NumberingStyle::LowerRoman)
+            .numbering(BookPart::BodyMatter, NumberingStyle::Arabic)
+            .add_contents(vec![
+                content(ReferenceType::TitlePage("Title Page".to_string())),
+                content(ReferenceType::Foreword("Foreword".to_string())),
+                content(ReferenceType::Preface("Preface".to_string())),
+                content(ReferenceType::Text("The Road".to_string())),
+                content(ReferenceType::Text("The River".to_string())),
+                content(ReferenceType::Index("Index".to_string())),
+            ])
+            .add_page_targets(vec![
+                PageTarget::numbered("c02.xhtml").page_type(PageType::Front),
+                PageTarget::numbered("c04.xhtml"),
+            ]);
+
+        let content = toc_ncx(&mock_epub.0).unwrap().bytes;
+
+        for label in [
+            "Title Page",
+            "i. Foreword",
+            "ii. Preface",
+            "1. The Road",
+            "2. The River",
+            "Index",
+        ] {
+            assert!(content.contains(&format!("<navLabel><text>{label}</text></navLabel>")));
+        }
+        assert!(content.contains(r#"<meta name="dtb:maxPageNumber" content="1"/>"#));
+        assert!(content.contains(r#"<pageTarget id="pageTarget-1" type="front" playOrder="2"><navLabel><text>i</text></navLabel>"#));
+        assert!(content.contains(r#"<pageTarget id="pageTarget-2" type="normal" value="1" playOrder="4"><navLabel><text>1</text></navLabel>"#));
+    }
+
+    #[test]
+    fn test_toc_label() {
+        let mock_epub = EpubBuilder::new(MetadataBuilder::title("Labels").build()).add_content(
+            ContentBuilder::new(
+                "<body><h1>The Road</h1></body>".as_bytes(),
+                ReferenceType::Text("Text".to_string()),
+            )
+            .toc_label("Chapter One: The Road")
+            .build(),
+        );
+
+        let ncx = toc_ncx(&mock_epub.0).unwrap().bytes;
+        assert!(ncx.contains(
+            r#"<navLabel><text>Chapter One: The Road</text></navLabel><content src="c01.xhtml"/>"#
+        ));
+
+        let opf = content_opf(&mock_epub.0).unwrap().bytes;
+        assert!(opf.contains(r#"<reference type="text" title="Text" href="c01.xhtml"/>"#));
+    }
+
+    #[test]
+    fn test_xml_capacity_estimates() {
+        let mut epub_builder = EpubBuilder::new(MetadataBuilder::title("Title").build());
+        for number in 1..=1000 {
+            epub_builder = epub_builder.add_content(
+                ContentBuilder::new(
+                    r#"<body><h1 id="id01">Chapter</h1></body>"#.as_bytes(),
+                    ReferenceType::Text(format!("Chapter {number}")),
+                )
+                .add_content_reference(ContentReference::new("Section"))
+                .build(),
+            );
+        }
+
+        let content_opf = content_opf(&epub_builder.0).unwrap().bytes;
+        let toc_ncx = toc_ncx(&epub_builder.0).unwrap().bytes;
+        assert!(content_opf.contains(r#"<itemref idref="c1000.xhtml"/>"#));
+        assert!(toc_ncx.contains(r#"<navPoint id="navPoint-1999-1" playOrder="2000">"#));
+
+        // The documents fit in their preallocated buffers, without overestimating them
+        for (len, capacity) in [
+            (content_opf.len(), content_opf_capacity(&epub_builder.0)),
+            (toc_ncx.len(), toc_ncx_capacity(&epub_builder.0)),
+        ] {
+            assert!(len <= capacity, "{len} > {capacity}");
+            assert!(capacity < 2 * len, "{capacity} >= 2 * {len}");
+        }
+    }
+
+    #[test]
+    fn test_content_opf_escapes_text_and_attributes() {
+        let mock_epub = EpubBuilder::new(
+            MetadataBuilder::title("Pride & <Prejudice>")
+                .creator("Austen \"Jane\"")
+                .build(),
+        )
+        .generator("tool & co")
+        .add_content(
+            ContentBuilder::new(
+                "<body><h1>Chapter I</h1></body>".as_bytes(),
+                ReferenceType::Text("Q&A".to_string()),
+            )
+            .build(),
+        );
+
+        let content = content_opf(&mock_epub.0).unwrap().bytes;
+
+        assert!(content.contains("<dc:title>Pride &amp; &lt;Prejudice&gt;</dc:title>"));
+        assert!(
+            content.contains(r#"<dc:creator opf:role="aut">Austen &quot;Jane&quot;</dc:creator>"#)
+        );
+        assert!(content.contains(r#"<meta name="generator" content="tool &amp; co"/>"#));
+        assert!(content.contains(r#"<reference type="text" title="Q&amp;A" href="c01.xhtml"/>"#));
+        assert!(content.ends_with("</guide></package>"));
+        assert!(crate::output::xml::format(&content).is_ok());
+
+        let content = toc_ncx(&mock_epub.0).unwrap().bytes;
+        assert!(
+            content.contains("<docTitle><text>Pride &amp; &lt;Prejudice&gt;</text></docTitle>")
+        );
+        assert!(content.contains("<navLabel><text>Q&amp;A</text></navLabel>"));
+        assert!(crate::output::xml::format(&content).is_ok());
+    }
+
+    #[test]
+    fn test_encryption_obfuscated_fonts() {
+        let mock_epub = EpubBuilder::new(MetadataBuilder::title("Title").build())
+            .add_resource(Resource::Font(Path::new("fonts/a.otf"), FontType::OpenType).obfuscate())
+            .add_resource(Resource::Font(Path::new("fonts/b.otf"), FontType::OpenType));
+
+        let file_content = encryption(&mock_epub.0).unwrap();
+
+        assert_eq!(file_content.filepath, "META-INF/encryption.xml");
+        assert!(file_content.bytes.contains(r#"<enc:EncryptedData><enc:EncryptionMethod Algorithm="http://www.idpf.org/2008/embedding"/><enc:CipherData><enc:CipherReference URI="OEBPS/a.otf"/></enc:CipherData></enc:EncryptedData>"#));
+        assert!(!file_content.bytes.contains("b.otf"));
+    }
+
+    #[test]
+    fn test_encryption_without_obfuscated_fonts() {
+        let mock_epub = EpubBuilder::new(MetadataBuilder::title("Title").build())
+            .add_resource(Resource::Font(Path::new("fonts/b.otf"), FontType::OpenType));
+
+        assert!(encryption(&mock_epub.0).is_none());
+        assert!(encryption(&EpubBuilder::new(MetadataBuilder::title("Title").build()).0).is_none());
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "async")]
+    async fn test_file_content_async_format() {
+        let file_content =
+            super::FileContent::new("OEBPS/toc.ncx", "<ncx><navMap/></ncx>".to_string());
+        let formatted = file_content.async_format(None).await.unwrap();
+
+        assert_eq!(formatted.filepath, "OEBPS/toc.ncx");
+        assert_eq!(
+            formatted.bytes,
+            crate::output::xml::format("<ncx><navMap/></ncx>").unwrap()
+        );
+    }
 }
@@ -0,0 +1,395 @@
+use std::collections::VecDeque;
+
+#[cfg(feature = "async")]
+use futures::{Stream, TryStreamExt, future, stream};
+
+#[cfg(feature = "crypto")]
+use crate::output::signature;
+use crate::{
+    ResultExt,
+    epub::{Content, Epub},
+    output::{
+        creator::{self, plugin_context},
+        file_content::{self, FileBytes, FileContent},
+        manifest::{self, Checksum},
+    },
+};
+
+/// A generated file of the EPUB: its path inside the archive and its bytes.
+type GeneratedFile = FileContent<String, FileBytes>;
+
+/// The steps of the generation of the files, in the order they are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stage {
+    /// The `mimetype`, `META-INF` and stylesheet files.
+    Fixed,
+    /// The cover image and page, the resources and `META-INF/encryption.xml`.
+    Resources,
+    /// The XHTML files of the contents, from the content at this index on.
+    Contents(usize),
+    /// The `content.opf` and `toc.ncx` files.
+    Package,
+    /// The `META-INF/liber-manifest.json` build manifest, if enabled.
+    Manifest,
+    /// The `META-INF/signatures.xml` digital signature, if the package is signed.
+    Signatures,
+    /// Every file was generated (or the generation failed).
+    Done,
+}
+
+/// The generation pipeline of the files of an EPUB, producing them in batches (one per
+/// [`Stage`], or one per chapter when rendering contents one at a time).
+#[derive(Debug)]
+struct Pipeline<'a> {
+    /// The source data structure containing all metadata and content of the EPUB.
+    epub: Epub<'a>,
+    /// The next step to run.
+    stage: Stage,
+    /// The number preceding the first file of the next content to render.
+    number: usize,
+    /// The files written so far, described in the build manifest or signed, if enabled.
+    checksums: Option<Vec<Checksum>>,
+}
+
+impl<'a> Pipeline<'a> {
+    /// Normalizes the Unicode text of `epub` (unless disabled) and finalizes its metadata.
+    fn new(mut epub: Epub<'a>) -> crate::Result<Self> {
+        epub.normalize_unicode();
+        epub.finalize_metadata()?;
+        Ok(Self {
+            checksums: (epub.build_manifest || is_signed(&epub)).then(Vec::new),
+            epub,
+            stage: Stage::Fixed,
+            number: 0,
+        })
+    }
+
+    /// Runs the next step, returning its files, or `None` once every file was generated.
+    fn next_batch(&mut self) -> crate::Result<Option<Vec<GeneratedFile>>> {
+        let files = match self.stage {
+            Stage::Fixed => {
+                self.stage = Stage::Resources;
+                self.fixed_files()
+            }
+            Stage::Resources => {
+                let cover_image = match self.epub.cover_image {
+                    Some(ref cover_image) => Some(cover_image.file_content()?),
+                    None => self.epub.generated_cover_file_content(),
+                };
+                let contents = match self.epub.resources {
+                    Some(ref resources) => creator::load_resources(resources)?,
+                    None => Vec::new(),
+                };
+                self.stage = Stage::Contents(0);
+                self.resource_files(cover_image, contents)?
+            }
+            Stage::Contents(index) => {
+                let contents = self.epub.contents.as_deref().unwrap_or_default();
+                // Rendered in parallel, the contents are all rendered at once; otherwise one
+                // chapter (with its subcontents) at a time.
+                let parallel = self.epub.renders_in_parallel();
+                let end = if parallel {
+                    contents.len()
+                } else {
+                    (index + 1).min(contents.len())
+                };
+
+                let contents = &contents[index..end];
+                self.stage = if end < self.epub.contents.as_ref().map_or(0, Vec::len) {
+                    Stage::Contents(end)
+                } else {
+                    Stage::Package
+                };
+
+                let files = creator::render_contents(
+                    contents,
+                    self.number,
+                    self.epub.xhtml_options(),
+                    self.epub.plugins(),
+                    parallel,
+                )?;
+                self.number += contents.iter().map(Content::file_count).sum::<usize>();
+                files.into_iter().map(into_bytes).collect()
+            }
+            Stage::Package => {
+                self.stage = Stage::Manifest;
+                creator::render_package(&self.epub, self.epub.renders_in_parallel())?
+                    .map(into_bytes)
+                    .into()
+            }
+            Stage::Manifest => return self.manifest_file().map(Some),
+            Stage::Signatures => return self.signatures_file().map(Some),
+            Stage::Done => return Ok(None),
+        };
+        self.before_entry(files).map(Some)
+    }
+
+    /// Runs the next step asynchronously, returning its files, or `None` once every file was
+    /// generated.
+    ///
+    /// Resources are loaded and contents rendered concurrently, keeping their order.
+    #[cfg(feature = "async")]
+    async fn async_next_batch(&mut self) -> crate::Result<Option<Vec<GeneratedFile>>> {
+        let spawner = self.epub.spawner;
+        let files = match self.stage {
+            Stage::Fixed => {
+                self.stage = Stage::Resources;
+                self.fixed_files()
+            }
+            Stage::Resources => {
+                let cover_image = match self.epub.cover_image {
+                    Some(ref cover_image) => Some(cover_image.async_file_content(spawner).await?),
+                    None => self.epub.generated_cover_file_content(),
+                };
+                let contents = match self.epub.resources {
+                    Some(ref resources) => {
+                        let contents = resources
+                            .iter()
+                            .map(|resource| resource.async_file_content(spawner));
+                        future::try_join_all(contents).await?
+                    }
+                    None => Vec::new(),
+                };
+                self.stage = Stage::Contents(0);
+                self.resource_files(cover_image, contents)?
+            }
+            Stage::Contents(_) => {
+                self.stage = Stage::Package;
+                let contents = self.epub.contents.as_deref().unwrap_or_default();
+                let options = self.epub.xhtml_options();
+                let renders = contents
+                    .iter()
+                    .zip(Content::first_numbers(contents, 0))
+                    .map(|(content, mut number)| async move {
+                        content
+                            .async_file_content(&mut number, options, spawner)
+                            .await
+                    });
+                let mut files: Vec<FileContent<String, String>> = future::try_join_all(renders)
+                    .await?
+                    .into_iter()
+                    .flatten()
+                    .collect();
+                for file in &mut files {
+                    for plugin in self.epub.plugins() {
+                        plugin
+                            .chapter_rendered(&file.filepath, &mut file.bytes)
+                            .with_context(|| plugin_context(*plugin, &file.filepath))?;
+                    }
+                }
+                files.into_iter().map(into_bytes).collect()
+            }
+            Stage::Package => {
+                self.stage = Stage::Manifest;
+                let mut content_opf = file_content::content_opf(&self.epub)?;
+                for plugin in self.epub.plugins() {
+                    plugin
+                        .manifest_assembled(&mut content_opf.bytes)
+                        .with_context(|| plugin_context(*plugin, &content_opf.filepath))?;
+                }
+                let mut toc_ncx = file_content::toc_ncx(&self.epub)?;
+                if self.epub.pretty_print {
+                    content_opf = content_opf.async_format(spawner).await?;
+                    toc_ncx = toc_ncx.async_format(spawner).await?;
+                }
+                vec![into_bytes(content_opf), into_bytes(toc_ncx)]
+            }
+            Stage::Manifest => return self.manifest_file().map(Some),
+            Stage::Signatures => return self.signatures_file().map(Some),
+            Stage::Done => return Ok(None),
+        };
+        self.before_entry(files).map(Some)
+    }
+
+    /// Generates the `mimetype` file (always first), the `META-INF` files and the stylesheet.
+    fn fixed_files(&self) -> Vec<GeneratedFile> {
+        let mut files = [file_content::mimetype(), file_content::container()]
+            .into_iter()
+            .map(|file| FileContent::new(file.filepath.to_string(), file.bytes.to_vec().into()))
+            .collect::<Vec<_>>();
+        if self.epub.display_options {
+            let fixed_layout = self.epub.fixed_layout.is_some();
+            files.push(into_bytes(file_content::display_options(fixed_layout)));
+        }
+
+        if let Some(stylesheet) = self.epub.stylesheet_content() {
+            files.push(FileContent::new(
+                "OEBPS/style.css".to_string(),
+                stylesheet.into_owned().into(),
+            ));
+        }
+        files
+    }
+
+    /// Deduplicates, optimizes and obfuscates the loaded cover image and resources, returning
+    /// them with the cover page and `META-INF/encryption.xml`, if any.
+    fn resource_files(
+        &mut self,
+        cover_image: Option<GeneratedFile>,
+        contents: Vec<GeneratedFile>,
+    ) -> crate::Result<Vec<GeneratedFile>> {
+        let (cover_image, contents) = self.epub.prepare_resources(cover_image, contents)?;
+
+        let mut files = Vec::new();
+        if let Some(mut cover_image) = cover_image {
+            let cover_page = self.epub.cover_page_file_content(&mut cover_image)?;
+            files.push(cover_image);
+            files.extend(cover_page.map(into_bytes));
+        }
+        files.extend(contents);
+        files.extend(file_content::encryption(&self.epub).map(into_bytes));
+        Ok(files)
+    }
+
+    /// Generates the build manifest describing the files written before it, if enabled.
+    ///
+    /// # Errors
+    /// Returns an error if the manifest cannot be hashed to be signed.
+    fn manifest_file(&mut self) -> crate::Result<Vec<GeneratedFile>> {
+        self.stage = Stage::Signatures;
+        let Some(ref mut checksums) = self.checksums else {
+            return Ok(Vec::new());
+        };
+        if !self.epub.build_manifest {
+            return Ok(Vec::new());
+        }
+        let file = into_bytes(manifest::build_manifest(checksums));
+        checksums.push(Checksum::new(&file)?);
+        Ok(vec![file])
+    }
+
+    /// Generates the digital signature of the files written before it, if the package is
+    /// signed.
+    ///
+    /// # Errors
+    /// Returns an error if a signed entry is missing, or if the signer fails.
+    fn signatures_file(&mut self) -> crate::Result<Vec<GeneratedFile>> {
+        self.stage = Stage::Done;
+        #[cfg(feature = "crypto")]
+        if let (Some(signer), Some(checksums)) = (self.epub.signer, self.checksums.take()) {
+            let file =
+                signature::signatures(signer, self.epub.signed_entries.as_deref(), &checksums)?;
+            return Ok(vec![into_bytes(file)]);
+        }
+        Ok(Vec::new())
+    }
+
+    /// Passes the files to the `before_entry` callback of the plugins, then hashes them for the
+    /// build manifest or the signature, if enabled. The `mimetype` file is never changed.
+    fn before_entry(&mut self, mut files: Vec<GeneratedFile>) -> crate::Result<Vec<GeneratedFile>> {
+        for file in &mut files {
+            if is_mimetype(&file.filepath) {
+                continue;
+            }
+            for plugin in self.epub.plugins() {
+                plugin
+                    .before_entry(&file.filepath, file.bytes.to_mut()?)
+                    .with_context(|| plugin_context(*plugin, &file.filepath))?;
+            }
+        }
+        if let Some(ref mut checksums) = self.checksums {
+            for file in &files {
+                checksums.push(
+                    Checksum::new(file).with_context(|| format!("entry '{}'", file.filepath))?,
+                );
+            }
+        }
+        Ok(files)
+    }
+}
+
+/// Checks whether the package of `epub` is signed.
+fn is_signed(epub: &Epub<'_>) -> bool {
+    #[cfg(feature = "crypto")]
+    return epub.signer.is_some();
+    #[cfg(not(feature = "crypto"))]
+    {
+        let _ = epub;
+        false
+    }
+}
+
+/// Checks whether `filepath` is the `mimetype` file, which must be stored (never compressed).
+pub(crate) fn is_mimetype(filepath: &str) -> bool {
+    filepath == file_content::mimetype().filepath
+}
+
+/// Converts a generated text file into bytes, without copying it.
+fn into_bytes<F: Into<String>>(file: FileContent<F, String>) -> GeneratedFile {
+    FileContent::new(file.filepath.into(), file.bytes.into_bytes().into())
+}
+
+/// An iterator over the generated files of an EPUB, in the order they are written to the
+/// archive (starting with `mimetype`), as returned by
+/// [`EpubBuilder::files`](crate::epub::EpubBuilder::files).
+///
+/// Files are generated as they are requested, one chapter at a time (or all the chapters at
+/// once, in parallel, when the **`rayon` feature** is enabled, see
+/// [`EpubBuilder::parallel_rendering`](crate::epub::EpubBuilder::parallel_rendering)), so they
+/// can be processed incrementally, e.g. uploaded or packaged in a custom way.
+///
+/// After an error, the iterator ends.
+#[derive(Debug)]
+pub struct EpubFiles<'a> {
+    /// The generation pipeline.
+    pipeline: Pipeline<'a>,
+    /// The files generated but not yet returned.
+    pending: VecDeque<GeneratedFile>,
+}
+
+impl<'a> EpubFiles<'a> {
+    /// Creates the iterator over the files of `epub`, normalizing its Unicode text (unless
+    /// disabled) and finalizing its metadata.
+    ///
+    /// # Errors
+    /// Returns an error if a plugin fails to finalize the metadata.
+    pub(crate) fn new(epub: Epub<'a>) -> crate::Result<Self> {
+        Ok(Self {
+            pipeline: Pipeline::new(epub)?,
+            pending: VecDeque::new(),
+        })
+    }
+
+    /// Gets the EPUB the files are generated for, normalized and with its metadata finalized.
+    pub(crate) fn epub(&self) -> &Epub<'a> {
+        &self.pipeline.epub
+    }
+}
+
+impl Iterator for EpubFiles<'_> {
+    type Item = crate::Result<FileContent<String, FileBytes>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(file) = self.pending.pop_front() {
+                return Some(Ok(file));
+            }
+            match self.pipeline.next_batch() {
+                Ok(Some(files)) => self.pending.extend(files),
+                Ok(None) => return None,
+                Err(e) => {
+                    self.pipeline.stage = Stage::Done;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+/// Creates the stream of the generated files of `epub`, in the order they are written to the
+/// archive (starting with `mimetype`). See
+/// [`EpubBuilder::async_files`](crate::epub::EpubBuilder::async_files).
+///
+/// # Errors
+/// Returns an error if a plugin fails to finalize the metadata.
+#[cfg(feature = "async")]
+pub(crate) fn async_files<'a>(
+    epub: Epub<'a>,
+) -> crate::Result<impl Stream<Item = crate::Result<FileContent<String, FileBytes>>> + 'a> {
+    let pipeline = Pipeline::new(epub)?;
+    Ok(stream::try_unfold(pipeline, |mut pipeline| async move {
+        let files = pipeline.async_next_batch().await?;
+        crate::Result::Ok(files.map(|files| (stream::iter(files.into_iter().map(Ok)), pipeline)))
+    })
+    .try_flatten())
+}
@@ -0,0 +1,43 @@
+/// Downloads the content of `url` synchronously (using `ureq`).
+///
+/// The whole response body is read into memory, without the default size limit of `ureq`,
+/// since resources like fonts or videos can be large.
+///
+/// # Errors
+/// Returns a [`crate::Error::Http`] if the request fails or the server answers with an error status.
+pub fn get(url: &str) -> crate::Result<Vec<u8>> {
+    let bytes = ureq::get(url)
+        .call()?
+        .body_mut()
+        .with_config()
+        .limit(u64::MAX)
+        .read_to_vec()?;
+
+    Ok(bytes)
+}
+
+/// Checks that `url` answers a `HEAD` request (or a `GET` one, if the server does not allow
+/// `HEAD`) with a success status, following redirects.
+///
+/// # Errors
+/// Returns a [`crate::Error::Http`] if the request fails or the server answers with an error status.
+pub fn check(url: &str) -> crate::Result<()> {
+    match ureq::head(url).call() {
+        Err(ureq::Error::StatusCode(405 | 501)) => ureq::get(url).call().map(|_| ())?,
+        response => response.map(|_| ())?,
+    }
+    Ok(())
+}
+
+/// Downloads the content of `url` asynchronously (using `reqwest`).
+///
+/// This function is only compiled when the **`http-async` feature** is enabled.
+///
+/// # Errors
+/// Returns a [`crate::Error::AsyncHttp`] if the request fails or the server answers with an error status.
+#[cfg(feature = "http-async")]
+pub async fn async_get(url: &str) -> crate::Result<Vec<u8>> {
+    let bytes = reqwest::get(url).await?.error_for_status()?.bytes().await?;
+
+    Ok(bytes.to_vec())
+}
@@ -0,0 +1,113 @@
+use std::{fmt::Write as _, io};
+
+use sha2::{Digest, Sha256};
+
+use crate::output::file_content::{FileBytes, FileContent};
+
+/// The path of the build manifest inside the archive.
+pub(crate) const BUILD_MANIFEST_PATH: &str = "META-INF/liber-manifest.json";
+
+/// The SHA-256 checksum of a file of the archive, as returned by
+/// [`EpubBuilder::create_with_checksums`](crate::epub::EpubBuilder::create_with_checksums) and
+/// listed in the build manifest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checksum {
+    /// The path of the file inside the archive.
+    pub path: String,
+    /// The size of the file in bytes.
+    pub size: u64,
+    /// The SHA-256 digest of the file, in lowercase hexadecimal.
+    pub sha256: String,
+}
+
+impl Checksum {
+    /// Describes `file`, hashing its bytes (reading them in chunks if they come from a resource
+    /// file).
+    ///
+    /// # Errors
+    /// Returns an error if the resource file cannot be read.
+    pub(crate) fn new(file: &FileContent<String, FileBytes>) -> io::Result<Self> {
+        let mut hasher = Sha256::new();
+        let size = file.bytes.copy_to(&mut hasher)?;
+        Ok(Self {
+            path: file.filepath.clone(),
+            size,
+            sha256: hex(&hasher.finalize()),
+        })
+    }
+}
+
+/// Generates the `META-INF/liber-manifest.json` file, listing the `entries` of the archive (in
+/// the order they are written) with their size and SHA-256 digest, and the liber version that
+/// generated them.
+pub(crate) fn build_manifest(entries: &[Checksum]) -> FileContent<&'static str, String> {
+    let mut json = format!(
+        "{{\n  \"generator\": \"liber {}\",\n  \"files\": [",
+        env!("CARGO_PKG_VERSION")
+    );
+    for (index, entry) in entries.iter().enumerate() {
+        let separator = if index == 0 { "" } else { "," };
+        let _ = write!(
+            json,
+            "{separator}\n    {{ \"path\": \"{}\", \"size\": {}, \"sha256\": \"{}\" }}",
+            json_escape(&entry.path),
+            entry.size,
+            entry.sha256
+        );
+    }
+    json.push_str("\n  ]\n}\n");
+    FileContent::new(BUILD_MANIFEST_PATH, json)
+}
+
+/// Formats `bytes` in lowercase hexadecimal.
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{byte:02x}");
+        hex
+    })
+}
+
+/// Escapes `text` as the content of a JSON string.
+pub(crate) fn json_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(escaped, "\\u{:04x}", c as u32);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_manifest() {
+        let file = FileContent::new("OEBPS/a\"b.css".to_string(), b"abc".to_vec().into());
+        let entry = Checksum::new(&file).unwrap();
+        assert_eq!(
+            entry.sha256,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+
+        let manifest = build_manifest(&[entry.clone(), entry]);
+        assert_eq!(manifest.filepath, "META-INF/liber-manifest.json");
+        assert!(manifest.bytes.contains(&format!(
+            "\"generator\": \"liber {}\"",
+            env!("CARGO_PKG_VERSION")
+        )));
+        assert!(manifest.bytes.contains(
+            r#"{ "path": "OEBPS/a\"b.css", "size": 3, "sha256": "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad" },"#
+        ));
+        assert!(manifest.bytes.ends_with("}\n  ]\n}\n"));
+    }
+}
@@ -1,5 +1,20 @@
 pub mod creator;
+pub mod css_lint;
 pub mod file_content;
+pub mod files;
+#[cfg(feature = "http")]
+pub mod http;
+pub mod manifest;
+pub mod obfuscation;
+#[cfg(feature = "onix")]
+pub mod onix;
+pub mod opds;
+#[cfg(feature = "image")]
+pub mod optimizer;
+#[cfg(feature = "crypto")]
+pub mod signature;
+pub mod sink;
+pub mod warning;
 pub mod xml;
 
 #[cfg(feature = "async")]
@@ -0,0 +1,84 @@
+use sha1::{Digest, Sha1};
+
+/// The number of leading bytes of a font file mangled by the IDPF obfuscation algorithm.
+const OBFUSCATED_LENGTH: usize = 1040;
+
+/// The algorithm URI declared in `META-INF/encryption.xml` for IDPF-obfuscated resources.
+pub const ALGORITHM: &str = "http://www.idpf.org/2008/embedding";
+
+/// Derives the IDPF obfuscation key from the publication's unique identifier.
+///
+/// The key is the SHA-1 digest of the identifier with all whitespace characters
+/// (U+0020, U+0009, U+000D and U+000A) removed.
+///
+/// # Arguments
+///
+/// * `identifier`: The value of the package's unique identifier (e.g. `urn:uuid:...`).
+pub fn key(identifier: &str) -> [u8; 20] {
+    let identifier = identifier
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '\t' | '\r' | '\n'))
+        .collect::<String>();
+
+    Sha1::digest(identifier.as_bytes()).into()
+}
+
+/// Applies the IDPF font mangling algorithm in place.
+///
+/// The first 1040 bytes are XORed with the key, cycling over its 20 bytes. Since XOR is
+/// its own inverse, applying it twice restores the original content.
+///
+/// # Arguments
+///
+/// * `key`: The key derived with [`key`].
+/// * `bytes`: The font content to mangle.
+pub fn obfuscate(key: &[u8; 20], bytes: &mut [u8]) {
+    for (byte, key_byte) in bytes
+        .iter_mut()
+        .take(OBFUSCATED_LENGTH)
+        .zip(key.iter().cycle())
+    {
+        *byte ^= key_byte;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_ignores_whitespace() {
+        assert_eq!(
+            key("urn:uuid:1234"),
+            key(" urn:uuid:\t1234\r\n"),
+            "whitespace must not change the key"
+        );
+        assert_ne!(key("urn:uuid:1234"), key("urn:uuid:1235"));
+    }
+
+    #[test]
+    fn test_obfuscate_only_leading_bytes() {
+        let key = key("urn:uuid:1234");
+        let original = (0..2000).map(|i| (i % 251) as u8).collect::<Vec<u8>>();
+
+        let mut bytes = original.clone();
+        obfuscate(&key, &mut bytes);
+
+        assert_ne!(bytes[..OBFUSCATED_LENGTH], original[..OBFUSCATED_LENGTH]);
+        assert_eq!(bytes[OBFUSCATED_LENGTH..], original[OBFUSCATED_LENGTH..]);
+        assert_eq!(bytes[0], original[0] ^ key[0]);
+        assert_eq!(bytes[20], original[20] ^ key[0]);
+
+        obfuscate(&key, &mut bytes);
+        assert_eq!(bytes, original);
+    }
+
+    #[test]
+    fn test_obfuscate_short_content() {
+        let key = key("urn:isbn:978-3-16-148410-0");
+        let mut bytes = vec![0u8; 10];
+        obfuscate(&key, &mut bytes);
+
+        assert_eq!(bytes, key[..10]);
+    }
+}
@@ -0,0 +1,275 @@
+use chrono::Utc;
+
+use crate::{
+    epub::{Audience, Identifier, Language, Metadata},
+    output::file_content::{XML_BASE_CAPACITY, XmlBuilder},
+};
+
+/// Generates an **ONIX 3.0** message (reference tags) holding the product record of the
+/// e-book described by `metadata`, for the distribution of the EPUB.
+///
+/// The record declares the identifier (ISBN-13, ISBN-10 or a proprietary UUID), the EPUB
+/// product form, the title, the creator (as author) and contributor (as translator), the
+/// language, the subject (as keywords), the audience and age range, the description and the
+/// publishing details (imprint, publisher, city and date of publication).
+pub(crate) fn product_record(metadata: &Metadata) -> crate::Result<String> {
+    let mut xml = XmlBuilder::with_capacity(
+        XML_BASE_CAPACITY + metadata.description.as_ref().map_or(0, String::len),
+    )?;
+
+    xml.start(
+        "ONIXMessage",
+        &[
+            ("release", "3.0"),
+            ("xmlns", "http://ns.editeur.org/onix/3.0/reference"),
+        ],
+    )?;
+
+    xml.start("Header", &[])?;
+    xml.start("Sender", &[])?;
+    xml.text(
+        "SenderName",
+        &[],
+        metadata.publisher.as_deref().unwrap_or("liber"),
+    )?;
+    xml.end("Sender")?;
+    xml.text(
+        "SentDateTime",
+        &[],
+        &Utc::now().format("%Y%m%dT%H%MZ").to_string(),
+    )?;
+    xml.end("Header")?;
+
+    xml.start("Product", &[])?;
+    xml.text("RecordReference", &[], &String::from(&metadata.identifier))?;
+    // A confirmed record (03), not an early notification
+    xml.text("NotificationType", &[], "03")?;
+    product_identifier(&mut xml, &metadata.identifier)?;
+
+    xml.start("DescriptiveDetail", &[])?;
+    xml.text("ProductComposition", &[], "00")?;
+    // Digital download, in EPUB format
+    xml.text("ProductForm", &[], "ED")?;
+    xml.text("ProductFormDetail", &[], "E101")?;
+
+    xml.start("TitleDetail", &[])?;
+    xml.text("TitleType", &[], "01")?;
+    xml.start("TitleElement", &[])?;
+    xml.text("TitleElementLevel", &[], "01")?;
+    xml.text("TitleText", &[], &metadata.title)?;
+    xml.end("TitleElement")?;
+    xml.end("TitleDetail")?;
+
+    let contributors = [("A01", &metadata.creator), ("B06", &metadata.contributor)]
+        .into_iter()
+        .filter_map(|(role, name)| Some((role, name.as_deref()?)));
+    for (number, (role, name)) in contributors.enumerate() {
+        xml.start("Contributor", &[])?;
+        xml.text("SequenceNumber", &[], &(number + 1).to_string())?;
+        xml.text("ContributorRole", &[], role)?;
+        xml.text("PersonName", &[], name)?;
+        xml.end("Contributor")?;
+    }
+
+    xml.start("Language", &[])?;
+    xml.text("LanguageRole", &[], "01")?;
+    xml.text("LanguageCode", &[], language_code(&metadata.language))?;
+    xml.end("Language")?;
+
+    if let Some(ref subject) = metadata.subject {
+        xml.start("Subject", &[])?;
+        xml.text("SubjectSchemeIdentifier", &[], "20")?;
+        xml.text("SubjectHeadingText", &[], subject)?;
+        xml.end("Subject")?;
+    }
+
+    if let Some(code) = metadata.audience.as_ref().and_then(audience_code) {
+        xml.start("Audience", &[])?;
+        xml.text("AudienceCodeType", &[], "01")?;
+        xml.text("AudienceCodeValue", &[], code)?;
+        xml.end("Audience")?;
+    }
+
+    if let Some(age_range) = metadata.age_range {
+        xml.start("AudienceRange", &[])?;
+        // Interest age, in years, from (03) and to (04)
+        xml.text("AudienceRangeQualifier", &[], "17")?;
+        for (precision, age) in [("03", age_range.min), ("04", age_range.max)] {
+            if let Some(age) = age {
+                xml.text("AudienceRangePrecision", &[], precision)?;
+                xml.text("AudienceRangeValue", &[], &age.to_string())?;
+            }
+        }
+        xml.end("AudienceRange")?;
+    }
+    xml.end("DescriptiveDetail")?;
+
+    if let Some(ref description) = metadata.description {
+        xml.start("CollateralDetail", &[])?;
+        xml.start("TextContent", &[])?;
+        xml.text("TextType", &[], "03")?;
+        xml.text("ContentAudience", &[], "00")?;
+        xml.text("Text", &[], description)?;
+        xml.end("TextContent")?;
+        xml.end("CollateralDetail")?;
+    }
+
+    xml.start("PublishingDetail", &[])?;
+    if let Some(ref imprint) = metadata.imprint {
+        xml.start("Imprint", &[])?;
+        xml.text("ImprintName", &[], imprint)?;
+        xml.end("Imprint")?;
+    }
+    if let Some(ref publisher) = metadata.publisher {
+        xml.start("Publisher", &[])?;
+        xml.text("PublishingRole", &[], "01")?;
+        xml.text("PublisherName", &[], publisher)?;
+        xml.end("Publisher")?;
+    }
+    xml.text_optional(
+        "CityOfPublication",
+        &[],
+        metadata.publication_place.as_deref(),
+    )?;
+    if let Some(date) = metadata.date {
+        xml.start("PublishingDate", &[])?;
+        xml.text("PublishingDateRole", &[], "01")?;
+        xml.text("Date", &[], &date.format("%Y%m%d").to_string())?;
+        xml.end("PublishingDate")?;
+    }
+    xml.end("PublishingDetail")?;
+
+    xml.end("Product")?;
+    xml.end("ONIXMessage")?;
+    xml.build()
+}
+
+/// Writes the `<ProductIdentifier>` of `identifier`: an ISBN-13 (15) or ISBN-10 (02), without
+/// hyphens, or a proprietary (01) UUID.
+fn product_identifier(xml: &mut XmlBuilder, identifier: &Identifier) -> crate::Result {
+    xml.start("ProductIdentifier", &[])?;
+    match identifier {
+        Identifier::ISBN(isbn) => {
+            let isbn = isbn
+                .chars()
+                .filter(|c| *c != '-' && !c.is_whitespace())
+                .collect::<String>();
+            let id_type = if isbn.len() == 10 { "02" } else { "15" };
+            xml.text("ProductIDType", &[], id_type)?;
+            xml.text("IDValue", &[], &isbn)?;
+        }
+        Identifier::UUID(uuid) => {
+            xml.text("ProductIDType", &[], "01")?;
+            xml.text("IDTypeName", &[], "UUID")?;
+            xml.text("IDValue", &[], uuid)?;
+        }
+    }
+    xml.end("ProductIdentifier")
+}
+
+/// Gets the ONIX audience code (list 28) of `audience`, if there is one.
+fn audience_code(audience: &Audience) -> Option<&'static str> {
+    match audience {
+        Audience::General | Audience::Adult => Some("01"),
+        Audience::Children => Some("02"),
+        Audience::YoungAdult => Some("03"),
+        Audience::Educational => Some("04"),
+        Audience::Professional => Some("06"),
+        Audience::Other(_) => None,
+    }
+}
+
+/// Gets the ISO 639-2/B code of `language`, used by ONIX (list 74).
+fn language_code(language: &Language) -> &'static str {
+    match language {
+        Language::Arabic => "ara",
+        Language::Bulgarian => "bul",
+        Language::Chinese => "chi",
+        Language::Croatian => "hrv",
+        Language::Czech => "cze",
+        Language::Danish => "dan",
+        Language::Dutch => "dut",
+        Language::English => "eng",
+        Language::Estonian => "est",
+        Language::Finnish => "fin",
+        Language::French => "fre",
+        Language::Greek => "gre",
+        Language::German => "ger",
+        Language::Hebrew => "heb",
+        Language::Hungarian => "hun",
+        Language::Icelandic => "ice",
+        Language::Indonesian => "ind",
+        Language::Irish => "gle",
+        Language::Italian => "ita",
+        Language::Japanese => "jpn",
+        Language::Korean => "kor",
+        Language::Latvian => "lav",
+        Language::Lithuanian => "lit",
+        Language::Macedonian => "mac",
+        Language::Malay => "may",
+        Language::Maltese => "mlt",
+        Language::Norwegian => "nor",
+        Language::Persian => "per",
+        Language::Polish => "pol",
+        Language::Portuguese => "por",
+        Language::Romanian => "rum",
+        Language::Russian => "rus",
+        Language::Serbian => "srp",
+        Language::Slovak => "slo",
+        Language::Slovenian => "slv",
+        Language::Spanish => "spa",
+        Language::Swahili => "swa",
+        Language::Swedish => "swe",
+        Language::Tagalog => "tgl",
+        Language::Thai => "tha",
+        Language::Turkish => "tur",
+        Language::Ukrainian => "ukr",
+        Language::Urdu => "urd",
+        Language::Vietnamese => "vie",
+        Language::Welsh => "wel",
+        Language::Yiddish => "yid",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::epub::{AgeRange, MetadataBuilder};
+
+    #[test]
+    fn test_onix_product_record() {
+        let metadata = MetadataBuilder::title("Alice & Bob")
+            .identifier(Identifier::ISBN("978-3-16-148410-0".to_string()))
+            .language(Language::French)
+            .creator("Author")
+            .contributor("Translator")
+            .publisher("Publisher")
+            .imprint("Imprint")
+            .publication_place("Paris")
+            .audience(Audience::Children)
+            .age_range(AgeRange::between(8, 12))
+            .date(Utc.with_ymd_and_hms(2024, 5, 1, 0, 0, 0).unwrap())
+            .build();
+        let record = product_record(&metadata).unwrap();
+
+        for fragment in [
+            "<RecordReference>urn:isbn:978-3-16-148410-0</RecordReference>",
+            "<ProductIdentifier><ProductIDType>15</ProductIDType><IDValue>9783161484100</IDValue></ProductIdentifier>",
+            "<TitleText>Alice &amp; Bob</TitleText>",
+            "<SequenceNumber>2</SequenceNumber><ContributorRole>B06</ContributorRole><PersonName>Translator</PersonName>",
+            "<LanguageCode>fre</LanguageCode>",
+            "<AudienceCodeValue>02</AudienceCodeValue>",
+            "<AudienceRangeQualifier>17</AudienceRangeQualifier><AudienceRangePrecision>03</AudienceRangePrecision><AudienceRangeValue>8</AudienceRangeValue><AudienceRangePrecision>04</AudienceRangePrecision><AudienceRangeValue>12</AudienceRangeValue>",
+            "<PublishingDetail><Imprint><ImprintName>Imprint</ImprintName></Imprint><Publisher><PublishingRole>01</PublishingRole><PublisherName>Publisher</PublisherName></Publisher><CityOfPublication>Paris</CityOfPublication><PublishingDate><PublishingDateRole>01</PublishingDateRole><Date>20240501</Date></PublishingDate></PublishingDetail>",
+        ] {
+            assert!(record.contains(fragment), "{fragment}");
+        }
+        assert!(!record.contains("<CollateralDetail>"));
+
+        let record = product_record(&MetadataBuilder::title("Untitled").build()).unwrap();
+        assert!(record.contains("<ProductIDType>01</ProductIDType><IDTypeName>UUID</IDTypeName>"));
+        assert!(!record.contains("<Contributor>"));
+    }
+}
@@ -0,0 +1,157 @@
+use chrono::Utc;
+
+use crate::{
+    epub::{Epub, GENERATED_COVER_MEDIA_TYPE},
+    output::file_content::{XML_BASE_CAPACITY, XmlBuilder},
+};
+
+/// The links of an OPDS catalog entry: where the book, and optionally its cover, are served
+/// by the catalog.
+#[derive(Debug, Clone)]
+pub struct OpdsLinks {
+    /// The URL the EPUB file is downloaded from.
+    acquisition: String,
+    /// The URL of the full-size cover image, if any.
+    cover: Option<String>,
+    /// The URL of the thumbnail of the cover image, if any.
+    thumbnail: Option<String>,
+}
+
+impl OpdsLinks {
+    /// Creates the links of an entry whose EPUB file is downloaded from `acquisition`.
+    #[must_use]
+    pub fn new<S: Into<String>>(acquisition: S) -> Self {
+        Self {
+            acquisition: acquisition.into(),
+            cover: None,
+            thumbnail: None,
+        }
+    }
+
+    /// Sets the URL of the full-size **cover** image.
+    pub fn cover<S: Into<String>>(mut self, cover: S) -> Self {
+        self.cover = Some(cover.into());
+        self
+    }
+
+    /// Sets the URL of the **thumbnail** of the cover image.
+    pub fn thumbnail<S: Into<String>>(mut self, thumbnail: S) -> Self {
+        self.thumbnail = Some(thumbnail.into());
+        self
+    }
+}
+
+/// Generates the OPDS catalog `<entry>` (an Atom entry document) of `epub`, linking to the
+/// book and its cover as served at `links`.
+///
+/// The entry holds the title, the identifier, the creator as author, the language, the
+/// publisher, the publication date, the subject as category and the description as summary.
+/// The entry is updated at the publication date, or now if there is none.
+pub(crate) fn entry(epub: &Epub<'_>, links: &OpdsLinks) -> crate::Result<String> {
+    let metadata = &epub.metadata;
+    let mut xml = XmlBuilder::with_capacity(
+        XML_BASE_CAPACITY + metadata.description.as_ref().map_or(0, String::len),
+    )?;
+
+    xml.start(
+        "entry",
+        &[
+            ("xmlns", "http://www.w3.org/2005/Atom"),
+            ("xmlns:dc", "http://purl.org/dc/terms/"),
+        ],
+    )?;
+    xml.text("title", &[], &metadata.title)?;
+    xml.text("id", &[], &String::from(&metadata.identifier))?;
+    xml.text(
+        "updated",
+        &[],
+        &metadata.date.unwrap_or_else(Utc::now).to_rfc3339(),
+    )?;
+    if let Some(ref creator) = metadata.creator {
+        xml.start("author", &[])?;
+        xml.text("name", &[], creator)?;
+        xml.end("author")?;
+    }
+    xml.text("dc:language", &[], metadata.language.as_ref())?;
+    xml.text_optional("dc:publisher", &[], metadata.publisher.as_deref())?;
+    xml.text_optional(
+        "dc:issued",
+        &[],
+        metadata
+            .date
+            .map(|date| date.format("%Y-%m-%d").to_string())
+            .as_deref(),
+    )?;
+    if let Some(ref subject) = metadata.subject {
+        xml.empty("category", &[("term", subject), ("label", subject)])?;
+    }
+    xml.text_optional("summary", &[], metadata.description.as_deref())?;
+
+    let cover_media_type = match epub.cover_image {
+        Some(ref cover_image) => Some(cover_image.media_type()),
+        None => epub
+            .generated_cover
+            .as_ref()
+            .map(|_| GENERATED_COVER_MEDIA_TYPE),
+    };
+    for (rel, href) in [
+        ("http://opds-spec.org/image", &links.cover),
+        ("http://opds-spec.org/image/thumbnail", &links.thumbnail),
+    ] {
+        if let Some(href) = href {
+            let mut attributes = vec![("rel", rel), ("href", href.as_str())];
+            attributes.extend(cover_media_type.map(|media_type| ("type", media_type)));
+            xml.empty("link", &attributes)?;
+        }
+    }
+    xml.empty(
+        "link",
+        &[
+            ("rel", "http://opds-spec.org/acquisition"),
+            ("href", &links.acquisition),
+            ("type", "application/epub+zip"),
+        ],
+    )?;
+
+    xml.end("entry")?;
+    xml.build()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::epub::{EpubBuilder, MetadataBuilder, Resource};
+
+    #[test]
+    fn test_opds_entry() {
+        let entry = EpubBuilder::new(
+            MetadataBuilder::title("Alice & Bob")
+                .creator("Author")
+                .subject("Fiction")
+                .date(Utc.with_ymd_and_hms(2024, 5, 1, 0, 0, 0).unwrap())
+                .build(),
+        )
+        .cover_image_item(Resource::Raw("cover.png", b"png", "image/png".to_string()).into())
+        .pretty_print(false)
+        .render_opds_entry(
+            &OpdsLinks::new("https://books.example/alice.epub").cover("/covers/alice.png"),
+        )
+        .unwrap();
+
+        for fragment in [
+            "<title>Alice &amp; Bob</title>",
+            "<updated>2024-05-01T00:00:00+00:00</updated>",
+            "<author><name>Author</name></author>",
+            "<dc:issued>2024-05-01</dc:issued>",
+            r#"<category term="Fiction" label="Fiction"/>"#,
+            r#"<link rel="http://opds-spec.org/image" href="/covers/alice.png" type="image/png"/>"#,
+            r#"<link rel="http://opds-spec.org/acquisition" href="https://books.example/alice.epub" type="application/epub+zip"/>"#,
+        ] {
+            assert!(entry.contains(fragment), "{fragment}");
+        }
+        assert!(!entry.contains("thumbnail"));
+        assert!(!entry.contains("<summary>"));
+    }
+}
@@ -0,0 +1,184 @@
+use image::{
+    ImageFormat,
+    codecs::{
+        jpeg::JpegEncoder,
+        png::{CompressionType, FilterType as PngFilterType, PngEncoder},
+    },
+    imageops::FilterType,
+};
+
+use crate::epub::ImageType;
+
+/// Defines how images are downscaled and compressed before being written into the EPUB.
+///
+/// Images larger than the maximum dimensions are resized, keeping their aspect ratio.
+/// JPEG images are re-encoded with the configured quality and PNG images with the best
+/// compression. GIF and SVG images are left untouched.
+///
+/// This struct is only available when the **`image` feature** is enabled.
+#[derive(Debug, Clone)]
+pub struct ImageOptions {
+    /// The maximum width in pixels.
+    max_width: u32,
+    /// The maximum height in pixels.
+    max_height: u32,
+    /// The JPEG quality, from 1 to 100.
+    quality: u8,
+    /// Whether image resources are processed too, not only the cover image.
+    interior_images: bool,
+}
+
+impl ImageOptions {
+    /// Creates new options with the maximum dimensions, a JPEG quality of `85`
+    /// and only the cover image being processed.
+    #[must_use]
+    pub fn new(max_width: u32, max_height: u32) -> Self {
+        Self {
+            max_width,
+            max_height,
+            quality: 85,
+            interior_images: false,
+        }
+    }
+
+    /// Sets the JPEG **quality** (clamped from 1 to 100).
+    pub fn quality(mut self, quality: u8) -> Self {
+        self.quality = quality.clamp(1, 100);
+        self
+    }
+
+    /// Sets whether image resources are processed too, not only the cover image.
+    pub fn interior_images(mut self, interior_images: bool) -> Self {
+        self.interior_images = interior_images;
+        self
+    }
+
+    /// Returns whether image resources are processed too, not only the cover image.
+    pub(crate) fn includes_interior_images(&self) -> bool {
+        self.interior_images
+    }
+}
+
+/// Downscales and re-encodes an image according to the given options.
+///
+/// # Arguments
+///
+/// * `bytes`: The original image content.
+/// * `image_type`: The type of the image, which is kept when re-encoding.
+/// * `options`: The maximum dimensions and quality to apply.
+///
+/// # Returns
+///
+/// Returns `Ok(None)` when the image is kept as is: GIF and SVG images, or images that
+/// were not resized and whose re-encoding is not smaller than the original.
+///
+/// # Errors
+///
+/// Returns [`crate::Error::Image`] if the image cannot be decoded or encoded.
+pub fn optimize(
+    bytes: &[u8],
+    image_type: &ImageType,
+    options: &ImageOptions,
+) -> crate::Result<Option<Vec<u8>>> {
+    let format = match image_type {
+        ImageType::Jpg => ImageFormat::Jpeg,
+        ImageType::Png => ImageFormat::Png,
+        ImageType::Gif | ImageType::Svg => return Ok(None),
+    };
+
+    let mut image = image::load_from_memory_with_format(bytes, format)?;
+
+    let resized = image.width() > options.max_width || image.height() > options.max_height;
+    if resized {
+        image = image.resize(options.max_width, options.max_height, FilterType::Lanczos3);
+    }
+
+    let mut output = Vec::new();
+    if let ImageFormat::Jpeg = format {
+        image.write_with_encoder(JpegEncoder::new_with_quality(&mut output, options.quality))?;
+    } else {
+        image.write_with_encoder(PngEncoder::new_with_quality(
+            &mut output,
+            CompressionType::Best,
+            PngFilterType::Adaptive,
+        ))?;
+    }
+
+    Ok((resized || output.len() < bytes.len()).then_some(output))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use image::{DynamicImage, GenericImageView, RgbImage};
+
+    use super::*;
+
+    fn encode(width: u32, height: u32, format: ImageFormat) -> Vec<u8> {
+        let image = DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| {
+            image::Rgb([(x % 256) as u8, (y % 256) as u8, 128])
+        }));
+
+        let mut bytes = Cursor::new(Vec::new());
+        image.write_to(&mut bytes, format).unwrap();
+        bytes.into_inner()
+    }
+
+    #[test]
+    fn test_optimize_downscales_keeping_aspect_ratio() {
+        let bytes = encode(400, 300, ImageFormat::Jpeg);
+        let options = ImageOptions::new(200, 200).quality(70);
+
+        let optimized = optimize(&bytes, &ImageType::Jpg, &options)
+            .unwrap()
+            .expect("image must be resized");
+
+        let image = image::load_from_memory_with_format(&optimized, ImageFormat::Jpeg).unwrap();
+        assert_eq!(image.dimensions(), (200, 150));
+    }
+
+    #[test]
+    fn test_optimize_png_within_bounds() {
+        let bytes = encode(50, 40, ImageFormat::Png);
+        let options = ImageOptions::new(200, 200);
+
+        let optimized = optimize(&bytes, &ImageType::Png, &options)
+            .unwrap()
+            .expect("re-encoding with the best compression must be smaller");
+        assert!(optimized.len() < bytes.len());
+        let image = image::load_from_memory_with_format(&optimized, ImageFormat::Png).unwrap();
+        assert_eq!(image.dimensions(), (50, 40));
+
+        // Already optimized, it is kept as is
+        assert!(
+            optimize(&optimized, &ImageType::Png, &options)
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_optimize_skips_gif_and_svg() {
+        let options = ImageOptions::new(10, 10);
+        assert!(
+            optimize(b"GIF89a", &ImageType::Gif, &options)
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            optimize(b"<svg/>", &ImageType::Svg, &options)
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_optimize_invalid_image() {
+        let options = ImageOptions::new(10, 10);
+        match optimize(b"not an image", &ImageType::Png, &options) {
+            Err(e) => assert!(matches!(e, crate::Error::Image(_))),
+            _ => panic!("Expected Image error when decoding invalid content"),
+        }
+    }
+}
@@ -0,0 +1,186 @@
+use base64::{Engine, engine::general_purpose::STANDARD};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    epub::PackageSigner,
+    output::{file_content::FileContent, files::is_mimetype, manifest::Checksum},
+};
+
+/// The path of the digital signatures file inside the archive.
+pub(crate) const SIGNATURES_PATH: &str = "META-INF/signatures.xml";
+
+/// The XML digital signature namespace.
+const XMLDSIG_NAMESPACE: &str = "http://www.w3.org/2000/09/xmldsig#";
+
+/// The canonicalization algorithm (inclusive C14N 1.0, without comments).
+const C14N_ALGORITHM: &str = "http://www.w3.org/TR/2001/REC-xml-c14n-20010315";
+
+/// The digest algorithm of the references.
+const SHA256_ALGORITHM: &str = "http://www.w3.org/2001/04/xmlenc#sha256";
+
+/// Generates the `META-INF/signatures.xml` file: an XML digital signature, made by `signer`,
+/// over the `entries` of the archive (every entry but the `mimetype` if `None`), described by
+/// their `checksums`.
+///
+/// The entries are referenced from a `<Manifest>` (as the OCF specification recommends), whose
+/// digest is the only reference of the `<SignedInfo>`. Both elements are written in their
+/// canonical form, so the signed bytes are exactly the ones in the file.
+///
+/// # Errors
+/// Returns an error if an entry is not in the archive, or if `signer` fails.
+pub(crate) fn signatures(
+    signer: &dyn PackageSigner,
+    entries: Option<&[String]>,
+    checksums: &[Checksum],
+) -> crate::Result<FileContent<&'static str, String>> {
+    let signed = match entries {
+        Some(entries) => entries
+            .iter()
+            .map(|entry| {
+                checksums
+                    .iter()
+                    .find(|checksum| checksum.path == *entry)
+                    .ok_or_else(|| crate::Error::FilenameNotFound(entry.clone()))
+            })
+            .collect::<crate::Result<Vec<_>>>()?,
+        None => checksums
+            .iter()
+            .filter(|checksum| !is_mimetype(&checksum.path))
+            .collect(),
+    };
+
+    let references = signed
+        .iter()
+        .map(|checksum| reference("", &checksum.path, &digest_value(&checksum.sha256)))
+        .collect::<String>();
+    let manifest =
+        format!(r#"<Manifest xmlns="{XMLDSIG_NAMESPACE}" Id="manifest">{references}</Manifest>"#);
+
+    let signed_info = format!(
+        r#"<SignedInfo xmlns="{XMLDSIG_NAMESPACE}"><CanonicalizationMethod Algorithm="{C14N_ALGORITHM}"></CanonicalizationMethod><SignatureMethod Algorithm="{}"></SignatureMethod>{}</SignedInfo>"#,
+        escape(signer.signature_method()),
+        reference(
+            &format!("{XMLDSIG_NAMESPACE}Manifest"),
+            "#manifest",
+            &STANDARD.encode(Sha256::digest(manifest.as_bytes()))
+        )
+    );
+    let signature_value = STANDARD.encode(signer.sign(signed_info.as_bytes())?);
+    let key_info = signer
+        .certificate()
+        .map(|certificate| {
+            format!(
+                "<KeyInfo><X509Data><X509Certificate>{}</X509Certificate></X509Data></KeyInfo>",
+                STANDARD.encode(certificate)
+            )
+        })
+        .unwrap_or_default();
+
+    Ok(FileContent::new(
+        SIGNATURES_PATH,
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?><signatures xmlns="urn:oasis:names:tc:opendocument:xmlns:container"><Signature xmlns="{XMLDSIG_NAMESPACE}" Id="signature">{signed_info}<SignatureValue>{signature_value}</SignatureValue>{key_info}<Object>{manifest}</Object></Signature></signatures>"#
+        ),
+    ))
+}
+
+/// Writes a canonical `<Reference>` to `uri`, of type `reference_type` (if not empty), with
+/// its base64 SHA-256 `digest`.
+fn reference(reference_type: &str, uri: &str, digest: &str) -> String {
+    let reference_type = match reference_type {
+        "" => String::new(),
+        reference_type => format!(r#" Type="{}""#, escape(reference_type)),
+    };
+    format!(
+        r#"<Reference{reference_type} URI="{}"><DigestMethod Algorithm="{SHA256_ALGORITHM}"></DigestMethod><DigestValue>{digest}</DigestValue></Reference>"#,
+        escape(uri)
+    )
+}
+
+/// Converts a lowercase hexadecimal SHA-256 digest to base64.
+fn digest_value(sha256: &str) -> String {
+    let bytes = (0..sha256.len())
+        .step_by(2)
+        .filter_map(|index| u8::from_str_radix(sha256.get(index..index + 2)?, 16).ok())
+        .collect::<Vec<_>>();
+    STANDARD.encode(bytes)
+}
+
+/// Escapes `value` as a canonical (C14N) attribute value.
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\t' => escaped.push_str("&#x9;"),
+            '\n' => escaped.push_str("&#xA;"),
+            '\r' => escaped.push_str("&#xD;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Signs by returning the signed bytes, to check them.
+    struct EchoSigner;
+
+    impl PackageSigner for EchoSigner {
+        fn signature_method(&self) -> &str {
+            "http://www.w3.org/2001/04/xmldsig-more#rsa-sha256"
+        }
+
+        fn sign(&self, signed_info: &[u8]) -> crate::Result<Vec<u8>> {
+            Ok(signed_info.to_vec())
+        }
+
+        fn certificate(&self) -> Option<&[u8]> {
+            Some(b"der")
+        }
+    }
+
+    #[test]
+    fn test_signatures() {
+        let checksums = ["mimetype", "OEBPS/a&b.xhtml", "OEBPS/content.opf"].map(|path| {
+            Checksum::new(&FileContent::new(path.to_string(), b"abc".to_vec().into())).unwrap()
+        });
+
+        let file = signatures(&EchoSigner, None, &checksums).unwrap();
+        assert_eq!(file.filepath, "META-INF/signatures.xml");
+        let xml = file.bytes;
+        assert!(!xml.contains(r#"URI="mimetype""#));
+        assert!(xml.contains(
+            r#"<Reference URI="OEBPS/a&amp;b.xhtml"><DigestMethod Algorithm="http://www.w3.org/2001/04/xmlenc#sha256"></DigestMethod><DigestValue>ungWv48Bz+pBQUDeXa4iI7ADYaOWF3qctBD/YfIAFa0=</DigestValue></Reference>"#
+        ));
+        assert!(xml.contains(
+            r##"<Reference Type="http://www.w3.org/2000/09/xmldsig#Manifest" URI="#manifest">"##
+        ));
+        assert!(xml.contains("<KeyInfo><X509Data><X509Certificate>ZGVy</X509Certificate>"));
+
+        let start = xml.find("<SignedInfo").unwrap();
+        let end = xml.find("</SignedInfo>").unwrap() + "</SignedInfo>".len();
+        let signature_value = format!(
+            "<SignatureValue>{}</SignatureValue>",
+            STANDARD.encode(&xml[start..end])
+        );
+        assert!(xml.contains(&signature_value));
+
+        let file = signatures(
+            &EchoSigner,
+            Some(&["OEBPS/content.opf".to_string()]),
+            &checksums,
+        )
+        .unwrap();
+        assert_eq!(file.bytes.matches("<Reference URI=").count(), 1);
+
+        let missing = signatures(&EchoSigner, Some(&["OEBPS/x.css".to_string()]), &checksums);
+        assert!(
+            matches!(missing, Err(crate::Error::FilenameNotFound(path)) if path == "OEBPS/x.css")
+        );
+    }
+}
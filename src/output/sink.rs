@@ -0,0 +1,445 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use std::{
+    io::{self, Seek, SeekFrom, Write},
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use zip::{
+    CompressionMethod, DateTime, ZipWriter,
+    write::{FileOptions, SimpleFileOptions},
+};
+
+use crate::{ZipCompression, epub::Epub, output::file_content::FileBytes};
+
+/// The destination of the files generated for an EPUB.
+///
+/// Files are written in order, starting with `mimetype`.
+pub trait Sink {
+    /// Writes a single file at `filepath` (relative to the EPUB root, e.g. `OEBPS/c01.xhtml`).
+    ///
+    /// `stored` is set for the `mimetype` file, which must never be compressed.
+    ///
+    /// Resource files are copied in chunks of bounded size (see [`FileBytes::copy_to`]).
+    fn write_file(&mut self, filepath: &str, bytes: &FileBytes, stored: bool) -> crate::Result;
+
+    /// Completes the output once every file has been written.
+    fn finish(self) -> crate::Result;
+}
+
+/// A [`Sink`] packaging the files into a ZIP archive, streamed to a writer one entry at a time.
+///
+/// Every local header is complete (CRC-32 and sizes, no data descriptor), as the EPUB container
+/// requires: the header of an entry is computed beforehand, by writing the entry a first time
+/// to a [`HeaderRecorder`] (reading resource files twice, in chunks), and it is then written as
+/// is by the [`EntryWriter`], which holds back nothing but the header.
+#[derive(Debug)]
+pub struct ZipSink<'a, W: Write> {
+    /// The file options (including compression method) used for writing files into the ZIP archive.
+    options: FileOptions<'a, ()>,
+    /// The ZIP writer, passing the entries to the external writer as they are written.
+    zip_writer: ZipWriter<EntryWriter<W>>,
+    /// The complete local header of the next entry, handed to the [`EntryWriter`].
+    next_header: Arc<Mutex<Option<Vec<u8>>>>,
+    /// The optional comment of the ZIP archive.
+    comment: Option<String>,
+}
+
+impl<'a, W: Write> ZipSink<'a, W> {
+    /// Creates a new `ZipSink` for `epub`, configuring the file options from the chosen
+    /// compression method and the reproducibility settings.
+    pub fn new(epub: &Epub<'_>, writer: W, compression: ZipCompression) -> Self {
+        let compression = match compression {
+            ZipCompression::Stored => CompressionMethod::Stored,
+            ZipCompression::Deflated => CompressionMethod::Deflated,
+        };
+
+        let mut options = SimpleFileOptions::default()
+            .compression_method(compression)
+            .unix_permissions(0o755);
+
+        if epub.deterministic {
+            options = options.last_modified_time(DateTime::default());
+        }
+
+        let next_header = Arc::default();
+        Self {
+            options,
+            zip_writer: ZipWriter::new(EntryWriter::new(writer, Arc::clone(&next_header))),
+            next_header,
+            comment: epub.archive_comment.clone(),
+        }
+    }
+}
+
+impl<W: Write> Sink for ZipSink<'_, W> {
+    fn write_file(&mut self, filepath: &str, bytes: &FileBytes, stored: bool) -> crate::Result {
+        let options = if stored {
+            self.options.compression_method(CompressionMethod::Stored)
+        } else {
+            self.options
+        };
+
+        let header = HeaderRecorder::local_header(filepath, bytes, options)?;
+        *self
+            .next_header
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Some(header);
+        self.zip_writer.start_file(filepath, options)?;
+        bytes.copy_to(&mut self.zip_writer)?;
+        Ok(())
+    }
+
+    /// Writes the central directory (and the archive comment, if any) and flushes the writer.
+    fn finish(mut self) -> crate::Result {
+        if let Some(comment) = self.comment {
+            self.zip_writer.set_comment(comment);
+        }
+        self.zip_writer.finish()?.inner.flush()?;
+        Ok(())
+    }
+}
+
+/// Gets the length of the local header starting `header` (the fixed fields, the filename and
+/// the extra field), or of its fixed fields while they are incomplete.
+fn local_header_len(header: &[u8]) -> usize {
+    const FIXED_LEN: usize = 30;
+
+    match header.get(26..FIXED_LEN) {
+        Some(&[name_0, name_1, extra_0, extra_1]) => {
+            FIXED_LEN
+                + usize::from(u16::from_le_bytes([name_0, name_1]))
+                + usize::from(u16::from_le_bytes([extra_0, extra_1]))
+        }
+        _ => FIXED_LEN,
+    }
+}
+
+/// A seekable writer discarding a ZIP archive of a single entry but for the local header of the
+/// entry, which the ZIP writer completes with its CRC-32 and sizes once it is finished.
+#[derive(Debug, Default)]
+struct HeaderRecorder {
+    /// The local header written so far.
+    header: Vec<u8>,
+    /// The current position in the archive.
+    position: u64,
+    /// The size of the archive.
+    end: u64,
+}
+
+impl HeaderRecorder {
+    /// Computes the complete local header of the entry `filepath` holding `bytes`, written
+    /// with `options`.
+    ///
+    /// # Errors
+    /// Returns an error if a resource file cannot be read or the entry cannot be compressed.
+    fn local_header(
+        filepath: &str,
+        bytes: &FileBytes,
+        options: FileOptions<'_, ()>,
+    ) -> crate::Result<Vec<u8>> {
+        let mut zip_writer = ZipWriter::new(Self::default());
+        zip_writer.start_file(filepath, options)?;
+        bytes.copy_to(&mut zip_writer)?;
+        Ok(zip_writer.finish()?.header)
+    }
+}
+
+impl Write for HeaderRecorder {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut recorded = 0;
+        while recorded < buf.len() {
+            let position = self.position as usize + recorded;
+            let header_len = local_header_len(&self.header);
+            if position >= header_len {
+                break;
+            }
+            let len = (header_len - position).min(buf.len() - recorded);
+            if self.header.len() < position + len {
+                self.header.resize(position + len, 0);
+            }
+            self.header[position..position + len].copy_from_slice(&buf[recorded..recorded + len]);
+            recorded += len;
+        }
+        self.position += buf.len() as u64;
+        self.end = self.end.max(self.position);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for HeaderRecorder {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.position = match pos {
+            SeekFrom::Start(position) => Some(position),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+            SeekFrom::End(offset) => self.end.checked_add_signed(offset),
+        }
+        .filter(|position| *position <= self.end)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid seek"))?;
+        Ok(self.position)
+    }
+}
+
+/// A seekable writer passing the ZIP archive to the external writer as it is written, writing
+/// the local header of every entry complete (as computed by the [`HeaderRecorder`]) instead of
+/// the one the ZIP writer completes once the entry is finished.
+///
+/// The only seeks supported are the ones of the ZIP writer completing the local header of the
+/// entry, whose bytes must then match the ones already written.
+#[derive(Debug)]
+struct EntryWriter<W: Write> {
+    /// The external writer.
+    inner: W,
+    /// The number of bytes passed to the external writer.
+    end: u64,
+    /// The current position in the archive: the end, or within the local header of the entry.
+    position: u64,
+    /// The position of the local header of the entry being written.
+    header_start: u64,
+    /// The complete local header of the entry being written.
+    header: Vec<u8>,
+    /// Whether the entry being written is finished (its local header was completed), so the
+    /// next bytes written start the next entry.
+    finished: bool,
+    /// The complete local header of the next entry, set by the [`ZipSink`] before starting it.
+    next_header: Arc<Mutex<Option<Vec<u8>>>>,
+}
+
+impl<W: Write> EntryWriter<W> {
+    /// Creates a new `EntryWriter` passing the archive to `inner`.
+    fn new(inner: W, next_header: Arc<Mutex<Option<Vec<u8>>>>) -> Self {
+        Self {
+            inner,
+            end: 0,
+            position: 0,
+            header_start: 0,
+            header: Vec::new(),
+            finished: true,
+            next_header,
+        }
+    }
+}
+
+impl<W: Write> Write for EntryWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.finished && self.position == self.end {
+            self.finished = false;
+            if let Some(header) = self
+                .next_header
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .take()
+            {
+                self.header_start = self.end;
+                self.header = header;
+            }
+        }
+
+        let offset = (self.position - self.header_start) as usize;
+        let header_len = self.header.len().saturating_sub(offset).min(buf.len());
+        if header_len > 0 {
+            let header = &self.header[offset..offset + header_len];
+            if self.position < self.end {
+                if buf[..header_len] != *header {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "the ZIP entry changed while being written",
+                    ));
+                }
+            } else {
+                self.inner.write_all(header)?;
+                self.end += header_len as u64;
+            }
+            self.position += header_len as u64;
+            return Ok(header_len);
+        }
+
+        if self.position < self.end {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "cannot overwrite a written ZIP entry",
+            ));
+        }
+        let written = self.inner.write(buf)?;
+        self.end += written as u64;
+        self.position = self.end;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<W: Write> Seek for EntryWriter<W> {
+    /// Moves within the local header of the entry being written, or back to the end.
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(position) => Some(position),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+            SeekFrom::End(offset) => self.end.checked_add_signed(offset),
+        };
+        let header = self.header_start..self.header_start + self.header.len() as u64;
+        match position {
+            Some(position) if position == self.end || header.contains(&position) => {
+                // Back to the end from the local header, the entry is finished
+                self.finished |= self.position < self.end && position == self.end;
+                self.position = position;
+                Ok(position)
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "cannot seek out of the local header of the ZIP entry being written",
+            )),
+        }
+    }
+}
+
+/// A [`Sink`] writing the files as an unpacked directory tree (`mimetype`, `META-INF`, `OEBPS`).
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug)]
+pub struct DirSink {
+    /// The root directory of the unpacked EPUB.
+    root: PathBuf,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl DirSink {
+    /// Creates a new `DirSink` writing below `root`, which is created if missing.
+    pub fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Sink for DirSink {
+    fn write_file(&mut self, filepath: &str, bytes: &FileBytes, _stored: bool) -> crate::Result {
+        let path = self.root.join(filepath);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        bytes.copy_to(&mut fs::File::create(path)?)?;
+        Ok(())
+    }
+
+    fn finish(self) -> crate::Result {
+        Ok(())
+    }
+}
+
+/// A file that would be written to the EPUB, as reported by
+/// [`EpubBuilder::plan`](crate::epub::EpubBuilder::plan).
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedFile {
+    /// The path of the file inside the EPUB (e.g. `OEBPS/c01.xhtml`).
+    pub path: String,
+    /// The media type declared for the file, or `None` for files outside the manifest
+    /// (`mimetype` and the `META-INF` files).
+    pub media_type: Option<String>,
+    /// The size of the file in bytes, before compression.
+    pub size: u64,
+}
+
+/// A [`Sink`] recording the files that would be written, without writing anything.
+#[derive(Debug, Default)]
+pub struct PlanSink {
+    /// The recorded files, with their media types still unresolved.
+    pub(crate) files: Vec<PlannedFile>,
+}
+
+impl Sink for &mut PlanSink {
+    fn write_file(&mut self, filepath: &str, bytes: &FileBytes, _stored: bool) -> crate::Result {
+        self.files.push(PlannedFile {
+            path: filepath.to_string(),
+            media_type: None,
+            size: bytes.len(),
+        });
+        Ok(())
+    }
+
+    fn finish(self) -> crate::Result {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use super::*;
+    use crate::{
+        epub::{EpubBuilder, MetadataBuilder},
+        output::file_content::COPY_CHUNK_SIZE,
+    };
+
+    /// A writer discarding the bytes, counting them and recording the largest write.
+    struct PeakWriter {
+        written: Rc<Cell<u64>>,
+        largest_write: Rc<Cell<usize>>,
+    }
+
+    impl Write for PeakWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.written.set(self.written.get() + buf.len() as u64);
+            self.largest_write
+                .set(self.largest_write.get().max(buf.len()));
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_zip_sink_streams_resource_files() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("track.mp3");
+        let len = COPY_CHUNK_SIZE as u64 * 4 + 5;
+        std::fs::write(&path, (0..len).map(|i| (i % 251) as u8).collect::<Vec<_>>()).unwrap();
+
+        let written = Rc::new(Cell::new(0));
+        let largest_write = Rc::new(Cell::new(0));
+        let writer = PeakWriter {
+            written: Rc::clone(&written),
+            largest_write: Rc::clone(&largest_write),
+        };
+        let epub = EpubBuilder::new(MetadataBuilder::title("Title").build()).0;
+        let mut sink = ZipSink::new(&epub, writer, ZipCompression::Stored);
+
+        sink.write_file("OEBPS/track.mp3", &FileBytes::File { path, len }, false)
+            .unwrap();
+        // The entry is passed on as it is copied, in chunks, without being held back
+        assert!(written.get() > len);
+        assert!(largest_write.get() <= COPY_CHUNK_SIZE);
+        sink.finish().unwrap();
+    }
+
+    #[test]
+    fn test_dir_sink_write_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut sink = DirSink::new(&temp_dir.path().join("book"));
+
+        sink.write_file("mimetype", &b"application/epub+zip".to_vec().into(), true)
+            .unwrap();
+        sink.write_file("OEBPS/images/map.png", &b"png".to_vec().into(), false)
+            .unwrap();
+        sink.finish().unwrap();
+
+        let root = temp_dir.path().join("book");
+        assert_eq!(
+            fs::read(root.join("mimetype")).unwrap(),
+            b"application/epub+zip"
+        );
+        assert_eq!(fs::read(root.join("OEBPS/images/map.png")).unwrap(), b"png");
+    }
+}
@@ -0,0 +1,718 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    io::{self, Read},
+};
+
+use quick_xml::{Reader, events::Event};
+
+use crate::{
+    epub::{
+        COVER_PAGE_FILENAME, Content, Epub, GENERATED_COVER_FILENAME, Profile, ReferenceType,
+        Resource, UNIQUE_GUIDE_TYPES, external_links, image_dimensions, link_syntax_error,
+    },
+    output::{
+        css_lint,
+        file_content::{COPY_CHUNK_SIZE, FileBytes, FileContent},
+    },
+};
+
+/// XHTML files above this size (300 KiB) are split or rejected by older reading systems.
+const MAX_XHTML_SIZE: u64 = 300 * 1024;
+
+/// Other files above this size (10 MiB) make the book slow to open on most reading systems.
+const MAX_FILE_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Guide reference types without an equivalent in the EPUB 3 structural semantics vocabulary,
+/// with the term that supersedes them.
+const DEPRECATED_GUIDE_TYPES: &[(&str, &str)] = &[("notes", "endnotes")];
+
+/// A non-fatal quality issue found while creating an EPUB, returned by
+/// [`crate::epub::EpubBuilder::create_with_warnings`].
+///
+/// The EPUB is created anyway; warnings let pipelines surface problems without failing the build.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Warning {
+    /// A content whose guide reference type is superseded in EPUB 3.
+    DeprecatedGuideType {
+        /// The filename of the content (e.g. `c03.xhtml`).
+        filename: String,
+        /// The guide reference type (e.g. `notes`).
+        ref_type: String,
+        /// The EPUB 3 term to use instead.
+        replacement: String,
+    },
+    /// A content whose guide reference type (`cover`, `toc` or `title-page`) was already used by
+    /// another file; only the first one is declared in the guide.
+    DuplicateGuideType {
+        /// The filename of the content (e.g. `c05.xhtml`).
+        filename: String,
+        /// The guide reference type (e.g. `toc`).
+        ref_type: String,
+        /// The filename of the file declared in the guide with this type.
+        first: String,
+    },
+    /// An anchor id used by more than one content reference of the same content, e.g. a custom
+    /// `.id("id02")` colliding with the one generated for another reference; every navigation
+    /// point with this anchor leads to the same place.
+    DuplicateAnchor {
+        /// The filename of the content (e.g. `c01.xhtml`).
+        filename: String,
+        /// The anchor id (e.g. `id02`).
+        anchor: String,
+    },
+    /// A file larger than reading systems handle comfortably.
+    OversizedFile {
+        /// The path of the file inside the archive.
+        path: String,
+        /// The size of the file in bytes.
+        size: u64,
+        /// The recommended maximum size in bytes.
+        limit: u64,
+    },
+    /// A resource mentioned neither by a content body nor by the stylesheet.
+    UnreferencedResource {
+        /// The filename of the resource (e.g. `images/map.png`).
+        filename: String,
+    },
+    /// An `<img>` of a content body without an `alt` attribute (an empty `alt` marks a
+    /// decorative image, and is accepted).
+    MissingAltText {
+        /// The filename of the content (e.g. `c01.xhtml`).
+        filename: String,
+        /// The `src` of the image.
+        src: String,
+    },
+    /// A cover page (a content of type [`ReferenceType::Cover`]) without an accessible
+    /// description: a non-empty `alt`, an `aria-label` or an SVG `<title>`.
+    MissingCoverDescription {
+        /// The filename of the cover page (e.g. `c01.xhtml`).
+        filename: String,
+    },
+    /// A cover image narrower than the retailer of the build profile accepts.
+    SmallCover {
+        /// The retailer of the build profile.
+        profile: Profile,
+        /// The width of the cover image in pixels.
+        width: u32,
+        /// The height of the cover image in pixels.
+        height: u32,
+        /// The minimum width in pixels the retailer accepts.
+        min_width: u32,
+    },
+    /// A metadata field the retailer of the build profile requires, missing from the book.
+    MissingProfileMetadata {
+        /// The retailer of the build profile.
+        profile: Profile,
+        /// The missing field (e.g. `dc:description`).
+        field: String,
+    },
+    /// A CSS construct known to break common reading systems (e.g. `position: fixed`, viewport
+    /// units or a `:not()` selector, which RMSDK drops).
+    IncompatibleCss {
+        /// The path of the stylesheet inside the `OEBPS` directory (e.g. `style.css`).
+        path: String,
+        /// The line of the construct in the stylesheet, starting at 1.
+        line: usize,
+        /// The construct (e.g. `position: fixed`).
+        construct: String,
+        /// Why it breaks, and where.
+        reason: String,
+    },
+    /// An external link of a content body with an invalid syntax (e.g. `https:/example.com`).
+    InvalidLink {
+        /// The filename of the content (e.g. `c02.xhtml`).
+        filename: String,
+        /// The `href` of the link.
+        href: String,
+        /// What is wrong with it.
+        reason: String,
+    },
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DeprecatedGuideType {
+                filename,
+                ref_type,
+                replacement,
+            } => write!(
+                f,
+                "{filename}: guide type {ref_type:?} is deprecated in EPUB 3, use {replacement:?}"
+            ),
+            Self::DuplicateGuideType {
+                filename,
+                ref_type,
+                first,
+            } => write!(
+                f,
+                "{filename}: guide type {ref_type:?} is already used by {first}, left out of the guide"
+            ),
+            Self::DuplicateAnchor { filename, anchor } => write!(
+                f,
+                "{filename}: anchor #{anchor} is used by more than one content reference"
+            ),
+            Self::OversizedFile { path, size, limit } => write!(
+                f,
+                "{path}: {size} bytes exceeds the recommended maximum of {limit} bytes"
+            ),
+            Self::UnreferencedResource { filename } => write!(
+                f,
+                "{filename}: not referenced by any content nor the stylesheet"
+            ),
+            Self::MissingAltText { filename, src } => {
+                write!(f, "{filename}: image {src} has no alt text")
+            }
+            Self::MissingCoverDescription { filename } => {
+                write!(f, "{filename}: cover page has no accessible description")
+            }
+            Self::SmallCover {
+                profile,
+                width,
+                height,
+                min_width,
+            } => write!(
+                f,
+                "cover image is {width}x{height} pixels, {profile} requires at least {min_width} pixels wide"
+            ),
+            Self::MissingProfileMetadata { profile, field } => {
+                write!(f, "{profile} requires the {field} metadata")
+            }
+            Self::IncompatibleCss {
+                path,
+                line,
+                construct,
+                reason,
+            } => write!(f, "{path}:{line}: {construct} {reason}"),
+            Self::InvalidLink {
+                filename,
+                href,
+                reason,
+            } => write!(f, "{filename}: external link {href:?} {reason}"),
+        }
+    }
+}
+
+/// Collects the warnings that depend only on the model: deprecated and duplicate guide types,
+/// duplicate anchors, images without alt text, undescribed cover pages, unreferenced
+/// resources, metadata missing for the build profile, external links with an invalid syntax
+/// and incompatible CSS in the stylesheet and the in-memory CSS resources.
+pub(crate) fn model_warnings(epub: &Epub<'_>) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+
+    if let Some(profile) = epub.profile {
+        warnings.extend(
+            profile
+                .missing_metadata(&epub.metadata)
+                .into_iter()
+                .map(|field| Warning::MissingProfileMetadata {
+                    profile,
+                    field: field.to_string(),
+                }),
+        );
+    }
+
+    if let Some(stylesheet) = epub.stylesheet_content() {
+        let stylesheet = String::from_utf8_lossy(&stylesheet);
+        warnings.extend(css_lint::css_warnings("style.css", &stylesheet));
+    }
+    for resource in epub.resources.iter().flatten() {
+        if let Resource::Raw(filename, bytes, _) = resource.resource
+            && resource.media_type() == "text/css"
+        {
+            let css = String::from_utf8_lossy(bytes);
+            warnings.extend(css_lint::css_warnings(filename, &css));
+        }
+    }
+
+    if let Some(ref contents) = epub.contents {
+        let mut guide = HashMap::new();
+        if epub.has_cover_page() {
+            guide.insert("cover", COVER_PAGE_FILENAME.to_string());
+        }
+        content_warnings(contents, &mut 0, &mut guide, &mut warnings);
+    }
+
+    if let Some(ref resources) = epub.resources {
+        let stylesheet = epub.stylesheet_content();
+        let stylesheet = stylesheet.as_deref().map(String::from_utf8_lossy);
+        let contents = epub.contents.as_deref().unwrap_or_default();
+
+        warnings.extend(
+            resources
+                .iter()
+                .filter_map(|resource| resource.filename().ok())
+                .filter(|filename| {
+                    !contents.iter().any(|content| content.mentions(filename))
+                        && !stylesheet
+                            .as_ref()
+                            .is_some_and(|stylesheet| stylesheet.contains(filename.as_str()))
+                })
+                .map(|filename| Warning::UnreferencedResource { filename }),
+        );
+    }
+
+    warnings
+}
+
+/// Gets a [`Warning::OversizedFile`] if the file at `path` is bigger than its recommended maximum.
+pub(crate) fn size_warning(path: &str, size: u64) -> Option<Warning> {
+    let limit = if path.ends_with(".xhtml") {
+        MAX_XHTML_SIZE
+    } else {
+        MAX_FILE_SIZE
+    };
+
+    (size > limit).then(|| Warning::OversizedFile {
+        path: path.to_string(),
+        size,
+        limit,
+    })
+}
+
+/// The minimum width of the cover image required by the build profile, checked as the cover
+/// image is written.
+pub(crate) struct CoverConstraint {
+    /// The path of the cover image inside the archive.
+    path: String,
+    /// The retailer of the build profile.
+    profile: Profile,
+    /// The minimum width in pixels the retailer accepts.
+    min_width: u32,
+}
+
+impl CoverConstraint {
+    /// Gets the constraint of the build profile of `epub`, if it has one and a cover image or
+    /// generated cover.
+    pub(crate) fn new(epub: &Epub<'_>) -> Option<Self> {
+        let profile = epub.profile?;
+        let filename = match epub.cover_image {
+            Some(ref cover_image) => cover_image.filename().ok()?,
+            None => epub
+                .generated_cover
+                .as_ref()
+                .map(|_| GENERATED_COVER_FILENAME.to_string())?,
+        };
+        Some(Self {
+            path: format!("OEBPS/{filename}"),
+            profile,
+            min_width: profile.min_cover_width()?,
+        })
+    }
+
+    /// Gets a [`Warning::SmallCover`] if `file` is the cover image and is too narrow, reading
+    /// only its header if it comes from a resource file.
+    ///
+    /// # Errors
+    /// Returns an error if the resource file cannot be read.
+    pub(crate) fn check(
+        &self,
+        file: &FileContent<String, FileBytes>,
+    ) -> io::Result<Option<Warning>> {
+        if file.filepath != self.path {
+            return Ok(None);
+        }
+        let mut header = Vec::new();
+        file.bytes
+            .reader()?
+            .take(COPY_CHUNK_SIZE as u64)
+            .read_to_end(&mut header)?;
+
+        Ok(image_dimensions(&header)
+            .filter(|(width, _)| *width < self.min_width)
+            .map(|(width, height)| Warning::SmallCover {
+                profile: self.profile,
+                width,
+                height,
+                min_width: self.min_width,
+            }))
+    }
+}
+
+/// Recursively collects the warnings of `contents` (deprecated and duplicate guide types,
+/// duplicate anchors, images without alt text, undescribed cover pages), numbering their files
+/// in book order.
+///
+/// `guide` holds the filename of the first file of each type a book has at most one of.
+fn content_warnings(
+    contents: &[Content<'_>],
+    number: &mut usize,
+    guide: &mut HashMap<&'static str, String>,
+    warnings: &mut Vec<Warning>,
+) {
+    for content in contents {
+        *number += 1;
+        let filename = content.filename(*number);
+        let (ref_type, _) = content.reference_type.type_and_title();
+
+        if let Some(unique_type) = UNIQUE_GUIDE_TYPES.iter().find(|t| **t == ref_type) {
+            match guide.get(unique_type) {
+                Some(first) => warnings.push(Warning::DuplicateGuideType {
+                    filename: filename.to_string(),
+                    ref_type: ref_type.to_string(),
+                    first: first.clone(),
+                }),
+                None => {
+                    guide.insert(unique_type, filename.to_string());
+                }
+            }
+        }
+
+        if let Some((_, replacement)) = DEPRECATED_GUIDE_TYPES
+            .iter()
+            .find(|(deprecated, _)| *deprecated == ref_type)
+        {
+            warnings.push(Warning::DeprecatedGuideType {
+                filename: filename.to_string(),
+                ref_type: ref_type.to_string(),
+                replacement: replacement.to_string(),
+            });
+        }
+
+        warnings.extend(content.duplicate_anchors().into_iter().map(|anchor| {
+            Warning::DuplicateAnchor {
+                filename: filename.to_string(),
+                anchor,
+            }
+        }));
+
+        let images = content.body().map(Images::scan).unwrap_or_default();
+        warnings.extend(
+            images
+                .without_alt
+                .into_iter()
+                .map(|src| Warning::MissingAltText {
+                    filename: filename.to_string(),
+                    src,
+                }),
+        );
+        warnings.extend(
+            content
+                .body()
+                .map(external_links)
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|href| {
+                    let reason = link_syntax_error(&href)?;
+                    Some(Warning::InvalidLink {
+                        filename: filename.to_string(),
+                        href,
+                        reason: reason.to_string(),
+                    })
+                }),
+        );
+        if matches!(content.reference_type, ReferenceType::Cover(_)) && !images.described {
+            warnings.push(Warning::MissingCoverDescription {
+                filename: filename.to_string(),
+            });
+        }
+
+        if let Some(ref subcontents) = content.subcontents {
+            content_warnings(subcontents, number, guide, warnings);
+        }
+    }
+}
+
+/// The accessibility of the images of an XHTML body.
+#[derive(Debug, Default, PartialEq)]
+struct Images {
+    /// The `src` of every `<img>` without an `alt` attribute.
+    without_alt: Vec<String>,
+    /// Whether the body has an accessible description: a non-empty `alt`, an `aria-label` or
+    /// an SVG `<title>`.
+    described: bool,
+}
+
+impl Images {
+    /// Scans `body`, stopping silently at the first markup error (bodies are not required to
+    /// be well formed).
+    fn scan(body: &[u8]) -> Self {
+        let mut reader = Reader::from_reader(body);
+        reader.config_mut().check_end_names = false;
+        let mut images = Self::default();
+        let mut buffer = Vec::new();
+
+        loop {
+            match reader.read_event_into(&mut buffer) {
+                Ok(Event::Start(element) | Event::Empty(element)) => {
+                    let attribute = |name: &[u8]| {
+                        element
+                            .try_get_attribute(name)
+                            .ok()
+                            .flatten()
+                            .map(|attribute| String::from_utf8_lossy(&attribute.value).into_owned())
+                    };
+
+                    match element.local_name().as_ref() {
+                        b"img" => match attribute(b"alt") {
+                            Some(alt) => images.described |= !alt.trim().is_empty(),
+                            None => images
+                                .without_alt
+                                .push(attribute(b"src").unwrap_or_default()),
+                        },
+                        b"title" => images.described = true,
+                        _ => {}
+                    }
+                    images.described |=
+                        attribute(b"aria-label").is_some_and(|label| !label.is_empty());
+                }
+                Ok(Event::Eof) | Err(_) => break,
+                _ => {}
+            }
+            buffer.clear();
+        }
+
+        images
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::epub::{
+        ContentBuilder, ContentReference, EpubBuilder, MetadataBuilder, ReferenceType, Resource,
+    };
+
+    #[test]
+    fn test_create_with_warnings() {
+        let big_chapter = format!("<body><p>{}</p></body>", "a".repeat(310 * 1024));
+
+        let warnings = EpubBuilder::new(MetadataBuilder::title("Warnings").build())
+            .stylesheet(b"@font-face { src: url(fonts/serif.otf); }")
+            .add_resource(Resource::Raw(
+                "fonts/serif.otf",
+                b"font",
+                "font/otf".to_string(),
+            ))
+            .add_resource(Resource::Raw(
+                "images/map.png",
+                b"png",
+                "image/png".to_string(),
+            ))
+            .add_resource(Resource::Raw(
+                "images/unused.png",
+                b"png",
+                "image/png".to_string(),
+            ))
+            .add_content(
+                ContentBuilder::new(
+                    br#"<body><img src="images/map.png" alt="Map"/></body>"#,
+                    ReferenceType::Text("Chapter 1".to_string()),
+                )
+                .add_child(
+                    ContentBuilder::new(
+                        big_chapter.as_bytes(),
+                        ReferenceType::Text("Chapter 2".to_string()),
+                    )
+                    .build(),
+                )
+                .build(),
+            )
+            .add_content(
+                ContentBuilder::new(
+                    b"<body><h1>Notes</h1></body>",
+                    ReferenceType::Notes("Notes".to_string()),
+                )
+                .build(),
+            )
+            .create_with_warnings(&mut Vec::new())
+            .unwrap();
+
+        assert_eq!(warnings.len(), 3);
+        assert_eq!(
+            warnings[0].to_string(),
+            r#"c03.xhtml: guide type "notes" is deprecated in EPUB 3, use "endnotes""#
+        );
+        assert_eq!(
+            warnings[1],
+            Warning::UnreferencedResource {
+                filename: "images/unused.png".to_string()
+            }
+        );
+        assert!(matches!(
+            &warnings[2],
+            Warning::OversizedFile { path, limit, .. } if path == "OEBPS/c02.xhtml" && *limit == MAX_XHTML_SIZE
+        ));
+    }
+
+    #[test]
+    fn test_images_scan() {
+        let images = Images::scan(
+            br#"<body><img src="a.png"/><img src="b.png" alt=""/><p><img src="c.png"></p></body>"#,
+        );
+        assert_eq!(images.without_alt, vec!["a.png", "c.png"]);
+        assert!(!images.described);
+
+        assert!(Images::scan(br#"<body><img src="cover.jpg" alt="The cover"/></body>"#).described);
+        assert!(Images::scan(br#"<body><div role="img" aria-label="Cover"/></body>"#).described);
+        assert!(
+            Images::scan(br#"<body><svg><title>Cover</title><image href="c.jpg"/></svg></body>"#)
+                .described
+        );
+    }
+
+    #[test]
+    fn test_cover_page_warnings() {
+        let warnings = EpubBuilder::new(MetadataBuilder::title("Cover").build())
+            .add_content(
+                ContentBuilder::new(
+                    br#"<body><img src="cover.jpg" alt=""/></body>"#,
+                    ReferenceType::Cover("Cover".to_string()),
+                )
+                .build(),
+            )
+            .add_content(
+                ContentBuilder::new(
+                    br#"<body><img src="map.png"/></body>"#,
+                    ReferenceType::Text("Chapter 1".to_string()),
+                )
+                .build(),
+            )
+            .create_with_warnings(&mut Vec::new())
+            .unwrap();
+
+        assert_eq!(
+            warnings,
+            vec![
+                Warning::MissingCoverDescription {
+                    filename: "c01.xhtml".to_string()
+                },
+                Warning::MissingAltText {
+                    filename: "c02.xhtml".to_string(),
+                    src: "map.png".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_duplicate_guide_type_warnings() {
+        let content = |ref_type| ContentBuilder::new(b"<body><p>Text</p></body>", ref_type).build();
+        let epub_builder = || {
+            EpubBuilder::new(MetadataBuilder::title("Guide").build())
+                .add_content(content(ReferenceType::Toc("Contents".to_string())))
+                .add_content(content(ReferenceType::Text("Chapter 1".to_string())))
+                .add_content(content(ReferenceType::Toc("Contents".to_string())))
+        };
+
+        let warnings = epub_builder()
+            .create_with_warnings(&mut Vec::new())
+            .unwrap();
+        assert_eq!(
+            warnings.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            [r#"c03.xhtml: guide type "toc" is already used by c01.xhtml, left out of the guide"#]
+        );
+
+        let content_opf = epub_builder().render_content_opf().unwrap();
+        assert_eq!(content_opf.matches(r#"<reference type="toc""#).count(), 1);
+        assert!(
+            content_opf.contains(r#"<reference type="toc" title="Contents" href="c01.xhtml"/>"#)
+        );
+        assert!(content_opf.contains(r#"<itemref idref="c03.xhtml"/>"#));
+    }
+
+    #[test]
+    fn test_duplicate_anchor_warnings() {
+        let warnings = EpubBuilder::new(MetadataBuilder::title("Anchors").build())
+            .add_content(
+                ContentBuilder::new(
+                    br#"<body><h2 id="id01">One</h2><h2 id="id02">Two</h2></body>"#,
+                    ReferenceType::Text("Chapter 1".to_string()),
+                )
+                .add_content_reference(ContentReference::new("One"))
+                .add_content_reference(ContentReference::new("Two").id("id01"))
+                .add_content_reference(ContentReference::new("Three").id("id01"))
+                .build(),
+            )
+            .create_with_warnings(&mut Vec::new())
+            .unwrap();
+
+        assert_eq!(
+            warnings,
+            vec![Warning::DuplicateAnchor {
+                filename: "c01.xhtml".to_string(),
+                anchor: "id01".to_string()
+            }]
+        );
+        assert_eq!(
+            warnings[0].to_string(),
+            "c01.xhtml: anchor #id01 is used by more than one content reference"
+        );
+    }
+
+    #[test]
+    fn test_css_warnings() {
+        let warnings = EpubBuilder::new(MetadataBuilder::title("CSS").build())
+            .stylesheet(b"body { margin: 0; }\nheader { position: fixed; }")
+            .add_resource(Resource::Raw(
+                "css/extra.css",
+                b"p:not(.first) { text-indent: 1em; }",
+                "text/css".to_string(),
+            ))
+            .add_content(
+                ContentBuilder::new(
+                    br#"<body><link href="css/extra.css"/></body>"#,
+                    ReferenceType::Text("One".to_string()),
+                )
+                .build(),
+            )
+            .create_with_warnings(&mut Vec::new())
+            .unwrap();
+
+        assert_eq!(
+            warnings,
+            [
+                Warning::IncompatibleCss {
+                    path: "style.css".to_string(),
+                    line: 2,
+                    construct: "position: fixed".to_string(),
+                    reason: "is ignored or breaks pagination in most reading systems".to_string(),
+                },
+                Warning::IncompatibleCss {
+                    path: "css/extra.css".to_string(),
+                    line: 1,
+                    construct: r#"selector "p:not(.first)": unsupported ":not(""#.to_string(),
+                    reason:
+                        "is not supported by RMSDK (Adobe Digital Editions), which drops the rule"
+                            .to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_link_warnings() {
+        let warnings = EpubBuilder::new(MetadataBuilder::title("Links").build())
+            .add_content(
+                ContentBuilder::new(
+                    br##"<body><a href="https://example.com">Ok</a><a href="#id01">Anchor</a><a href="https:/example.com">Bad</a></body>"##,
+                    ReferenceType::Text("One".to_string()),
+                )
+                .build(),
+            )
+            .create_with_warnings(&mut Vec::new())
+            .unwrap();
+
+        assert_eq!(
+            warnings,
+            [Warning::InvalidLink {
+                filename: "c01.xhtml".to_string(),
+                href: "https:/example.com".to_string(),
+                reason: "is missing the // before the host".to_string(),
+            }]
+        );
+        assert_eq!(
+            warnings[0].to_string(),
+            r#"c01.xhtml: external link "https:/example.com" is missing the // before the host"#
+        );
+    }
+
+    #[test]
+    fn test_size_warning() {
+        assert!(size_warning("OEBPS/c01.xhtml", MAX_XHTML_SIZE).is_none());
+        assert!(size_warning("OEBPS/c01.xhtml", MAX_XHTML_SIZE + 1).is_some());
+        assert!(size_warning("OEBPS/images/map.png", MAX_XHTML_SIZE + 1).is_none());
+        assert!(size_warning("OEBPS/images/map.png", MAX_FILE_SIZE + 1).is_some());
+    }
+}